@@ -0,0 +1,75 @@
+//! `pseudo flowchart`: prints a flowchart for every function in a program (see
+//! [`logic::flowchart`]).
+
+use std::{fs, process};
+
+use codespan_reporting::{
+    files::SimpleFiles,
+    term::{
+        emit,
+        termcolor::{ColorChoice, StandardStream},
+    },
+};
+use logic::{
+    flowchart::{flowcharts, to_dot, to_mermaid},
+    parse,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Dot,
+    Mermaid,
+}
+
+fn usage() -> ! {
+    println!("usage: pseudo flowchart <file> [--format dot|mermaid]");
+    process::exit(1);
+}
+
+/// Prints the flowcharts (`args` are the arguments which follow `flowchart`).
+pub fn main(args: &[String]) {
+    let mut file_name = None;
+    let mut format = Format::Dot;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        match flag {
+            "--format" => {
+                format = match value.or_else(|| args.next().map(String::as_str)) {
+                    Some("dot") => Format::Dot,
+                    Some("mermaid") => Format::Mermaid,
+                    _ => usage(),
+                }
+            }
+            _ if flag.starts_with('-') || file_name.is_some() => usage(),
+            _ => file_name = Some(arg),
+        }
+    }
+    let file_name = file_name.unwrap_or_else(|| usage());
+
+    let text = fs::read_to_string(file_name).unwrap_or_else(|error| {
+        println!("`{file_name}` could not be read: {error}");
+        process::exit(1);
+    });
+    let table = match parse::parse_with_prelude(&text) {
+        Ok(table) => table,
+        Err(error) => {
+            let mut files = SimpleFiles::new();
+            let file_id = files.add(file_name, text.clone());
+            let mut writer = StandardStream::stderr(ColorChoice::Always);
+            let config = codespan_reporting::term::Config::default();
+            emit(&mut writer, &config, &files, &error.report(file_id)).unwrap();
+            process::exit(1);
+        }
+    };
+
+    let charts = flowcharts(&table);
+    match format {
+        Format::Dot => print!("{}", to_dot(&charts)),
+        Format::Mermaid => print!("{}", to_mermaid(&charts)),
+    }
+}
@@ -0,0 +1,75 @@
+//! `pseudo fmt`: formats programs (see [`logic::fmt`]).
+
+use std::{fs, process};
+
+use codespan_reporting::{
+    files::SimpleFiles,
+    term::{
+        emit,
+        termcolor::{ColorChoice, StandardStream},
+    },
+};
+use logic::{fmt::format, parse};
+
+fn usage() -> ! {
+    println!(
+        "usage: pseudo fmt <file>... [--check]\n\n\
+         (with `--check` the files are not changed; instead the files which are not formatted \
+         are listed, and the exit code is 1 if there are any)"
+    );
+    process::exit(1);
+}
+
+/// Formats each of the files (`args` are the arguments which follow `fmt`).
+pub fn main(args: &[String]) {
+    let mut file_names = vec![];
+    let mut check = false;
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ if arg.starts_with('-') => usage(),
+            _ => file_names.push(arg),
+        }
+    }
+    if file_names.is_empty() {
+        usage();
+    }
+
+    let mut files = SimpleFiles::new();
+    let mut writer = StandardStream::stderr(ColorChoice::Always);
+    let config = codespan_reporting::term::Config::default();
+
+    let mut failed = false;
+    for file_name in file_names {
+        let text = fs::read_to_string(file_name).unwrap_or_else(|error| {
+            println!("`{file_name}` could not be read: {error}");
+            process::exit(1);
+        });
+        let file_id = files.add(file_name, text.clone());
+
+        let table = match parse::parse_with_prelude(&text) {
+            Ok(table) => table,
+            Err(error) => {
+                emit(&mut writer, &config, &files, &error.report(file_id)).unwrap();
+                failed = true;
+                continue;
+            }
+        };
+        let formatted = format(&table, &text);
+        if formatted == text {
+            continue;
+        }
+
+        if check {
+            println!("`{file_name}` is not formatted");
+            failed = true;
+        } else if let Err(error) = fs::write(file_name, formatted) {
+            println!("`{file_name}` could not be written: {error}");
+            failed = true;
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     env, fs,
     panic::{catch_unwind, resume_unwind},
+    path::Path,
     process,
 };
 
@@ -11,7 +12,25 @@ use codespan_reporting::{
         termcolor::{ColorChoice, StandardStream},
     },
 };
-use logic::{codegen::codegen, parse, ty::type_check};
+use logic::{
+    codegen::{codegen, emit_object, optimisation_passes, optimised_mir, CodegenOptions, Target},
+    interpret,
+    io::Stdio,
+    mir::opt::OptLevel,
+    parse,
+    ty::type_check,
+    wasm,
+};
+
+mod flowchart;
+mod fmt;
+mod serve;
+mod trace;
+mod transpile;
+
+/// The amount of fuel which programs are given when they are run (so that a
+/// program which never finishes is stopped, rather than running forever).
+const DEFAULT_FUEL: u64 = 100_000_000;
 
 /// Runs the compiler.
 ///
@@ -21,12 +40,134 @@ fn main() {
     let result = catch_unwind(|| {
         let args = env::args().collect::<Vec<_>>();
 
-        if args.is_empty() {
-            println!("You must provide the name of the file you would like to compile!");
-            process::exit(1);
+        // `pseudo serve` starts the playground server
+        if args.get(1).map(|arg| arg.as_str()) == Some("serve") {
+            serve::main(&args[2..]);
+            return;
+        }
+        // `pseudo trace` prints a trace table
+        if args.get(1).map(|arg| arg.as_str()) == Some("trace") {
+            trace::main(&args[2..]);
+            return;
+        }
+        // `pseudo flowchart` prints the flowcharts for a program
+        if args.get(1).map(|arg| arg.as_str()) == Some("flowchart") {
+            flowchart::main(&args[2..]);
+            return;
+        }
+        // `pseudo transpile` translates a program into another language
+        if args.get(1).map(|arg| arg.as_str()) == Some("transpile") {
+            transpile::main(&args[2..]);
+            return;
+        }
+        // `pseudo fmt` formats programs
+        if args.get(1).map(|arg| arg.as_str()) == Some("fmt") {
+            fmt::main(&args[2..]);
+            return;
+        }
+
+        let mut file_name = None;
+        let mut options = CodegenOptions::default();
+        let mut dump_mir = false;
+        let mut dump_passes = false;
+        let mut use_interpreter = false;
+        let mut emit_wasm = None;
+        let mut target = None;
+        let mut output = None;
+
+        let mut args = args.iter().skip(1).peekable();
+        // `pseudo run <file>` is the same as `pseudo <file>`
+        if args.peek().map(|arg| arg.as_str()) == Some("run") {
+            args.next();
+        }
+        while let Some(arg) = args.next() {
+            if arg == "--seed" {
+                options.seed = match args.next().map(|seed| seed.parse::<u64>()) {
+                    Some(Ok(seed)) => Some(seed),
+                    _ => {
+                        println!(
+                            "The `--seed` flag must be followed by a whole number (for example \
+                             `--seed 42`)."
+                        );
+                        process::exit(1);
+                    }
+                };
+            } else if arg == "--fuel" {
+                options.fuel = match args.next().map(|fuel| fuel.parse::<u64>()) {
+                    Some(Ok(fuel)) => Some(fuel),
+                    _ => {
+                        println!(
+                            "The `--fuel` flag must be followed by a whole number (for example \
+                             `--fuel 1000000`)."
+                        );
+                        process::exit(1);
+                    }
+                };
+            } else if let Some(opt_level) = OptLevel::from_flag(arg) {
+                options.opt_level = opt_level;
+            } else if arg == "--dump-mir" {
+                dump_mir = true;
+            } else if arg == "--dump-passes" {
+                dump_passes = true;
+            } else if arg == "--interpret" {
+                use_interpreter = true;
+            } else if arg == "--emit-wasm" {
+                emit_wasm = match args.next() {
+                    Some(output) => Some(output),
+                    None => {
+                        println!(
+                            "The `--emit-wasm` flag must be followed by the name of the file to \
+                             write the WebAssembly module to (for example `--emit-wasm out.wasm`)."
+                        );
+                        process::exit(1);
+                    }
+                };
+            } else if arg == "--target" {
+                target = match args.next().map(|triple| Target::from_triple(triple)) {
+                    Some(Ok(target)) => Some(target),
+                    Some(Err(error)) => {
+                        println!("{error}");
+                        process::exit(1);
+                    }
+                    None => {
+                        println!(
+                            "The `--target` flag must be followed by a target triple (for example \
+                             `--target aarch64-unknown-linux-gnu`)."
+                        );
+                        process::exit(1);
+                    }
+                };
+            } else if arg == "-g" {
+                options.debug_info = true;
+            } else if arg == "-o" {
+                output = match args.next() {
+                    Some(output) => Some(output),
+                    None => {
+                        println!(
+                            "The `-o` flag must be followed by the name of the file to write the \
+                             object file to (for example `-o program.o`)."
+                        );
+                        process::exit(1);
+                    }
+                };
+            } else {
+                file_name = Some(arg);
+            }
+        }
+
+        // programs which are run (rather than written to a file) are stopped
+        // if they run for too long
+        if target.is_none() && emit_wasm.is_none() {
+            options.fuel.get_or_insert(DEFAULT_FUEL);
         }
 
-        let file_name = args.get(1).unwrap();
+        let file_name = match file_name {
+            Some(file_name) => file_name,
+            None => {
+                println!("You must provide the name of the file you would like to compile!");
+                process::exit(1);
+            }
+        };
 
         let input = fs::read_to_string(file_name)
             .expect("the file in question could not be opened; are you sure it exists");
@@ -37,7 +178,7 @@ fn main() {
         let mut writer = StandardStream::stderr(ColorChoice::Always);
         let config = codespan_reporting::term::Config::default();
 
-        let ast = match parse::parse(&input) {
+        let ast = match parse::parse_with_prelude(&input) {
             Ok(ast) => ast,
             Err(error) => {
                 let report = error.report(file_id);
@@ -45,6 +186,9 @@ fn main() {
                 process::exit(1);
             }
         };
+        for warning in ast.warnings() {
+            emit(&mut writer, &config, &files, &warning.report(file_id)).unwrap();
+        }
 
         let env = match type_check(&ast) {
             Ok(env) => env,
@@ -55,7 +199,82 @@ fn main() {
             }
         };
 
-        match codegen(&ast, &env) {
+        if options.debug_info && target.is_none() {
+            println!(
+                "The `-g` flag can only be used when producing an object file (using `--target`)."
+            );
+            process::exit(1);
+        }
+
+        if dump_passes {
+            // (if the program cannot be lowered, the error is reported when it
+            // is compiled below)
+            if let Ok(passes) = optimisation_passes(&ast, &env, &options) {
+                for (when, program) in passes {
+                    println!(";; {when}\n{program}");
+                }
+            }
+        }
+
+        if dump_mir || use_interpreter || emit_wasm.is_some() || target.is_some() {
+            let program = match optimised_mir(&ast, &env, &options) {
+                Ok(program) => program,
+                Err(error) => {
+                    let report = error.report(file_id);
+                    emit(&mut writer, &config, &files, &report).unwrap();
+                    process::exit(1);
+                }
+            };
+
+            if dump_mir {
+                print!("{program}");
+            } else if let Some(target) = target {
+                // `pseudo program.pseudo --target <triple>` writes `program.o`
+                let output = output.map_or_else(
+                    || Path::new(file_name).with_extension("o"),
+                    |output| Path::new(output).to_owned(),
+                );
+                match emit_object(&ast, &program, &options, &target, Path::new(file_name)) {
+                    Ok(object) => {
+                        fs::write(&output, object.bytes)
+                            .expect("the object file could not be written");
+                        // object files cannot record the libraries which they
+                        // need, so the user has to pass them to the linker
+                        if !object.libraries.is_empty() {
+                            eprintln!(
+                                "note: `{}` must be linked with {}",
+                                output.display(),
+                                object
+                                    .libraries
+                                    .iter()
+                                    .map(|library| format!("`{library}`"))
+                                    .collect::<Vec<_>>()
+                                    .join(", ")
+                            );
+                        }
+                    }
+                    Err(error) => {
+                        eprintln!("error: {error}");
+                        process::exit(1);
+                    }
+                }
+            } else if let Some(output) = emit_wasm {
+                match wasm::compile(&program, options.seed) {
+                    Ok(module) => fs::write(output, module)
+                        .expect("the WebAssembly module could not be written"),
+                    Err(error) => {
+                        eprintln!("error: {error}");
+                        process::exit(1);
+                    }
+                }
+            } else if let Err(error) = interpret::run(&program, options.seed, options.fuel, Stdio) {
+                eprintln!("error: {error}");
+                process::exit(1);
+            }
+            return;
+        }
+
+        match codegen(&ast, &env, &options, &mut Stdio) {
             Ok(env) => env,
             Err(error) => {
                 let report = error.report(file_id);
@@ -0,0 +1,17 @@
+//! Checks programs sent by the playground (the diagnostics are sent back as
+//! JSON).
+
+use logic::{
+    query::Database,
+    session::{convert, Diagnostic},
+};
+
+/// Parses and type checks the program, returning every error (and warning)
+/// found.
+pub fn check(source: &str) -> Vec<Diagnostic> {
+    Database::new(source)
+        .diagnostics(())
+        .into_iter()
+        .map(|diagnostic| convert(source, diagnostic))
+        .collect()
+}
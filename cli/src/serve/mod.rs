@@ -0,0 +1,136 @@
+//! `pseudo serve`: a small HTTP server which powers the online playground.
+//!
+//! - `GET /` returns the playground page
+//! - `POST /api/run` accepts a [`RunRequest`] (as JSON) and checks the
+//!   program, responding with a [`RunResponse`] which contains any
+//!   diagnostics and (if there were none) the output of the program
+//!
+//! Programs are run by the interpreter (which cannot call native functions,
+//! so `extern` functions cannot be used to escape the sandbox) in a separate
+//! process, which is subject to the limits described by [`Limits`].
+
+mod diagnostics;
+mod sandbox;
+#[cfg(test)]
+mod test;
+
+use std::{io::Read, process, thread, time::Duration};
+
+use logic::session::{Diagnostic, Severity};
+use serde::{Deserialize, Serialize};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use self::sandbox::{Limits, Outcome};
+
+/// The playground page.
+static INDEX: &str = include_str!("index.html");
+
+/// Requests with a body larger than this (in bytes) are rejected.
+const MAX_REQUEST_SIZE: u64 = 1 << 20;
+
+#[derive(Debug, Deserialize)]
+pub struct RunRequest {
+    pub source: String,
+    /// The seed for the random number generator (see `--seed`).
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunResponse {
+    pub diagnostics: Vec<Diagnostic>,
+    /// This is `None` if the program was not run (because it contained
+    /// errors, rather than just warnings).
+    pub outcome: Option<Outcome>,
+}
+
+/// Starts the server (`args` are the arguments which follow `serve`).
+pub fn main(args: &[String]) {
+    let mut address = "127.0.0.1:8080".to_owned();
+    let mut limits = Limits::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args.next();
+        let parsed = match arg.as_str() {
+            "--address" => value.map(|value| address = value.clone()),
+            "--time-limit" => parse(value).map(|seconds| limits.cpu_seconds = seconds),
+            "--memory-limit" => parse(value).map(|megabytes| limits.memory_bytes = megabytes << 20),
+            "--output-limit" => parse(value).map(|kilobytes| limits.output_bytes = kilobytes << 10),
+            _ => None,
+        };
+        if parsed.is_none() {
+            println!(
+                "usage: pseudo serve [--address <host:port>] [--time-limit <seconds>] \
+                 [--memory-limit <megabytes>] [--output-limit <kilobytes>]"
+            );
+            process::exit(1);
+        }
+    }
+    limits.wall_clock = Duration::from_secs(limits.cpu_seconds * 2 + 1);
+
+    let server = Server::http(&address).unwrap_or_else(|error| {
+        println!("The server could not be started on {address}: {error}");
+        process::exit(1);
+    });
+    println!("The playground is running at http://{address}");
+
+    for request in server.incoming_requests() {
+        thread::spawn(move || handle(request, limits));
+    }
+}
+
+fn parse(value: Option<&String>) -> Option<u64> {
+    value?.parse().ok()
+}
+
+fn handle(mut request: Request, limits: Limits) {
+    let response = match (request.method(), request.url()) {
+        (Method::Get, "/") => {
+            Response::from_string(INDEX).with_header(content_type("text/html; charset=utf-8"))
+        }
+        (Method::Post, "/api/run") => {
+            let mut body = String::new();
+            let read = request
+                .as_reader()
+                .take(MAX_REQUEST_SIZE)
+                .read_to_string(&mut body);
+            match read
+                .ok()
+                .and_then(|_| serde_json::from_str::<RunRequest>(&body).ok())
+            {
+                Some(run_request) => {
+                    let response = run(&run_request, &limits);
+                    Response::from_string(serde_json::to_string(&response).unwrap())
+                        .with_header(content_type("application/json"))
+                }
+                None => Response::from_string("invalid request").with_status_code(400),
+            }
+        }
+        _ => Response::from_string("not found").with_status_code(404),
+    };
+    // the client may have gone away, in which case there's nothing to do
+    let _ = request.respond(response);
+}
+
+/// Checks the program, and runs it if it does not contain any errors (it is
+/// still run if there are only warnings).
+pub fn run(request: &RunRequest, limits: &Limits) -> RunResponse {
+    let diagnostics = diagnostics::check(&request.source);
+    let outcome = if diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.severity != Severity::Error)
+    {
+        Some(sandbox::run(&request.source, request.seed, limits))
+    } else {
+        None
+    };
+    RunResponse {
+        diagnostics,
+        outcome,
+    }
+}
+
+fn content_type(value: &str) -> Header {
+    Header::from_bytes("Content-Type", value).unwrap()
+}
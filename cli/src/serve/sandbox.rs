@@ -0,0 +1,183 @@
+//! Runs programs in a separate process, with limits on the resources which
+//! they can use.
+
+use std::{
+    env,
+    io::{Read, Write},
+    process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Limits {
+    /// The amount of processor time which the program may use.
+    pub cpu_seconds: u64,
+    /// The maximum size of the program's address space.
+    pub memory_bytes: u64,
+    /// The maximum amount of output (the program is stopped if it prints
+    /// more than this).
+    pub output_bytes: u64,
+    /// How long the program may run for (this catches programs which are
+    /// not using the processor, e.g. because they are waiting for input).
+    pub wall_clock: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            cpu_seconds: 2,
+            memory_bytes: 256 << 20,
+            output_bytes: 64 << 10,
+            wall_clock: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Success,
+    /// The program could not be compiled, or reported an error while it was
+    /// running (which is written to `stderr`).
+    Failure,
+    TimeLimitExceeded,
+    OutputLimitExceeded,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Outcome {
+    pub status: Status,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Runs the program (using the interpreter) in a new process.
+pub fn run(source: &str, seed: Option<u64>, limits: &Limits) -> Outcome {
+    let mut file = tempfile::Builder::new()
+        .suffix(".pseudo")
+        .tempfile()
+        .unwrap();
+    file.write_all(source.as_bytes()).unwrap();
+
+    let mut command = Command::new(env::current_exe().unwrap());
+    command.args(["run", "--interpret"]);
+    if let Some(seed) = seed {
+        command.args(["--seed", &seed.to_string()]);
+    }
+    command
+        .arg(file.path())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    restrict(&mut command, limits);
+    let mut child = command.spawn().expect("failed to start the program");
+
+    let exceeded = Arc::new(AtomicBool::new(false));
+    let stdout = read(child.stdout.take().unwrap(), limits.output_bytes, {
+        let exceeded = exceeded.clone();
+        move || exceeded.store(true, Ordering::SeqCst)
+    });
+    let stderr = read(child.stderr.take().unwrap(), limits.output_bytes, || {});
+
+    let started = Instant::now();
+    let status = loop {
+        if exceeded.load(Ordering::SeqCst) {
+            break Some(Status::OutputLimitExceeded);
+        }
+        if started.elapsed() > limits.wall_clock {
+            break Some(Status::TimeLimitExceeded);
+        }
+        if let Some(status) = child.try_wait().unwrap() {
+            break if status.success() {
+                Some(Status::Success)
+            } else if killed_for_using_too_much_time(status) {
+                Some(Status::TimeLimitExceeded)
+            } else {
+                Some(Status::Failure)
+            };
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    // the program may still be running
+    let _ = child.kill();
+    let _ = child.wait();
+
+    Outcome {
+        status: status.unwrap(),
+        stdout: stdout.join().unwrap(),
+        stderr: stderr.join().unwrap(),
+    }
+}
+
+/// Reads (at most `limit` bytes of) the output on another thread, calling
+/// `exceeded` if there is more output than this.
+fn read(
+    mut output: impl Read + Send + 'static,
+    limit: u64,
+    exceeded: impl FnOnce() + Send + 'static,
+) -> thread::JoinHandle<String> {
+    thread::spawn(move || {
+        let mut buffer = vec![];
+        let _ = (&mut output).take(limit).read_to_end(&mut buffer);
+        if output.read(&mut [0]).map_or(false, |read| read > 0) {
+            exceeded();
+        }
+        String::from_utf8_lossy(&buffer).into_owned()
+    })
+}
+
+#[cfg(unix)]
+fn restrict(command: &mut Command, limits: &Limits) {
+    use std::{io, os::unix::process::CommandExt};
+
+    let Limits {
+        cpu_seconds,
+        memory_bytes,
+        ..
+    } = *limits;
+    // safety: `setrlimit` is async-signal-safe
+    unsafe {
+        command.pre_exec(move || {
+            // the process receives `SIGXCPU` once it reaches the soft limit
+            // (and is killed at the hard limit)
+            let cpu = libc::rlimit {
+                rlim_cur: cpu_seconds as libc::rlim_t,
+                rlim_max: cpu_seconds as libc::rlim_t + 1,
+            };
+            let memory = libc::rlimit {
+                rlim_cur: memory_bytes as libc::rlim_t,
+                rlim_max: memory_bytes as libc::rlim_t,
+            };
+            if libc::setrlimit(libc::RLIMIT_CPU, &cpu) != 0
+                || libc::setrlimit(libc::RLIMIT_AS, &memory) != 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+/// todo: restrict the resources which programs can use on other platforms
+/// (for now, only the time limit is enforced)
+#[cfg(not(unix))]
+fn restrict(_: &mut Command, _: &Limits) {}
+
+#[cfg(unix)]
+fn killed_for_using_too_much_time(status: std::process::ExitStatus) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+
+    matches!(status.signal(), Some(libc::SIGXCPU | libc::SIGKILL))
+}
+
+#[cfg(not(unix))]
+fn killed_for_using_too_much_time(_: std::process::ExitStatus) -> bool {
+    false
+}
@@ -0,0 +1,100 @@
+use logic::session::{Location, Severity};
+use serde_json::json;
+
+use super::{
+    diagnostics, run,
+    sandbox::{Limits, Outcome, Status},
+    RunRequest,
+};
+
+#[test]
+fn syntax_errors_have_locations() {
+    let diagnostics =
+        diagnostics::check("function main()\n  x = (1 + 2\n  return 0\nendfunction\n");
+    assert!(!diagnostics.is_empty());
+    let diagnostic = &diagnostics[0];
+    assert_eq!(diagnostic.severity, Severity::Error);
+    let label = diagnostic
+        .labels
+        .iter()
+        .find(|label| label.primary)
+        .unwrap();
+    assert!(label.start.line >= 2, "{:?}", label.start);
+}
+
+#[test]
+fn type_errors_have_locations() {
+    let diagnostics =
+        diagnostics::check("function main()\n  x = 1 + \"one\"\n  return 0\nendfunction\n");
+    assert!(!diagnostics.is_empty());
+    let label = diagnostics[0]
+        .labels
+        .iter()
+        .find(|label| label.primary)
+        .unwrap();
+    assert_eq!(label.start.line, 2);
+    assert!(label.start.column > 1);
+    assert!(label.end.line > label.start.line || label.end.column > label.start.column);
+}
+
+#[test]
+fn valid_programs_have_no_diagnostics() {
+    assert_eq!(
+        diagnostics::check("function main()\n  print(1)\n  return 0\nendfunction\n"),
+        vec![]
+    );
+}
+
+#[test]
+fn programs_with_warnings_are_run() {
+    let request = RunRequest {
+        source: "function main()\nprint(1)\n    return 0\nendfunction\n".to_owned(),
+        seed: None,
+    };
+    let response = run(&request, &Limits::default());
+    assert!(!response.diagnostics.is_empty());
+    assert!(response
+        .diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.severity == Severity::Warning));
+    // (the sandbox runs the current executable, which is the test binary here,
+    // so only whether the program was run can be checked)
+    assert!(response.outcome.is_some());
+
+    let request = RunRequest {
+        source: "function main()\n  x = 1 + \"one\"\n  return 0\nendfunction\n".to_owned(),
+        seed: None,
+    };
+    assert!(run(&request, &Limits::default()).outcome.is_none());
+}
+
+#[test]
+fn requests() {
+    let request: RunRequest = serde_json::from_str(r#"{"source": "x"}"#).unwrap();
+    assert_eq!(request.source, "x");
+    assert_eq!(request.seed, None);
+
+    let request: RunRequest = serde_json::from_str(r#"{"source": "", "seed": 3}"#).unwrap();
+    assert_eq!(request.seed, Some(3));
+}
+
+#[test]
+fn responses() {
+    let outcome = Outcome {
+        status: Status::TimeLimitExceeded,
+        stdout: "1\n".to_owned(),
+        stderr: String::new(),
+    };
+    assert_eq!(
+        serde_json::to_value(&outcome).unwrap(),
+        json!({ "status": "time_limit_exceeded", "stdout": "1\n", "stderr": "" })
+    );
+    assert_eq!(
+        serde_json::to_value(&Location { line: 1, column: 2 }).unwrap(),
+        json!({ "line": 1, "column": 2 })
+    );
+    assert_eq!(
+        serde_json::to_value(&Severity::Warning).unwrap(),
+        json!("warning")
+    );
+}
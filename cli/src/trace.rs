@@ -0,0 +1,96 @@
+//! `pseudo trace`: runs a program and prints its trace table (see
+//! [`logic::trace`]).
+
+use std::{fs, process};
+
+use logic::{codegen::CodegenOptions, io::Stdio, trace::trace, Session};
+
+/// The amount of fuel which programs are given by default (so that a program
+/// which never finishes does not produce a table which never ends).
+const DEFAULT_FUEL: u64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Csv,
+    Markdown,
+}
+
+fn usage() -> ! {
+    println!(
+        "usage: pseudo trace <file> [--format text|csv|markdown] [--seed <number>] \
+         [--fuel <number>]"
+    );
+    process::exit(1);
+}
+
+/// Prints the trace table (`args` are the arguments which follow `trace`).
+pub fn main(args: &[String]) {
+    let mut file_name = None;
+    let mut format = Format::Text;
+    let mut options = CodegenOptions {
+        fuel: Some(DEFAULT_FUEL),
+        ..CodegenOptions::default()
+    };
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        match flag {
+            "--format" => {
+                format = match value.or_else(|| args.next().map(String::as_str)) {
+                    Some("text") => Format::Text,
+                    Some("csv") => Format::Csv,
+                    Some("markdown") => Format::Markdown,
+                    _ => usage(),
+                }
+            }
+            "--seed" | "--fuel" => {
+                let number = match value
+                    .or_else(|| args.next().map(String::as_str))
+                    .map(str::parse::<u64>)
+                {
+                    Some(Ok(number)) => number,
+                    _ => usage(),
+                };
+                if flag == "--seed" {
+                    options.seed = Some(number);
+                } else {
+                    options.fuel = Some(number);
+                }
+            }
+            _ if flag.starts_with('-') || file_name.is_some() => usage(),
+            _ => file_name = Some(arg),
+        }
+    }
+    let file_name = file_name.unwrap_or_else(|| usage());
+
+    let text = fs::read_to_string(file_name).unwrap_or_else(|error| {
+        println!("`{file_name}` could not be read: {error}");
+        process::exit(1);
+    });
+    let seed = options.seed;
+    let fuel = options.fuel;
+    let mut session = Session::new(options);
+    let id = session.add_source(file_name, text);
+    let compiled = session.compile(id).unwrap_or_else(|diagnostics| {
+        for diagnostic in diagnostics {
+            eprintln!("{diagnostic}");
+        }
+        process::exit(1);
+    });
+
+    let table = trace(compiled.mir(), seed, fuel, Stdio);
+    match format {
+        Format::Text => print!("{table}"),
+        Format::Csv => print!("{}", table.to_csv()),
+        Format::Markdown => print!("{}", table.to_markdown()),
+    }
+    if let Err(error) = table.outcome {
+        eprintln!("error: {error}");
+        process::exit(1);
+    }
+}
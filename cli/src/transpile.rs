@@ -0,0 +1,109 @@
+//! `pseudo transpile`: translates a program into another programming language
+//! (see [`logic::transpile`]).
+
+use std::{fs, process};
+
+use codespan_reporting::{
+    files::SimpleFiles,
+    term::{
+        emit,
+        termcolor::{ColorChoice, StandardStream},
+    },
+};
+use logic::{
+    codegen::{optimised_mir, CodegenOptions},
+    parse,
+    transpile::{c::to_c, python::to_python},
+    ty::type_check,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Python,
+    C,
+}
+
+fn usage() -> ! {
+    println!(
+        "usage: pseudo transpile <file> --to python|c [--seed <seed>] [--fuel <fuel>]\n\n\
+         (`--seed` and `--fuel` have the same meaning as when compiling the program, and are \
+         only used when translating into C)"
+    );
+    process::exit(1);
+}
+
+/// Prints the translated program (`args` are the arguments which follow
+/// `transpile`).
+pub fn main(args: &[String]) {
+    let mut file_name = None;
+    let mut language = None;
+    let mut options = CodegenOptions::default();
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (arg.as_str(), None),
+        };
+        match flag {
+            "--to" => {
+                language = match value.or_else(|| args.next().map(String::as_str)) {
+                    Some("python") => Some(Language::Python),
+                    Some("c") => Some(Language::C),
+                    _ => usage(),
+                }
+            }
+            "--seed" => {
+                options.seed = match value.or_else(|| args.next().map(String::as_str)) {
+                    Some(seed) => Some(seed.parse().unwrap_or_else(|_| usage())),
+                    None => usage(),
+                }
+            }
+            "--fuel" => {
+                options.fuel = match value.or_else(|| args.next().map(String::as_str)) {
+                    Some(fuel) => Some(fuel.parse().unwrap_or_else(|_| usage())),
+                    None => usage(),
+                }
+            }
+            _ if flag.starts_with('-') || file_name.is_some() => usage(),
+            _ => file_name = Some(arg),
+        }
+    }
+    let file_name = file_name.unwrap_or_else(|| usage());
+    let language = language.unwrap_or_else(|| usage());
+
+    let text = fs::read_to_string(file_name).unwrap_or_else(|error| {
+        println!("`{file_name}` could not be read: {error}");
+        process::exit(1);
+    });
+    let mut files = SimpleFiles::new();
+    let file_id = files.add(file_name, text.clone());
+    let mut writer = StandardStream::stderr(ColorChoice::Always);
+    let config = codespan_reporting::term::Config::default();
+
+    let table = match parse::parse_with_prelude(&text) {
+        Ok(table) => table,
+        Err(error) => {
+            emit(&mut writer, &config, &files, &error.report(file_id)).unwrap();
+            process::exit(1);
+        }
+    };
+    let env = match type_check(&table) {
+        Ok(env) => env,
+        Err(error) => {
+            emit(&mut writer, &config, &files, &error.report(file_id, &table)).unwrap();
+            process::exit(1);
+        }
+    };
+
+    match language {
+        Language::Python => print!("{}", to_python(&table, &env, &text)),
+        Language::C => match optimised_mir(&table, &env, &options) {
+            Ok(program) => print!("{}", to_c(&program, options.seed, options.fuel)),
+            Err(error) => {
+                emit(&mut writer, &config, &files, &error.report(file_id)).unwrap();
+                process::exit(1);
+            }
+        },
+    }
+}
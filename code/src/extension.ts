@@ -1,6 +1,6 @@
 'use strict';
 
-import { workspace, ExtensionContext } from 'vscode';
+import { workspace, debug, DebugAdapterExecutable, ExtensionContext } from 'vscode';
 import { LanguageClient, LanguageClientOptions, ServerOptions } from 'vscode-languageclient';
 import { Trace } from 'vscode-jsonrpc';
 
@@ -29,4 +29,8 @@ export function activate(context: ExtensionContext) {
     let disposable = client.start();
 
     context.subscriptions.push(disposable);
+
+    context.subscriptions.push(debug.registerDebugAdapterDescriptorFactory('pseudo', {
+        createDebugAdapterDescriptor: () => new DebugAdapterExecutable('pseudo-dap')
+    }));
 }
@@ -0,0 +1,584 @@
+//! Handles the requests from the editor, and runs the program (using
+//! [`logic::interpret`]) while it is being debugged.
+//!
+//! The program is paused at the start of a statement (i.e. at a
+//! [`Statement::Location`]), at which point its call stack and variables can be
+//! inspected. Only functions written by the user appear in the call stack;
+//! functions from the prelude do not have any locations, so they are stepped
+//! over.
+
+use std::{
+    collections::VecDeque,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::mpsc::{Receiver, Sender, TryRecvError},
+};
+
+use logic::{
+    codegen::CodegenOptions,
+    interpret::{Interpreter, Value},
+    io::Io,
+    mir::{Local, Program, Statement},
+    session::{Compiled, Session},
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::Deserialize;
+use serde_json::{from_value, json, Value as Json};
+
+use crate::protocol::{Event, Message, Request, Response};
+
+/// The id of the only thread which programs have.
+const THREAD_ID: i64 = 1;
+
+/// The connection to the editor.
+pub(crate) struct Adapter {
+    incoming: Receiver<Request>,
+    outgoing: Sender<Message>,
+    /// Requests which were received while the program was running, and which
+    /// have not been handled yet.
+    pending: VecDeque<Request>,
+}
+
+impl Adapter {
+    pub(crate) fn new(incoming: Receiver<Request>, outgoing: Sender<Message>) -> Self {
+        Self {
+            incoming,
+            outgoing,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Returns the next request (`None` once the editor has disconnected).
+    fn next_request(&mut self) -> Option<Request> {
+        self.pending
+            .pop_front()
+            .or_else(|| self.incoming.recv().ok())
+    }
+
+    fn send(&self, message: Message) {
+        // if the editor has gone away there is nobody to tell
+        let _ = self.outgoing.send(message);
+    }
+
+    fn respond(&self, request: &Request, body: Json) {
+        self.send(Message::Response(Response {
+            request_seq: request.seq,
+            success: true,
+            command: request.command.clone(),
+            message: None,
+            body,
+        }))
+    }
+
+    fn fail(&self, request: &Request, message: impl Into<String>) {
+        self.send(Message::Response(Response {
+            request_seq: request.seq,
+            success: false,
+            command: request.command.clone(),
+            message: Some(message.into()),
+            body: Json::Null,
+        }))
+    }
+
+    fn event(&self, event: &str, body: Json) {
+        self.send(Message::Event(Event {
+            event: event.to_owned(),
+            body,
+        }))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LaunchArguments {
+    /// The path to the program.
+    program: PathBuf,
+    /// Pause the program before it runs its first statement.
+    #[serde(default)]
+    stop_on_entry: bool,
+    /// The lines of input which are given to the program.
+    #[serde(default)]
+    input: Vec<String>,
+    /// The seed for the random number generator.
+    seed: Option<u64>,
+}
+
+/// Handles requests until the editor disconnects.
+pub(crate) fn run(mut adapter: Adapter) {
+    // the program is only known once the editor asks us to launch it
+    let (request, arguments) = loop {
+        let request = match adapter.next_request() {
+            Some(request) => request,
+            None => return,
+        };
+        match request.command.as_str() {
+            "initialize" => adapter.respond(
+                &request,
+                json!({
+                    "supportsConfigurationDoneRequest": true,
+                    "supportsTerminateRequest": true,
+                }),
+            ),
+            "launch" => match from_value::<LaunchArguments>(request.arguments.clone()) {
+                Ok(arguments) => break (request, arguments),
+                Err(error) => adapter.fail(&request, format!("Invalid launch arguments: {error}")),
+            },
+            "disconnect" => {
+                adapter.respond(&request, json!({}));
+                return;
+            }
+            _ => adapter.fail(&request, "The program has not been launched yet."),
+        }
+    };
+
+    let text = match fs::read_to_string(&arguments.program) {
+        Ok(text) => text,
+        Err(error) => {
+            adapter.fail(
+                &request,
+                format!(
+                    "`{}` could not be read: {error}",
+                    arguments.program.display()
+                ),
+            );
+            return;
+        }
+    };
+    let mut session = Session::new(CodegenOptions {
+        seed: arguments.seed,
+        ..CodegenOptions::default()
+    });
+    let id = session.add_source(arguments.program.display().to_string(), text);
+    let compiled = match session.compile(id) {
+        Ok(compiled) => compiled,
+        Err(diagnostics) => {
+            let message = diagnostics
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join("\n");
+            adapter.fail(
+                &request,
+                format!("The program could not be compiled.\n{message}"),
+            );
+            return;
+        }
+    };
+    let io = DebugIo {
+        outgoing: adapter.outgoing.clone(),
+        input: arguments.input.clone().into(),
+    };
+    let interpreter = match Interpreter::new(compiled.mir(), arguments.seed, None, io) {
+        Ok(interpreter) => interpreter,
+        Err(error) => {
+            adapter.fail(&request, error.message);
+            return;
+        }
+    };
+
+    adapter.respond(&request, json!({}));
+    // the editor now sends the breakpoints
+    adapter.event("initialized", json!({}));
+
+    Debugger::new(adapter, &compiled, interpreter, &arguments).run();
+}
+
+/// Sends everything which the program prints to the editor.
+struct DebugIo {
+    outgoing: Sender<Message>,
+    input: VecDeque<String>,
+}
+
+impl Io for DebugIo {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.outgoing
+            .send(Message::Event(Event {
+                event: "output".to_owned(),
+                body: json!({ "category": "stdout", "output": text }),
+            }))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the editor has disconnected"))
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        Ok(self.input.pop_front().unwrap_or_default())
+    }
+}
+
+/// How far to run the program before pausing it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    /// Until a breakpoint is reached.
+    Continue,
+    /// To the next statement in the current function (or a function which
+    /// called it).
+    Over,
+    /// To the next statement.
+    In,
+    /// To the next statement in a function which called the current one.
+    Out,
+}
+
+/// Why a running program should be stopped (other than because it reached
+/// the point it was running to).
+enum Interruption {
+    None,
+    Pause,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Paused,
+    /// The program stopped because of an error (it is paused, so that the
+    /// user can see what went wrong, but it cannot be resumed).
+    Failed,
+    Finished,
+}
+
+struct Debugger<'p> {
+    adapter: Adapter,
+    program: &'p Program,
+    interpreter: Interpreter<'p, DebugIo>,
+    state: State,
+    path: PathBuf,
+    stop_on_entry: bool,
+    /// The lines which have a breakpoint on them.
+    breakpoints: FxHashSet<u32>,
+    /// The type of each variable (keyed by the name of the function, and then
+    /// the name of the variable).
+    types: FxHashMap<(String, String), String>,
+}
+
+impl<'p> Debugger<'p> {
+    fn new(
+        adapter: Adapter,
+        compiled: &'p Compiled,
+        interpreter: Interpreter<'p, DebugIo>,
+        arguments: &LaunchArguments,
+    ) -> Self {
+        let mut types = FxHashMap::default();
+        for function in compiled.checked().functions() {
+            for variable in function.variables() {
+                if let Some(ty) = variable.ty {
+                    types.insert((function.name().to_owned(), variable.name), ty.to_string());
+                }
+            }
+        }
+
+        Self {
+            adapter,
+            program: compiled.mir(),
+            interpreter,
+            state: State::Paused,
+            path: arguments.program.clone(),
+            stop_on_entry: arguments.stop_on_entry,
+            breakpoints: FxHashSet::default(),
+            types,
+        }
+    }
+
+    fn run(mut self) {
+        while let Some(request) = self.adapter.next_request() {
+            match request.command.as_str() {
+                "setBreakpoints" => self.set_breakpoints(&request),
+                "setExceptionBreakpoints" => self.adapter.respond(&request, json!({})),
+                "configurationDone" => {
+                    self.adapter.respond(&request, json!({}));
+                    if self.stop_on_entry {
+                        self.resume(Step::In, "entry");
+                    } else {
+                        self.resume(Step::Continue, "step");
+                    }
+                }
+                "threads" => self.adapter.respond(
+                    &request,
+                    json!({ "threads": [{ "id": THREAD_ID, "name": "main" }] }),
+                ),
+                "stackTrace" => self.stack_trace(&request),
+                "scopes" => self.scopes(&request),
+                "variables" => self.variables(&request),
+                "continue" => {
+                    self.adapter
+                        .respond(&request, json!({ "allThreadsContinued": true }));
+                    self.resume(Step::Continue, "step");
+                }
+                "next" => {
+                    self.adapter.respond(&request, json!({}));
+                    self.resume(Step::Over, "step");
+                }
+                "stepIn" => {
+                    self.adapter.respond(&request, json!({}));
+                    self.resume(Step::In, "step");
+                }
+                "stepOut" => {
+                    self.adapter.respond(&request, json!({}));
+                    self.resume(Step::Out, "step");
+                }
+                // the program is not running
+                "pause" => self.adapter.respond(&request, json!({})),
+                "disconnect" | "terminate" => {
+                    self.adapter.respond(&request, json!({}));
+                    if request.command == "terminate" && self.state != State::Finished {
+                        self.adapter.event("terminated", json!({}));
+                    }
+                    return;
+                }
+                command => self
+                    .adapter
+                    .fail(&request, format!("`{command}` is not supported.")),
+            }
+        }
+    }
+
+    /// Whether the path refers to the program which is being debugged.
+    fn is_program(&self, path: &Path) -> bool {
+        path == self.path
+            || matches!(
+                (path.canonicalize(), self.path.canonicalize()),
+                (Ok(a), Ok(b)) if a == b
+            )
+    }
+
+    fn set_breakpoints(&mut self, request: &Request) {
+        #[derive(Deserialize)]
+        struct Source {
+            path: Option<PathBuf>,
+        }
+        #[derive(Deserialize)]
+        struct Breakpoint {
+            line: u32,
+        }
+        #[derive(Deserialize)]
+        struct Arguments {
+            source: Source,
+            #[serde(default)]
+            breakpoints: Vec<Breakpoint>,
+        }
+
+        let arguments = match from_value::<Arguments>(request.arguments.clone()) {
+            Ok(arguments) => arguments,
+            Err(error) => return self.adapter.fail(request, error.to_string()),
+        };
+        let is_program = arguments
+            .source
+            .path
+            .map_or(false, |path| self.is_program(&path));
+        if is_program {
+            self.breakpoints = arguments.breakpoints.iter().map(|bp| bp.line).collect();
+        }
+
+        let lines = self.lines();
+        let breakpoints = arguments
+            .breakpoints
+            .iter()
+            .map(|breakpoint| {
+                if !is_program {
+                    json!({
+                        "verified": false,
+                        "line": breakpoint.line,
+                        "message": "Breakpoints can only be set in the program which is being debugged.",
+                    })
+                } else if lines.contains(&breakpoint.line) {
+                    json!({ "verified": true, "line": breakpoint.line })
+                } else {
+                    json!({
+                        "verified": false,
+                        "line": breakpoint.line,
+                        "message": "There is no code on this line.",
+                    })
+                }
+            })
+            .collect::<Vec<_>>();
+        self.adapter
+            .respond(request, json!({ "breakpoints": breakpoints }));
+    }
+
+    /// Every line which the program can be paused at.
+    fn lines(&self) -> FxHashSet<u32> {
+        self.program
+            .functions
+            .iter()
+            .flat_map(|function| &function.blocks)
+            .flat_map(|block| &block.statements)
+            .filter_map(|statement| match statement {
+                Statement::Location(location) => Some(location.line),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Runs the program (until it reaches the point described by `step`, a
+    /// breakpoint, or the user pauses it).
+    fn resume(&mut self, step: Step, reason: &str) {
+        match self.state {
+            State::Paused => {}
+            State::Failed => return self.finish(1),
+            State::Finished => return self.adapter.event("terminated", json!({})),
+        }
+
+        let depth = self.interpreter.frames().len();
+        loop {
+            match self.interruption() {
+                Interruption::None => {}
+                Interruption::Pause => return self.stopped("pause", None),
+                // the request is handled by `run`
+                Interruption::End => return,
+            }
+            match self.interpreter.run_to_location() {
+                Ok(Some(code)) => return self.finish(code),
+                Ok(None) => {}
+                Err(error) => {
+                    self.state = State::Failed;
+                    self.adapter.event(
+                        "output",
+                        json!({ "category": "stderr", "output": format!("{}\n", error.message) }),
+                    );
+                    return self.stopped("exception", Some(&error.message));
+                }
+            }
+
+            let frames = self.interpreter.frames();
+            let stop = match step {
+                Step::Continue => false,
+                Step::Over => frames.len() <= depth,
+                Step::In => true,
+                Step::Out => frames.len() < depth,
+            };
+            if stop {
+                return self.stopped(reason, None);
+            }
+            let line = frames.last().unwrap().location().map(|l| l.line);
+            if line.map_or(false, |line| self.breakpoints.contains(&line)) {
+                return self.stopped("breakpoint", None);
+            }
+        }
+    }
+
+    /// Checks whether the editor has asked for the program to be paused (or
+    /// for the debugging session to end). Other requests are handled once the
+    /// program has been paused.
+    fn interruption(&mut self) -> Interruption {
+        loop {
+            match self.adapter.incoming.try_recv() {
+                Ok(request) if request.command == "pause" => {
+                    self.adapter.respond(&request, json!({}));
+                    return Interruption::Pause;
+                }
+                Ok(request) => {
+                    let end = matches!(request.command.as_str(), "disconnect" | "terminate");
+                    self.adapter.pending.push_back(request);
+                    if end {
+                        return Interruption::End;
+                    }
+                }
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => return Interruption::None,
+            }
+        }
+    }
+
+    fn stopped(&self, reason: &str, text: Option<&str>) {
+        let mut body = json!({
+            "reason": reason,
+            "threadId": THREAD_ID,
+            "allThreadsStopped": true,
+        });
+        if let Some(text) = text {
+            body["text"] = text.into();
+        }
+        self.adapter.event("stopped", body);
+    }
+
+    fn finish(&mut self, code: i64) {
+        self.state = State::Finished;
+        self.adapter.event("exited", json!({ "exitCode": code }));
+        self.adapter.event("terminated", json!({}));
+    }
+
+    /// The frames which are shown to the user (those for functions which they
+    /// wrote). Each frame's id is its index in the interpreter's call stack
+    /// (plus one, as zero means "no frame" in the protocol).
+    fn stack_trace(&self, request: &Request) {
+        let name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned());
+        let frames = self
+            .interpreter
+            .frames()
+            .iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(index, frame)| {
+                let location = frame.location()?;
+                Some(json!({
+                    "id": index + 1,
+                    "name": frame.function().name,
+                    "source": { "name": name, "path": self.path },
+                    "line": location.line,
+                    "column": location.column,
+                }))
+            })
+            .collect::<Vec<_>>();
+        self.adapter.respond(
+            request,
+            json!({ "stackFrames": frames, "totalFrames": frames.len() }),
+        );
+    }
+
+    fn scopes(&self, request: &Request) {
+        let frame = request.arguments["frameId"].as_i64().unwrap_or_default();
+        self.adapter.respond(
+            request,
+            json!({
+                "scopes": [{
+                    "name": "Locals",
+                    "presentationHint": "locals",
+                    "variablesReference": frame,
+                    "expensive": false,
+                }]
+            }),
+        );
+    }
+
+    /// The variables in a frame (which have been given a value).
+    fn variables(&self, request: &Request) {
+        let reference = request.arguments["variablesReference"]
+            .as_u64()
+            .unwrap_or_default() as usize;
+        let frame = match reference
+            .checked_sub(1)
+            .and_then(|index| self.interpreter.frames().get(index))
+        {
+            Some(frame) => frame,
+            None => return self.adapter.respond(request, json!({ "variables": [] })),
+        };
+
+        let function = frame.function();
+        let variables = function
+            .locals
+            .iter()
+            .enumerate()
+            .filter_map(|(local, decl)| {
+                let name = decl.name.as_ref()?;
+                let value = frame.local(Local(local as u32))?;
+                let ty = self.types.get(&(function.name.clone(), name.clone()));
+                Some(json!({
+                    "name": name,
+                    "value": format_value(value),
+                    "type": ty,
+                    "variablesReference": 0,
+                }))
+            })
+            .collect::<Vec<_>>();
+        self.adapter
+            .respond(request, json!({ "variables": variables }));
+    }
+}
+
+/// Formats the value in the way it would be written in a program.
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Str(string) => format!("{:?}", &**string),
+        value => value.to_string(),
+    }
+}
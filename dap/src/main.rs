@@ -0,0 +1,51 @@
+//! An implementation of the debug adapter protocol, which makes it possible to
+//! step through programs (using the interpreter) in an editor.
+//!
+//! Messages are read from standard input (and responses and events are written
+//! to standard output) by separate threads, so that the program can be paused
+//! while it is running.
+
+#![deny(clippy::disallowed_types)]
+
+mod adapter;
+mod protocol;
+#[cfg(test)]
+mod test;
+
+use std::{
+    io::{self, BufReader},
+    sync::mpsc,
+    thread,
+};
+
+use adapter::Adapter;
+use protocol::{read_message, write_message, Message};
+
+fn main() {
+    let (requests, incoming) = mpsc::channel();
+    let (outgoing, messages) = mpsc::channel::<Message>();
+
+    // this thread is not joined, as it may be blocked reading from standard
+    // input when the adapter exits
+    thread::spawn(move || {
+        let mut stdin = BufReader::new(io::stdin());
+        while let Ok(Some(request)) = read_message(&mut stdin) {
+            if requests.send(request).is_err() {
+                break;
+            }
+        }
+    });
+
+    let writer = thread::spawn(move || {
+        let mut stdout = io::stdout();
+        for (seq, message) in (1..).zip(messages) {
+            if write_message(&mut stdout, seq, &message).is_err() {
+                break;
+            }
+        }
+    });
+
+    adapter::run(Adapter::new(incoming, outgoing));
+
+    writer.join().unwrap();
+}
@@ -0,0 +1,92 @@
+//! The messages which are sent between the editor and the debug adapter (and
+//! how they are encoded).
+//!
+//! Every message is a JSON object, which is preceded by a `Content-Length`
+//! header (in the same way as in the language server protocol).
+
+use std::io::{self, BufRead, Write};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// A request from the editor.
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Request {
+    pub(crate) seq: i64,
+    pub(crate) command: String,
+    #[serde(default)]
+    pub(crate) arguments: Value,
+}
+
+/// A message from the debug adapter to the editor.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum Message {
+    Response(Response),
+    Event(Event),
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Response {
+    pub(crate) request_seq: i64,
+    pub(crate) success: bool,
+    pub(crate) command: String,
+    /// Why the request failed (this is shown to the user).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) message: Option<String>,
+    pub(crate) body: Value,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct Event {
+    pub(crate) event: String,
+    pub(crate) body: Value,
+}
+
+/// Reads the next request. Returns `None` once the input has been closed.
+pub(crate) fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Request>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?,
+            );
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "a message did not have a `Content-Length` header",
+        )
+    })?;
+
+    let mut content = vec![0; length];
+    reader.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+}
+
+/// Writes the message, giving it the sequence number `seq`.
+pub(crate) fn write_message(
+    writer: &mut impl Write,
+    seq: i64,
+    message: &Message,
+) -> io::Result<()> {
+    let mut value = json!(message);
+    value["seq"] = seq.into();
+    let content = value.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{content}", content.len())?;
+    writer.flush()
+}
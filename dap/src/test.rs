@@ -0,0 +1,365 @@
+use std::{
+    collections::VecDeque,
+    fs,
+    io::Cursor,
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use serde_json::{json, Value};
+
+use crate::{
+    adapter::{self, Adapter},
+    protocol::{read_message, write_message, Message, Request},
+};
+
+static PROGRAM: &str = "function main()
+  total = 0
+  i = 1
+  while i != 4
+    total = add(total, i)
+    i = i + 1
+  endwhile
+  print_int(total)
+  return 0
+endfunction
+function add(a, b)
+  sum = a + b
+  return sum
+endfunction
+";
+
+/// Talks to an adapter (which is running on another thread) in the same way
+/// as an editor.
+struct Client {
+    requests: Sender<Request>,
+    messages: Receiver<Message>,
+    seq: i64,
+    /// Events which have been received, but not yet looked at.
+    events: VecDeque<(String, Value)>,
+    /// Everything which the program has printed.
+    output: String,
+    adapter: Option<JoinHandle<()>>,
+}
+
+impl Client {
+    fn new() -> Self {
+        let (requests, incoming) = mpsc::channel();
+        let (outgoing, messages) = mpsc::channel();
+        let adapter = thread::spawn(move || adapter::run(Adapter::new(incoming, outgoing)));
+        Self {
+            requests,
+            messages,
+            seq: 0,
+            events: VecDeque::new(),
+            output: String::new(),
+            adapter: Some(adapter),
+        }
+    }
+
+    fn receive(&mut self) -> Message {
+        self.messages
+            .recv_timeout(Duration::from_secs(10))
+            .expect("the adapter did not send a message")
+    }
+
+    /// Sends the request, and returns the response (which may be a failure).
+    fn try_request(&mut self, command: &str, arguments: Value) -> Result<Value, String> {
+        self.seq += 1;
+        self.requests
+            .send(Request {
+                seq: self.seq,
+                command: command.to_owned(),
+                arguments,
+            })
+            .unwrap();
+        loop {
+            match self.receive() {
+                Message::Response(response) => {
+                    assert_eq!(response.request_seq, self.seq);
+                    assert_eq!(response.command, command);
+                    return if response.success {
+                        Ok(response.body)
+                    } else {
+                        Err(response.message.unwrap())
+                    };
+                }
+                Message::Event(event) if event.event == "output" => {
+                    self.output += event.body["output"].as_str().unwrap()
+                }
+                Message::Event(event) => self.events.push_back((event.event, event.body)),
+            }
+        }
+    }
+
+    fn request(&mut self, command: &str, arguments: Value) -> Value {
+        self.try_request(command, arguments)
+            .unwrap_or_else(|message| panic!("`{command}` failed: {message}"))
+    }
+
+    /// Waits for the next event (other than program output).
+    fn event(&mut self) -> (String, Value) {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return event;
+            }
+            match self.receive() {
+                Message::Event(event) if event.event == "output" => {
+                    self.output += event.body["output"].as_str().unwrap()
+                }
+                Message::Event(event) => return (event.event, event.body),
+                Message::Response(response) => panic!("unexpected response {response:?}"),
+            }
+        }
+    }
+
+    /// Waits for the program to stop, returning why it stopped.
+    fn stopped(&mut self) -> String {
+        let (event, body) = self.event();
+        assert_eq!(event, "stopped", "{body}");
+        body["reason"].as_str().unwrap().to_owned()
+    }
+
+    /// Returns the name and line of every frame in the call stack (innermost
+    /// first).
+    fn stack(&mut self) -> Vec<(String, u64, i64)> {
+        let body = self.request("stackTrace", json!({ "threadId": 1 }));
+        body["stackFrames"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|frame| {
+                (
+                    frame["name"].as_str().unwrap().to_owned(),
+                    frame["line"].as_u64().unwrap(),
+                    frame["id"].as_i64().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the name, value and type of every variable in the frame.
+    fn variables(&mut self, frame: i64) -> Vec<String> {
+        let scopes = self.request("scopes", json!({ "frameId": frame }));
+        let reference = scopes["scopes"][0]["variablesReference"].clone();
+        let body = self.request("variables", json!({ "variablesReference": reference }));
+        body["variables"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variable| {
+                format!(
+                    "{}: {} = {}",
+                    variable["name"].as_str().unwrap(),
+                    variable["type"].as_str().unwrap(),
+                    variable["value"].as_str().unwrap()
+                )
+            })
+            .collect()
+    }
+
+    fn launch(&mut self, program: &Path, stop_on_entry: bool) {
+        self.request("initialize", json!({ "adapterID": "pseudo" }));
+        self.request(
+            "launch",
+            json!({ "program": program, "stopOnEntry": stop_on_entry }),
+        );
+        assert_eq!(self.event().0, "initialized");
+    }
+
+    fn set_breakpoints(&mut self, program: &Path, lines: &[u32]) -> Vec<bool> {
+        let breakpoints = lines
+            .iter()
+            .map(|line| json!({ "line": line }))
+            .collect::<Vec<_>>();
+        let body = self.request(
+            "setBreakpoints",
+            json!({ "source": { "path": program }, "breakpoints": breakpoints }),
+        );
+        body["breakpoints"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|breakpoint| breakpoint["verified"].as_bool().unwrap())
+            .collect()
+    }
+
+    fn disconnect(mut self) {
+        self.request("disconnect", json!({}));
+        self.adapter.take().unwrap().join().unwrap();
+    }
+}
+
+fn write_program(dir: &Path, text: &str) -> std::path::PathBuf {
+    let path = dir.join("program.pseudo");
+    fs::write(&path, text).unwrap();
+    path
+}
+
+#[test]
+fn breakpoints_and_stepping() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = write_program(dir.path(), PROGRAM);
+
+    let mut client = Client::new();
+    client.launch(&program, false);
+    // there is no code on the line with `endwhile`
+    assert_eq!(client.set_breakpoints(&program, &[5, 7]), [true, false]);
+    client.request("configurationDone", json!({}));
+
+    assert_eq!(client.stopped(), "breakpoint");
+    let stack = client.stack();
+    assert_eq!(stack.len(), 1);
+    assert_eq!((stack[0].0.as_str(), stack[0].1), ("main", 5));
+    assert_eq!(
+        client.variables(stack[0].2),
+        ["total: Int = 0", "i: Int = 1"]
+    );
+
+    // step into `add`
+    client.request("stepIn", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "step");
+    let stack = client.stack();
+    assert_eq!(
+        stack
+            .iter()
+            .map(|(name, line, _)| (name.as_str(), *line))
+            .collect::<Vec<_>>(),
+        [("add", 12), ("main", 5)]
+    );
+    // `sum` has not been given a value yet
+    assert_eq!(client.variables(stack[0].2), ["a: Int = 0", "b: Int = 1"]);
+
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "step");
+    let stack = client.stack();
+    assert_eq!((stack[0].0.as_str(), stack[0].1), ("add", 13));
+    assert_eq!(
+        client.variables(stack[0].2),
+        ["a: Int = 0", "b: Int = 1", "sum: Int = 1"]
+    );
+
+    // back out to `main`, which continues with the next line
+    client.request("stepOut", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "step");
+    let stack = client.stack();
+    assert_eq!(stack.len(), 1);
+    assert_eq!(stack[0].1, 6);
+    assert_eq!(
+        client.variables(stack[0].2),
+        ["total: Int = 1", "i: Int = 1"]
+    );
+
+    // stepping over a line does not go into the functions it calls
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "step");
+    assert_eq!(client.stack()[0].1, 4);
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "step");
+    assert_eq!(client.stack()[0].1, 5);
+    client.request("next", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "step");
+    let stack = client.stack();
+    assert_eq!((stack.len(), stack[0].1), (1, 6));
+
+    // the breakpoint is hit again on the next iteration of the loop
+    client.request("continue", json!({ "threadId": 1 }));
+    assert_eq!(client.stopped(), "breakpoint");
+    let stack = client.stack();
+    assert_eq!(
+        client.variables(stack[0].2),
+        ["total: Int = 3", "i: Int = 3"]
+    );
+
+    assert_eq!(client.set_breakpoints(&program, &[]), Vec::<bool>::new());
+    client.request("continue", json!({ "threadId": 1 }));
+    let (event, body) = client.event();
+    assert_eq!(event, "exited");
+    assert_eq!(body["exitCode"], 0);
+    assert_eq!(client.event().0, "terminated");
+    assert_eq!(client.output, "6\n");
+
+    client.disconnect();
+}
+
+#[test]
+fn stopping_on_entry_and_runtime_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = write_program(
+        dir.path(),
+        "function main()\n  x = 10\n  print(x / 0)\n  return 0\nendfunction\n",
+    );
+
+    let mut client = Client::new();
+    client.launch(&program, true);
+    client.request("configurationDone", json!({}));
+    assert_eq!(client.stopped(), "entry");
+    let stack = client.stack();
+    assert_eq!(stack[0].1, 2);
+    assert!(client.variables(stack[0].2).is_empty());
+
+    client.request("continue", json!({ "threadId": 1 }));
+    let (event, body) = client.event();
+    assert_eq!(event, "stopped");
+    assert_eq!(body["reason"], "exception");
+    assert_eq!(body["text"], "A number was divided by zero.");
+    assert_eq!(client.output, "A number was divided by zero.\n");
+    // the variables can still be inspected
+    let stack = client.stack();
+    assert_eq!(stack[0].1, 3);
+    assert_eq!(client.variables(stack[0].2), ["x: Int = 10"]);
+
+    client.request("continue", json!({ "threadId": 1 }));
+    let (event, body) = client.event();
+    assert_eq!(event, "exited");
+    assert_eq!(body["exitCode"], 1);
+    assert_eq!(client.event().0, "terminated");
+
+    client.disconnect();
+}
+
+#[test]
+fn programs_which_do_not_compile() {
+    let dir = tempfile::tempdir().unwrap();
+    let program = write_program(
+        dir.path(),
+        "function main()\n  x = 1 + \"one\"\n  return 0\nendfunction\n",
+    );
+
+    let mut client = Client::new();
+    client.request("initialize", json!({ "adapterID": "pseudo" }));
+    let error = client
+        .try_request("launch", json!({ "program": program }))
+        .unwrap_err();
+    assert!(
+        error.starts_with("The program could not be compiled."),
+        "{error}"
+    );
+}
+
+#[test]
+fn messages_are_framed() {
+    let mut input = Cursor::new(
+        "Content-Length: 43\r\n\r\n{\"seq\":1,\"type\":\"request\",\"command\":\"next\"}",
+    );
+    let request = read_message(&mut input).unwrap().unwrap();
+    assert_eq!((request.seq, request.command.as_str()), (1, "next"));
+    assert_eq!(request.arguments, Value::Null);
+    assert!(read_message(&mut input).unwrap().is_none());
+
+    let mut output = vec![];
+    let message = Message::Event(crate::protocol::Event {
+        event: "initialized".to_owned(),
+        body: json!({}),
+    });
+    write_message(&mut output, 7, &message).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let (header, content) = output.split_once("\r\n\r\n").unwrap();
+    assert_eq!(header, format!("Content-Length: {}", content.len()));
+    assert_eq!(
+        serde_json::from_str::<Value>(content).unwrap(),
+        json!({ "type": "event", "event": "initialized", "body": {}, "seq": 7 })
+    );
+}
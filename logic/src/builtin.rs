@@ -0,0 +1,255 @@
+//! The functions which are built into the language.
+//!
+//! Each builtin is described declaratively (its name, its signature and the
+//! runtime function which implements it). Both the type checker and the code
+//! generator consult this registry, so adding a new builtin only requires an
+//! entry here (and, if it is a new runtime function, its implementation in the
+//! runtime).
+
+use crate::ty::PrimitiveType::{self, Bool, Int, Pointer, Real, StrSlice};
+
+use self::Slot::{Any, Concrete, Generic};
+
+/// The type of a parameter (or of the return value) of a builtin.
+#[derive(Debug, Clone, Copy)]
+pub enum Slot {
+    /// This slot always has the given type.
+    Concrete(PrimitiveType),
+    /// This slot may have any type, but every generic slot in the same
+    /// signature must have the same type. The types which are actually
+    /// supported are determined by [`Symbol::ByType`] (the type checker
+    /// rejects any others).
+    Generic,
+    /// This slot may have any type (independently of every other slot).
+    Any,
+}
+
+/// The runtime function which implements a builtin.
+#[derive(Debug, Clone, Copy)]
+pub enum Symbol {
+    /// The builtin is always implemented by this function.
+    Fixed(&'static str),
+    /// The function to call depends on the type which the generic slots of
+    /// the builtin were instantiated with.
+    ByType(&'static [(PrimitiveType, &'static str)]),
+    /// The builtin prints each of its arguments (separated by spaces, and
+    /// followed by a new line) using the function for the type of that
+    /// argument (records are printed field by field).
+    Print(&'static [(PrimitiveType, &'static str)]),
+}
+
+/// A function which is built into the language.
+#[derive(Debug)]
+pub struct Builtin {
+    /// The name which programs use to call this function.
+    pub name: &'static str,
+    /// The types of the parameters (in order).
+    pub params: &'static [Slot],
+    /// If this is `true`, the last parameter may be given any number of times
+    /// (including none at all).
+    pub variadic: bool,
+    /// The type of the value which the function returns.
+    pub returns: Slot,
+    /// The runtime function which implements the builtin.
+    pub symbol: Symbol,
+    /// If this is `true`, the builtin can only be called from the prelude.
+    pub privileged: bool,
+}
+
+impl Builtin {
+    /// Returns the name of the runtime function which should be called when
+    /// the generic slots of this builtin have the type `generic` (this is
+    /// `None` for builtins which have no generic slots).
+    ///
+    /// For [`Symbol::Print`] this is the function which prints a value of the
+    /// type `generic`.
+    ///
+    /// Returns `None` if the builtin does not support this type.
+    pub fn symbol_for(&self, generic: Option<PrimitiveType>) -> Option<&'static str> {
+        match self.symbol {
+            Symbol::Fixed(symbol) => Some(symbol),
+            Symbol::ByType(symbols) | Symbol::Print(symbols) => symbols
+                .iter()
+                .find(|(ty, _)| Some(*ty) == generic)
+                .map(|(_, symbol)| *symbol),
+        }
+    }
+
+    /// Returns the types which the generic slots of this builtin can have
+    /// (this is empty for builtins which do not have any generic slots).
+    pub fn generic_types(&self) -> Vec<PrimitiveType> {
+        match self.symbol {
+            Symbol::ByType(symbols) => symbols.iter().map(|(ty, _)| *ty).collect(),
+            Symbol::Fixed(_) | Symbol::Print(_) => vec![],
+        }
+    }
+}
+
+/// Every builtin function.
+///
+/// note: the runtime printing functions don't return anything meaningful, but
+/// every call must currently produce a value (so they are given the type
+/// `Int`)
+pub static BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "print",
+        params: &[Any],
+        variadic: true,
+        returns: Concrete(Int),
+        symbol: Symbol::Print(&[
+            (Int, "write_int"),
+            (Real, "write_real"),
+            (Bool, "write_bool"),
+            (StrSlice, "write_string"),
+        ]),
+        privileged: false,
+    },
+    Builtin {
+        name: "str",
+        params: &[Generic],
+        variadic: false,
+        returns: Concrete(StrSlice),
+        symbol: Symbol::ByType(&[
+            (Int, "str_int"),
+            (Real, "str_real"),
+            (Bool, "str_bool"),
+            (StrSlice, "str_string"),
+        ]),
+        privileged: false,
+    },
+    Builtin {
+        name: "print_int",
+        params: &[Concrete(Int)],
+        variadic: false,
+        returns: Concrete(Int),
+        symbol: Symbol::Fixed("print_int"),
+        privileged: false,
+    },
+    Builtin {
+        name: "print_bool",
+        params: &[Concrete(Bool)],
+        variadic: false,
+        returns: Concrete(Int),
+        symbol: Symbol::Fixed("print_bool"),
+        privileged: false,
+    },
+    Builtin {
+        name: "print_real",
+        params: &[Concrete(Real)],
+        variadic: false,
+        returns: Concrete(Int),
+        symbol: Symbol::Fixed("print_real"),
+        privileged: false,
+    },
+    Builtin {
+        name: "input",
+        params: &[],
+        variadic: false,
+        returns: Concrete(StrSlice),
+        symbol: Symbol::Fixed("input"),
+        privileged: false,
+    },
+    Builtin {
+        name: "random",
+        params: &[Generic, Generic],
+        variadic: false,
+        returns: Generic,
+        symbol: Symbol::ByType(&[(Int, "random_int"), (Real, "random_real")]),
+        privileged: false,
+    },
+    // maths
+    Builtin {
+        name: "abs",
+        params: &[Generic],
+        variadic: false,
+        returns: Generic,
+        symbol: Symbol::ByType(&[(Int, "abs_int"), (Real, "abs_real")]),
+        privileged: false,
+    },
+    Builtin {
+        name: "sqrt",
+        params: &[Concrete(Real)],
+        variadic: false,
+        returns: Concrete(Real),
+        symbol: Symbol::Fixed("sqrt_real"),
+        privileged: false,
+    },
+    Builtin {
+        name: "round",
+        params: &[Concrete(Real)],
+        variadic: false,
+        returns: Concrete(Int),
+        symbol: Symbol::Fixed("round_real"),
+        privileged: false,
+    },
+    Builtin {
+        name: "floor",
+        params: &[Concrete(Real)],
+        variadic: false,
+        returns: Concrete(Int),
+        symbol: Symbol::Fixed("floor_real"),
+        privileged: false,
+    },
+    Builtin {
+        name: "ceil",
+        params: &[Concrete(Real)],
+        variadic: false,
+        returns: Concrete(Int),
+        symbol: Symbol::Fixed("ceil_real"),
+        privileged: false,
+    },
+    Builtin {
+        name: "min",
+        params: &[Generic, Generic],
+        variadic: false,
+        returns: Generic,
+        symbol: Symbol::ByType(&[(Int, "min_int"), (Real, "min_real")]),
+        privileged: false,
+    },
+    Builtin {
+        name: "max",
+        params: &[Generic, Generic],
+        variadic: false,
+        returns: Generic,
+        symbol: Symbol::ByType(&[(Int, "max_int"), (Real, "max_real")]),
+        privileged: false,
+    },
+    Builtin {
+        name: "pow",
+        params: &[Generic, Generic],
+        variadic: false,
+        returns: Generic,
+        symbol: Symbol::ByType(&[(Int, "pow_int"), (Real, "pow_real")]),
+        privileged: false,
+    },
+    // memory management (only available to the prelude)
+    Builtin {
+        name: "malloc",
+        params: &[Concrete(Int)],
+        variadic: false,
+        returns: Concrete(Pointer),
+        symbol: Symbol::Fixed("malloc"),
+        privileged: true,
+    },
+    Builtin {
+        name: "realloc",
+        params: &[Concrete(Pointer), Concrete(Int)],
+        variadic: false,
+        returns: Concrete(Pointer),
+        symbol: Symbol::Fixed("realloc"),
+        privileged: true,
+    },
+    Builtin {
+        name: "free",
+        params: &[Concrete(Pointer)],
+        variadic: false,
+        returns: Concrete(Pointer),
+        symbol: Symbol::Fixed("free"),
+        privileged: true,
+    },
+];
+
+/// Finds the builtin with the given name (if there is one).
+pub fn lookup(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
@@ -4,136 +4,194 @@
 //!
 //! todo: report errors properly
 
-use std::env;
+use std::{env, path::Path};
 
 use cranelift_codegen::{
-    entity::EntityRef,
     ir::{self, AbiParam},
     Context,
 };
-use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext, Variable};
+use cranelift_frontend::{FunctionBuilder, FunctionBuilderContext};
 use cranelift_jit::JITModule;
-use cranelift_module::{Linkage, Module};
+use cranelift_module::{DataContext, DataId, Linkage, Module};
+use cranelift_object::ObjectModule;
 
 use crate::{
-    codegen::make_module::make_module_for_compiler_host_architecture,
-    diagnostics::{
-        position::Position,
-        reportable_error::ReportableError,
-        span::{HasSpan, Span},
-    },
-    parse::table::{ItemKind, ParseTable},
-    ty::PrimitiveType,
-};
-use crate::{
-    diagnostics::reportable_error::ReportableResult,
-    ty::{Ty, TyEnv},
+    codegen::make_module::{make_jit_module, make_object_module},
+    diagnostics::{position::Position, reportable_error::ReportableError, span::Span},
+    mir::{self, opt::OptLevel, Callee, Rvalue, Statement, Type},
+    parse::table::ParseTable,
+    runtime,
 };
 
-use super::func::FunctionCompiler;
+use super::{
+    debug::DebugInfo,
+    func::FunctionCompiler,
+    target::{Target, TargetError},
+};
 
 /// The core compiler struct.
-pub struct Codegen<'i> {
+///
+/// `M` is the module which the code is generated into: either a
+/// [`JITModule`] (which loads the code into the compiler's own process, so
+/// that it can be run immediately), or an [`ObjectModule`] (which produces an
+/// object file for any supported target).
+pub struct Codegen<M: Module> {
     context: Context,
-    ty_env: &'i TyEnv,
-    table: &'i ParseTable<'i>,
-    module: JITModule,
+    module: M,
+    /// The counter which holds the amount of fuel the program has left (see
+    /// [`crate::codegen::CodegenOptions::fuel`]). This is `None` if the
+    /// amount of fuel is unlimited (in which case no fuel is used at all).
+    fuel: Option<DataId>,
+    /// The flag which runtime functions set if they fail (see
+    /// [`runtime::FALLIBLE`]). This is `None` if the program does not call any
+    /// of the runtime functions which can fail.
+    failed: Option<DataId>,
+    /// The debug information for the functions which have been compiled (this
+    /// is only collected for object files, when it has been asked for).
+    debug_info: Option<DebugInfo>,
+}
+
+/// The compiled program.
+pub struct Compiled {
+    /// The `main` function.
+    pub main: *const u8,
+    /// The amount of fuel the program has left (this is negative once the
+    /// program has run out of fuel).
+    pub fuel: Option<*mut i64>,
+    /// This is non-zero once a runtime function has failed.
+    pub failed: Option<*mut u8>,
 }
 
-// todo: pointer types
-/// Retrieves the Cranelift type of a Pseudocompiler type.
-pub fn cranelift_of_ty_module(module: &JITModule, ty: Ty) -> ir::Type {
+/// Retrieves the Cranelift type of a MIR type.
+pub fn cranelift_of_ty_module(module: &impl Module, ty: Type) -> ir::Type {
     match ty {
-        Ty::PrimitiveType(PrimitiveType::Int) => ir::types::I64,
-        Ty::PrimitiveType(PrimitiveType::Bool) => ir::types::B1,
-        Ty::PrimitiveType(PrimitiveType::StrSlice) => module.target_config().pointer_type(),
-        // todo: compile structs (using stack slots)
-        Ty::Record { ref_: _ } => todo!(),
-        Ty::PrimitiveType(PrimitiveType::Pointer) => module.target_config().pointer_type(),
+        Type::Int => ir::types::I64,
+        Type::Real => ir::types::F64,
+        Type::Bool => ir::types::B1,
+        // records are stored on the stack, and are referred to using a pointer
+        Type::Str | Type::Pointer | Type::Record => module.target_config().pointer_type(),
     }
 }
 
-impl<'i> Codegen<'i> {
-    /// Create a new instance of the compiler.
-    pub fn new(ty_env: &'i TyEnv, table: &'i ParseTable<'i>) -> Self {
-        let module = make_module_for_compiler_host_architecture();
+impl Codegen<JITModule> {
+    /// Create a new instance of the compiler, which generates code for the
+    /// machine the compiler is running on.
+    ///
+    /// This fails if a library which the program uses could not be loaded.
+    pub fn new(
+        table: &ParseTable,
+        opt_level: OptLevel,
+        fuel: Option<u64>,
+    ) -> Result<Self, ReportableError> {
+        Ok(Self::with_module(make_jit_module(table, opt_level)?, fuel))
+    }
+
+    pub fn finish(mut self) -> Result<Compiled, ReportableError> {
+        self.module.finalize_definitions();
+        let main_func = match self.module.get_name("main") {
+            Some(cranelift_module::FuncOrDataId::Func(func)) => func,
+            Some(cranelift_module::FuncOrDataId::Data(_)) => {
+                panic!("should not have data with name `main`")
+            }
+            None => {
+                return Err(ReportableError::new(
+                    Span::new(Position::default(), Position::default()),
+                    "Your program does not have a `main` function.".to_owned(),
+                ))
+            }
+        };
+        Ok(Compiled {
+            main: self.module.get_finalized_function(main_func),
+            fuel: self
+                .fuel
+                .map(|fuel| self.module.get_finalized_data(fuel).0 as *mut i64),
+            failed: self
+                .failed
+                .map(|failed| self.module.get_finalized_data(failed).0 as *mut u8),
+        })
+    }
+}
+
+impl Codegen<ObjectModule> {
+    /// Create a new instance of the compiler, which produces an object file
+    /// for the given target.
+    ///
+    /// If `debug_info` is the path of the source file, the object file also
+    /// contains DWARF debug information which refers to it.
+    pub fn for_target(
+        target: &Target,
+        opt_level: OptLevel,
+        fuel: Option<u64>,
+        debug_info: Option<&Path>,
+    ) -> Result<Self, TargetError> {
+        let module = make_object_module(target, opt_level)?;
+        let debug_info = match debug_info {
+            Some(source) => Some(DebugInfo::new(target, module.isa(), source)?),
+            None => None,
+        };
+        Ok(Self {
+            debug_info,
+            ..Self::with_module(module, fuel)
+        })
+    }
+
+    /// Returns the contents of the object file.
+    pub fn emit(self) -> Vec<u8> {
+        let mut product = self.module.finish();
+        if let Some(debug_info) = self.debug_info {
+            debug_info.write(&mut product);
+        }
+        product
+            .emit()
+            .expect("the object file could not be written")
+    }
+}
+
+impl<M: Module> Codegen<M> {
+    fn with_module(mut module: M, fuel: Option<u64>) -> Self {
+        let fuel = fuel.map(|fuel| {
+            let fuel = i64::try_from(fuel).unwrap_or(i64::MAX);
+            define_data(&mut module, 8, Box::new(fuel.to_ne_bytes()))
+        });
         Self {
             context: module.make_context(),
-            ty_env,
-            table,
             module,
+            fuel,
+            failed: None,
+            debug_info: None,
         }
     }
 
     /// Convert the given type into the corresponding Cranelift type.
-    ///
-    /// TODO: add support for more complex objects
-    fn cranelift_of_ty(&self, ty: Ty) -> ir::Type {
+    fn cranelift_of_ty(&self, ty: Type) -> ir::Type {
         cranelift_of_ty_module(&self.module, ty)
     }
 
-    /// Transforms the provided AST into Cranelift IR.
-    pub fn compile(&mut self, table: &ParseTable<'i>) -> ReportableResult {
-        let functions = table.root.1.inner.iter().filter_map(|item| {
-            if let ItemKind::Func = item.item_kind {
-                table.func.get(&item.id)
-            } else {
-                None
-            }
-        });
-
+    /// Transforms the provided MIR into Cranelift IR.
+    pub fn compile(&mut self, program: &mir::Program) {
         let mut function_builder_context = FunctionBuilderContext::new();
 
-        for func in functions {
-            // set up the signature
-            let returns = if table.get_ident(func.name).inner() == "print_int"
-                || table.get_ident(func.name).inner() == "print"
-            {
-                self.cranelift_of_ty(Ty::PrimitiveType(PrimitiveType::Int))
-            } else if let Some(ty) = self
-                .ty_env
-                .ty_of(func.name.id)
-                .map(|x| self.cranelift_of_ty(x))
-            {
-                ty
-            } else {
-                return Err(ReportableError::new(
-                    table.get_ident(func.name).span(table),
-                    "The return type of this function could not be deduced.".to_owned(),
-                ));
-            };
-            let returns = AbiParam::new(returns);
-            self.context.func.signature.returns = vec![returns];
+        if calls_fallible(program) {
+            self.failed = Some(define_data(&mut self.module, 1, Box::new([0])));
+        }
 
-            let mut parameters = Vec::with_capacity(func.parameters.len());
-
-            for ident in &func.parameters {
-                if let Some(ty) = self.ty_env.ty_of(ident.id) {
-                    parameters.push(AbiParam::new(self.cranelift_of_ty(ty)));
-                } else {
-                    return Err(ReportableError::new(
-                        self.table.get_ident(*ident).span(table),
-                        "A type of variable could not be established for this function parameter."
-                            .to_owned(),
-                    ));
-                }
+        for function in &program.functions {
+            if self.debug_info.is_some() {
+                self.context.func.collect_debug_info();
             }
 
-            let parameters = func
-                .parameters
-                .iter()
-                .map(|ident| self.ty_env.ty_of(ident.id).unwrap())
-                .map(|x| self.cranelift_of_ty(x))
-                .map(AbiParam::new)
-                .collect::<Vec<_>>();
-            for param in parameters {
+            // set up the signature
+            let returns = AbiParam::new(self.cranelift_of_ty(function.returns));
+            self.context.func.signature.returns = vec![returns];
+            for param in &function.params {
+                let param = AbiParam::new(self.cranelift_of_ty(function.local_ty(*param)));
                 self.context.func.signature.params.push(param);
             }
 
             let func_id = self
                 .module
                 .declare_function(
-                    table.get_ident(func.name).inner(),
+                    &function.name,
                     Linkage::Export,
                     &self.context.func.signature,
                 )
@@ -142,33 +200,16 @@ impl<'i> Codegen<'i> {
             let mut function_builder =
                 FunctionBuilder::new(&mut self.context.func, &mut function_builder_context);
 
-            let entry_block = function_builder.create_block();
-
-            function_builder.append_block_params_for_function_params(entry_block);
-
-            function_builder.switch_to_block(entry_block);
-
-            function_builder.seal_block(entry_block);
+            let mut function_compiler = FunctionCompiler::new(
+                &mut function_builder,
+                &mut self.module,
+                program,
+                function,
+                self.fuel,
+                self.failed,
+            );
 
-            for (i, param) in func.parameters.iter().enumerate() {
-                let var = Variable::new(param.id.as_u32() as usize);
-                function_builder.declare_var(
-                    var,
-                    self.ty_env
-                        .ty_of(param.id)
-                        .map(|x| cranelift_of_ty_module(&self.module, x))
-                        .unwrap(),
-                );
-
-                let val = function_builder.block_params(entry_block)[i];
-
-                function_builder.def_var(var, val);
-            }
-
-            let mut function_compiler =
-                FunctionCompiler::new(&mut function_builder, self.ty_env, &mut self.module);
-
-            function_compiler.compile_block(table.get_block(&func.block), table)?;
+            function_compiler.compile();
 
             function_compiler.builder.finalize();
 
@@ -176,30 +217,50 @@ impl<'i> Codegen<'i> {
                 println!("{}", function_compiler.builder.func);
             }
 
+            let locations = function_compiler.locations;
+
             self.module
                 .define_function(func_id, &mut self.context)
                 .unwrap();
 
+            if let Some(debug_info) = &mut self.debug_info {
+                debug_info.add_function(
+                    func_id,
+                    function,
+                    &locations,
+                    self.context.compiled_code().unwrap(),
+                    self.module.isa(),
+                );
+            }
+
             self.module.clear_context(&mut self.context);
         }
-
-        Ok(())
     }
+}
 
-    pub fn finish(mut self) -> Result<*const u8, ReportableError> {
-        self.module.finalize_definitions();
-        let main_func = match self.module.get_name("main") {
-            Some(cranelift_module::FuncOrDataId::Func(func)) => func,
-            Some(cranelift_module::FuncOrDataId::Data(_)) => {
-                panic!("should not have data with name `main`")
-            }
-            None => {
-                return Err(ReportableError::new(
-                    Span::new(Position::default(), Position::default()),
-                    "Your program does not have a `main` function.".to_owned(),
-                ))
+/// Defines some (writable) data, which is only used by the program itself.
+fn define_data(module: &mut impl Module, align: u64, contents: Box<[u8]>) -> DataId {
+    let id = module.declare_anonymous_data(true, false).unwrap();
+    let mut data_ctx = DataContext::new();
+    data_ctx.set_align(align);
+    data_ctx.define(contents);
+    module.define_data(id, &data_ctx).unwrap();
+    id
+}
+
+/// Returns `true` if the program calls any of the runtime functions which can
+/// fail (see [`runtime::FALLIBLE`]).
+fn calls_fallible(program: &mir::Program) -> bool {
+    program
+        .functions
+        .iter()
+        .flat_map(|function| &function.blocks)
+        .flat_map(|block| &block.statements)
+        .any(|statement| match statement {
+            Statement::Assign(_, Rvalue::Call(Callee::Native(symbol), _))
+            | Statement::Eval(Rvalue::Call(Callee::Native(symbol), _)) => {
+                runtime::FALLIBLE.contains(&symbol.as_str())
             }
-        };
-        Ok(self.module.get_finalized_function(main_func))
-    }
+            _ => false,
+        })
 }
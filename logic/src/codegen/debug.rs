@@ -0,0 +1,520 @@
+//! Produces DWARF debug information for object files, so that debuggers can
+//! show which line of the `.pseudo` source code is being run (and the values
+//! of the variables in it).
+//!
+//! This consists of
+//! - a line table, which maps every instruction which Cranelift produced to
+//!   the line (and column) of the code it was produced from (using the
+//!   [`Statement::Location`](crate::mir::Statement::Location) markers in the
+//!   MIR)
+//! - a `DW_TAG_subprogram` for every function, which contains a
+//!   `DW_TAG_formal_parameter` or `DW_TAG_variable` for every variable, whose
+//!   location is the register which Cranelift stored it in
+
+use std::{env, path::Path};
+
+use cranelift_codegen::{
+    ir::{Endianness, LabelValueLoc, ValueLabel},
+    isa::TargetIsa,
+    CompiledCode,
+};
+use cranelift_module::FuncId;
+use cranelift_object::{
+    object::{
+        write::{Relocation, StandardSegment},
+        RelocationEncoding, RelocationKind, SectionKind,
+    },
+    ObjectProduct,
+};
+use gimli::{
+    constants,
+    write::{
+        Address, AttributeValue, DwarfUnit, EndianVec, Expression, FileId, LineProgram, LineString,
+        Location, LocationList, Range, RangeList, Sections, UnitEntryId, Writer,
+    },
+    Encoding, Format, LineEncoding, Register, RunTimeEndian, SectionId,
+};
+use rustc_hash::FxHashMap;
+use target_lexicon::BinaryFormat;
+
+use crate::mir::{self, Local, SourceLocation, Type};
+
+use super::target::{Target, TargetError};
+
+/// The debug information for the functions which have been compiled so far.
+pub(crate) struct DebugInfo {
+    dwarf: DwarfUnit,
+    /// The source file (every function comes from the same file).
+    file: FileId,
+    /// The functions which have been compiled, in order. Addresses in the
+    /// debug information refer to the start of a function by its index in
+    /// this list (see [`Address::Symbol`]).
+    functions: Vec<FuncId>,
+    /// The code of every function (this is the `DW_AT_ranges` of the
+    /// compilation unit).
+    ranges: Vec<Range>,
+    types: FxHashMap<Type, UnitEntryId>,
+    endian: RunTimeEndian,
+}
+
+impl DebugInfo {
+    /// Starts the debug information for the given source file.
+    ///
+    /// This fails for targets whose object files are not ELF files (putting
+    /// debug information into Mach-O and COFF files works differently, and is
+    /// not supported yet).
+    pub(crate) fn new(
+        target: &Target,
+        isa: &dyn TargetIsa,
+        source: &Path,
+    ) -> Result<Self, TargetError> {
+        if target.triple().binary_format != BinaryFormat::Elf {
+            return Err(TargetError {
+                message: format!(
+                    "Debug information cannot be generated for `{target}` (it can only be \
+                     generated for targets which use ELF object files, such as Linux)."
+                ),
+            });
+        }
+
+        let encoding = Encoding {
+            format: Format::Dwarf32,
+            version: 4,
+            address_size: isa.pointer_bytes(),
+        };
+        let comp_dir = env::current_dir()
+            .map(|dir| dir.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| ".".to_owned());
+        let name = source.to_string_lossy().into_owned();
+
+        let mut dwarf = DwarfUnit::new(encoding);
+        dwarf.unit.line_program = LineProgram::new(
+            encoding,
+            LineEncoding::default(),
+            LineString::String(comp_dir.clone().into_bytes()),
+            LineString::String(name.clone().into_bytes()),
+            None,
+        );
+        let directory = dwarf.unit.line_program.default_directory();
+        let file = dwarf.unit.line_program.add_file(
+            LineString::String(name.clone().into_bytes()),
+            directory,
+            None,
+        );
+
+        let root = dwarf.unit.root();
+        let entry = dwarf.unit.get_mut(root);
+        entry.set(
+            constants::DW_AT_producer,
+            AttributeValue::String(b"pseudo".to_vec()),
+        );
+        // there is no language code for our language; C is the closest (and
+        // makes debuggers use C's syntax for expressions, which mostly works)
+        entry.set(
+            constants::DW_AT_language,
+            AttributeValue::Language(constants::DW_LANG_C99),
+        );
+        entry.set(
+            constants::DW_AT_name,
+            AttributeValue::String(name.into_bytes()),
+        );
+        entry.set(
+            constants::DW_AT_comp_dir,
+            AttributeValue::String(comp_dir.into_bytes()),
+        );
+
+        Ok(Self {
+            dwarf,
+            file,
+            functions: vec![],
+            ranges: vec![],
+            types: FxHashMap::default(),
+            endian: match isa.endianness() {
+                Endianness::Little => RunTimeEndian::Little,
+                Endianness::Big => RunTimeEndian::Big,
+            },
+        })
+    }
+
+    /// Adds the debug information for a function which has just been
+    /// compiled. `locations` are the locations which the instructions in the
+    /// function were marked with (see [`super::func::FunctionCompiler`]).
+    pub(crate) fn add_function(
+        &mut self,
+        id: FuncId,
+        function: &mir::Function,
+        locations: &[SourceLocation],
+        code: &CompiledCode,
+        isa: &dyn TargetIsa,
+    ) {
+        let symbol = self.functions.len();
+        self.functions.push(id);
+        let start = Address::Symbol { symbol, addend: 0 };
+        let size = u64::from(code.buffer.total_size());
+        self.ranges.push(Range::StartLength {
+            begin: start,
+            length: size,
+        });
+
+        // functions from the prelude have no location (they come from a
+        // different source file), so they have no line table
+        if let Some(location) = function.location {
+            let line_program = &mut self.dwarf.unit.line_program;
+            line_program.begin_sequence(Some(start));
+            let mut previous = None;
+            // the prologue is attributed to the line which the function is
+            // defined on
+            let rows = std::iter::once((0, location)).chain(
+                code.buffer
+                    .get_srclocs_sorted()
+                    .iter()
+                    .filter(|srcloc| !srcloc.loc.is_default())
+                    .map(|srcloc| (srcloc.start, locations[srcloc.loc.bits() as usize])),
+            );
+            for (offset, location) in rows {
+                if previous == Some(location) {
+                    continue;
+                }
+                previous = Some(location);
+                let row = line_program.row();
+                row.address_offset = u64::from(offset);
+                row.file = self.file;
+                row.line = u64::from(location.line);
+                row.column = u64::from(location.column);
+                line_program.generate_row();
+            }
+            line_program.end_sequence(size);
+        }
+
+        let returns = self.ty(function.returns);
+        let root = self.dwarf.unit.root();
+        let subprogram = self.dwarf.unit.add(root, constants::DW_TAG_subprogram);
+        let entry = self.dwarf.unit.get_mut(subprogram);
+        entry.set(
+            constants::DW_AT_name,
+            AttributeValue::String(function.name.as_bytes().to_vec()),
+        );
+        entry.set(constants::DW_AT_external, AttributeValue::Flag(true));
+        entry.set(constants::DW_AT_low_pc, AttributeValue::Address(start));
+        entry.set(constants::DW_AT_high_pc, AttributeValue::Udata(size));
+        entry.set(constants::DW_AT_type, AttributeValue::UnitRef(returns));
+        if let Some(location) = function.location {
+            entry.set(
+                constants::DW_AT_decl_file,
+                AttributeValue::FileIndex(Some(self.file)),
+            );
+            entry.set(
+                constants::DW_AT_decl_line,
+                AttributeValue::Udata(u64::from(location.line)),
+            );
+        }
+
+        // the parameters come first (in order), followed by the rest of the
+        // variables
+        let variables = function.params.iter().copied().chain(
+            (0..function.locals.len() as u32)
+                .map(Local)
+                .filter(|local| !function.params.contains(local)),
+        );
+        for local in variables {
+            let decl = &function.locals[local.0 as usize];
+            let name = match &decl.name {
+                Some(name) => name,
+                None => continue,
+            };
+
+            // every range of code where the variable is stored in a register
+            // (values which have been spilled to the stack are not described)
+            let mut locations = vec![];
+            for range in code
+                .value_labels_ranges
+                .get(&ValueLabel::from_u32(local.0))
+                .into_iter()
+                .flatten()
+            {
+                let register = match range.loc {
+                    LabelValueLoc::Reg(reg) => match isa.map_regalloc_reg_to_dwarf(reg) {
+                        Ok(register) => register,
+                        Err(_) => continue,
+                    },
+                    LabelValueLoc::SPOffset(_) => continue,
+                };
+                let (begin, end) = (
+                    u64::from(range.start).min(size),
+                    u64::from(range.end).min(size),
+                );
+                if begin >= end {
+                    continue;
+                }
+                let mut expression = Expression::new();
+                expression.op_reg(Register(register));
+                locations.push(Location::StartLength {
+                    begin: Address::Symbol {
+                        symbol,
+                        addend: begin as i64,
+                    },
+                    length: end - begin,
+                    data: expression,
+                });
+            }
+
+            let ty = self.ty(decl.ty);
+            let tag = if function.params.contains(&local) {
+                constants::DW_TAG_formal_parameter
+            } else {
+                constants::DW_TAG_variable
+            };
+            let variable = self.dwarf.unit.add(subprogram, tag);
+            let locations = (!locations.is_empty())
+                .then(|| self.dwarf.unit.locations.add(LocationList(locations)));
+            let entry = self.dwarf.unit.get_mut(variable);
+            entry.set(
+                constants::DW_AT_name,
+                AttributeValue::String(name.as_bytes().to_vec()),
+            );
+            entry.set(constants::DW_AT_type, AttributeValue::UnitRef(ty));
+            if let Some(locations) = locations {
+                entry.set(
+                    constants::DW_AT_location,
+                    AttributeValue::LocationListRef(locations),
+                );
+            }
+        }
+    }
+
+    /// Returns the DIE which describes the type (creating it if necessary).
+    fn ty(&mut self, ty: Type) -> UnitEntryId {
+        if let Some(id) = self.types.get(&ty) {
+            return *id;
+        }
+
+        let root = self.dwarf.unit.root();
+        let address_size = self.dwarf.unit.address_size();
+        let id = match ty {
+            Type::Int | Type::Real | Type::Bool => {
+                let (encoding, size) = match ty {
+                    Type::Int => (constants::DW_ATE_signed, 8),
+                    Type::Real => (constants::DW_ATE_float, 8),
+                    _ => (constants::DW_ATE_boolean, 1),
+                };
+                let id = self.dwarf.unit.add(root, constants::DW_TAG_base_type);
+                let entry = self.dwarf.unit.get_mut(id);
+                entry.set(
+                    constants::DW_AT_encoding,
+                    AttributeValue::Encoding(encoding),
+                );
+                entry.set(constants::DW_AT_byte_size, AttributeValue::Data1(size));
+                id
+            }
+            Type::Str | Type::Pointer | Type::Record => {
+                // strings point to (null-terminated) characters; what other
+                // pointers point to is not described
+                let pointee = (ty == Type::Str).then(|| {
+                    let id = self.dwarf.unit.add(root, constants::DW_TAG_base_type);
+                    let entry = self.dwarf.unit.get_mut(id);
+                    entry.set(
+                        constants::DW_AT_name,
+                        AttributeValue::String(b"char".to_vec()),
+                    );
+                    entry.set(
+                        constants::DW_AT_encoding,
+                        AttributeValue::Encoding(constants::DW_ATE_unsigned_char),
+                    );
+                    entry.set(constants::DW_AT_byte_size, AttributeValue::Data1(1));
+                    id
+                });
+                let id = self.dwarf.unit.add(root, constants::DW_TAG_pointer_type);
+                let entry = self.dwarf.unit.get_mut(id);
+                entry.set(
+                    constants::DW_AT_byte_size,
+                    AttributeValue::Data1(address_size),
+                );
+                if let Some(pointee) = pointee {
+                    entry.set(constants::DW_AT_type, AttributeValue::UnitRef(pointee));
+                }
+                id
+            }
+        };
+        self.dwarf.unit.get_mut(id).set(
+            constants::DW_AT_name,
+            AttributeValue::String(ty.to_string().into_bytes()),
+        );
+        self.types.insert(ty, id);
+        id
+    }
+
+    /// Adds the debug information to the object file.
+    pub(crate) fn write(mut self, product: &mut ObjectProduct) {
+        let root = self.dwarf.unit.root();
+        let ranges = self.dwarf.unit.ranges.add(RangeList(self.ranges));
+        let entry = self.dwarf.unit.get_mut(root);
+        entry.set(
+            constants::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(0)),
+        );
+        entry.set(
+            constants::DW_AT_ranges,
+            AttributeValue::RangeListRef(ranges),
+        );
+
+        let mut sections = Sections::new(DebugSection::new(self.endian));
+        self.dwarf
+            .write(&mut sections)
+            .expect("the debug information could not be written");
+
+        // every section is added before any relocations, because sections
+        // can refer to each other
+        let mut section_ids = FxHashMap::default();
+        sections
+            .for_each(|id, section| {
+                if !section.data.slice().is_empty() {
+                    let segment = product.object.segment_name(StandardSegment::Debug).to_vec();
+                    let section_id = product.object.add_section(
+                        segment,
+                        id.name().as_bytes().to_vec(),
+                        SectionKind::Debug,
+                    );
+                    product
+                        .object
+                        .set_section_data(section_id, section.data.slice().to_vec(), 1);
+                    section_ids.insert(id, section_id);
+                }
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+
+        let functions = self.functions;
+        sections
+            .for_each(|id, section| {
+                for relocation in &section.relocations {
+                    let (symbol, addend) = match relocation.target {
+                        RelocationTarget::Function(index) => {
+                            (product.function_symbol(functions[index]), relocation.addend)
+                        }
+                        RelocationTarget::Section(target) => (
+                            product.object.section_symbol(section_ids[&target]),
+                            relocation.addend,
+                        ),
+                    };
+                    product
+                        .object
+                        .add_relocation(
+                            section_ids[&id],
+                            Relocation {
+                                offset: relocation.offset as u64,
+                                size: relocation.size * 8,
+                                kind: RelocationKind::Absolute,
+                                encoding: RelocationEncoding::Generic,
+                                symbol,
+                                addend,
+                            },
+                        )
+                        .expect("the debug information could not be relocated");
+                }
+                Ok::<_, ()>(())
+            })
+            .unwrap();
+    }
+}
+
+/// A section of debug information, along with the relocations which need to
+/// be applied to it once the addresses of the functions (and the offsets of
+/// the other sections) are known.
+#[derive(Clone)]
+struct DebugSection {
+    data: EndianVec<RunTimeEndian>,
+    relocations: Vec<DebugRelocation>,
+}
+
+#[derive(Clone)]
+struct DebugRelocation {
+    /// Where the relocation is applied (relative to the start of the
+    /// section).
+    offset: usize,
+    /// The size of the value (in bytes).
+    size: u8,
+    target: RelocationTarget,
+    addend: i64,
+}
+
+#[derive(Clone, Copy)]
+enum RelocationTarget {
+    /// The start of the function (this is the function's index in
+    /// [`DebugInfo::functions`]).
+    Function(usize),
+    /// The start of another section of debug information.
+    Section(SectionId),
+}
+
+impl DebugSection {
+    fn new(endian: RunTimeEndian) -> Self {
+        Self {
+            data: EndianVec::new(endian),
+            relocations: vec![],
+        }
+    }
+}
+
+impl Writer for DebugSection {
+    type Endian = RunTimeEndian;
+
+    fn endian(&self) -> Self::Endian {
+        self.data.endian()
+    }
+
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> gimli::write::Result<()> {
+        self.data.write(bytes)
+    }
+
+    fn write_at(&mut self, offset: usize, bytes: &[u8]) -> gimli::write::Result<()> {
+        self.data.write_at(offset, bytes)
+    }
+
+    fn write_address(&mut self, address: Address, size: u8) -> gimli::write::Result<()> {
+        match address {
+            Address::Constant(value) => self.write_udata(value, size),
+            Address::Symbol { symbol, addend } => {
+                self.relocations.push(DebugRelocation {
+                    offset: self.len(),
+                    size,
+                    target: RelocationTarget::Function(symbol),
+                    addend,
+                });
+                self.write_udata(0, size)
+            }
+        }
+    }
+
+    fn write_offset(
+        &mut self,
+        value: usize,
+        section: SectionId,
+        size: u8,
+    ) -> gimli::write::Result<()> {
+        let offset = self.len();
+        self.write_udata(0, size)?;
+        self.write_offset_at(offset, value, section, size)
+    }
+
+    fn write_offset_at(
+        &mut self,
+        offset: usize,
+        value: usize,
+        section: SectionId,
+        size: u8,
+    ) -> gimli::write::Result<()> {
+        self.relocations.push(DebugRelocation {
+            offset,
+            size,
+            target: RelocationTarget::Section(section),
+            addend: value as i64,
+        });
+        // the offset is also written directly, for tools which read the
+        // object file without applying the relocations
+        self.write_udata_at(offset, value as u64, size)
+    }
+}
@@ -1,174 +1,510 @@
-use cranelift_codegen::ir::InstBuilder;
-use cranelift_frontend::FunctionBuilder;
-use cranelift_jit::JITModule;
+use cranelift_codegen::ir::{
+    self,
+    condcodes::{FloatCC, IntCC},
+    AbiParam, InstBuilder, ValueLabel,
+};
+use cranelift_frontend::{FunctionBuilder, Variable};
+use cranelift_module::{DataContext, DataId, Linkage, Module};
 
 use crate::{
-    diagnostics::{
-        reportable_error::{ReportableError, ReportableResult},
-        span::HasSpan,
-    },
-    parse::{
-        func::Return,
-        r#for::ForLoop,
-        r#if::If,
-        r#while::While,
-        table::{Item, ParseTable, WithId},
+    mir::{
+        self, BinaryOp, BlockId, Callee, Constant, Operand, Rvalue, SourceLocation, Statement,
+        Terminator, Type, UnaryOp,
     },
-    ty::TyEnv,
+    runtime,
 };
 
-/// Translates an individual function into Cranelift IR.
-pub(crate) struct FunctionCompiler<'i, 'builder> {
+use super::compile::cranelift_of_ty_module;
+
+/// Translates an individual (MIR) function into Cranelift IR.
+pub(crate) struct FunctionCompiler<'i, 'builder, M: Module> {
     pub(crate) builder: &'builder mut FunctionBuilder<'i>,
-    pub(crate) ty_env: &'i TyEnv,
-    pub(crate) module: &'builder mut JITModule,
+    pub(crate) module: &'builder mut M,
+    /// The whole program (this is used to find the signatures of the
+    /// functions which are called).
+    program: &'builder mir::Program,
+    function: &'builder mir::Function,
+    /// The Cranelift block corresponding to each MIR block.
+    blocks: Vec<ir::Block>,
+    /// The fuel counter (see [`crate::codegen::CodegenOptions::fuel`]).
+    fuel: Option<DataId>,
+    /// The flag which runtime functions set if they fail (see
+    /// [`runtime::FALLIBLE`]).
+    failed: Option<DataId>,
+    /// The block which returns from the function once the program has to
+    /// stop, because it has run out of fuel or a runtime function failed
+    /// (this is created when it is first needed).
+    stop: Option<ir::Block>,
+    /// The locations which the instructions have been marked with (the
+    /// `SourceLoc` of each instruction is an index into this).
+    pub(crate) locations: Vec<SourceLocation>,
 }
 
-impl<'i, 'builder> FunctionCompiler<'i, 'builder> {
+impl<'i, 'builder, M: Module> FunctionCompiler<'i, 'builder, M> {
     pub(crate) fn new(
-        function: &'builder mut FunctionBuilder<'i>,
-        ty_env: &'i TyEnv,
-        module: &'builder mut JITModule,
+        builder: &'builder mut FunctionBuilder<'i>,
+        module: &'builder mut M,
+        program: &'builder mir::Program,
+        function: &'builder mir::Function,
+        fuel: Option<DataId>,
+        failed: Option<DataId>,
     ) -> Self {
         Self {
-            builder: function,
-            ty_env,
+            builder,
             module,
+            program,
+            function,
+            blocks: vec![],
+            fuel,
+            failed,
+            stop: None,
+            locations: vec![],
         }
     }
 
-    /// Transforms the provided series of instructions into Cranelift IR.
-    pub(crate) fn compile_block(
-        &mut self,
-        block: &crate::parse::block::Block,
-        table: &ParseTable,
-    ) -> ReportableResult {
-        for block in &block.inner {
-            match table.get(block).unwrap() {
-                Item::Expr(e) => {
-                    if !self.builder.is_filled() {
-                        self.compile_expr(
-                            WithId {
-                                inner: e,
-                                id: block.id,
-                            },
-                            table,
-                        )?;
-                    } else {
-                        // todo: report this properly
-                        if !cfg!(fuzzing) {
-                            println!("warning: any statements added after a return instruction have no effect")
-                        }
-                    }
-                }
-                Item::For(f) => self.compile_for(f, table),
-                Item::If(i) => self.compile_if(i, table)?,
-                Item::While(w) => self.compile_while(w, table)?,
-                Item::Return(r) => self.compile_return(r, table)?,
-                Item::Func(_) => {
-                    panic!("should have checked this error before now!");
+    /// Transforms the function into Cranelift IR.
+    pub(crate) fn compile(&mut self) {
+        let function = self.function;
+        self.blocks = function
+            .blocks
+            .iter()
+            .map(|_| self.builder.create_block())
+            .collect();
+
+        let entry_block = self.blocks[0];
+        self.builder
+            .append_block_params_for_function_params(entry_block);
+        self.builder.switch_to_block(entry_block);
+
+        for (i, local) in function.locals.iter().enumerate() {
+            let ty = self.cranelift_ty(local.ty);
+            self.builder.declare_var(Variable::from_u32(i as u32), ty);
+        }
+        for (i, param) in function.params.iter().enumerate() {
+            let value = self.builder.block_params(entry_block)[i];
+            self.builder.def_var(variable(*param), value);
+            self.label(*param, value);
+        }
+
+        // fuel is used on entry to the function, and at the start of every
+        // block which is the target of a backwards jump (every loop contains
+        // at least one of these)
+        let mut uses_fuel = vec![false; function.blocks.len()];
+        uses_fuel[0] = true;
+        for (i, block) in function.blocks.iter().enumerate() {
+            for target in block.terminator.successors() {
+                if target.0 as usize <= i {
+                    uses_fuel[target.0 as usize] = true;
                 }
-                Item::Record(rec) => {
-                    // todo: this restriction may be lifted in the future
-                    return Err(ReportableError::new(
-                        table.get_ident(rec.name).span(table),
-                        "Record definitions are not allowed inside functions.".to_owned(),
-                    ));
+            }
+        }
+
+        for (i, block) in function.blocks.iter().enumerate() {
+            self.builder.switch_to_block(self.blocks[i]);
+            if uses_fuel[i] {
+                self.use_fuel();
+            }
+            for statement in &block.statements {
+                self.compile_statement(statement);
+            }
+            self.compile_terminator(&block.terminator);
+        }
+
+        if let Some(stop) = self.stop {
+            // the value which is returned does not matter, because the
+            // program stops as soon as it can
+            self.builder.switch_to_block(stop);
+            let value = match function.returns {
+                Type::Real => self.builder.ins().f64const(0.0),
+                Type::Bool => self.builder.ins().bconst(ir::types::B1, false),
+                ty => {
+                    let ty = self.cranelift_ty(ty);
+                    self.builder.ins().iconst(ty, 0)
                 }
-                Item::Ident(_) => unreachable!(),
-                Item::Block(b) => self.compile_block(b, table)?,
             };
+            self.builder.ins().return_(&[value]);
         }
 
-        Ok(())
+        self.builder.seal_all_blocks();
     }
 
-    /// Compiles a return statement.
-    pub(crate) fn compile_return(&mut self, ret: &Return, table: &ParseTable) -> ReportableResult {
-        let return_value = self.compile_expr(table.get_expr_with_id(ret.expr), table)?;
-        self.builder.ins().return_(&[return_value]);
-        Ok(())
+    /// Returns the address of the data (e.g. the fuel counter).
+    fn data_address(&mut self, data: DataId) -> ir::Value {
+        let local_id = self.module.declare_data_in_func(data, self.builder.func);
+        let pointer = self.module.target_config().pointer_type();
+        self.builder.ins().symbol_value(pointer, local_id)
     }
 
-    pub(crate) fn compile_if(&mut self, stmt: &If, table: &ParseTable) -> ReportableResult {
-        let condition_value =
-            self.compile_expr(table.get_expr_with_id(stmt.r#if.condition), table)?;
-
-        let if_block = self.builder.create_block();
-        let else_block = self.builder.create_block();
-        // all instructions exit through this block
-        let exit_block = self.builder.create_block();
+    /// Uses one unit of fuel, returning from the function if there is none
+    /// left.
+    fn use_fuel(&mut self) {
+        if let Some(fuel) = self.fuel {
+            let counter = self.data_address(fuel);
+            let remaining =
+                self.builder
+                    .ins()
+                    .load(ir::types::I64, ir::MemFlags::trusted(), counter, 0);
+            let remaining = self.builder.ins().iadd_imm(remaining, -1);
+            self.builder
+                .ins()
+                .store(ir::MemFlags::trusted(), remaining, counter, 0);
+            self.return_if_out_of_fuel(remaining);
+        }
+    }
 
-        if !stmt.else_ifs.is_empty() && !cfg!(fuzzing) {
-            // todo: proper warning API
-            println!("WARNING: else if is not yet supported!");
+    /// Returns from the function if a function which it called stopped the
+    /// program (by running out of fuel, or because a runtime function
+    /// failed).
+    fn check_stopped(&mut self) {
+        if let Some(fuel) = self.fuel {
+            let counter = self.data_address(fuel);
+            let remaining =
+                self.builder
+                    .ins()
+                    .load(ir::types::I64, ir::MemFlags::trusted(), counter, 0);
+            self.return_if_out_of_fuel(remaining);
         }
+        self.check_failed();
+    }
 
-        self.builder.ins().brz(condition_value, else_block, &[]);
-        self.builder.ins().jump(if_block, &[]);
+    /// Returns from the function if a runtime function has failed.
+    fn check_failed(&mut self) {
+        if let Some(failed) = self.failed {
+            let flag = self.data_address(failed);
+            let failed = self
+                .builder
+                .ins()
+                .load(ir::types::I8, ir::MemFlags::trusted(), flag, 0);
+            self.stop_if(failed);
+        }
+    }
 
-        self.builder.switch_to_block(if_block);
-        self.builder.seal_block(if_block);
+    fn return_if_out_of_fuel(&mut self, remaining: ir::Value) {
+        let is_out_of_fuel = self
+            .builder
+            .ins()
+            .icmp_imm(IntCC::SignedLessThan, remaining, 0);
+        self.stop_if(is_out_of_fuel);
+    }
 
-        self.compile_block(table.get_block(&stmt.r#if.block), table)?;
+    /// Returns from the function (so that the program stops) if `condition`
+    /// is true (or non-zero).
+    fn stop_if(&mut self, condition: ir::Value) {
+        let stop = match self.stop {
+            Some(block) => block,
+            None => {
+                let block = self.builder.create_block();
+                self.builder.set_cold_block(block);
+                self.stop = Some(block);
+                block
+            }
+        };
+        let next = self.builder.create_block();
+        self.builder.ins().brnz(condition, stop, &[]);
+        self.builder.ins().jump(next, &[]);
+        self.builder.switch_to_block(next);
+    }
 
-        // the block we just compiled might have included a terminator instruction; if it didn't
-        // then we jump to the exit block
-        if !self.builder.is_filled() {
-            self.builder.ins().jump(exit_block, &[]);
-        }
+    fn cranelift_ty(&self, ty: Type) -> ir::Type {
+        cranelift_of_ty_module(self.module, ty)
+    }
 
-        self.builder.switch_to_block(else_block);
-        self.builder.seal_block(else_block);
+    fn block(&self, block: BlockId) -> ir::Block {
+        self.blocks[block.0 as usize]
+    }
 
-        if let Some(else_block) = &stmt.r#else {
-            self.compile_block(table.get_block(else_block), table)?;
+    fn compile_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Assign(local, rvalue) => {
+                let ty = self.function.local_ty(*local);
+                let value = self.compile_rvalue(rvalue, Some(ty)).unwrap();
+                self.builder.def_var(variable(*local), value);
+                self.label(*local, value);
+            }
+            Statement::Eval(rvalue) => {
+                self.compile_rvalue(rvalue, None);
+            }
+            Statement::Store {
+                address,
+                offset,
+                value,
+            } => {
+                let address = self.compile_operand(address);
+                let value = self.compile_stored_operand(value);
+                self.builder
+                    .ins()
+                    .store(ir::MemFlags::new(), value, address, *offset);
+            }
+            Statement::Location(location) => {
+                self.locations.push(*location);
+                self.builder
+                    .set_srcloc(ir::SourceLoc::new(self.locations.len() as u32 - 1));
+            }
         }
+    }
 
-        // the block we just compiled might have included a terminator instruction; if it didn't
-        // then we jump to the exit block
-        if !self.builder.is_filled() {
-            self.builder.ins().jump(exit_block, &[]);
+    /// Records that `value` is the value of the local, if it is a variable
+    /// from the program (this only has an effect when debug information is
+    /// being collected for the function).
+    fn label(&mut self, local: mir::Local, value: ir::Value) {
+        if self.function.locals[local.0 as usize].name.is_some() {
+            self.builder
+                .set_val_label(value, ValueLabel::from_u32(local.0));
         }
-
-        self.builder.switch_to_block(exit_block);
-        self.builder.seal_block(exit_block);
-
-        assert!(!self.builder.is_filled());
-
-        Ok(())
     }
 
-    pub(crate) fn compile_for(&self, _: &ForLoop, _: &ParseTable) {
-        panic!("compilation of for loops is not yet implemented")
+    fn compile_terminator(&mut self, terminator: &Terminator) {
+        match terminator {
+            Terminator::Jump(block) => {
+                let block = self.block(*block);
+                self.builder.ins().jump(block, &[]);
+            }
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let condition = self.compile_operand(condition);
+                let (then, otherwise) = (self.block(*then), self.block(*otherwise));
+                self.builder.ins().brnz(condition, then, &[]);
+                self.builder.ins().jump(otherwise, &[]);
+            }
+            Terminator::Return(value) => {
+                let value = self.compile_operand(value);
+                self.builder.ins().return_(&[value]);
+            }
+            Terminator::Unreachable => {
+                self.builder
+                    .ins()
+                    .trap(ir::TrapCode::UnreachableCodeReached);
+            }
+        }
     }
 
-    pub(crate) fn compile_while(&mut self, stmt: &While, table: &ParseTable) -> ReportableResult {
-        let header_block = self.builder.create_block();
-        let body_block = self.builder.create_block();
-        let exit_block = self.builder.create_block();
-
-        self.builder.ins().jump(header_block, &[]);
-        self.builder.switch_to_block(header_block);
+    fn compile_operand(&mut self, operand: &Operand) -> ir::Value {
+        match operand {
+            Operand::Local(local) => {
+                // the value may be a block parameter which was introduced by
+                // `use_var` (and so has not been labelled yet)
+                let value = self.builder.use_var(variable(*local));
+                self.label(*local, value);
+                value
+            }
+            Operand::Const(Constant::Int(int)) => self.builder.ins().iconst(ir::types::I64, *int),
+            Operand::Const(Constant::Real(real)) => self.builder.ins().f64const(*real),
+            Operand::Const(Constant::Bool(boolean)) => {
+                self.builder.ins().bconst(ir::types::B1, *boolean)
+            }
+            Operand::Const(Constant::Str(string)) => {
+                // strings are null-terminated (see `crate::mir::Type::Str`)
+                let mut data = string.as_bytes().to_vec();
+                data.push(0);
+                let mut data_ctx = DataContext::new();
+                data_ctx.define(data.into_boxed_slice());
+                let id = self.module.declare_anonymous_data(false, false).unwrap();
+                self.module.define_data(id, &data_ctx).unwrap();
+                let local_id = self.module.declare_data_in_func(id, self.builder.func);
+                let pointer = self.module.target_config().pointer_type();
+                self.builder.ins().symbol_value(pointer, local_id)
+            }
+        }
+    }
 
-        let condition_value = self.compile_expr(table.get_expr_with_id(stmt.condition), table)?;
+    /// Compiles an operand which is about to be stored in memory (or passed to
+    /// a native function). Booleans are stored as 32-bit integers.
+    fn compile_stored_operand(&mut self, operand: &Operand) -> ir::Value {
+        let value = self.compile_operand(operand);
+        if self.function.operand_ty(operand) == Type::Bool {
+            self.builder.ins().bint(ir::types::I32, value)
+        } else {
+            value
+        }
+    }
 
-        self.builder.ins().brz(condition_value, exit_block, &[]);
-        self.builder.ins().jump(body_block, &[]);
+    /// Compiles the rvalue, whose result has the type `ty` (this is `None` if
+    /// the result is not used). Returns `None` if the rvalue does not produce
+    /// a value.
+    fn compile_rvalue(&mut self, rvalue: &Rvalue, ty: Option<Type>) -> Option<ir::Value> {
+        Some(match rvalue {
+            Rvalue::Use(operand) => self.compile_operand(operand),
+            Rvalue::Binary(op, left, right) => {
+                let operand_ty = self.function.operand_ty(left);
+                if let BinaryOp::Concat = op {
+                    return self.compile_native_call(
+                        "string_concat",
+                        &[left.clone(), right.clone()],
+                        Some(Type::Str),
+                    );
+                }
 
-        self.builder.switch_to_block(body_block);
-        self.builder.seal_block(body_block);
+                let lhs = self.compile_operand(left);
+                let rhs = self.compile_operand(right);
+                let ins = self.builder.ins();
+                match (op, operand_ty) {
+                    (BinaryOp::Add, Type::Real) => ins.fadd(lhs, rhs),
+                    (BinaryOp::Subtract, Type::Real) => ins.fsub(lhs, rhs),
+                    (BinaryOp::Multiply, Type::Real) => ins.fmul(lhs, rhs),
+                    (BinaryOp::Divide, Type::Real) => ins.fdiv(lhs, rhs),
+                    (BinaryOp::Add | BinaryOp::Offset, _) => ins.iadd(lhs, rhs),
+                    (BinaryOp::Subtract, _) => ins.isub(lhs, rhs),
+                    (BinaryOp::Multiply, _) => ins.imul(lhs, rhs),
+                    (BinaryOp::Divide, _) => ins.sdiv(lhs, rhs),
+                    (BinaryOp::Equal, Type::Real) => ins.fcmp(FloatCC::Equal, lhs, rhs),
+                    (BinaryOp::NotEqual, Type::Real) => ins.fcmp(FloatCC::NotEqual, lhs, rhs),
+                    (BinaryOp::Equal, Type::Bool) => {
+                        // exclusive or
+                        // A | B | Output
+                        // T | T | F
+                        // T | F | T
+                        // F | T | T
+                        // F | F | F
+                        // essentially, if A == B is true then A XOR B is false (and if A==B is
+                        // false, then A XOR B is true)
+                        // to compare if two boolean values are equal we want to negate A XOR B
+                        // which we can do by evaluating A XOR True
+                        let xor_res = ins.bxor(lhs, rhs);
+                        let truth = self.builder.ins().bconst(ir::types::B1, true);
+                        self.builder.ins().bxor(xor_res, truth)
+                    }
+                    // see the implementation of `Equal` (above) for documentation
+                    (BinaryOp::NotEqual, Type::Bool) => ins.bxor(lhs, rhs),
+                    (BinaryOp::Equal, _) => ins.icmp(IntCC::Equal, lhs, rhs),
+                    (BinaryOp::NotEqual, _) => ins.icmp(IntCC::NotEqual, lhs, rhs),
+                    (BinaryOp::LessThan, _) => ins.icmp(IntCC::SignedLessThan, lhs, rhs),
+                    (BinaryOp::Concat, _) => unreachable!(),
+                }
+            }
+            Rvalue::Unary(UnaryOp::Negate, operand) => {
+                let value = self.compile_operand(operand);
+                if self.function.operand_ty(operand) == Type::Real {
+                    self.builder.ins().fneg(value)
+                } else {
+                    self.builder.ins().ineg(value)
+                }
+            }
+            Rvalue::Call(Callee::Native(symbol), args) => {
+                return self.compile_native_call(symbol, args, ty)
+            }
+            Rvalue::Call(Callee::Function(name), args) => {
+                let callee = self
+                    .program
+                    .function(name)
+                    .expect("the program called a function which does not exist");
+                let mut sig = self.module.make_signature();
+                for param in &callee.params {
+                    sig.params
+                        .push(AbiParam::new(self.cranelift_ty(callee.local_ty(*param))));
+                }
+                sig.returns
+                    .push(AbiParam::new(self.cranelift_ty(callee.returns)));
 
-        self.compile_block(table.get_block(&stmt.block), table)?;
+                let func_id = self
+                    .module
+                    .declare_function(name, Linkage::Import, &sig)
+                    .expect("problem declaring function");
+                let local_callee = self.module.declare_func_in_func(func_id, self.builder.func);
 
-        self.builder.ins().jump(header_block, &[]);
+                let args = args
+                    .iter()
+                    .map(|arg| self.compile_operand(arg))
+                    .collect::<Vec<_>>();
+                let call = self.builder.ins().call(local_callee, &args);
+                let result = self.builder.inst_results(call)[0];
+                self.check_stopped();
+                result
+            }
+            Rvalue::Record { size, fields } => {
+                let slot = self.builder.create_sized_stack_slot(ir::StackSlotData::new(
+                    ir::StackSlotKind::ExplicitSlot,
+                    *size,
+                ));
+                for (offset, value) in fields {
+                    let value = self.compile_stored_operand(value);
+                    self.builder.ins().stack_store(value, slot, *offset);
+                }
+                self.builder
+                    .ins()
+                    .stack_addr(self.module.target_config().pointer_type(), slot, 0)
+            }
+            Rvalue::Load {
+                ty,
+                address,
+                offset,
+            } => {
+                let address = self.compile_operand(address);
+                if *ty == Type::Bool {
+                    let value = self.builder.ins().load(
+                        ir::types::I32,
+                        ir::MemFlags::new(),
+                        address,
+                        *offset,
+                    );
+                    self.builder.ins().icmp_imm(IntCC::Equal, value, 1)
+                } else {
+                    let ty = self.cranelift_ty(*ty);
+                    self.builder
+                        .ins()
+                        .load(ty, ir::MemFlags::new(), address, *offset)
+                }
+            }
+        })
+    }
 
-        self.builder.switch_to_block(exit_block);
-        self.builder.seal_block(header_block);
-        self.builder.seal_block(exit_block);
+    /// Lowers a call to a function which is implemented natively (i.e. in the
+    /// runtime, or in an external library).
+    ///
+    /// Values are passed to native functions as they are stored, except for
+    /// booleans, which are passed (and returned) as 32-bit integers. Strings
+    /// are pointers to null-terminated UTF-8 data (so they can be passed to C
+    /// functions as they are). Runtime functions which can fail are also
+    /// passed the address of the flag which they set if they do.
+    fn compile_native_call(
+        &mut self,
+        symbol: &str,
+        args: &[Operand],
+        returns: Option<Type>,
+    ) -> Option<ir::Value> {
+        let mut sig = self.module.make_signature();
+        let mut values = Vec::with_capacity(args.len());
+        for arg in args {
+            let value = self.compile_stored_operand(arg);
+            sig.params
+                .push(AbiParam::new(self.builder.func.dfg.value_type(value)));
+            values.push(value);
+        }
+        let failed = self.failed.filter(|_| runtime::FALLIBLE.contains(&symbol));
+        if let Some(failed) = failed {
+            let flag = self.data_address(failed);
+            sig.params
+                .push(AbiParam::new(self.builder.func.dfg.value_type(flag)));
+            values.push(flag);
+        }
+        if let Some(returns) = returns {
+            sig.returns.push(AbiParam::new(match returns {
+                Type::Bool => ir::types::I32,
+                ty => self.cranelift_ty(ty),
+            }));
+        }
 
-        assert!(!self.builder.is_filled());
+        let func_id = self
+            .module
+            .declare_function(symbol, Linkage::Import, &sig)
+            .unwrap();
+        let local_callee = self.module.declare_func_in_func(func_id, self.builder.func);
 
-        Ok(())
+        let call = self.builder.ins().call(local_callee, &values);
+        let value = self.builder.inst_results(call).first().copied();
+        if failed.is_some() {
+            self.check_failed();
+        }
+        let value = value?;
+        Some(match returns {
+            Some(Type::Bool) => self.builder.ins().icmp_imm(IntCC::NotEqual, value, 0),
+            _ => value,
+        })
     }
 }
+
+fn variable(local: mir::Local) -> Variable {
+    Variable::from_u32(local.0)
+}
@@ -1,55 +1,121 @@
-use cranelift_codegen::settings::{self, Configurable};
+use cranelift_codegen::{
+    isa::TargetIsa,
+    settings::{self, Configurable},
+};
 use cranelift_jit::{JITBuilder, JITModule};
 use cranelift_module::default_libcall_names;
+use cranelift_object::{ObjectBuilder, ObjectModule};
+use libloading::Library;
 
-#[no_mangle]
-unsafe fn print(len: i64, ptr: *const u8) {
-    let slice = std::str::from_utf8(std::slice::from_raw_parts(ptr, len as usize)).unwrap();
-    println!("{}", slice)
-}
+use crate::{
+    diagnostics::reportable_error::ReportableError, mir::opt::OptLevel, parse::table::ParseTable,
+    runtime::*,
+};
 
-#[no_mangle]
-fn print_int(int: i32) {
-    println!("{}", int)
-}
+use super::target::{Target, TargetError};
 
-#[no_mangle]
-fn print_bool(boolean: i32) {
-    println!(
-        "{}",
-        if boolean == 1 {
-            "True"
-        } else if boolean == 0 {
-            "False"
-        } else {
-            panic!("invalid boolean value {boolean}")
-        }
-    )
+/// Loads every library named in an `extern function ... from "library"`
+/// declaration in the program.
+fn load_libraries(table: &ParseTable) -> Result<Vec<Library>, ReportableError> {
+    let mut libraries = Vec::new();
+    for library in table.extern_.values().filter_map(|ext| ext.library) {
+        let loaded = unsafe { Library::new(library.token) }.map_err(|error| {
+            ReportableError::new(
+                library.span,
+                format!(
+                    "The library `{}` could not be loaded: {}",
+                    library.token, error
+                ),
+            )
+        })?;
+        libraries.push(loaded);
+    }
+    Ok(libraries)
 }
 
-pub(crate) fn make_module_for_compiler_host_architecture() -> JITModule {
+/// Creates the ISA for the given target.
+fn isa(target: &Target, opt_level: OptLevel) -> Result<Box<dyn TargetIsa>, TargetError> {
     let mut flag_builder = settings::builder();
     flag_builder.set("use_colocated_libcalls", "false").unwrap();
+    flag_builder
+        .set("opt_level", opt_level.cranelift_opt_level())
+        .unwrap();
+    flag_builder.set("is_pic", "true").unwrap();
 
-    // workaround for https://github.com/bytecodealliance/wasmtime/issues/2735
-    if cfg!(target_arch = "aarch64") || cfg!(target_arch = "arm") {
-        flag_builder.set("is_pic", "false").unwrap();
-    } else {
-        flag_builder.set("is_pic", "true").unwrap();
-    }
+    target
+        .isa_builder()?
+        .finish(settings::Flags::new(flag_builder))
+        .map_err(|error| TargetError {
+            message: format!("Code cannot be generated for `{target}`: {error}"),
+        })
+}
 
-    let isa_builder = cranelift_native::builder().unwrap_or_else(|msg| {
-        panic!("host machine is not supported: {}", msg);
+/// Creates a module which produces an object file for the given target.
+pub(crate) fn make_object_module(
+    target: &Target,
+    opt_level: OptLevel,
+) -> Result<ObjectModule, TargetError> {
+    let builder = ObjectBuilder::new(isa(target, opt_level)?, "pseudo", default_libcall_names())
+        .map_err(|error| TargetError {
+            message: format!("Object files cannot be produced for `{target}`: {error}"),
+        })?;
+    Ok(ObjectModule::new(builder))
+}
+
+/// Creates a module which compiles the program for (and loads it into) the
+/// compiler's own process.
+pub(crate) fn make_jit_module(
+    table: &ParseTable,
+    opt_level: OptLevel,
+) -> Result<JITModule, ReportableError> {
+    let libraries = load_libraries(table)?;
+
+    let isa = isa(&Target::host(), opt_level).unwrap_or_else(|error| {
+        panic!("host machine is not supported: {}", error);
     });
-    let isa = isa_builder
-        .finish(settings::Flags::new(flag_builder))
-        .unwrap();
     let mut builder = JITBuilder::with_isa(isa, default_libcall_names());
 
     // define some standard library items
-    builder.symbol("print", print as *const u8);
     builder.symbol("print_int", print_int as *const u8);
     builder.symbol("print_bool", print_bool as *const u8);
+    builder.symbol("print_real", print_real as *const u8);
+    builder.symbol("write_string", write_string as *const u8);
+    builder.symbol("write_int", write_int as *const u8);
+    builder.symbol("write_bool", write_bool as *const u8);
+    builder.symbol("write_real", write_real as *const u8);
+    builder.symbol("write_space", write_space as *const u8);
+    builder.symbol("write_newline", write_newline as *const u8);
+    builder.symbol("str_int", str_int as *const u8);
+    builder.symbol("str_bool", str_bool as *const u8);
+    builder.symbol("str_real", str_real as *const u8);
+    builder.symbol("str_string", str_string as *const u8);
+    builder.symbol("string_concat", string_concat as *const u8);
+    builder.symbol("input", input as *const u8);
+    builder.symbol("random_int", random_int as *const u8);
+    builder.symbol("random_real", random_real as *const u8);
+    builder.symbol("abs_int", abs_int as *const u8);
+    builder.symbol("abs_real", abs_real as *const u8);
+    builder.symbol("sqrt_real", sqrt_real as *const u8);
+    builder.symbol("round_real", round_real as *const u8);
+    builder.symbol("floor_real", floor_real as *const u8);
+    builder.symbol("ceil_real", ceil_real as *const u8);
+    builder.symbol("min_int", min_int as *const u8);
+    builder.symbol("min_real", min_real as *const u8);
+    builder.symbol("max_int", max_int as *const u8);
+    builder.symbol("max_real", max_real as *const u8);
+    builder.symbol("pow_int", pow_int as *const u8);
+    builder.symbol("pow_real", pow_real as *const u8);
+
+    // functions from external libraries (anything which cannot be found in
+    // these libraries is looked up in the compiler's own process)
+    builder.symbol_lookup_fn(Box::new(move |name| {
+        libraries.iter().find_map(|library| unsafe {
+            library
+                .get::<*const u8>(name.as_bytes())
+                .ok()
+                .map(|symbol| *symbol)
+        })
+    }));
 
-    JITModule::new(builder)
+    Ok(JITModule::new(builder))
 }
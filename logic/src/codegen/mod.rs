@@ -1,32 +1,207 @@
-use crate::{diagnostics::reportable_error::ReportableError, parse::table::ParseTable, ty::TyEnv};
+use std::path::Path;
+
+use crate::{
+    diagnostics::reportable_error::ReportableError,
+    io::Io,
+    mir::{self, opt::OptLevel},
+    parse::table::ParseTable,
+    runtime::{self, seed_random},
+    ty::TyEnv,
+};
 
 use self::compile::Codegen;
+pub use self::target::{Target, TargetError};
 
-/// Performs the actual AST -> Cranelift IR pass
+/// Performs the actual MIR -> Cranelift IR pass
 mod compile;
-/// Translation of expressions into Cranelift IR.
-mod expr;
+/// Produces DWARF debug information for object files.
+mod debug;
 /// Translation of individual functions into Cranelift IR.
 mod func;
-/// Layouts of objects in memory.
-pub(self) mod layout;
-/// Produces the `ObjectModule` necessary for the compiler target in question.
+/// Produces the `JITModule` or `ObjectModule` necessary for the compiler
+/// target in question.
 pub(self) mod make_module;
+/// Describes the machines which code can be generated for.
+mod target;
+#[cfg(test)]
+mod test;
+
+/// Options which control how the program is compiled and run.
+#[derive(Debug, Default, Clone)]
+pub struct CodegenOptions {
+    /// The seed used for the random number generator. If this is `None`, the
+    /// generator is seeded using the current time.
+    pub seed: Option<u64>,
+    /// Controls both which of our own optimisations are run on the MIR, and
+    /// Cranelift's optimisation level.
+    pub opt_level: OptLevel,
+    /// The amount of "fuel" which the program may use before it is stopped.
+    /// One unit is used every time a function is called, and on every
+    /// iteration of a loop. If this is `None`, the program may run forever.
+    pub fuel: Option<u64>,
+    /// Include DWARF debug information (line tables and the locations of
+    /// variables) in object files.
+    pub debug_info: bool,
+}
+
+/// Lowers the program to MIR, and then optimises it according to the
+/// options. This is the program which is passed to the backend (either the
+/// code generator, or the interpreter).
+pub fn optimised_mir(
+    ast: &ParseTable,
+    env: &TyEnv,
+    options: &CodegenOptions,
+) -> Result<mir::Program, ReportableError> {
+    let mut program = mir::lower(ast, env)?;
+    mir::opt::optimise(&mut program, options.opt_level, |_, _| {});
+    Ok(program)
+}
+
+/// Lowers the program to MIR and optimises it (in the same way as
+/// [`optimised_mir`]), returning the MIR before optimisation and after every
+/// optimisation pass which changes it. Each version of the program is paired
+/// with a description of when it was taken (e.g. `after constant folding`).
+pub fn optimisation_passes(
+    ast: &ParseTable,
+    env: &TyEnv,
+    options: &CodegenOptions,
+) -> Result<Vec<(String, mir::Program)>, ReportableError> {
+    let mut program = mir::lower(ast, env)?;
+    let mut passes = vec![("before optimisation".to_owned(), program.clone())];
+    mir::opt::optimise(&mut program, options.opt_level, |pass, program| {
+        passes.push((format!("after {pass}"), program.clone()));
+    });
+    Ok(passes)
+}
 
-/// Compiles the AST to machine code and writes it to an object file called `program.o`.
+/// Compiles the AST to machine code and runs it (performing any input and
+/// output using `io`), returning the value returned by `main`.
 ///
 /// todo: automatically link
 /// todo: allow custom file outputs
 pub fn codegen<'compiler>(
     ast: &'compiler ParseTable<'compiler>,
     env: &'compiler TyEnv,
+    options: &CodegenOptions,
+    io: &mut dyn Io,
 ) -> Result<i32, ReportableError> {
-    let mut compiler = Codegen::new(env, ast);
+    let program = optimised_mir(ast, env, options)?;
+    compile(ast, &program, options)?.run(io)
+}
+
+/// A program which has been compiled to machine code (but has not been run
+/// yet).
+pub struct Executable {
+    main: *const u8,
+    /// The fuel counter (see [`CodegenOptions::fuel`]).
+    fuel: Option<(*mut i64, i64)>,
+    /// The flag which is set if a runtime function fails.
+    failed: Option<*mut u8>,
+    seed: Option<u64>,
+}
+
+/// Compiles the (already lowered) program to machine code.
+///
+/// `table` is the program which `program` was lowered from (this is needed to
+/// load any libraries which it uses).
+pub fn compile(
+    table: &ParseTable,
+    program: &mir::Program,
+    options: &CodegenOptions,
+) -> Result<Executable, ReportableError> {
+    let mut compiler = Codegen::new(table, options.opt_level, options.fuel)?;
 
-    compiler.compile(ast)?;
+    compiler.compile(program);
 
     let output = compiler.finish()?;
 
-    let code_fn = unsafe { std::mem::transmute::<_, fn(()) -> i32>(output) };
-    Ok(code_fn(()))
+    Ok(Executable {
+        main: output.main,
+        fuel: output.fuel.map(|counter| (counter, unsafe { *counter })),
+        failed: output.failed,
+        seed: options.seed,
+    })
+}
+
+/// Compiles the (already lowered) program to an object file for the given
+/// target. `source` is the path of the file which the program was read from
+/// (which the debug information refers to, if [`CodegenOptions::debug_info`]
+/// is set), and `table` is the program which `program` was lowered from.
+///
+/// Every function in the program is exported from the object file (so the
+/// entry point is the symbol `main`), and the functions provided by the
+/// runtime (and any `extern` functions) are left undefined, to be resolved
+/// when the object file is linked.
+pub fn emit_object(
+    table: &ParseTable,
+    program: &mir::Program,
+    options: &CodegenOptions,
+    target: &Target,
+    source: &Path,
+) -> Result<ObjectFile, TargetError> {
+    let mut compiler = Codegen::for_target(
+        target,
+        options.opt_level,
+        options.fuel,
+        options.debug_info.then_some(source),
+    )?;
+
+    compiler.compile(program);
+
+    let mut libraries = table
+        .extern_
+        .values()
+        .filter_map(|ext| ext.library)
+        .map(|library| library.token.to_owned())
+        .collect::<Vec<_>>();
+    libraries.sort();
+    libraries.dedup();
+
+    Ok(ObjectFile {
+        bytes: compiler.emit(),
+        libraries,
+    })
+}
+
+/// An object file produced by [`emit_object`].
+#[derive(Debug)]
+pub struct ObjectFile {
+    pub bytes: Vec<u8>,
+    /// The libraries named by `extern` functions (e.g. `from "libm.so.6"`).
+    /// Object files cannot record which libraries they need, so these have to
+    /// be passed to the linker along with the object file.
+    pub libraries: Vec<String>,
+}
+
+impl Executable {
+    /// Runs the program (performing any input and output using `io`),
+    /// returning the value returned by `main`. The program can be run more
+    /// than once; each run starts with the full amount of fuel.
+    pub fn run(&self, io: &mut dyn Io) -> Result<i32, ReportableError> {
+        seed_random(self.seed);
+        if let Some((counter, fuel)) = self.fuel {
+            unsafe { *counter = fuel };
+        }
+        if let Some(failed) = self.failed {
+            unsafe { *failed = 0 };
+        }
+
+        let code_fn = unsafe { std::mem::transmute::<_, fn(()) -> i32>(self.main) };
+        let result = runtime::with_io(io, || code_fn(()));
+        io.flush()
+            .expect("the output of the program could not be written");
+
+        if let Some(failed) = self.failed {
+            if unsafe { *failed } != 0 {
+                let error = runtime::take_error().expect("a runtime function failed silently");
+                return Err(ReportableError::without_location(error));
+            }
+        }
+        match self.fuel {
+            Some((counter, _)) if unsafe { *counter } < 0 => Err(
+                ReportableError::without_location(runtime::RAN_FOR_TOO_LONG.to_owned()),
+            ),
+            _ => Ok(result),
+        }
+    }
 }
@@ -0,0 +1,65 @@
+use std::{fmt, str::FromStr};
+
+use cranelift_codegen::isa::{self, LookupError};
+use target_lexicon::Triple;
+
+/// A machine which code can be generated for, described by its target
+/// triple (for example `x86_64-unknown-linux-gnu`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    triple: Triple,
+}
+
+/// The reason why a target triple could not be used.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetError {
+    pub message: String,
+}
+
+impl Target {
+    /// The machine which the compiler itself is running on.
+    pub fn host() -> Self {
+        Self {
+            triple: Triple::host(),
+        }
+    }
+
+    /// Looks up the target with the given triple, failing if the triple is
+    /// invalid or Cranelift cannot generate code for its architecture.
+    pub fn from_triple(triple: &str) -> Result<Self, TargetError> {
+        let triple = Triple::from_str(triple).map_err(|error| TargetError {
+            message: format!("`{triple}` is not a valid target triple ({error})."),
+        })?;
+        let target = Self { triple };
+        target.isa_builder()?;
+        Ok(target)
+    }
+
+    pub fn triple(&self) -> &Triple {
+        &self.triple
+    }
+
+    pub(super) fn isa_builder(&self) -> Result<isa::Builder, TargetError> {
+        isa::lookup(self.triple.clone()).map_err(|error| TargetError {
+            message: match error {
+                LookupError::SupportDisabled | LookupError::Unsupported => format!(
+                    "Code cannot be generated for the architecture `{}` (the supported \
+                     architectures are x86_64, aarch64, riscv64 and s390x).",
+                    self.triple.architecture
+                ),
+            },
+        })
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.triple)
+    }
+}
+
+impl fmt::Display for TargetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
@@ -0,0 +1,174 @@
+use std::path::Path;
+
+use object::{Architecture, BinaryFormat, Object, ObjectSymbol, SymbolKind};
+
+use crate::{io::MemoryIo, mir::lower, parse::parse_with_prelude, ty::type_check};
+
+use super::{codegen, emit_object, optimisation_passes, optimised_mir, CodegenOptions, Target};
+
+static PROGRAM: &str = "function main()\n  print_int(fib(13))\n  return 0\nendfunction\nfunction fib(n)\n  if n == 0 then\n    return 0\n  elseif n == 1 then\n    return 1\n  endif\n  return fib(n - 1) + fib(n - 2)\nendfunction\n";
+
+fn object_file(input: &str, triple: &str, options: &CodegenOptions) -> Vec<u8> {
+    let table = parse_with_prelude(input).unwrap();
+    let env = type_check(&table).unwrap();
+    let program = lower(&table, &env).unwrap();
+    emit_object(
+        &table,
+        &program,
+        options,
+        &Target::from_triple(triple).unwrap(),
+        Path::new("program.pseudo"),
+    )
+    .unwrap()
+    .bytes
+}
+
+/// Object files are produced for each target (without being run, so this
+/// test does not depend on the machine it is run on).
+#[test]
+fn object_files_for_other_targets() {
+    for (triple, architecture) in [
+        ("x86_64-unknown-linux-gnu", Architecture::X86_64),
+        ("aarch64-unknown-linux-gnu", Architecture::Aarch64),
+        ("riscv64gc-unknown-linux-gnu", Architecture::Riscv64),
+    ] {
+        let bytes = object_file(PROGRAM, triple, &CodegenOptions::default());
+        let file = object::File::parse(&*bytes).unwrap();
+        assert_eq!(file.format(), BinaryFormat::Elf, "{triple}");
+        assert_eq!(file.architecture(), architecture, "{triple}");
+
+        let symbol = |name: &str| {
+            file.symbols()
+                .find(|symbol| symbol.name() == Ok(name))
+                .unwrap_or_else(|| panic!("{triple}: there is no symbol `{name}`"))
+        };
+        for function in ["main", "fib"] {
+            let symbol = symbol(function);
+            assert!(symbol.is_definition(), "{triple}: {function}");
+            assert!(symbol.is_global(), "{triple}: {function}");
+            assert_eq!(symbol.kind(), SymbolKind::Text, "{triple}: {function}");
+        }
+        // this is provided by the runtime, which the object file is linked
+        // against
+        assert!(symbol("print_int").is_undefined(), "{triple}");
+    }
+}
+
+#[test]
+fn object_files_with_fuel() {
+    let options = CodegenOptions {
+        fuel: Some(1000),
+        ..CodegenOptions::default()
+    };
+    let bytes = object_file(PROGRAM, "x86_64-unknown-linux-gnu", &options);
+    let file = object::File::parse(&*bytes).unwrap();
+    assert!(file
+        .symbols()
+        .any(|symbol| symbol.name() == Ok("main") && symbol.is_definition()));
+}
+
+#[test]
+fn object_files_with_extern_libraries() {
+    let emit = |declaration: &str| {
+        let input = format!("{declaration}\nfunction main()\n  return labs(-1)\nendfunction\n");
+        let table = parse_with_prelude(&input).unwrap();
+        let env = type_check(&table).unwrap();
+        let program = lower(&table, &env).unwrap();
+        emit_object(
+            &table,
+            &program,
+            &CodegenOptions::default(),
+            &Target::from_triple("x86_64-unknown-linux-gnu").unwrap(),
+            Path::new("program.pseudo"),
+        )
+        .unwrap()
+    };
+    let is_undefined = |bytes: &[u8]| {
+        object::File::parse(bytes)
+            .unwrap()
+            .symbols()
+            .any(|symbol| symbol.name() == Ok("labs") && symbol.is_undefined())
+    };
+
+    // `labs` is resolved when the object file is linked
+    let object = emit("extern function labs(x: Int) -> Int");
+    assert!(is_undefined(&object.bytes));
+    assert!(object.libraries.is_empty());
+
+    // and the library which contains it has to be passed to the linker
+    let object = emit("extern function labs(x: Int) -> Int from \"libc.so.6\"");
+    assert!(is_undefined(&object.bytes));
+    assert_eq!(object.libraries, ["libc.so.6"]);
+}
+
+/// Errors which happen while the program is running are not reported against
+/// any particular part of the program.
+#[test]
+fn running_out_of_fuel() {
+    let input = "function main()\n  while True\n  endwhile\n  return 0\nendfunction\n";
+    let table = parse_with_prelude(input).unwrap();
+    let env = type_check(&table).unwrap();
+    let options = CodegenOptions {
+        fuel: Some(1000),
+        ..CodegenOptions::default()
+    };
+    let error = codegen(&table, &env, &options, &mut MemoryIo::default()).unwrap_err();
+    let diagnostic = error.report(());
+    assert!(diagnostic.message.contains("ran for too long"));
+    assert!(diagnostic.labels.is_empty());
+}
+
+/// Runtime functions which fail stop the program (rather than panicking,
+/// which would abort the compiler).
+#[test]
+fn runtime_function_failing() {
+    let input = "function f()\n  return random(6, 1)\nendfunction\nfunction main()\n  print(1)\n  \
+                 print(f())\n  print(2)\n  return 0\nendfunction\n";
+    let table = parse_with_prelude(input).unwrap();
+    let env = type_check(&table).unwrap();
+    let mut io = MemoryIo::default();
+    let error = codegen(&table, &env, &CodegenOptions::default(), &mut io).unwrap_err();
+    assert_eq!(io.output(), "1\n");
+    let diagnostic = error.report(());
+    assert!(diagnostic.message.contains("lower bound"), "{diagnostic:?}");
+    assert!(diagnostic.labels.is_empty());
+}
+
+#[test]
+fn unsupported_targets() {
+    let error = Target::from_triple("not a target").unwrap_err();
+    assert!(error.message.contains("`not a target`"), "{error}");
+
+    let error = Target::from_triple("mips-unknown-linux-gnu").unwrap_err();
+    assert!(error.message.contains("`mips`"), "{error}");
+
+    assert_eq!(
+        Target::from_triple("aarch64-unknown-linux-gnu")
+            .unwrap()
+            .to_string(),
+        "aarch64-unknown-linux-gnu"
+    );
+}
+
+#[test]
+fn optimisation_passes_are_returned() {
+    let table = parse_with_prelude(PROGRAM).unwrap();
+    let env = type_check(&table).unwrap();
+    let options = CodegenOptions::default();
+
+    let passes = optimisation_passes(&table, &env, &options).unwrap();
+    assert_eq!(
+        passes[0],
+        (
+            "before optimisation".to_owned(),
+            lower(&table, &env).unwrap()
+        )
+    );
+    assert!(passes[1..]
+        .iter()
+        .all(|(when, _)| when.starts_with("after ")));
+    assert_eq!(
+        passes.last().unwrap().1,
+        optimised_mir(&table, &env, &options).unwrap()
+    );
+}
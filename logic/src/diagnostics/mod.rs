@@ -3,3 +3,30 @@
 pub mod position;
 pub mod reportable_error;
 pub mod span;
+
+use codespan_reporting::diagnostic::Diagnostic;
+
+use crate::parse::table::PRELUDE;
+
+use self::position::PRELUDE_OFFSET;
+
+/// Diagnostics can only point at parts of the program, so this turns any labels
+/// which point into the prelude into notes (which say where in the prelude they
+/// point).
+pub(crate) fn prelude_labels_to_notes<ID>(mut diagnostic: Diagnostic<ID>) -> Diagnostic<ID> {
+    let (labels, prelude): (Vec<_>, Vec<_>) = diagnostic
+        .labels
+        .into_iter()
+        .partition(|label| label.range.start < PRELUDE_OFFSET);
+    diagnostic.labels = labels;
+    for label in prelude {
+        let line = PRELUDE[..label.range.start - PRELUDE_OFFSET]
+            .matches('\n')
+            .count()
+            + 1;
+        diagnostic
+            .notes
+            .push(format!("in the prelude (line {line}): {}", label.message));
+    }
+    diagnostic
+}
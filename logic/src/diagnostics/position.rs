@@ -6,3 +6,39 @@ pub struct Position {
     pub(crate) line: usize,
     pub(crate) index: usize,
 }
+
+/// The offset at which positions in the prelude start. This is far beyond the
+/// end of any program, so positions in the prelude can never be mistaken for
+/// positions in the program.
+pub(crate) const PRELUDE_OFFSET: usize = usize::MAX / 2;
+
+/// How far apart the positions of consecutive top-level items are when the
+/// items of a program are parsed separately (see
+/// [`crate::parse::table::parse_item`]). Each item's positions start at the
+/// start of its own range (rather than at its place in the file), so that
+/// editing one item does not move the positions in the items after it.
+pub(crate) const ITEM_SIZE: usize = 1 << 32;
+
+impl Position {
+    /// The position at the start of the prelude.
+    pub(crate) fn prelude_start() -> Self {
+        Self {
+            index: PRELUDE_OFFSET,
+            ..Default::default()
+        }
+    }
+
+    /// The position at the start of the top-level item with the given index,
+    /// when the items are parsed separately (see [`ITEM_SIZE`]).
+    pub(crate) fn item_start(item: usize) -> Self {
+        Self {
+            index: (item + 1) * ITEM_SIZE,
+            ..Default::default()
+        }
+    }
+
+    /// Whether this is a position in the prelude (rather than the program).
+    pub fn in_prelude(&self) -> bool {
+        self.index >= PRELUDE_OFFSET
+    }
+}
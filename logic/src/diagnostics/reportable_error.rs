@@ -4,25 +4,44 @@ use super::span::Span;
 
 #[derive(Debug)]
 pub struct ReportableError {
-    span: Span,
+    /// The part of the program which the error is about (this is `None` for
+    /// errors which happen while the program is running, e.g. if it runs for
+    /// too long).
+    span: Option<Span>,
     explanation: String,
 }
 
 impl ReportableError {
     pub fn new(span: Span, explanation: String) -> Self {
-        Self { span, explanation }
+        Self {
+            span: Some(span),
+            explanation,
+        }
+    }
+
+    /// An error which is not about any particular part of the program.
+    pub fn without_location(explanation: String) -> Self {
+        Self {
+            span: None,
+            explanation,
+        }
     }
 
     pub fn report<ID>(&self, id: ID) -> Diagnostic<ID>
     where
         ID: Copy,
     {
+        let span = match self.span {
+            Some(span) => span,
+            None => return Diagnostic::error().with_message(&self.explanation),
+        };
+
         let diagnostic: Diagnostic<ID> =
             Diagnostic::error().with_message("Your program contains an error!");
 
-        diagnostic.with_labels(vec![
-            Label::primary(id, self.span.index_only().range()).with_message(&self.explanation)
-        ])
+        super::prelude_labels_to_notes(diagnostic.with_labels(vec![
+            Label::primary(id, span.index_only().range()).with_message(&self.explanation),
+        ]))
     }
 
     pub fn explanation(&self) -> &str {
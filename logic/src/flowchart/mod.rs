@@ -0,0 +1,312 @@
+//! Flowcharts (in the style used by GCSE exam questions), which can be
+//! exported as Graphviz DOT or Mermaid diagrams.
+//!
+//! Each function becomes a separate flowchart, which
+//!
+//! - starts with a terminator (containing the name and parameters of the
+//!   function) and ends with a terminator for each `return` (or `end`, if the
+//!   end of the function can be reached without returning)
+//! - contains a process box for each assignment, a subroutine box for each
+//!   statement which calls a function and a decision diamond for the condition
+//!   of each `if`, `while` and `for` loop (the edges leaving a decision are
+//!   labelled "Yes" and "No")
+//!
+//! The flowcharts are built by walking the program using
+//! [`crate::visitor::Visitor`].
+
+use std::fmt::Write;
+
+use crate::{
+    parse::{
+        expr::{BinOp, Expr, ExprRef, UnOp},
+        func::{Func, Return},
+        ident::Ident,
+        r#extern::Extern,
+        r#for::ForLoop,
+        r#if::If,
+        r#while::While,
+        record::Record,
+        table::ParseTable,
+    },
+    visitor::Visitor,
+};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// The start (or an end) of the function.
+    Terminator,
+    Process,
+    Decision,
+    /// A call to another function.
+    Subroutine,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub shape: Shape,
+    pub label: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    /// The index of the node which the edge leaves.
+    pub from: usize,
+    /// The index of the node which the edge goes to.
+    pub to: usize,
+    pub label: Option<&'static str>,
+}
+
+/// The flowchart for a single function. The first node is the start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Flowchart {
+    /// The name of the function.
+    pub name: String,
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+}
+
+/// Builds a flowchart for every function in the program (not including those
+/// in the prelude).
+pub fn flowcharts<'i>(table: &'i ParseTable<'i>) -> Vec<Flowchart> {
+    let mut builder = Builder {
+        charts: vec![],
+        exits: vec![],
+    };
+    builder.visit_table(table);
+    builder.charts
+}
+
+struct Builder {
+    charts: Vec<Flowchart>,
+    /// The nodes (in the flowchart which is being built) which should be
+    /// connected to the next node which is added (and the label which the
+    /// edge should have).
+    exits: Vec<(usize, Option<&'static str>)>,
+}
+
+impl Builder {
+    fn chart(&mut self) -> &mut Flowchart {
+        self.charts.last_mut().expect("not inside a function")
+    }
+
+    /// Adds a node, connecting the current exits to it (the node becomes the
+    /// only exit).
+    fn add(&mut self, shape: Shape, label: String) -> usize {
+        let chart = self.chart();
+        chart.nodes.push(Node { shape, label });
+        let node = chart.nodes.len() - 1;
+        self.connect(node);
+        self.exits = vec![(node, None)];
+        node
+    }
+
+    /// Connects the current exits to the node.
+    fn connect(&mut self, to: usize) {
+        let exits = std::mem::take(&mut self.exits);
+        self.chart().edges.extend(
+            exits
+                .into_iter()
+                .map(|(from, label)| Edge { from, to, label }),
+        );
+    }
+}
+
+/// Returns `true` if the statement is a function call (or assigns the result of
+/// one to a variable).
+fn is_call(expr: &Expr, table: &ParseTable) -> bool {
+    match expr {
+        Expr::FunctionCall(..) => true,
+        Expr::BinOp(op, _, value) if op.token == BinOp::SetEquals => {
+            table.get_expr(value).is_function_call()
+        }
+        _ => false,
+    }
+}
+
+impl<'i> Visitor<'i> for Builder {
+    type Output = ();
+
+    fn visit_rec(&mut self, _: &'i Record, _: &'i ParseTable<'i>) {}
+
+    fn visit_expr(&mut self, expr: &'i Expr<'i>, table: &'i ParseTable<'i>) {
+        let shape = if is_call(expr, table) {
+            Shape::Subroutine
+        } else {
+            Shape::Process
+        };
+        self.add(shape, expr.display(table).to_string());
+    }
+
+    fn visit_for(&mut self, stmt: &'i ForLoop, table: &'i ParseTable<'i>) {
+        let var = table.get_ident(stmt.var).inner();
+        let expr = |expr: &ExprRef| table.get_expr(expr).display(table).to_string();
+        let between = &stmt.between;
+
+        self.add(Shape::Process, format!("{var} = {}", expr(&between.start)));
+        // loops with a negative step count down
+        let counts_down = between.step.map_or(
+            false,
+            |step| matches!(table.get_expr(&step), Expr::UnOp(op, _) if op.token == UnOp::Negative),
+        );
+        let comparison = if counts_down { ">=" } else { "<=" };
+        let condition = self.add(
+            Shape::Decision,
+            format!("{var} {comparison} {}", expr(&between.stop)),
+        );
+        self.exits = vec![(condition, Some("Yes"))];
+        self.visit_block(table.get_block(&stmt.block), table);
+        let step = between
+            .step
+            .map_or_else(|| "1".to_owned(), |step| expr(&step));
+        self.add(Shape::Process, format!("{var} = {var} + {step}"));
+        self.connect(condition);
+        self.exits = vec![(condition, Some("No"))];
+    }
+
+    fn visit_if(&mut self, stmt: &'i If, table: &'i ParseTable<'i>) {
+        let mut after = vec![];
+        for branch in std::iter::once(&stmt.r#if).chain(&stmt.else_ifs) {
+            let condition = self.add(
+                Shape::Decision,
+                table.get_expr(&branch.condition).display(table).to_string(),
+            );
+            self.exits = vec![(condition, Some("Yes"))];
+            self.visit_block(table.get_block(&branch.block), table);
+            after.append(&mut self.exits);
+            // the next branch is only tried if this condition is false
+            self.exits = vec![(condition, Some("No"))];
+        }
+        if let Some(block) = &stmt.r#else {
+            self.visit_block(table.get_block(block), table);
+        }
+        after.append(&mut self.exits);
+        self.exits = after;
+    }
+
+    fn visit_while(&mut self, stmt: &'i While, table: &'i ParseTable<'i>) {
+        let condition = self.add(
+            Shape::Decision,
+            table.get_expr(&stmt.condition).display(table).to_string(),
+        );
+        self.exits = vec![(condition, Some("Yes"))];
+        self.visit_block(table.get_block(&stmt.block), table);
+        self.connect(condition);
+        self.exits = vec![(condition, Some("No"))];
+    }
+
+    fn visit_ret(&mut self, ret: &'i Return, table: &'i ParseTable<'i>) {
+        self.add(
+            Shape::Terminator,
+            format!("return {}", table.get_expr(&ret.expr).display(table)),
+        );
+        // nothing runs after a `return`
+        self.exits.clear();
+    }
+
+    fn visit_func(&mut self, func: &'i Func, table: &'i ParseTable<'i>) {
+        let name = table.get_ident(func.name).inner();
+        let params = func
+            .parameters
+            .iter()
+            .map(|param| table.get_ident(*param).inner())
+            .collect::<Vec<_>>();
+        self.charts.push(Flowchart {
+            name: name.to_owned(),
+            nodes: vec![],
+            edges: vec![],
+        });
+        self.exits.clear();
+        self.add(Shape::Terminator, format!("{name}({})", params.join(", ")));
+        self.visit_block(table.get_block(&func.block), table);
+        if !self.exits.is_empty() {
+            self.add(Shape::Terminator, "end".to_owned());
+        }
+        self.exits.clear();
+    }
+
+    fn visit_extern(&mut self, _: &'i Extern<'i>, _: &'i ParseTable<'i>) {}
+
+    fn visit_ident(&mut self, ident: &'i Ident<'i>, _: &'i ParseTable<'i>) {
+        self.add(Shape::Process, ident.inner().to_owned());
+    }
+}
+
+/// Formats the flowcharts as a Graphviz graph (with a cluster for each
+/// function).
+pub fn to_dot(charts: &[Flowchart]) -> String {
+    fn quote(text: &str) -> String {
+        format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+
+    let mut dot = "digraph flowchart {\n".to_owned();
+    for chart in charts {
+        let name = &chart.name;
+        writeln!(dot, "  subgraph cluster_{name} {{").unwrap();
+        writeln!(dot, "    label = {};", quote(name)).unwrap();
+        for (index, node) in chart.nodes.iter().enumerate() {
+            let shape = match node.shape {
+                Shape::Terminator => "shape = box, style = rounded",
+                Shape::Process => "shape = box",
+                Shape::Decision => "shape = diamond",
+                Shape::Subroutine => "shape = box, peripheries = 2",
+            };
+            writeln!(
+                dot,
+                "    {name}_{index} [{shape}, label = {}];",
+                quote(&node.label)
+            )
+            .unwrap();
+        }
+        for edge in &chart.edges {
+            write!(dot, "    {name}_{} -> {name}_{}", edge.from, edge.to).unwrap();
+            if let Some(label) = edge.label {
+                write!(dot, " [label = {}]", quote(label)).unwrap();
+            }
+            dot += ";\n";
+        }
+        dot += "  }\n";
+    }
+    dot += "}\n";
+    dot
+}
+
+/// Formats the flowcharts as a Mermaid diagram (with a subgraph for each
+/// function).
+pub fn to_mermaid(charts: &[Flowchart]) -> String {
+    fn quote(text: &str) -> String {
+        format!("\"{}\"", text.replace('"', "#quot;"))
+    }
+
+    let mut mermaid = "flowchart TD\n".to_owned();
+    for chart in charts {
+        let name = &chart.name;
+        writeln!(mermaid, "  subgraph {name}").unwrap();
+        for (index, node) in chart.nodes.iter().enumerate() {
+            let label = quote(&node.label);
+            let node = match node.shape {
+                Shape::Terminator => format!("([{label}])"),
+                Shape::Process => format!("[{label}]"),
+                Shape::Decision => format!("{{{label}}}"),
+                Shape::Subroutine => format!("[[{label}]]"),
+            };
+            writeln!(mermaid, "    {name}_{index}{node}").unwrap();
+        }
+        for edge in &chart.edges {
+            let arrow = match edge.label {
+                Some(label) => format!("-->|{label}|"),
+                None => "-->".to_owned(),
+            };
+            writeln!(
+                mermaid,
+                "    {name}_{} {arrow} {name}_{}",
+                edge.from, edge.to
+            )
+            .unwrap();
+        }
+        mermaid += "  end\n";
+    }
+    mermaid
+}
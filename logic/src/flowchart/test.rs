@@ -0,0 +1,165 @@
+use crate::parse::parse;
+
+use super::{flowcharts, to_dot, to_mermaid, Edge, Flowchart, Shape};
+
+static PROGRAM: &str = "function main()
+  total = 0
+  i = 1
+  while i != 4
+    total = add(total, i * 2)
+    i = i + 1
+  endwhile
+  if total == 12 then
+    print(\"twelve\")
+  elseif total == 0 then
+    print(\"zero\")
+  else
+    print(total)
+  endif
+  return 0
+endfunction
+function add(a, b)
+  return a + b
+endfunction
+";
+
+/// Describes every edge of the flowchart using the labels of the nodes.
+fn edges(chart: &Flowchart) -> Vec<String> {
+    chart
+        .edges
+        .iter()
+        .map(|Edge { from, to, label }| {
+            let arrow = label.map_or("->".to_owned(), |label| format!("-{label}->"));
+            format!(
+                "{} {arrow} {}",
+                chart.nodes[*from].label, chart.nodes[*to].label
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn flowchart_for_each_function() {
+    let table = parse(PROGRAM).unwrap();
+    let charts = flowcharts(&table);
+    assert_eq!(charts.len(), 2);
+
+    let main = &charts[0];
+    assert_eq!(main.name, "main");
+    let nodes = main
+        .nodes
+        .iter()
+        .map(|node| (node.shape, node.label.as_str()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        nodes,
+        [
+            (Shape::Terminator, "main()"),
+            (Shape::Process, "total = 0"),
+            (Shape::Process, "i = 1"),
+            (Shape::Decision, "i != 4"),
+            (Shape::Subroutine, "total = add(total, i * 2)"),
+            (Shape::Process, "i = i + 1"),
+            (Shape::Decision, "total == 12"),
+            (Shape::Subroutine, "print(\"twelve\")"),
+            (Shape::Decision, "total == 0"),
+            (Shape::Subroutine, "print(\"zero\")"),
+            (Shape::Subroutine, "print(total)"),
+            (Shape::Terminator, "return 0"),
+        ]
+    );
+    assert_eq!(
+        edges(main),
+        [
+            "main() -> total = 0",
+            "total = 0 -> i = 1",
+            "i = 1 -> i != 4",
+            "i != 4 -Yes-> total = add(total, i * 2)",
+            "total = add(total, i * 2) -> i = i + 1",
+            "i = i + 1 -> i != 4",
+            "i != 4 -No-> total == 12",
+            "total == 12 -Yes-> print(\"twelve\")",
+            "total == 12 -No-> total == 0",
+            "total == 0 -Yes-> print(\"zero\")",
+            "total == 0 -No-> print(total)",
+            "print(\"twelve\") -> return 0",
+            "print(\"zero\") -> return 0",
+            "print(total) -> return 0",
+        ]
+    );
+
+    assert_eq!(edges(&charts[1]), ["add(a, b) -> return a + b"]);
+}
+
+#[test]
+fn for_loops_and_falling_off_the_end() {
+    let table =
+        parse("function count(n)\n  for i = n to 0 step -1\n    print(i)\n  next i\nendfunction\n")
+            .unwrap();
+    let charts = flowcharts(&table);
+    assert_eq!(
+        edges(&charts[0]),
+        [
+            "count(n) -> i = n",
+            "i = n -> i >= 0",
+            "i >= 0 -Yes-> print(i)",
+            "print(i) -> i = i + -1",
+            "i = i + -1 -> i >= 0",
+            "i >= 0 -No-> end",
+        ]
+    );
+}
+
+#[test]
+fn brackets_are_kept_where_they_are_needed() {
+    let table = parse(
+        "function main()\n  x = (1 + 2) * 3 - (4 - 5)\n  y = -(x + 1)\n  return x\nendfunction\n",
+    )
+    .unwrap();
+    let labels = flowcharts(&table)[0]
+        .nodes
+        .iter()
+        .map(|node| node.label.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        labels,
+        [
+            "main()",
+            "x = (1 + 2) * 3 - (4 - 5)",
+            "y = -(x + 1)",
+            "return x"
+        ]
+    );
+}
+
+#[test]
+fn formats() {
+    let table = parse("function main()\n  print(\"hi\")\n  return 0\nendfunction\n").unwrap();
+    let charts = flowcharts(&table);
+    assert_eq!(
+        to_dot(&charts),
+        r#"digraph flowchart {
+  subgraph cluster_main {
+    label = "main";
+    main_0 [shape = box, style = rounded, label = "main()"];
+    main_1 [shape = box, peripheries = 2, label = "print(\"hi\")"];
+    main_2 [shape = box, style = rounded, label = "return 0"];
+    main_0 -> main_1;
+    main_1 -> main_2;
+  }
+}
+"#
+    );
+    assert_eq!(
+        to_mermaid(&charts),
+        r#"flowchart TD
+  subgraph main
+    main_0(["main()"])
+    main_1[["print(#quot;hi#quot;)"]]
+    main_2(["return 0"])
+    main_0 --> main_1
+    main_1 --> main_2
+  end
+"#
+    );
+}
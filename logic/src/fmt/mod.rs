@@ -0,0 +1,307 @@
+//! Formats programs (this is what `pseudo fmt` uses).
+//!
+//! The program is reprinted from its parse table, so every block is indented
+//! by two spaces (which is what the parser expects), there is one space on
+//! each side of every binary operator and brackets are only kept where they
+//! are needed. Comments are kept, as are blank lines (although several blank
+//! lines in a row become one, and blank lines at the start and end of blocks
+//! are removed).
+
+use std::{fmt::Display, iter};
+
+use crate::{
+    parse::{
+        block::{Block, BlockRef},
+        cst::Comment,
+        expr::ExprRef,
+        func::Func,
+        ident::IdentRef,
+        r#extern::Extern,
+        r#for::ForLoop,
+        r#if::If,
+        record::Record,
+        table::{Id, Item, ItemRef, ParseTable},
+    },
+    ty::PrimitiveType,
+};
+
+#[cfg(test)]
+mod test;
+
+/// Formats the (parsed) program. `source` is the source code of the program,
+/// which the blank lines are taken from (the comments are taken from the
+/// syntax tree in the parse table).
+pub fn format(table: &ParseTable, source: &str) -> String {
+    let mut formatter = Formatter {
+        table,
+        source,
+        output: String::new(),
+        comments: table.cst.comments(),
+        next_comment: 0,
+        starts: vec![],
+        position: 0,
+        block_start: true,
+    };
+    formatter.find_starts(&table.root.1);
+    formatter.starts.sort_unstable();
+
+    for item in &table.root.1.inner {
+        formatter.statement(item, 0);
+    }
+    formatter.comments_before(usize::MAX, 0);
+    formatter.output
+}
+
+struct Formatter<'t, 'i, 's> {
+    table: &'t ParseTable<'i>,
+    source: &'s str,
+    output: String,
+    comments: Vec<Comment<'i>>,
+    /// The first comment which has not been written yet.
+    next_comment: usize,
+    /// Where each statement (and each condition of an `if` or `while`
+    /// statement) in the program starts, in order.
+    starts: Vec<usize>,
+    /// The start of the statement (or condition) which was written last.
+    position: usize,
+    /// Whether nothing has been written in the current block yet (in which
+    /// case blank lines are left out).
+    block_start: bool,
+}
+
+impl Formatter<'_, '_, '_> {
+    fn find_starts(&mut self, block: &Block) {
+        let table = self.table;
+        for item in &block.inner {
+            if let Some(start) = table.starts.get(&item.id) {
+                self.starts.push(start.index);
+            }
+            match table.get(item) {
+                Some(Item::If(stmt)) => {
+                    for branch in iter::once(&stmt.r#if).chain(&stmt.else_ifs) {
+                        if let Some(start) = table.starts.get(&branch.condition.id) {
+                            self.starts.push(start.index);
+                        }
+                        self.find_starts(table.get_block(&branch.block));
+                    }
+                    if let Some(block) = &stmt.r#else {
+                        self.find_starts(table.get_block(block));
+                    }
+                }
+                Some(Item::While(stmt)) => self.find_starts(table.get_block(&stmt.block)),
+                Some(Item::For(stmt)) => self.find_starts(table.get_block(&stmt.block)),
+                Some(Item::Func(func)) => self.find_starts(table.get_block(&func.block)),
+                Some(Item::Block(block)) => self.find_starts(block),
+                _ => {}
+            }
+        }
+    }
+
+    fn line(&mut self, indent: usize, text: impl Display) {
+        for _ in 0..indent {
+            self.output.push_str("  ");
+        }
+        self.output.push_str(&text.to_string());
+        self.output.push('\n');
+        self.block_start = false;
+    }
+
+    /// Writes a blank line if the line before `index` in the source code is
+    /// blank (and this is not the start of a block).
+    fn blank_line_before(&mut self, index: usize) {
+        let line = self.source[..index].rfind('\n').unwrap_or(0);
+        let previous = match self.source[..line].rfind('\n') {
+            Some(previous) => previous + 1,
+            None => 0,
+        };
+        if !self.block_start && line > 0 && self.source[previous..line].trim().is_empty() {
+            self.output.push('\n');
+        }
+    }
+
+    /// Writes the comment which `next_comment` points to.
+    fn comment(&mut self, indent: usize) {
+        let comment = &self.comments[self.next_comment];
+        let (index, text) = (comment.index, comment.text);
+        self.blank_line_before(index);
+        self.line(indent, format_args!(";;{text}"));
+        self.next_comment += 1;
+    }
+
+    /// Writes the comments which come before `index` in the source code.
+    fn comments_before(&mut self, index: usize, indent: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.index >= index {
+                break;
+            }
+            self.comment(indent);
+        }
+    }
+
+    /// The column which the statement with the given id starts in.
+    fn column(&self, id: Id) -> usize {
+        self.table.starts.get(&id).map_or(0, |start| {
+            let line = self.source[..start.index].rfind('\n').map_or(0, |i| i + 1);
+            start.index - line
+        })
+    }
+
+    /// Writes the body of a statement which starts in `column`.
+    fn block(&mut self, block: &BlockRef, indent: usize, column: usize) {
+        self.block_start = true;
+        let block = self.table.get_block(block);
+        for item in &block.inner {
+            self.statement(item, indent);
+        }
+
+        // the comments at the end of the block are those which come before the
+        // next statement, and are indented more than the statement which
+        // contains the block
+        let next = self
+            .starts
+            .iter()
+            .copied()
+            .find(|start| *start > self.position)
+            .unwrap_or(usize::MAX);
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.index >= next || comment.column <= column {
+                break;
+            }
+            self.comment(indent);
+        }
+    }
+
+    fn statement(&mut self, item: &ItemRef, indent: usize) {
+        let table = self.table;
+        if let Some(start) = table.starts.get(&item.id) {
+            self.comments_before(start.index, indent);
+            self.blank_line_before(start.index);
+            self.position = start.index;
+        }
+        let column = self.column(item.id);
+        match table.get(item) {
+            Some(Item::Expr(_)) => {
+                let expr = self.expr(ExprRef { id: item.id });
+                self.line(indent, expr);
+            }
+            Some(Item::Return(ret)) => {
+                let value = self.expr(ret.expr);
+                self.line(indent, format_args!("return {value}"));
+            }
+            Some(Item::If(stmt)) => self.r#if(stmt, indent, column),
+            Some(Item::While(stmt)) => {
+                let condition = self.expr(stmt.condition);
+                self.line(indent, format_args!("while {condition}"));
+                self.block(&stmt.block, indent + 1, column);
+                self.line(indent, "endwhile");
+            }
+            Some(Item::For(stmt)) => self.r#for(stmt, indent, column),
+            Some(Item::Func(func)) => self.func(func, indent, column),
+            Some(Item::Record(record)) => self.record(record, indent),
+            Some(Item::Extern(ext)) => self.r#extern(ext, indent),
+            Some(Item::Block(block)) => {
+                for item in &block.inner {
+                    self.statement(item, indent);
+                }
+            }
+            Some(Item::Ident(_)) | None => unreachable!(),
+        }
+    }
+
+    fn r#if(&mut self, stmt: &If, indent: usize, column: usize) {
+        let condition = self.expr(stmt.r#if.condition);
+        self.line(indent, format_args!("if {condition} then"));
+        self.block(&stmt.r#if.block, indent + 1, column);
+        for branch in &stmt.else_ifs {
+            if let Some(start) = self.table.starts.get(&branch.condition.id) {
+                self.comments_before(start.index, indent);
+                self.position = start.index;
+            }
+            let condition = self.expr(branch.condition);
+            self.line(indent, format_args!("elseif {condition} then"));
+            self.block(&branch.block, indent + 1, column);
+        }
+        if let Some(block) = &stmt.r#else {
+            self.line(indent, "else");
+            self.block(block, indent + 1, column);
+        }
+        self.line(indent, "endif");
+    }
+
+    fn r#for(&mut self, stmt: &ForLoop, indent: usize, column: usize) {
+        let var = self.name(stmt.var);
+        let start = self.expr(stmt.between.start);
+        let stop = self.expr(stmt.between.stop);
+        let step = match stmt.between.step {
+            Some(step) => format!(" step {}", self.expr(step)),
+            None => String::new(),
+        };
+        self.line(indent, format_args!("for {var} = {start} to {stop}{step}"));
+        self.block(&stmt.block, indent + 1, column);
+        self.line(indent, format_args!("next {var}"));
+    }
+
+    fn func(&mut self, func: &Func, indent: usize, column: usize) {
+        let name = self.name(func.name);
+        let params = func
+            .parameters
+            .iter()
+            .map(|param| self.name(*param))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.line(indent, format_args!("function {name}({params})"));
+        self.block(&func.block, indent + 1, column);
+        self.line(indent, "endfunction");
+    }
+
+    fn record(&mut self, record: &Record, indent: usize) {
+        let name = self.name(record.name);
+        self.line(indent, format_args!("record {name}"));
+        for field in &record.fields {
+            let field_name = self.name(field.name);
+            self.line(
+                indent + 1,
+                format_args!("{field_name} of {}", type_name(field.ty.token)),
+            );
+        }
+        self.line(indent, "endrecord");
+    }
+
+    fn r#extern(&mut self, ext: &Extern, indent: usize) {
+        let name = self.name(ext.name);
+        let params = ext
+            .parameters
+            .iter()
+            .map(|(param, ty)| format!("{}: {}", self.name(*param), type_name(ty.token)))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let returns = type_name(ext.returns.token);
+        let library = match &ext.library {
+            Some(library) => format!(" from \"{}\"", library.token),
+            None => String::new(),
+        };
+        self.line(
+            indent,
+            format_args!("extern function {name}({params}) -> {returns}{library}"),
+        );
+    }
+
+    fn name(&self, ident: IdentRef) -> String {
+        self.table.get_ident(ident).inner().to_string()
+    }
+
+    fn expr(&self, expr: ExprRef) -> String {
+        self.table.get_expr(&expr).display(self.table).to_string()
+    }
+}
+
+/// The name which is used for the type in the source code.
+fn type_name(ty: PrimitiveType) -> &'static str {
+    match ty {
+        PrimitiveType::Int => "Int",
+        PrimitiveType::Bool => "Bool",
+        PrimitiveType::Real => "Real",
+        PrimitiveType::StrSlice => "String",
+        PrimitiveType::Pointer => "Pointer",
+    }
+}
@@ -0,0 +1,94 @@
+use crate::parse::parse_with_prelude;
+
+use super::format;
+
+fn fmt(program: &str) -> String {
+    let table = parse_with_prelude(program).unwrap();
+    format(&table, program)
+}
+
+#[test]
+fn formats_program() {
+    let program = ";; adds up some numbers
+record   Point
+  x of   Int
+  visible of Bool
+endrecord
+
+
+extern function labs(x:Int)->Int from \"libc.so.6\"
+function main( )
+  p = Point{x:(1), visible:True}
+  ;; count down
+  for i = 10 to 0 step -(1)
+    if (i+1)*2==p.x then
+      print(i)
+    elseif i!=3 then
+
+      ;; nothing to do
+
+
+      x = 1
+    else
+      print( \"three\" )
+      ;; the end of the `else`
+    endif
+  next i
+
+  ;; between two statements
+  while   (p.x == 1)
+    print(labs(-1))
+  endwhile
+  return 0
+endfunction
+;; the end
+";
+    assert_eq!(
+        fmt(program),
+        ";; adds up some numbers
+record Point
+  x of Int
+  visible of Bool
+endrecord
+
+extern function labs(x: Int) -> Int from \"libc.so.6\"
+function main()
+  p = Point { x: 1, visible: True }
+  ;; count down
+  for i = 10 to 0 step -1
+    if (i + 1) * 2 == p.x then
+      print(i)
+    elseif i != 3 then
+      ;; nothing to do
+
+      x = 1
+    else
+      print(\"three\")
+      ;; the end of the `else`
+    endif
+  next i
+
+  ;; between two statements
+  while p.x == 1
+    print(labs(-1))
+  endwhile
+  return 0
+endfunction
+;; the end
+"
+    );
+}
+
+#[test]
+fn formatting_is_idempotent() {
+    let program = "function f(a, b)
+  return a - (b - 1)
+endfunction
+
+x = f(1, 2) * (3 + 4)
+print(x)
+";
+    let formatted = fmt(program);
+    assert_eq!(formatted, program);
+    assert_eq!(fmt(&formatted), formatted);
+}
@@ -0,0 +1,628 @@
+//! An interpreter for the MIR.
+//!
+//! This runs programs without generating any machine code, so it works on
+//! every host (including those which Cranelift does not support) and is
+//! entirely safe. It is also useful as a reference implementation of the
+//! language: it checks for things which compiled code does not (such as
+//! reading memory which has been freed, or which was never written to) and
+//! reports them as a [`RuntimeError`] rather than crashing (or silently
+//! producing garbage).
+//!
+//! The interpreter executes one statement at a time (see
+//! [`Interpreter::step`]) and keeps its own call stack (rather than using the
+//! Rust stack), so deeply recursive programs do not crash the compiler.
+//!
+//! This also makes it possible to pause a program part of the way through:
+//! [`Interpreter::run_to_location`] runs the program up to the start of the
+//! next statement in the source code, after which the call stack (see
+//! [`Interpreter::frames`]) can be inspected. This is what the debugger is
+//! built on.
+
+use std::{fmt, rc::Rc};
+
+use rustc_hash::FxHashMap;
+
+use crate::{
+    io::Io,
+    mir::{
+        BinaryOp, BlockId, Callee, Constant, Function, Local, Operand, Program, Rvalue,
+        SourceLocation, Statement, Terminator, UnaryOp,
+    },
+    runtime,
+};
+
+#[cfg(test)]
+mod test;
+
+/// The maximum number of function calls which may be in progress at once.
+const MAX_DEPTH: usize = 100_000;
+
+/// Something which went wrong while a program was being run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub message: String,
+}
+
+impl RuntimeError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// A value which the program is working with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Real(f64),
+    Bool(bool),
+    Str(Rc<str>),
+    /// Records are also referred to using pointers.
+    Pointer(Pointer),
+}
+
+impl Value {
+    fn from_constant(constant: &Constant) -> Self {
+        match constant {
+            Constant::Int(int) => Value::Int(*int),
+            Constant::Real(real) => Value::Real(*real),
+            Constant::Bool(boolean) => Value::Bool(*boolean),
+            Constant::Str(string) => Value::Str(string.as_str().into()),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(int) => write!(f, "{int}"),
+            Value::Real(real) => f.write_str(&runtime::format_real(*real)),
+            Value::Bool(boolean) => f.write_str(runtime::format_bool(*boolean as i32)),
+            Value::Str(string) => f.write_str(string),
+            Value::Pointer(pointer) => {
+                write!(f, "<pointer {}+{}>", pointer.allocation, pointer.offset)
+            }
+        }
+    }
+}
+
+/// A pointer to (somewhere inside) an allocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pointer {
+    allocation: usize,
+    offset: i64,
+}
+
+/// A block of memory (either a record, or memory allocated using `malloc`).
+struct Allocation {
+    /// The size of the allocation (in bytes).
+    size: i64,
+    /// The value stored at each offset.
+    values: FxHashMap<i64, Value>,
+}
+
+/// A function call which is in progress.
+pub struct Frame<'p> {
+    function: &'p Function,
+    /// The value of every local (`None` if it has not been assigned yet).
+    locals: Vec<Option<Value>>,
+    block: BlockId,
+    /// The index of the next statement to run in the current block.
+    statement: usize,
+    /// The local (in the caller's frame) which the result should be stored
+    /// in.
+    destination: Option<Local>,
+    /// The location of the statement (in the source code) which is being
+    /// run.
+    location: Option<SourceLocation>,
+}
+
+impl<'p> Frame<'p> {
+    pub fn function(&self) -> &'p Function {
+        self.function
+    }
+
+    /// The location of the statement (in the source code) which is being run
+    /// (this is `None` until the function has reached its first statement,
+    /// and for functions from the prelude).
+    pub fn location(&self) -> Option<SourceLocation> {
+        self.location
+    }
+
+    /// The value of the local (`None` if it has not been assigned yet).
+    pub fn local(&self, local: Local) -> Option<&Value> {
+        self.locals[local.0 as usize].as_ref()
+    }
+}
+
+/// Runs a program.
+pub struct Interpreter<'p, I> {
+    program: &'p Program,
+    io: I,
+    frames: Vec<Frame<'p>>,
+    /// Every allocation made so far (`None` once it has been freed).
+    memory: Vec<Option<Allocation>>,
+    /// The amount of fuel the program has left (`None` if it is unlimited).
+    fuel: Option<u64>,
+}
+
+/// Runs the `main` function of the program (using `io` for its input and
+/// output), returning the value which `main` returns.
+///
+/// `seed` is used to seed the random number generator, and `fuel` limits how
+/// long the program may run for, in the same way as
+/// [`crate::codegen::CodegenOptions::seed`] and
+/// [`crate::codegen::CodegenOptions::fuel`].
+pub fn run<I: Io>(
+    program: &Program,
+    seed: Option<u64>,
+    fuel: Option<u64>,
+    io: I,
+) -> Result<i64, RuntimeError> {
+    Interpreter::new(program, seed, fuel, io)?.run()
+}
+
+impl<'p, I: Io> Interpreter<'p, I> {
+    /// Prepares to run the `main` function of the program, which may use (at
+    /// most) `fuel` units of fuel. The random number generator is seeded
+    /// using `seed` (as in [`run`]).
+    pub fn new(
+        program: &'p Program,
+        seed: Option<u64>,
+        fuel: Option<u64>,
+        io: I,
+    ) -> Result<Self, RuntimeError> {
+        runtime::seed_random(seed);
+        let main = program
+            .function("main")
+            .ok_or_else(|| RuntimeError::new("Your program does not have a `main` function."))?;
+        let mut interpreter = Self {
+            program,
+            io,
+            frames: vec![],
+            memory: vec![],
+            fuel,
+        };
+        interpreter.enter(main, vec![], None)?;
+        Ok(interpreter)
+    }
+
+    /// Runs the program until it finishes.
+    pub fn run(mut self) -> Result<i64, RuntimeError> {
+        loop {
+            if let Some(result) = self.step()? {
+                return Ok(result);
+            }
+        }
+    }
+
+    /// Runs the program until it reaches the start of the next statement in
+    /// the source code (i.e. the next [`Statement::Location`]), which may be in
+    /// another function. Returns the value which the program returned if it
+    /// finished first.
+    pub fn run_to_location(&mut self) -> Result<Option<i64>, RuntimeError> {
+        loop {
+            let frame = self
+                .frames
+                .last()
+                .expect("the program has already finished");
+            let at_location = matches!(
+                frame.function.blocks[frame.block.0 as usize]
+                    .statements
+                    .get(frame.statement),
+                Some(Statement::Location(_))
+            );
+            if let Some(result) = self.step()? {
+                return Ok(Some(result));
+            }
+            if at_location {
+                return Ok(None);
+            }
+        }
+    }
+
+    pub fn io_mut(&mut self) -> &mut I {
+        &mut self.io
+    }
+
+    /// The function calls which are in progress (the innermost call is last).
+    pub fn frames(&self) -> &[Frame<'p>] {
+        &self.frames
+    }
+
+    /// Runs a single statement (or terminator). Returns the value which the
+    /// program returned if it has now finished.
+    pub fn step(&mut self) -> Result<Option<i64>, RuntimeError> {
+        let frame = self
+            .frames
+            .last_mut()
+            .expect("the program has already finished");
+        let function = frame.function;
+        let block = &function.blocks[frame.block.0 as usize];
+
+        if let Some(statement) = block.statements.get(frame.statement) {
+            frame.statement += 1;
+            self.run_statement(statement)?;
+            return Ok(None);
+        }
+
+        match &block.terminator {
+            Terminator::Jump(target) => self.jump(*target)?,
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => match self.operand(condition)? {
+                Value::Bool(true) => self.jump(*then)?,
+                Value::Bool(false) => self.jump(*otherwise)?,
+                value => panic!("the condition of a branch was {value:?}, not a boolean"),
+            },
+            Terminator::Return(value) => {
+                let value = self.operand(value)?;
+                let frame = self.frames.pop().unwrap();
+                match self.frames.last_mut() {
+                    Some(caller) => {
+                        if let Some(destination) = frame.destination {
+                            caller.locals[destination.0 as usize] = Some(value);
+                        }
+                    }
+                    None => {
+                        self.io.flush().map_err(output_error)?;
+                        return match value {
+                            Value::Int(int) => Ok(Some(int)),
+                            value => panic!("`main` returned {value:?}, not an integer"),
+                        };
+                    }
+                }
+            }
+            Terminator::Unreachable => {
+                return Err(RuntimeError::new(format!(
+                    "The end of the function `{}` was reached without returning a value.",
+                    function.name
+                )))
+            }
+        }
+        Ok(None)
+    }
+
+    fn frame(&mut self) -> &mut Frame<'p> {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn jump(&mut self, target: BlockId) -> Result<(), RuntimeError> {
+        let frame = self.frame();
+        // jumping backwards (i.e. starting another iteration of a loop) uses
+        // fuel, in the same way as in compiled code
+        let backwards = target <= frame.block;
+        frame.block = target;
+        frame.statement = 0;
+        if backwards {
+            self.use_fuel()?;
+        }
+        Ok(())
+    }
+
+    fn use_fuel(&mut self) -> Result<(), RuntimeError> {
+        match &mut self.fuel {
+            Some(0) => Err(RuntimeError::new(runtime::RAN_FOR_TOO_LONG)),
+            Some(fuel) => {
+                *fuel -= 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Starts running the function.
+    fn enter(
+        &mut self,
+        function: &'p Function,
+        args: Vec<Value>,
+        destination: Option<Local>,
+    ) -> Result<(), RuntimeError> {
+        if self.frames.len() == MAX_DEPTH {
+            return Err(RuntimeError::new(format!(
+                "Too many functions were called inside one another (while calling `{}`); this \
+                 is usually caused by a recursive function which never stops calling itself.",
+                function.name
+            )));
+        }
+        self.use_fuel()?;
+
+        let mut locals = vec![None; function.locals.len()];
+        for (param, arg) in function.params.iter().zip(args) {
+            locals[param.0 as usize] = Some(arg);
+        }
+        self.frames.push(Frame {
+            function,
+            locals,
+            block: BlockId(0),
+            statement: 0,
+            destination,
+            location: None,
+        });
+        Ok(())
+    }
+
+    fn run_statement(&mut self, statement: &'p Statement) -> Result<(), RuntimeError> {
+        match statement {
+            Statement::Assign(local, Rvalue::Call(Callee::Function(name), args)) => {
+                self.call(name, args, Some(*local))
+            }
+            Statement::Eval(Rvalue::Call(Callee::Function(name), args)) => {
+                self.call(name, args, None)
+            }
+            Statement::Assign(local, rvalue) => {
+                let value = self.rvalue(rvalue)?;
+                self.frame().locals[local.0 as usize] = Some(value);
+                Ok(())
+            }
+            Statement::Eval(rvalue) => self.rvalue(rvalue).map(drop),
+            Statement::Store {
+                address,
+                offset,
+                value,
+            } => {
+                let address = self.pointer(address)?;
+                let value = self.operand(value)?;
+                self.store(address, *offset, value)
+            }
+            Statement::Location(location) => {
+                self.frame().location = Some(*location);
+                Ok(())
+            }
+        }
+    }
+
+    fn call(
+        &mut self,
+        name: &str,
+        args: &[Operand],
+        destination: Option<Local>,
+    ) -> Result<(), RuntimeError> {
+        let program = self.program;
+        let function = program
+            .function(name)
+            .expect("the program called a function which does not exist");
+        let args = self.operands(args)?;
+        self.enter(function, args, destination)
+    }
+
+    fn operand(&mut self, operand: &Operand) -> Result<Value, RuntimeError> {
+        match operand {
+            Operand::Local(local) => {
+                let frame = self.frame();
+                frame.locals[local.0 as usize].clone().ok_or_else(|| {
+                    let local = &frame.function.locals[local.0 as usize];
+                    RuntimeError::new(match &local.name {
+                        Some(name) => {
+                            format!("The variable `{name}` was used before it was given a value.")
+                        }
+                        None => "A value was used before it was computed.".to_owned(),
+                    })
+                })
+            }
+            Operand::Const(constant) => Ok(Value::from_constant(constant)),
+        }
+    }
+
+    fn operands(&mut self, operands: &[Operand]) -> Result<Vec<Value>, RuntimeError> {
+        operands
+            .iter()
+            .map(|operand| self.operand(operand))
+            .collect()
+    }
+
+    fn pointer(&mut self, operand: &Operand) -> Result<Pointer, RuntimeError> {
+        match self.operand(operand)? {
+            Value::Pointer(pointer) => Ok(pointer),
+            value => panic!("expected a pointer, but found {value:?}"),
+        }
+    }
+
+    fn rvalue(&mut self, rvalue: &Rvalue) -> Result<Value, RuntimeError> {
+        Ok(match rvalue {
+            Rvalue::Use(operand) => self.operand(operand)?,
+            Rvalue::Binary(op, left, right) => {
+                let left = self.operand(left)?;
+                let right = self.operand(right)?;
+                binary(*op, left, right)?
+            }
+            Rvalue::Unary(UnaryOp::Negate, operand) => match self.operand(operand)? {
+                Value::Int(int) => Value::Int(int.wrapping_neg()),
+                Value::Real(real) => Value::Real(-real),
+                value => panic!("cannot negate {value:?}"),
+            },
+            Rvalue::Call(Callee::Native(symbol), args) => {
+                let args = self.operands(args)?;
+                self.native(symbol, args)?
+            }
+            Rvalue::Call(Callee::Function(_), _) => {
+                unreachable!("calls to functions are handled by `run_statement`")
+            }
+            Rvalue::Record { size, fields } => {
+                // note: records are never freed (in compiled code they are
+                // stored on the stack of the function which creates them)
+                let pointer = self.allocate(*size as i64);
+                for (offset, value) in fields {
+                    let value = self.operand(value)?;
+                    self.store(pointer, *offset, value)?;
+                }
+                Value::Pointer(pointer)
+            }
+            Rvalue::Load {
+                ty: _,
+                address,
+                offset,
+            } => {
+                let address = self.pointer(address)?;
+                self.load(address, *offset)?
+            }
+        })
+    }
+
+    fn allocate(&mut self, size: i64) -> Pointer {
+        self.memory.push(Some(Allocation {
+            size,
+            values: FxHashMap::default(),
+        }));
+        Pointer {
+            allocation: self.memory.len() - 1,
+            offset: 0,
+        }
+    }
+
+    /// Finds the allocation which the pointer points into, checking that
+    /// `pointer + offset` is inside it.
+    fn allocation(
+        &mut self,
+        pointer: Pointer,
+        offset: i32,
+    ) -> Result<(&mut Allocation, i64), RuntimeError> {
+        let allocation = self.memory[pointer.allocation]
+            .as_mut()
+            .ok_or_else(|| RuntimeError::new("Memory was used after it had been freed."))?;
+        let offset = pointer.offset + offset as i64;
+        if offset < 0 || offset >= allocation.size {
+            return Err(RuntimeError::new(format!(
+                "Memory was accessed out of bounds (at an offset of {offset} bytes, in an \
+                 allocation of {} bytes).",
+                allocation.size
+            )));
+        }
+        Ok((allocation, offset))
+    }
+
+    fn load(&mut self, pointer: Pointer, offset: i32) -> Result<Value, RuntimeError> {
+        let (allocation, offset) = self.allocation(pointer, offset)?;
+        allocation.values.get(&offset).cloned().ok_or_else(|| {
+            RuntimeError::new("Memory was read before anything had been stored in it.")
+        })
+    }
+
+    fn store(&mut self, pointer: Pointer, offset: i32, value: Value) -> Result<(), RuntimeError> {
+        let (allocation, offset) = self.allocation(pointer, offset)?;
+        allocation.values.insert(offset, value);
+        Ok(())
+    }
+
+    fn write(&mut self, value: impl fmt::Display) -> Result<Value, RuntimeError> {
+        self.io.write(&value.to_string()).map_err(output_error)?;
+        Ok(Value::Int(0))
+    }
+
+    /// Calls the runtime function with the given name (see [`crate::runtime`]).
+    fn native(&mut self, symbol: &str, args: Vec<Value>) -> Result<Value, RuntimeError> {
+        use Value::*;
+
+        Ok(match (symbol, args.as_slice()) {
+            ("write_int" | "write_real" | "write_bool" | "write_string", [value]) => {
+                self.write(value)?
+            }
+            ("print_int" | "print_real" | "print_bool", [value]) => {
+                self.write(format_args!("{value}\n"))?
+            }
+            ("write_space", []) => self.write(" ")?,
+            ("write_newline", []) => self.write("\n")?,
+            ("str_int" | "str_real" | "str_bool" | "str_string", [value]) => {
+                Str(value.to_string().into())
+            }
+            ("input", []) => Str(self.io.read_line().map_err(input_error)?.into()),
+            ("random_int", [Int(lower), Int(upper)]) => {
+                Int(runtime::try_random_int(*lower, *upper).map_err(RuntimeError::new)?)
+            }
+            ("random_real", [Real(lower), Real(upper)]) => {
+                Real(runtime::try_random_real(*lower, *upper).map_err(RuntimeError::new)?)
+            }
+            ("abs_int", [Int(int)]) => Int(runtime::abs_int(*int)),
+            ("abs_real", [Real(real)]) => Real(runtime::abs_real(*real)),
+            ("sqrt_real", [Real(real)]) => Real(runtime::sqrt_real(*real)),
+            ("round_real", [Real(real)]) => Int(runtime::round_real(*real)),
+            ("floor_real", [Real(real)]) => Int(runtime::floor_real(*real)),
+            ("ceil_real", [Real(real)]) => Int(runtime::ceil_real(*real)),
+            ("min_int", [Int(a), Int(b)]) => Int(runtime::min_int(*a, *b)),
+            ("min_real", [Real(a), Real(b)]) => Real(runtime::min_real(*a, *b)),
+            ("max_int", [Int(a), Int(b)]) => Int(runtime::max_int(*a, *b)),
+            ("max_real", [Real(a), Real(b)]) => Real(runtime::max_real(*a, *b)),
+            ("pow_int", [Int(base), Int(exponent)]) => {
+                Int(runtime::try_pow_int(*base, *exponent).map_err(RuntimeError::new)?)
+            }
+            ("pow_real", [Real(base), Real(exponent)]) => Real(runtime::pow_real(*base, *exponent)),
+            ("malloc", [Int(size)]) => Value::Pointer(self.allocate(*size)),
+            ("realloc", [Value::Pointer(old), Int(size)]) => {
+                let new = self.allocate(*size);
+                let old = self.memory[old.allocation]
+                    .take()
+                    .ok_or_else(|| RuntimeError::new("Memory was used after it had been freed."))?;
+                self.memory[new.allocation].as_mut().unwrap().values = old
+                    .values
+                    .into_iter()
+                    .filter(|(offset, _)| offset < size)
+                    .collect();
+                Value::Pointer(new)
+            }
+            ("free", [Value::Pointer(pointer)]) => {
+                if self.memory[pointer.allocation].take().is_none() {
+                    return Err(RuntimeError::new("Memory was freed twice."));
+                }
+                Value::Pointer(*pointer)
+            }
+            (symbol, _) => {
+                return Err(RuntimeError::new(format!(
+                    "The function `{symbol}` is implemented natively, so it cannot be run by \
+                     the interpreter."
+                )))
+            }
+        })
+    }
+}
+
+/// Evaluates a binary operation in the same way as compiled code (integer
+/// arithmetic wraps on overflow).
+fn binary(op: BinaryOp, left: Value, right: Value) -> Result<Value, RuntimeError> {
+    use Value::*;
+
+    Ok(match (op, left, right) {
+        (BinaryOp::Add, Int(a), Int(b)) => Int(a.wrapping_add(b)),
+        (BinaryOp::Subtract, Int(a), Int(b)) => Int(a.wrapping_sub(b)),
+        (BinaryOp::Multiply, Int(a), Int(b)) => Int(a.wrapping_mul(b)),
+        (BinaryOp::Divide, Int(_), Int(0)) => {
+            return Err(RuntimeError::new("A number was divided by zero."))
+        }
+        (BinaryOp::Divide, Int(a), Int(b)) => Int(a.checked_div(b).ok_or_else(|| {
+            RuntimeError::new(format!("The result of dividing {a} by {b} is too large."))
+        })?),
+        (BinaryOp::Add, Real(a), Real(b)) => Real(a + b),
+        (BinaryOp::Subtract, Real(a), Real(b)) => Real(a - b),
+        (BinaryOp::Multiply, Real(a), Real(b)) => Real(a * b),
+        (BinaryOp::Divide, Real(a), Real(b)) => Real(a / b),
+        (BinaryOp::Equal, a, b) => Bool(a == b),
+        (BinaryOp::NotEqual, a, b) => Bool(a != b),
+        (BinaryOp::LessThan, Int(a), Int(b)) => Bool(a < b),
+        (BinaryOp::Concat, Str(a), Str(b)) => Str([&*a, &*b].concat().into()),
+        (BinaryOp::Offset, Value::Pointer(pointer), Int(offset)) => Value::Pointer(self::Pointer {
+            allocation: pointer.allocation,
+            offset: pointer.offset.wrapping_add(offset),
+        }),
+        (op, left, right) => panic!("cannot apply `{op}` to {left:?} and {right:?}"),
+    })
+}
+
+fn output_error(error: std::io::Error) -> RuntimeError {
+    RuntimeError::new(format!(
+        "The output of the program could not be written: {error}"
+    ))
+}
+
+fn input_error(error: std::io::Error) -> RuntimeError {
+    RuntimeError::new(format!(
+        "The input to the program could not be read: {error}"
+    ))
+}
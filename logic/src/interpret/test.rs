@@ -0,0 +1,204 @@
+use crate::{
+    io::MemoryIo,
+    mir::{lower, Local},
+    parse::{parse, parse_with_prelude},
+    ty::type_check,
+};
+
+use super::{run, Interpreter, RuntimeError, Value};
+
+/// Runs the program, returning what it printed (or the error which occurred).
+fn interpret(input: &str) -> Result<String, RuntimeError> {
+    let table = parse_with_prelude(input).unwrap();
+    let env = type_check(&table).unwrap();
+    let program = lower(&table, &env).unwrap();
+    let mut io = MemoryIo::default();
+    run(&program, Some(42), None, &mut io)?;
+    Ok(io.into_output())
+}
+
+#[test]
+fn prints() {
+    assert_eq!(
+        interpret(
+            "function main()\n  X = 2.5\n  print(\"Total: \" + str(X))\n  print(1, True, X, \"hello\")\n  print_int(7)\n  return 0\nendfunction\n"
+        )
+        .unwrap(),
+        "Total: 2.5\n1 True 2.5 hello\n7\n"
+    );
+}
+
+#[test]
+fn recursion() {
+    assert_eq!(
+        interpret(
+            "function main()\n  print(fib(13))\n  return 0\nendfunction\nfunction fib(n)\n  if n == 0 then\n    return 0\n  elseif n == 1 then\n    return 1\n  endif\n  return fib(n - 1) + fib(n - 2)\nendfunction\n"
+        )
+        .unwrap(),
+        "233\n"
+    );
+}
+
+#[test]
+fn loops_and_records() {
+    assert_eq!(
+        interpret(
+            "record Point\n  x of Int\n  y of Real\nendrecord\nfunction main()\n  i = 0\n  while i != 3\n    P = Point { x: i, y: 0.5 }\n    print(P.x, P.y)\n    i = i + 1\n  endwhile\n  return 0\nendfunction\n"
+        )
+        .unwrap(),
+        "0 0.5\n1 0.5\n2 0.5\n"
+    );
+}
+
+#[test]
+fn prelude_arrays() {
+    assert_eq!(
+        interpret(
+            "function main()\n  array = array_new(20)\n  array_set(array, 0, 10)\n  array_set(array, 19, 30)\n  print_int(array_get(array, 0))\n  print_int(array_get(array, 19))\n  array_free(array)\n  return 0\nendfunction\n"
+        )
+        .unwrap(),
+        "10\n30\n"
+    );
+}
+
+#[test]
+fn memory_errors() {
+    assert_eq!(
+        interpret(
+            "function main()\n  array = array_new(2)\n  array_set(array, 2, 10)\n  return 0\nendfunction\n"
+        ),
+        Err(RuntimeError::new(
+            "Memory was accessed out of bounds (at an offset of 16 bytes, in an allocation of 16 \
+             bytes)."
+        ))
+    );
+    assert_eq!(
+        interpret(
+            "function main()\n  array = array_new(2)\n  array_free(array)\n  print_int(array_get(array, 0))\n  return 0\nendfunction\n"
+        ),
+        Err(RuntimeError::new("Memory was used after it had been freed."))
+    );
+}
+
+#[test]
+fn division_by_zero() {
+    assert_eq!(
+        interpret("function main()\n  X = 0\n  return 1 / X\nendfunction\n"),
+        Err(RuntimeError::new("A number was divided by zero."))
+    );
+}
+
+#[test]
+fn deep_recursion_is_reported() {
+    let error = interpret(
+        "function main()\n  return forever(1)\nendfunction\nfunction forever(n)\n  if n == 0 then\n    return 0\n  endif\n  return forever(n + 1)\nendfunction\n",
+    )
+    .unwrap_err();
+    assert!(error.message.starts_with("Too many functions were called"));
+}
+
+#[test]
+fn input() {
+    let table = parse_with_prelude(
+        "function main()\n  name = input()\n  print(\"Hello, \" + name + \"!\")\n  print(input() + \".\")\n  return 0\nendfunction\n",
+    )
+    .unwrap();
+    let env = type_check(&table).unwrap();
+    let program = lower(&table, &env).unwrap();
+
+    // once the input has been used up, `input` returns an empty string
+    let mut io = MemoryIo::new(["world"]);
+    run(&program, None, None, &mut io).unwrap();
+    assert_eq!(io.output(), "Hello, world!\n.\n");
+}
+
+#[test]
+fn running_out_of_fuel() {
+    let table = parse_with_prelude(
+        "function main()\n  print(1)\n  while True\n  endwhile\n  return 0\nendfunction\n",
+    )
+    .unwrap();
+    let env = type_check(&table).unwrap();
+    let program = lower(&table, &env).unwrap();
+
+    let mut io = MemoryIo::default();
+    let error = run(&program, None, Some(1000), &mut io).unwrap_err();
+    assert_eq!(error.message, crate::runtime::RAN_FOR_TOO_LONG);
+    assert_eq!(io.output(), "1\n");
+
+    // calling `main` uses one unit of fuel, and each function call (or loop
+    // iteration) uses another
+    let table = parse(
+        "function main()\n  i = 0\n  while i != 3\n    i = next(i)\n  endwhile\n  return i\nendfunction\nfunction next(i)\n  return i + 1\nendfunction\n",
+    )
+    .unwrap();
+    let env = type_check(&table).unwrap();
+    let program = lower(&table, &env).unwrap();
+    assert_eq!(run(&program, None, Some(7), MemoryIo::default()), Ok(3));
+    assert!(run(&program, None, Some(6), MemoryIo::default()).is_err());
+}
+
+#[test]
+fn random_numbers_match_the_runtime() {
+    let table = parse("function main()\n  return random(1, 1000)\nendfunction\n").unwrap();
+    let env = type_check(&table).unwrap();
+    let program = lower(&table, &env).unwrap();
+
+    let interpreted = run(&program, Some(7), None, MemoryIo::default()).unwrap();
+    crate::runtime::seed_random(Some(7));
+    assert_eq!(
+        interpreted,
+        crate::runtime::try_random_int(1, 1000).unwrap()
+    );
+}
+
+#[test]
+fn running_to_each_location() {
+    let table = parse(
+        "function main()\n  x = double(2)\n  return x\nendfunction\nfunction double(n)\n  return n * 2\nendfunction\n",
+    )
+    .unwrap();
+    let env = type_check(&table).unwrap();
+    let program = lower(&table, &env).unwrap();
+
+    let mut interpreter = Interpreter::new(&program, None, None, MemoryIo::default()).unwrap();
+    let mut stops = vec![];
+    loop {
+        if let Some(result) = interpreter.run_to_location().unwrap() {
+            assert_eq!(result, 4);
+            break;
+        }
+        let frames = interpreter.frames();
+        let frame = frames.last().unwrap();
+        let function = frame.function();
+        let variables = function
+            .locals
+            .iter()
+            .enumerate()
+            .filter(|(_, decl)| decl.name.is_some())
+            .map(|(local, decl)| {
+                let value = frame.local(Local(local as u32));
+                format!(
+                    "{}={}",
+                    decl.name.as_ref().unwrap(),
+                    value.map_or("?".to_owned(), Value::to_string)
+                )
+            })
+            .collect::<Vec<_>>();
+        stops.push((
+            frames.len(),
+            function.name.clone(),
+            frame.location().unwrap().line,
+            variables,
+        ));
+    }
+
+    assert_eq!(
+        stops,
+        [
+            (1, "main".to_owned(), 2, vec!["x=?".to_owned()]),
+            (2, "double".to_owned(), 6, vec!["n=2".to_owned()]),
+            (1, "main".to_owned(), 3, vec!["x=4".to_owned()]),
+        ]
+    );
+}
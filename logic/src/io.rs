@@ -0,0 +1,102 @@
+//! The interface through which programs read input and print output.
+//!
+//! Both compiled code (see [`crate::codegen::codegen`]) and the interpreter
+//! (see [`crate::interpret::run`]) perform all of their input and output
+//! through an [`Io`] provided by the host, so that the output of a program can
+//! be captured (e.g. by the test suite, or the playground) rather than always
+//! being written to the terminal.
+
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, Write},
+};
+
+/// Something which a program can print output to and read input from.
+pub trait Io {
+    /// Writes some text which the program printed.
+    fn write(&mut self, text: &str) -> io::Result<()>;
+
+    /// Reads a line of input (without the line terminator). Returns an empty
+    /// string if there is no more input.
+    fn read_line(&mut self) -> io::Result<String>;
+
+    /// Ensures that everything which has been written so far is visible to
+    /// the user (this is called when the program finishes).
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<I: Io + ?Sized> Io for &mut I {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        (**self).write(text)
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        (**self).read_line()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+/// Uses the standard input and output of the compiler's process.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stdio;
+
+impl Io for Stdio {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        io::stdout().write_all(text.as_bytes())
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        let end = line.trim_end_matches(&['\r', '\n'][..]).len();
+        line.truncate(end);
+        Ok(line)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+/// Reads input from (and writes output to) memory.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryIo {
+    input: VecDeque<String>,
+    output: String,
+}
+
+impl MemoryIo {
+    /// Creates a `MemoryIo` which provides the given lines of input to the
+    /// program.
+    pub fn new<S: Into<String>>(input: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            input: input.into_iter().map(Into::into).collect(),
+            output: String::new(),
+        }
+    }
+
+    /// Everything which the program has printed so far.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    pub fn into_output(self) -> String {
+        self.output
+    }
+}
+
+impl Io for MemoryIo {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.output.push_str(text);
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        Ok(self.input.pop_front().unwrap_or_default())
+    }
+}
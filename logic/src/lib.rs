@@ -4,8 +4,22 @@
     feature(no_coverage, trivial_bounds, type_alias_impl_trait)
 )]
 
+pub mod builtin;
 pub mod codegen;
 pub mod diagnostics;
+pub mod flowchart;
+pub mod fmt;
+pub mod interpret;
+pub mod io;
+pub mod mir;
 pub mod parse;
+pub mod query;
+mod runtime;
+pub mod session;
+pub mod trace;
+pub mod transpile;
 pub mod ty;
 pub mod visitor;
+pub mod wasm;
+
+pub use session::{Backend, Session};
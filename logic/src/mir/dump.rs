@@ -0,0 +1,207 @@
+//! The textual form of the MIR, for example
+//!
+//! ```text
+//! function double(_0: Int) -> Int
+//!   let _0: Int (x)
+//!   let _1: Int
+//!
+//!   bb0:
+//!     ;; 2:3
+//!     _1 = mul _0, 2
+//!     return _1
+//! ```
+//!
+//! where `;; 2:3` marks the statements which were produced from the statement
+//! which starts at line 2, column 3 of the source code.
+
+use std::fmt;
+
+use super::{
+    BasicBlock, BinaryOp, BlockId, Callee, Constant, Function, Local, Operand, Program, Rvalue,
+    SourceLocation, Statement, Terminator, Type, UnaryOp,
+};
+
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, function) in self.functions.iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{function}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "function {}(", self.name)?;
+        for (i, param) in self.params.iter().enumerate() {
+            if i != 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}: {}", param, self.local_ty(*param))?;
+        }
+        writeln!(f, ") -> {}", self.returns)?;
+
+        for (i, local) in self.locals.iter().enumerate() {
+            write!(f, "  let {}: {}", Local(i as u32), local.ty)?;
+            if let Some(name) = &local.name {
+                write!(f, " ({name})")?;
+            }
+            writeln!(f)?;
+        }
+
+        for (i, block) in self.blocks.iter().enumerate() {
+            writeln!(f)?;
+            writeln!(f, "  {}:", BlockId(i as u32))?;
+            write!(f, "{block}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for BasicBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for statement in &self.statements {
+            writeln!(f, "    {statement}")?;
+        }
+        writeln!(f, "    {}", self.terminator)
+    }
+}
+
+impl fmt::Display for Local {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "_{}", self.0)
+    }
+}
+
+impl fmt::Display for BlockId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "bb{}", self.0)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Type::Int => "Int",
+            Type::Real => "Real",
+            Type::Bool => "Bool",
+            Type::Str => "String",
+            Type::Pointer => "Pointer",
+            Type::Record => "Record",
+        })
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Assign(local, rvalue) => write!(f, "{local} = {rvalue}"),
+            Statement::Eval(rvalue) => write!(f, "{rvalue}"),
+            Statement::Store {
+                address,
+                offset,
+                value,
+            } => write!(f, "store {value}, {address}+{offset}"),
+            Statement::Location(location) => write!(f, ";; {location}"),
+        }
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+impl fmt::Display for Terminator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Terminator::Jump(block) => write!(f, "jump {block}"),
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => write!(f, "branch {condition}, {then}, {otherwise}"),
+            Terminator::Return(value) => write!(f, "return {value}"),
+            Terminator::Unreachable => write!(f, "unreachable"),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Local(local) => write!(f, "{local}"),
+            Operand::Const(constant) => write!(f, "{constant}"),
+        }
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Constant::Int(int) => write!(f, "{int}"),
+            Constant::Real(real) => write!(f, "{real:?}"),
+            Constant::Bool(true) => write!(f, "True"),
+            Constant::Bool(false) => write!(f, "False"),
+            Constant::Str(string) => write!(f, "{string:?}"),
+        }
+    }
+}
+
+impl fmt::Display for Rvalue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rvalue::Use(operand) => write!(f, "{operand}"),
+            Rvalue::Binary(op, left, right) => write!(f, "{op} {left}, {right}"),
+            Rvalue::Unary(UnaryOp::Negate, operand) => write!(f, "neg {operand}"),
+            Rvalue::Call(callee, args) => {
+                match callee {
+                    Callee::Function(name) => write!(f, "call {name}(")?,
+                    Callee::Native(name) => write!(f, "call native {name}(")?,
+                }
+                for (i, arg) in args.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{arg}")?;
+                }
+                write!(f, ")")
+            }
+            Rvalue::Record { size, fields } => {
+                write!(f, "record[{size}] {{")?;
+                for (i, (offset, value)) in fields.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, " {offset}: {value}")?;
+                }
+                write!(f, " }}")
+            }
+            Rvalue::Load {
+                ty,
+                address,
+                offset,
+            } => write!(f, "load {ty}, {address}+{offset}"),
+        }
+    }
+}
+
+impl fmt::Display for BinaryOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BinaryOp::Add => "add",
+            BinaryOp::Subtract => "sub",
+            BinaryOp::Multiply => "mul",
+            BinaryOp::Divide => "div",
+            BinaryOp::Equal => "eq",
+            BinaryOp::NotEqual => "ne",
+            BinaryOp::LessThan => "lt",
+            BinaryOp::Concat => "concat",
+            BinaryOp::Offset => "offset",
+        })
+    }
+}
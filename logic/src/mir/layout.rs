@@ -0,0 +1,112 @@
+use crate::{
+    parse::{record::Record, table::ParseTable},
+    ty::{PrimitiveType, Ty},
+};
+
+use super::Type;
+
+/// Returns the size (in bytes) of a value of the type in question.
+///
+/// Note that records are always stored behind a pointer, so for them (and
+/// strings) this returns the size of the _pointer_.
+pub fn type_size(ty: Type) -> u32 {
+    match ty {
+        // integers are 64 bits
+        Type::Int => 8,
+        // reals are stored as 64-bit floating point numbers
+        Type::Real => 8,
+        // this is quite big, but we can always adjust it later
+        // note: all bools need to be converted to/from integers when they are
+        // stored/loaded in memory
+        Type::Bool => 8,
+        // todo: support 32-bit targets
+        Type::Str | Type::Pointer | Type::Record => 8,
+    }
+}
+
+/// Computes the offset (in bytes, from the start of the record) of every field
+/// of the record, in the order in which they are defined.
+///
+/// For example, for the following record (a possible implementation of a
+/// vector)
+///
+/// ```ignore
+/// record Vector
+///   start of Pointer
+///   capacity of Int
+///   len of Int
+/// endrecord
+/// ```
+///
+/// the fields are laid out one after another
+///
+/// ```ignore
+///   ┌──────────┬──────────┬──────────┐
+///   │  start   │ capacity │   len    │
+///   └──────────┴──────────┴──────────┘
+///   0          8          16         24
+/// ```
+///
+/// so to access (for example) `len` we add the sizes of the two previous
+/// fields to the address of the record.
+///
+/// todo: padding
+pub fn field_offsets<'t>(record: &'t Record, table: &'t ParseTable) -> Vec<(&'t str, i32, Type)> {
+    let mut offset = 0;
+    record
+        .fields
+        .iter()
+        .map(|field| {
+            let ty = Type::from(field.ty.token);
+            let layout = (table.get_ident(field.name).inner, offset, ty);
+            offset += type_size(ty) as i32;
+            layout
+        })
+        .collect()
+}
+
+/// Returns the size (in bytes) of the record.
+pub fn record_size(record: &Record) -> u32 {
+    record
+        .fields
+        .iter()
+        .map(|field| type_size(Type::from(field.ty.token)))
+        .sum()
+}
+
+impl From<PrimitiveType> for Type {
+    fn from(ty: PrimitiveType) -> Self {
+        match ty {
+            PrimitiveType::Int => Type::Int,
+            PrimitiveType::Real => Type::Real,
+            PrimitiveType::Bool => Type::Bool,
+            PrimitiveType::StrSlice => Type::Str,
+            PrimitiveType::Pointer => Type::Pointer,
+        }
+    }
+}
+
+impl From<Ty> for Type {
+    fn from(ty: Ty) -> Self {
+        match ty {
+            Ty::PrimitiveType(ty) => ty.into(),
+            Ty::Record { .. } => Type::Record,
+        }
+    }
+}
+
+impl Type {
+    /// Converts the type back into the corresponding primitive type.
+    ///
+    /// Panics if the type is [`Type::Record`].
+    pub fn as_primitive(self) -> PrimitiveType {
+        match self {
+            Type::Int => PrimitiveType::Int,
+            Type::Real => PrimitiveType::Real,
+            Type::Bool => PrimitiveType::Bool,
+            Type::Str => PrimitiveType::StrSlice,
+            Type::Pointer => PrimitiveType::Pointer,
+            Type::Record => panic!("records are not primitive types"),
+        }
+    }
+}
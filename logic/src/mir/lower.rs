@@ -0,0 +1,904 @@
+use rustc_hash::FxHashMap;
+
+use crate::{
+    builtin::{self, Builtin, Slot, Symbol},
+    diagnostics::{
+        reportable_error::{ReportableError, ReportableResult},
+        span::{HasSpan, Span},
+    },
+    parse::{
+        block::Block,
+        expr::{BinOp, Constructor, Expr, ExprRef, UnOp},
+        func::Func,
+        ident::IdentRef,
+        lit::Literal,
+        r#for::ForLoop,
+        r#if::If,
+        r#while::While,
+        record::Record,
+        table::{Id, Item, ItemKind, ParseTable, WithId},
+    },
+    ty::{Ty, TyEnv},
+};
+
+use super::{
+    layout::{field_offsets, record_size},
+    BasicBlock, BinaryOp, BlockId, Callee, Constant, FieldDecl, Function, Local, LocalDecl,
+    Operand, Program, RecordDecl, Rvalue, Statement, Terminator, Type, UnaryOp,
+};
+
+/// Produces the MIR for every function in the (type checked) program.
+pub fn lower(table: &ParseTable, ty_env: &TyEnv) -> Result<Program, ReportableError> {
+    let records = table
+        .record_
+        .values()
+        .map(|record| lower_record(record, table))
+        .collect();
+
+    let mut functions = lower_functions(table, ty_env, &table.prelude.1)?;
+    functions.extend(lower_functions(table, ty_env, &table.root.1)?);
+
+    Ok(Program { functions, records })
+}
+
+/// Describes the layout of the record (see [`RecordDecl`]).
+pub(crate) fn lower_record(record: &Record, table: &ParseTable) -> RecordDecl {
+    RecordDecl {
+        name: table.get_ident(record.name).inner().to_owned(),
+        fields: field_offsets(record, table)
+            .into_iter()
+            .map(|(name, offset, ty)| FieldDecl {
+                name: name.to_owned(),
+                offset,
+                ty,
+            })
+            .collect(),
+    }
+}
+
+/// Produces the MIR for the functions defined in the block (which should be
+/// either the prelude or the root block of the table).
+pub(crate) fn lower_functions(
+    table: &ParseTable,
+    ty_env: &TyEnv,
+    block: &Block,
+) -> Result<Vec<Function>, ReportableError> {
+    let mut functions = vec![];
+    for item in &block.inner {
+        let (id, func) = match item.item_kind {
+            ItemKind::Func => table.func.get_key_value(&item.id).unwrap(),
+            _ => continue,
+        };
+
+        // the types of prelude functions which the program never calls
+        // usually cannot be inferred (so we don't compile them)
+        if table.is_prelude_func(*id)
+            && std::iter::once(func.name)
+                .chain(func.parameters.iter().copied())
+                .any(|ident| ty_env.ty_of(ident.id).is_none())
+        {
+            continue;
+        }
+
+        functions.push(
+            FunctionLowerer::new(table, ty_env, !table.is_prelude_func(*id)).lower(*id, func)?,
+        );
+    }
+    Ok(functions)
+}
+
+/// A basic block which is still being built.
+struct PartialBlock {
+    statements: Vec<Statement>,
+    terminator: Option<Terminator>,
+}
+
+/// Translates an individual function into MIR.
+struct FunctionLowerer<'t, 'i> {
+    table: &'t ParseTable<'i>,
+    ty_env: &'t TyEnv,
+    locals: Vec<LocalDecl>,
+    /// The local which stores each variable in the program.
+    variables: FxHashMap<Id, Local>,
+    blocks: Vec<PartialBlock>,
+    /// The block which statements are currently being added to.
+    current: BlockId,
+    /// Whether to record where statements come from in the source code (this
+    /// is not done for functions from the prelude, which is a different
+    /// source file).
+    locations: bool,
+}
+
+impl<'t, 'i> FunctionLowerer<'t, 'i> {
+    fn new(table: &'t ParseTable<'i>, ty_env: &'t TyEnv, locations: bool) -> Self {
+        Self {
+            table,
+            ty_env,
+            locals: vec![],
+            variables: Default::default(),
+            blocks: vec![PartialBlock {
+                statements: vec![],
+                terminator: None,
+            }],
+            current: BlockId(0),
+            locations,
+        }
+    }
+
+    fn lower(mut self, id: Id, func: &Func) -> Result<Function, ReportableError> {
+        let table = self.table;
+
+        let returns = match self.ty_env.ty_of(func.name.id) {
+            Some(ty) => Type::from(ty),
+            None => {
+                return Err(ReportableError::new(
+                    table.get_ident(func.name).span(table),
+                    "The return type of this function could not be deduced.".to_owned(),
+                ))
+            }
+        };
+
+        let mut params = Vec::with_capacity(func.parameters.len());
+        for ident in &func.parameters {
+            if self.ty_env.ty_of(ident.id).is_none() {
+                return Err(ReportableError::new(
+                    table.get_ident(*ident).span(table),
+                    "A type of variable could not be established for this function parameter."
+                        .to_owned(),
+                ));
+            }
+            params.push(self.variable(*ident)?);
+        }
+
+        self.lower_block(table.get_block(&func.block))?;
+
+        Ok(Function {
+            name: table.get_ident(func.name).inner().to_owned(),
+            params,
+            returns,
+            location: table
+                .starts
+                .get(&id)
+                .filter(|_| self.locations)
+                .map(|start| (*start).into()),
+            locals: self.locals,
+            blocks: self
+                .blocks
+                .into_iter()
+                .map(|block| BasicBlock {
+                    statements: block.statements,
+                    terminator: block.terminator.unwrap_or(Terminator::Unreachable),
+                })
+                .collect(),
+        })
+    }
+
+    /// Returns the type of the item with the given id.
+    fn ty(&self, id: Id, span: Span) -> Result<Type, ReportableError> {
+        self.ty_env
+            .ty_of(id)
+            .map(Type::from)
+            .ok_or_else(|| ReportableError::could_not_infer_ty(span))
+    }
+
+    /// Returns the local which stores the variable (creating it if necessary).
+    fn variable(&mut self, ident: IdentRef) -> Result<Local, ReportableError> {
+        if let Some(local) = self.variables.get(&ident.id) {
+            return Ok(*local);
+        }
+
+        let span = self.table.get_ident(ident).span(self.table);
+        let local = self.new_local(
+            self.ty(ident.id, span)?,
+            Some(self.table.get_ident(ident).inner().to_owned()),
+            self.record_name(ident.id),
+        );
+        self.variables.insert(ident.id, local);
+        Ok(local)
+    }
+
+    fn new_local(&mut self, ty: Type, name: Option<String>, record: Option<String>) -> Local {
+        self.locals.push(LocalDecl { ty, name, record });
+        Local(self.locals.len() as u32 - 1)
+    }
+
+    /// Returns the name of the record which the item with the given id points
+    /// to (if it is a record).
+    fn record_name(&self, id: Id) -> Option<String> {
+        match self.ty_env.ty_of(id) {
+            Some(Ty::Record { ref_ }) => {
+                let record = self.table.get_record(ref_);
+                Some(self.table.get_ident(record.name).inner().to_owned())
+            }
+            _ => None,
+        }
+    }
+
+    /// Creates a temporary to hold the value of the expression.
+    fn temporary(&mut self, expr: &WithId<&Expr>) -> Result<Local, ReportableError> {
+        let ty = self.ty(expr.id(), expr.inner().span(self.table))?;
+        Ok(self.new_local(ty, None, self.record_name(expr.id())))
+    }
+
+    fn new_block(&mut self) -> BlockId {
+        self.blocks.push(PartialBlock {
+            statements: vec![],
+            terminator: None,
+        });
+        BlockId(self.blocks.len() as u32 - 1)
+    }
+
+    fn switch_to(&mut self, block: BlockId) {
+        self.current = block;
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.blocks[self.current.0 as usize].terminator.is_some()
+    }
+
+    fn push(&mut self, statement: Statement) {
+        self.blocks[self.current.0 as usize]
+            .statements
+            .push(statement);
+    }
+
+    /// Marks the statements which follow as having been produced from the
+    /// statement (or condition) with the given id (see
+    /// [`ParseTable::starts`]).
+    fn mark(&mut self, id: Id) {
+        if let (true, Some(start)) = (self.locations, self.table.starts.get(&id)) {
+            self.push(Statement::Location((*start).into()));
+        }
+    }
+
+    /// Ends the current block (unless it has already been ended, for example
+    /// by a `return` statement).
+    fn terminate(&mut self, terminator: Terminator) {
+        let block = &mut self.blocks[self.current.0 as usize];
+        if block.terminator.is_none() {
+            block.terminator = Some(terminator);
+        }
+    }
+
+    /// Assigns the value to a new temporary (with the type of `expr`).
+    fn assign_temporary(
+        &mut self,
+        expr: &WithId<&Expr>,
+        rvalue: Rvalue,
+    ) -> Result<Operand, ReportableError> {
+        let temporary = self.temporary(expr)?;
+        self.push(Statement::Assign(temporary, rvalue));
+        Ok(Operand::Local(temporary))
+    }
+
+    fn lower_block(&mut self, block: &Block) -> Result<(), ReportableError> {
+        let table = self.table;
+
+        for item in &block.inner {
+            if self.is_terminated() {
+                // the remaining statements are unreachable (the parser warns
+                // about this), but we still lower them (into a block which
+                // nothing jumps to)
+                let dead = self.new_block();
+                self.switch_to(dead);
+            }
+
+            let inner = table.get(item).unwrap();
+            // `if`, `while` and `for` statements are marked at each of their
+            // conditions instead
+            if !matches!(inner, Item::If(_) | Item::While(_) | Item::For(_)) {
+                self.mark(item.id);
+            }
+
+            match inner {
+                Item::Expr(e) => {
+                    self.lower_expr(WithId {
+                        inner: e,
+                        id: item.id,
+                    })?;
+                }
+                Item::For(f) => self.lower_for(item.id, f)?,
+                Item::If(i) => self.lower_if(i)?,
+                Item::While(w) => self.lower_while(w)?,
+                Item::Return(r) => {
+                    let value = self.lower_expr(table.get_expr_with_id(r.expr))?;
+                    self.terminate(Terminator::Return(value));
+                }
+                Item::Func(_) => {
+                    panic!("should have checked this error before now!");
+                }
+                Item::Extern(ext) => {
+                    return Err(ReportableError::new(
+                        ext.span,
+                        "External functions must be declared outside of functions.".to_owned(),
+                    ));
+                }
+                Item::Record(rec) => {
+                    // todo: this restriction may be lifted in the future
+                    return Err(ReportableError::new(
+                        table.get_ident(rec.name).span(table),
+                        "Record definitions are not allowed inside functions.".to_owned(),
+                    ));
+                }
+                Item::Ident(_) => unreachable!(),
+                Item::Block(b) => self.lower_block(b)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn lower_if(&mut self, stmt: &If) -> Result<(), ReportableError> {
+        let table = self.table;
+        // all branches exit through this block
+        let exit = self.new_block();
+
+        for branch in std::iter::once(&stmt.r#if).chain(&stmt.else_ifs) {
+            self.mark(branch.condition.id);
+            let condition = self.lower_expr(table.get_expr_with_id(branch.condition))?;
+            let then = self.new_block();
+            let otherwise = self.new_block();
+            self.terminate(Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            });
+
+            self.switch_to(then);
+            self.lower_block(table.get_block(&branch.block))?;
+            self.terminate(Terminator::Jump(exit));
+
+            // the next condition is tested if this one was false
+            self.switch_to(otherwise);
+        }
+
+        if let Some(else_block) = &stmt.r#else {
+            self.lower_block(table.get_block(else_block))?;
+        }
+        self.terminate(Terminator::Jump(exit));
+
+        self.switch_to(exit);
+        Ok(())
+    }
+
+    fn lower_while(&mut self, stmt: &While) -> Result<(), ReportableError> {
+        let table = self.table;
+        let header = self.new_block();
+        let body = self.new_block();
+        let exit = self.new_block();
+
+        self.terminate(Terminator::Jump(header));
+        self.switch_to(header);
+        self.mark(stmt.condition.id);
+        let condition = self.lower_expr(table.get_expr_with_id(stmt.condition))?;
+        self.terminate(Terminator::Branch {
+            condition,
+            then: body,
+            otherwise: exit,
+        });
+
+        self.switch_to(body);
+        self.lower_block(table.get_block(&stmt.block))?;
+        self.terminate(Terminator::Jump(header));
+
+        self.switch_to(exit);
+        Ok(())
+    }
+
+    /// Lowers the `for` loop (with the given id) to a loop which counts from
+    /// the start to the end (inclusive). The bounds and the step are only
+    /// evaluated once, and the loop counts down if the step is negative.
+    fn lower_for(&mut self, id: Id, stmt: &ForLoop) -> Result<(), ReportableError> {
+        let table = self.table;
+        let start = self.lower_expr(table.get_expr_with_id(stmt.between.start))?;
+        let stop = self.lower_expr(table.get_expr_with_id(stmt.between.stop))?;
+        let step = match stmt.between.step {
+            Some(step) => self.lower_expr(table.get_expr_with_id(step))?,
+            None => Operand::Const(Constant::Int(1)),
+        };
+
+        // (the body of the loop cannot change these, even if they are
+        // variables)
+        let counter = self.new_local(Type::Int, None, None);
+        self.push(Statement::Assign(counter, Rvalue::Use(start)));
+        let stop = self.copy(Type::Int, stop);
+        let step = self.copy(Type::Int, step);
+        let ascending = self.new_local(Type::Bool, None, None);
+        self.push(Statement::Assign(
+            ascending,
+            Rvalue::Binary(
+                BinaryOp::LessThan,
+                Operand::Const(Constant::Int(0)),
+                step.clone(),
+            ),
+        ));
+
+        let header = self.new_block();
+        let up = self.new_block();
+        let down = self.new_block();
+        let body = self.new_block();
+        let exit = self.new_block();
+
+        self.terminate(Terminator::Jump(header));
+        self.switch_to(header);
+        self.mark(id);
+        self.terminate(Terminator::Branch {
+            condition: Operand::Local(ascending),
+            then: up,
+            otherwise: down,
+        });
+
+        // the loop finishes once the counter has gone past the end
+        for (block, left, right) in [
+            (up, stop.clone(), Operand::Local(counter)),
+            (down, Operand::Local(counter), stop),
+        ] {
+            self.switch_to(block);
+            let finished = self.new_local(Type::Bool, None, None);
+            self.push(Statement::Assign(
+                finished,
+                Rvalue::Binary(BinaryOp::LessThan, left, right),
+            ));
+            self.terminate(Terminator::Branch {
+                condition: Operand::Local(finished),
+                then: exit,
+                otherwise: body,
+            });
+        }
+
+        self.switch_to(body);
+        let variable = self.variable(stmt.var)?;
+        self.push(Statement::Assign(
+            variable,
+            Rvalue::Use(Operand::Local(counter)),
+        ));
+        self.lower_block(table.get_block(&stmt.block))?;
+        if !self.is_terminated() {
+            self.push(Statement::Assign(
+                counter,
+                Rvalue::Binary(BinaryOp::Add, Operand::Local(counter), step),
+            ));
+        }
+        self.terminate(Terminator::Jump(header));
+
+        self.switch_to(exit);
+        Ok(())
+    }
+
+    /// Copies the value into a new local (unless it is a constant).
+    fn copy(&mut self, ty: Type, value: Operand) -> Operand {
+        match value {
+            Operand::Const(_) => value,
+            _ => {
+                let local = self.new_local(ty, None, None);
+                self.push(Statement::Assign(local, Rvalue::Use(value)));
+                Operand::Local(local)
+            }
+        }
+    }
+
+    /// Lowers the expression, returning the operand which holds its value.
+    fn lower_expr(&mut self, expr: WithId<&Expr>) -> Result<Operand, ReportableError> {
+        let table = self.table;
+
+        Ok(match expr.inner() {
+            Expr::Ident(ident) => Operand::Local(self.variable(*ident)?),
+            Expr::Literal(lit) => Operand::Const(match &lit.token {
+                Literal::String(string) => Constant::Str((*string).to_owned()),
+                Literal::Number(number) if number.is_real() => Constant::Real(number.as_real()),
+                Literal::Number(number) => Constant::Int(number.as_int()),
+                Literal::Bool(boolean) => Constant::Bool(*boolean),
+            }),
+            Expr::BinOp(op, left, right) if op.token == BinOp::SetEquals => {
+                self.lower_assignment(*left, *right)?
+            }
+            Expr::BinOp(op, left, right) => match op.token {
+                BinOp::Add
+                | BinOp::Subtract
+                | BinOp::Multiply
+                | BinOp::Divide
+                | BinOp::IsEqual
+                | BinOp::IsNotEqual => {
+                    let lhs = self.lower_expr(table.get_expr_with_id(*left))?;
+                    let rhs = self.lower_expr(table.get_expr_with_id(*right))?;
+                    let operand_ty = self.ty(left.id, table.get_expr(left).span(table))?;
+                    let op = match op.token {
+                        BinOp::Add if operand_ty == Type::Str => BinaryOp::Concat,
+                        BinOp::Add => BinaryOp::Add,
+                        BinOp::Subtract => BinaryOp::Subtract,
+                        BinOp::Multiply => BinaryOp::Multiply,
+                        BinOp::Divide => BinaryOp::Divide,
+                        BinOp::IsEqual => BinaryOp::Equal,
+                        BinOp::IsNotEqual => BinaryOp::NotEqual,
+                        _ => unreachable!(),
+                    };
+                    if matches!(op, BinaryOp::Equal | BinaryOp::NotEqual)
+                        && !matches!(operand_ty, Type::Int | Type::Real | Type::Bool)
+                    {
+                        return Err(ReportableError::new(
+                            expr.inner().span(table),
+                            "Only `Int`, `Real` and `Bool` values can be compared (for now)."
+                                .to_owned(),
+                        ));
+                    }
+                    self.assign_temporary(&expr, Rvalue::Binary(op, lhs, rhs))?
+                }
+                BinOp::SetEquals => unreachable!(),
+                BinOp::Dot => self.lower_field_access(&expr, *left, *right)?,
+                BinOp::Index => match self.ty(expr.id(), expr.inner().span(table))? {
+                    Type::Pointer => {
+                        let lhs = self.lower_expr(table.get_expr_with_id(*left))?;
+                        let rhs = self.lower_expr(table.get_expr_with_id(*right))?;
+                        self.assign_temporary(&expr, Rvalue::Binary(BinaryOp::Offset, lhs, rhs))?
+                    }
+                    _ => {
+                        return Err(ReportableError::new(
+                            op.span,
+                            "Only pointers can be indexed (for now).".to_owned(),
+                        ))
+                    }
+                },
+            },
+            Expr::UnOp(op, arg) => {
+                match op.token {
+                    UnOp::Deref => {
+                        let address = self.lower_expr(table.get_expr_with_id(*arg))?;
+                        let ty = self.ty(expr.id(), expr.inner().span(table))?;
+                        self.assign_temporary(
+                            &expr,
+                            Rvalue::Load {
+                                ty,
+                                address,
+                                offset: 0,
+                            },
+                        )?
+                    }
+                    UnOp::Positive | UnOp::Negative => {
+                        let value = self.lower_expr(table.get_expr_with_id(*arg))?;
+                        match self.ty_env.ty_of(arg.id).map(Type::from) {
+                            Some(Type::Int | Type::Real) if op.token == UnOp::Negative => {
+                                self.assign_temporary(&expr, Rvalue::Unary(UnaryOp::Negate, value))?
+                            }
+                            Some(Type::Int | Type::Real) => value,
+                            _ => return Err(ReportableError::new(
+                                table.get_expr(arg).span(table),
+                                "The `+` and `-` operators can only be applied to an `Int` or a \
+                                 `Real`."
+                                    .to_owned(),
+                            )),
+                        }
+                    }
+                }
+            }
+            Expr::FunctionCall(name, params) => self.lower_call(&expr, *name, params)?,
+            Expr::Constructor(con) => self.lower_constructor(&expr, con)?,
+        })
+    }
+
+    fn lower_assignment(
+        &mut self,
+        left: ExprRef,
+        right: ExprRef,
+    ) -> Result<Operand, ReportableError> {
+        let table = self.table;
+        match table.get_expr(&left) {
+            Expr::Ident(ident) => {
+                let value = self.lower_expr(table.get_expr_with_id(right))?;
+                let variable = self.variable(*ident)?;
+                self.push(Statement::Assign(variable, Rvalue::Use(value)));
+                Ok(Operand::Local(variable))
+            }
+            Expr::UnOp(op, pointer) if op.token.is_deref() => {
+                let value = self.lower_expr(table.get_expr_with_id(right))?;
+                let address = self.lower_expr(table.get_expr_with_id(*pointer))?;
+                self.push(Statement::Store {
+                    address,
+                    offset: 0,
+                    value: value.clone(),
+                });
+                Ok(value)
+            }
+            other => Err(ReportableError::new(
+                other.span(table),
+                "Only variables (and pointers) can be assigned to.".to_owned(),
+            )),
+        }
+    }
+
+    fn lower_field_access(
+        &mut self,
+        expr: &WithId<&Expr>,
+        left: ExprRef,
+        right: ExprRef,
+    ) -> Result<Operand, ReportableError> {
+        let table = self.table;
+        let record_ident = match table.get_expr(&left).as_ident() {
+            Some(ident) => *ident,
+            None => {
+                return Err(ReportableError::new(
+                    table.get_expr(&left).span(table),
+                    "This variable has been used, but it was never defined.".to_owned(),
+                ))
+            }
+        };
+        let key = *table.get_expr(&right).as_ident().unwrap();
+
+        let record = match self.ty_env.ty_of(record_ident.id) {
+            Some(Ty::Record { ref_ }) => table.get_record(ref_),
+            _ => {
+                return Err(ReportableError::new(
+                    table.get_expr(&left).span(table),
+                    "Only records have fields.".to_owned(),
+                ))
+            }
+        };
+        let (_, offset, ty) = field_offsets(record, table)
+            .into_iter()
+            .find(|(name, _, _)| *name == table.get_ident(key).inner())
+            .ok_or_else(|| {
+                ReportableError::new(
+                    table.get_ident(key).span(table),
+                    "This record does not have a field with this name.".to_owned(),
+                )
+            })?;
+
+        let address = Operand::Local(self.variable(record_ident)?);
+        self.assign_temporary(
+            expr,
+            Rvalue::Load {
+                ty,
+                address,
+                offset,
+            },
+        )
+    }
+
+    fn lower_constructor(
+        &mut self,
+        expr: &WithId<&Expr>,
+        con: &Constructor,
+    ) -> Result<Operand, ReportableError> {
+        let table = self.table;
+        let record = match self.ty_env.ty_of(expr.id()) {
+            Some(Ty::Record { ref_ }) => table.get_record(ref_),
+            _ => {
+                return Err(ReportableError::could_not_infer_ty(
+                    expr.inner().span(table),
+                ))
+            }
+        };
+
+        let mut fields = vec![];
+        for (name, offset, _) in field_offsets(record, table) {
+            let value = match con
+                .fields
+                .iter()
+                .find(|(field, _)| table.get_ident(**field).inner() == name)
+            {
+                Some((_, value)) => self.lower_expr(table.get_expr_with_id(*value))?,
+                None => {
+                    return Err(ReportableError::new(
+                        expr.inner().span(table),
+                        format!("The field `{name}` has not been given a value."),
+                    ))
+                }
+            };
+            fields.push((offset, value));
+        }
+
+        self.assign_temporary(
+            expr,
+            Rvalue::Record {
+                size: record_size(record),
+                fields,
+            },
+        )
+    }
+
+    fn lower_call(
+        &mut self,
+        expr: &WithId<&Expr>,
+        name: IdentRef,
+        params: &[ExprRef],
+    ) -> Result<Operand, ReportableError> {
+        let table = self.table;
+        let function_name = table.get_ident(name).inner();
+
+        if let Some(builtin) = builtin::lookup(function_name) {
+            return self.lower_builtin_call(builtin, expr, params);
+        }
+
+        let callee = if table.find_extern(function_name).is_some() {
+            Callee::Native(function_name.to_owned())
+        } else {
+            if self.ty_env.ty_of(name.id).is_none() {
+                return Err(ReportableError::new(
+                    table.get_ident(name).span(table),
+                    "The return type of this function could not be inferred.".to_owned(),
+                ));
+            }
+            for param in params {
+                if self.ty_env.ty_of(param.id).is_none() {
+                    return Err(ReportableError::new(
+                        table.get_expr(param).span(table),
+                        "The type of this function parameter could not be inferred.".to_owned(),
+                    ));
+                }
+            }
+            Callee::Function(function_name.to_owned())
+        };
+
+        let args = params
+            .iter()
+            .map(|param| self.lower_expr(table.get_expr_with_id(*param)))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.assign_temporary(expr, Rvalue::Call(callee, args))
+    }
+
+    /// Retrieves the type of the given expression, which must be one of the
+    /// primitive types (the runtime functions do not accept records).
+    fn primitive_ty_of(
+        &self,
+        builtin: &Builtin,
+        expr: &WithId<&Expr>,
+    ) -> Result<Type, ReportableError> {
+        match self.ty(expr.id(), expr.inner().span(self.table))? {
+            Type::Record => Err(ReportableError::new(
+                expr.inner().span(self.table),
+                format!("`{}` cannot (yet) be called with records.", builtin.name),
+            )),
+            ty => Ok(ty),
+        }
+    }
+
+    /// Lowers a call to a builtin function into a call to the runtime function
+    /// which implements it (see [`crate::builtin`]).
+    fn lower_builtin_call(
+        &mut self,
+        builtin: &Builtin,
+        expr: &WithId<&Expr>,
+        params: &[ExprRef],
+    ) -> Result<Operand, ReportableError> {
+        let table = self.table;
+
+        if let Symbol::Print(_) = builtin.symbol {
+            return self.lower_print(builtin, params);
+        }
+
+        // find the type which the generic slots were instantiated with (all the
+        // generic slots have the same type, so we only need to check one)
+        let generic = match builtin
+            .params
+            .iter()
+            .zip(params)
+            .find(|(slot, _)| matches!(slot, Slot::Generic))
+        {
+            Some((_, param)) => {
+                Some(self.primitive_ty_of(builtin, &table.get_expr_with_id(*param))?)
+            }
+            None if matches!(builtin.returns, Slot::Generic) => {
+                Some(self.primitive_ty_of(builtin, expr)?)
+            }
+            None => None,
+        };
+
+        let symbol = builtin
+            .symbol_for(generic.map(Type::as_primitive))
+            .ok_or_else(|| {
+                ReportableError::new(
+                    expr.inner().span(table),
+                    format!(
+                        "`{}` cannot be called with values of type `{:?}`.",
+                        builtin.name,
+                        generic.unwrap()
+                    ),
+                )
+            })?;
+
+        let args = params
+            .iter()
+            .map(|param| self.lower_expr(table.get_expr_with_id(*param)))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.assign_temporary(expr, Rvalue::Call(Callee::Native(symbol.to_owned()), args))
+    }
+
+    /// Prints every argument (using the runtime function for its type),
+    /// separated by spaces and followed by a new line.
+    fn lower_print(
+        &mut self,
+        builtin: &Builtin,
+        params: &[ExprRef],
+    ) -> Result<Operand, ReportableError> {
+        let table = self.table;
+        let native = |symbol: &str, args| {
+            Statement::Eval(Rvalue::Call(Callee::Native(symbol.to_owned()), args))
+        };
+
+        for (i, param) in params.iter().enumerate() {
+            if i != 0 {
+                self.push(native("write_space", vec![]));
+            }
+
+            let param_expr = table.get_expr_with_id(*param);
+            let span = param_expr.inner().span(table);
+            if let Some(Ty::Record { ref_ }) = self.ty_env.ty_of(param_expr.id()) {
+                let value = self.lower_expr(param_expr)?;
+                self.lower_print_record(builtin, table.get_record(ref_), value, span)?;
+                continue;
+            }
+
+            let ty = self.primitive_ty_of(builtin, &param_expr)?;
+            let value = self.lower_expr(param_expr)?;
+            self.lower_print_value(builtin, ty, value, span)?;
+        }
+        self.push(native("write_newline", vec![]));
+
+        // `print` has the type `Int`, but its value is meaningless
+        Ok(Operand::Const(Constant::Int(0)))
+    }
+
+    /// Prints a value of one of the primitive types (using the runtime
+    /// function for its type).
+    fn lower_print_value(
+        &mut self,
+        builtin: &Builtin,
+        ty: Type,
+        value: Operand,
+        span: Span,
+    ) -> ReportableResult {
+        let symbol = builtin.symbol_for(Some(ty.as_primitive())).ok_or_else(|| {
+            ReportableError::new(
+                span,
+                format!("Values of type `{:?}` cannot be printed.", ty),
+            )
+        })?;
+        self.push(Statement::Eval(Rvalue::Call(
+            Callee::Native(symbol.to_owned()),
+            vec![value],
+        )));
+        Ok(())
+    }
+
+    /// Prints the record (at `address`) in the same way as it would be written
+    /// in a program, for example `Point { x: 1, y: 2.5 }`.
+    fn lower_print_record(
+        &mut self,
+        builtin: &Builtin,
+        record: &Record,
+        address: Operand,
+        span: Span,
+    ) -> ReportableResult {
+        let table = self.table;
+        let write_string = |string: String| {
+            Statement::Eval(Rvalue::Call(
+                Callee::Native("write_string".to_owned()),
+                vec![Operand::Const(Constant::Str(string))],
+            ))
+        };
+
+        let fields = field_offsets(record, table);
+        self.push(write_string(format!(
+            "{} {{",
+            table.get_ident(record.name).inner()
+        )));
+        for (i, (name, offset, ty)) in fields.iter().copied().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            self.push(write_string(format!("{separator}{name}: ")));
+
+            let field = self.new_local(ty, None, None);
+            self.push(Statement::Assign(
+                field,
+                Rvalue::Load {
+                    ty,
+                    address: address.clone(),
+                    offset,
+                },
+            ));
+            self.lower_print_value(builtin, ty, Operand::Local(field), span)?;
+        }
+        self.push(write_string(
+            if fields.is_empty() { "}" } else { " }" }.to_owned(),
+        ));
+        Ok(())
+    }
+}
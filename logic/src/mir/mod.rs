@@ -0,0 +1,384 @@
+//! The mid-level intermediate representation (MIR).
+//!
+//! The MIR sits between the (type checked) [`ParseTable`] and the code
+//! generator. Each function is a control flow graph of basic blocks, each of
+//! which contains a list of statements and ends with a single terminator
+//! (a jump, a conditional branch or a return).
+//!
+//! Unlike the syntax tree, the MIR is explicit about everything
+//! - every intermediate value is stored in its own (typed) local
+//! - calls to builtin functions have been resolved to the runtime function
+//!   which implements them
+//! - accesses to record fields have been resolved to loads and stores at a
+//!   fixed offset
+//!
+//! so the code generator (and any optimisation passes) do not need to know
+//! anything about the surface syntax of the language.
+//!
+//! The MIR can be printed (using its `Display` implementation), which is
+//! useful both for debugging the compiler and for seeing how programs are
+//! compiled.
+
+mod dump;
+/// Layouts of records in memory.
+pub mod layout;
+/// Produces the MIR from the syntax tree.
+mod lower;
+/// Optimisations which are performed on the MIR.
+pub mod opt;
+#[cfg(test)]
+mod test;
+
+pub use lower::lower;
+pub(crate) use lower::{lower_functions, lower_record};
+
+use crate::diagnostics::position::Position;
+#[cfg(doc)]
+use crate::parse::table::ParseTable;
+
+/// A whole program.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub functions: Vec<Function>,
+    /// Every record defined by the program (in the order in which they are
+    /// defined).
+    pub records: Vec<RecordDecl>,
+}
+
+impl Program {
+    /// Finds the function with the given name.
+    pub fn function(&self, name: &str) -> Option<&Function> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+
+    /// Finds the record with the given name.
+    pub fn record(&self, name: &str) -> Option<&RecordDecl> {
+        self.records.iter().find(|record| record.name == name)
+    }
+}
+
+/// The layout of a record. The MIR itself only deals with pointers to records
+/// (and loads and stores at fixed offsets from them), but backends which
+/// produce source code use this to describe records in the same way as the
+/// program does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordDecl {
+    pub name: String,
+    /// The fields of the record (in the order in which they are defined).
+    pub fields: Vec<FieldDecl>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDecl {
+    pub name: String,
+    /// The offset (in bytes) of the field from the start of the record.
+    pub offset: i32,
+    pub ty: Type,
+}
+
+/// A single function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub name: String,
+    /// The locals which hold the parameters (in order).
+    pub params: Vec<Local>,
+    pub returns: Type,
+    /// Every local used in the function (indexed by [`Local`]).
+    pub locals: Vec<LocalDecl>,
+    /// Every basic block in the function (indexed by [`BlockId`]). The first
+    /// block is the entry block.
+    pub blocks: Vec<BasicBlock>,
+    /// Where the function is defined in the source code (this is `None` for
+    /// functions from the prelude).
+    pub location: Option<SourceLocation>,
+}
+
+impl Function {
+    /// Returns the type of the given operand.
+    pub fn operand_ty(&self, operand: &Operand) -> Type {
+        match operand {
+            Operand::Local(local) => self.locals[local.0 as usize].ty,
+            Operand::Const(constant) => constant.ty(),
+        }
+    }
+
+    pub fn local_ty(&self, local: Local) -> Type {
+        self.locals[local.0 as usize].ty
+    }
+}
+
+/// A local variable (either one defined by the program, or a temporary value
+/// introduced by the compiler).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Local(pub u32);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalDecl {
+    pub ty: Type,
+    /// The name of the variable in the source code (this is `None` for
+    /// temporary values).
+    pub name: Option<String>,
+    /// The name of the record which this local points to (if it has type
+    /// [`Type::Record`]).
+    pub record: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockId(pub u32);
+
+/// A position in the source code of the program (lines and columns are
+/// counted from 1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourceLocation {
+    pub line: u32,
+    pub column: u32,
+}
+
+impl From<Position> for SourceLocation {
+    fn from(position: Position) -> Self {
+        Self {
+            line: position.line as u32 + 1,
+            column: position.column as u32 + 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicBlock {
+    pub statements: Vec<Statement>,
+    pub terminator: Terminator,
+}
+
+impl BasicBlock {
+    /// Returns every local which is read in this block (in order, including
+    /// any duplicates).
+    pub fn reads(&self) -> impl Iterator<Item = Local> + '_ {
+        self.statements
+            .iter()
+            .flat_map(Statement::operands)
+            .chain(self.terminator.operand())
+            .filter_map(Operand::as_local)
+    }
+}
+
+/// The type of a value in the MIR.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Type {
+    Int,
+    Real,
+    Bool,
+    /// A pointer to a null-terminated string.
+    Str,
+    Pointer,
+    /// A pointer to a record (which is stored on the stack).
+    Record,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    /// Computes the value and stores it in the local.
+    Assign(Local, Rvalue),
+    /// Computes the value, but discards the result (this is used for calls to
+    /// functions which do not return anything).
+    Eval(Rvalue),
+    /// Stores the value at `address + offset`.
+    Store {
+        address: Operand,
+        offset: i32,
+        value: Operand,
+    },
+    /// Marks the statements which follow it (up to the next marker) as
+    /// having been produced from the code at this location. This is only
+    /// used to produce debug information, and has no effect when the
+    /// program is run.
+    Location(SourceLocation),
+}
+
+impl Statement {
+    /// Returns every operand which this statement reads.
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Statement::Assign(_, rvalue) | Statement::Eval(rvalue) => rvalue.operands(),
+            Statement::Store { address, value, .. } => vec![address, value],
+            Statement::Location(_) => vec![],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Statement::Assign(_, rvalue) | Statement::Eval(rvalue) => rvalue.operands_mut(),
+            Statement::Store { address, value, .. } => vec![address, value],
+            Statement::Location(_) => vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Terminator {
+    Jump(BlockId),
+    /// Jumps to `then` if the condition is true, and to `otherwise` if it is
+    /// false.
+    Branch {
+        condition: Operand,
+        then: BlockId,
+        otherwise: BlockId,
+    },
+    Return(Operand),
+    /// The end of this block can never be reached (e.g. because every path
+    /// through an `if` statement returns).
+    Unreachable,
+}
+
+impl Terminator {
+    /// Returns the blocks which control can be transferred to.
+    pub fn successors(&self) -> Vec<BlockId> {
+        match self {
+            Terminator::Jump(block) => vec![*block],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => vec![*then, *otherwise],
+            Terminator::Return(_) | Terminator::Unreachable => vec![],
+        }
+    }
+
+    pub fn successors_mut(&mut self) -> Vec<&mut BlockId> {
+        match self {
+            Terminator::Jump(block) => vec![block],
+            Terminator::Branch {
+                then, otherwise, ..
+            } => vec![then, otherwise],
+            Terminator::Return(_) | Terminator::Unreachable => vec![],
+        }
+    }
+
+    /// Returns the operand which this terminator reads (if any).
+    pub fn operand(&self) -> Option<&Operand> {
+        match self {
+            Terminator::Branch { condition, .. } => Some(condition),
+            Terminator::Return(value) => Some(value),
+            Terminator::Jump(_) | Terminator::Unreachable => None,
+        }
+    }
+
+    pub fn operand_mut(&mut self) -> Option<&mut Operand> {
+        match self {
+            Terminator::Branch { condition, .. } => Some(condition),
+            Terminator::Return(value) => Some(value),
+            Terminator::Jump(_) | Terminator::Unreachable => None,
+        }
+    }
+}
+
+/// A value which can be used directly (without needing to be computed).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Local(Local),
+    Const(Constant),
+}
+
+impl Operand {
+    pub fn as_local(&self) -> Option<Local> {
+        match self {
+            Operand::Local(local) => Some(*local),
+            Operand::Const(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Int(i64),
+    Real(f64),
+    Bool(bool),
+    Str(String),
+}
+
+impl Constant {
+    pub fn ty(&self) -> Type {
+        match self {
+            Constant::Int(_) => Type::Int,
+            Constant::Real(_) => Type::Real,
+            Constant::Bool(_) => Type::Bool,
+            Constant::Str(_) => Type::Str,
+        }
+    }
+}
+
+/// A computation which produces a value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rvalue {
+    Use(Operand),
+    /// A binary operation (both operands always have the same type, apart from
+    /// [`BinaryOp::Offset`]).
+    Binary(BinaryOp, Operand, Operand),
+    Unary(UnaryOp, Operand),
+    Call(Callee, Vec<Operand>),
+    /// Allocates a record of the given size (in bytes) on the stack, stores
+    /// each of the values at the corresponding offset and produces a pointer
+    /// to it.
+    Record {
+        size: u32,
+        fields: Vec<(i32, Operand)>,
+    },
+    /// Loads a value of the given type from `address + offset`.
+    Load {
+        ty: Type,
+        address: Operand,
+        offset: i32,
+    },
+}
+
+impl Rvalue {
+    /// Returns every operand which this rvalue reads.
+    pub fn operands(&self) -> Vec<&Operand> {
+        match self {
+            Rvalue::Use(operand) | Rvalue::Unary(_, operand) => vec![operand],
+            Rvalue::Binary(_, left, right) => vec![left, right],
+            Rvalue::Call(_, args) => args.iter().collect(),
+            Rvalue::Record { fields, .. } => fields.iter().map(|(_, value)| value).collect(),
+            Rvalue::Load { address, .. } => vec![address],
+        }
+    }
+
+    pub fn operands_mut(&mut self) -> Vec<&mut Operand> {
+        match self {
+            Rvalue::Use(operand) | Rvalue::Unary(_, operand) => vec![operand],
+            Rvalue::Binary(_, left, right) => vec![left, right],
+            Rvalue::Call(_, args) => args.iter_mut().collect(),
+            Rvalue::Record { fields, .. } => fields.iter_mut().map(|(_, value)| value).collect(),
+            Rvalue::Load { address, .. } => vec![address],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Equal,
+    NotEqual,
+    /// Whether the first integer is smaller than the second.
+    LessThan,
+    /// Joins two strings.
+    Concat,
+    /// Adds an integer to a pointer.
+    Offset,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+}
+
+/// The function which is called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Callee {
+    /// A function defined in the program.
+    Function(String),
+    /// A function which is implemented natively (either by the runtime, or by
+    /// an external library). Booleans are passed to (and returned from) these
+    /// as 32-bit integers.
+    Native(String),
+}
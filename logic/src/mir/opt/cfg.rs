@@ -0,0 +1,68 @@
+use crate::mir::{BlockId, Function};
+
+/// Returns the predecessors of every block (indexed by [`BlockId`]).
+pub(super) fn predecessors(function: &Function) -> Vec<Vec<BlockId>> {
+    let mut predecessors = vec![vec![]; function.blocks.len()];
+    for (i, block) in function.blocks.iter().enumerate() {
+        for successor in block.terminator.successors() {
+            predecessors[successor.0 as usize].push(BlockId(i as u32));
+        }
+    }
+    predecessors
+}
+
+/// Returns, for every block, whether it can be reached from the entry block.
+pub(super) fn reachable(function: &Function) -> Vec<bool> {
+    let mut reachable = vec![false; function.blocks.len()];
+    let mut stack = vec![BlockId(0)];
+    while let Some(block) = stack.pop() {
+        if std::mem::replace(&mut reachable[block.0 as usize], true) {
+            continue;
+        }
+        stack.extend(function.blocks[block.0 as usize].terminator.successors());
+    }
+    reachable
+}
+
+/// Computes the dominators of every block; `dominators[b][a]` is `true` if
+/// block `a` dominates block `b` (i.e. every path from the entry block to `b`
+/// passes through `a`).
+///
+/// This uses the simple iterative algorithm (the functions we compile are
+/// small enough that there is no need for anything cleverer). Unreachable
+/// blocks are considered to be dominated by every block.
+pub(super) fn dominators(function: &Function) -> Vec<Vec<bool>> {
+    let n = function.blocks.len();
+    let predecessors = predecessors(function);
+    let reachable = reachable(function);
+
+    let mut dominators = vec![vec![true; n]; n];
+    dominators[0] = (0..n).map(|i| i == 0).collect();
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for block in 1..n {
+            if !reachable[block] {
+                continue;
+            }
+            let mut new = vec![true; n];
+            for predecessor in &predecessors[block] {
+                let predecessor = predecessor.0 as usize;
+                if !reachable[predecessor] {
+                    continue;
+                }
+                for (new, dominates) in new.iter_mut().zip(&dominators[predecessor]) {
+                    *new &= *dominates;
+                }
+            }
+            new[block] = true;
+            if new != dominators[block] {
+                dominators[block] = new;
+                changed = true;
+            }
+        }
+    }
+
+    dominators
+}
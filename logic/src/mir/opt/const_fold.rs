@@ -0,0 +1,78 @@
+use crate::mir::{BinaryOp, Constant, Function, Operand, Rvalue, Statement, Terminator, UnaryOp};
+
+use super::propagate::propagate;
+
+/// Propagates constants into the places where they are used, and then
+/// evaluates every operation whose operands are all constants (including
+/// conditional branches, which are replaced by a jump to the block which would
+/// always be chosen).
+pub(super) fn constant_folding(function: &mut Function) -> bool {
+    let mut changed = propagate(function, |operand| matches!(operand, Operand::Const(_)));
+
+    for block in &mut function.blocks {
+        for statement in &mut block.statements {
+            if let Statement::Assign(_, rvalue) = statement {
+                if let Some(constant) = fold(rvalue) {
+                    *rvalue = Rvalue::Use(Operand::Const(constant));
+                    changed = true;
+                }
+            }
+        }
+
+        if let Terminator::Branch {
+            condition: Operand::Const(Constant::Bool(condition)),
+            then,
+            otherwise,
+        } = block.terminator
+        {
+            block.terminator = Terminator::Jump(if condition { then } else { otherwise });
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+/// Evaluates the rvalue, if it is an operation on constants.
+fn fold(rvalue: &Rvalue) -> Option<Constant> {
+    match rvalue {
+        Rvalue::Binary(op, Operand::Const(left), Operand::Const(right)) => {
+            fold_binary(*op, left, right)
+        }
+        Rvalue::Unary(UnaryOp::Negate, Operand::Const(Constant::Int(int))) => {
+            Some(Constant::Int(int.wrapping_neg()))
+        }
+        Rvalue::Unary(UnaryOp::Negate, Operand::Const(Constant::Real(real))) => {
+            Some(Constant::Real(-real))
+        }
+        _ => None,
+    }
+}
+
+/// Evaluates the binary operation in the same way as the compiled program
+/// would (integer arithmetic wraps on overflow).
+fn fold_binary(op: BinaryOp, left: &Constant, right: &Constant) -> Option<Constant> {
+    use Constant::*;
+
+    Some(match (op, left, right) {
+        (BinaryOp::Add, Int(a), Int(b)) => Int(a.wrapping_add(*b)),
+        (BinaryOp::Subtract, Int(a), Int(b)) => Int(a.wrapping_sub(*b)),
+        (BinaryOp::Multiply, Int(a), Int(b)) => Int(a.wrapping_mul(*b)),
+        // division by zero (and overflowing division) is left for the program
+        // to trap on when it is run
+        (BinaryOp::Divide, Int(a), Int(b)) => Int(a.checked_div(*b)?),
+        (BinaryOp::Add, Real(a), Real(b)) => Real(a + b),
+        (BinaryOp::Subtract, Real(a), Real(b)) => Real(a - b),
+        (BinaryOp::Multiply, Real(a), Real(b)) => Real(a * b),
+        (BinaryOp::Divide, Real(a), Real(b)) => Real(a / b),
+        (BinaryOp::Equal, Int(a), Int(b)) => Bool(a == b),
+        (BinaryOp::Equal, Real(a), Real(b)) => Bool(a == b),
+        (BinaryOp::Equal, Bool(a), Bool(b)) => Bool(a == b),
+        (BinaryOp::NotEqual, Int(a), Int(b)) => Bool(a != b),
+        (BinaryOp::NotEqual, Real(a), Real(b)) => Bool(a != b),
+        (BinaryOp::NotEqual, Bool(a), Bool(b)) => Bool(a != b),
+        (BinaryOp::LessThan, Int(a), Int(b)) => Bool(a < b),
+        (BinaryOp::Concat, Str(a), Str(b)) => Str(format!("{a}{b}")),
+        _ => return None,
+    })
+}
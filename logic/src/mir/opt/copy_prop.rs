@@ -0,0 +1,11 @@
+use crate::mir::{Function, Operand};
+
+use super::propagate::propagate;
+
+/// After `_1 = _0`, uses of `_1` are replaced with `_0` (for as long as neither
+/// of them is reassigned). This mostly removes the copies introduced when a
+/// value is assigned to a variable, after which the variable is often unused
+/// (and can then be removed by dead code elimination).
+pub(super) fn copy_propagation(function: &mut Function) -> bool {
+    propagate(function, |operand| matches!(operand, Operand::Local(_)))
+}
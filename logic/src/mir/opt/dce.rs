@@ -0,0 +1,201 @@
+use crate::mir::{BlockId, Function, Local, Operand, Rvalue, Statement, Terminator};
+
+use super::{
+    cfg::{predecessors, reachable},
+    has_side_effects,
+};
+
+/// Removes
+/// - blocks which can never be reached (such as statements after a `return`,
+///   or the branch of an `if` statement whose condition is always false)
+/// - assignments to locals whose value is never used
+/// - locals which are no longer mentioned anywhere in the function
+/// - location markers which are immediately followed by another marker (so no
+///   code comes from the location they describe)
+///
+/// Blocks which can only be reached by jumping from the end of a single other
+/// block are also merged into that block.
+pub(super) fn dead_code_elimination(function: &mut Function) -> bool {
+    let mut changed = merge_blocks(function);
+    changed |= remove_unreachable_blocks(function);
+    changed |= remove_dead_assignments(function);
+    changed |= remove_unused_locals(function);
+    changed |= remove_empty_locations(function);
+    changed
+}
+
+/// Merges `b` into `a` if `a` always jumps to `b`, and nothing else jumps to
+/// `b`. Afterwards, `b` can no longer be reached.
+fn merge_blocks(function: &mut Function) -> bool {
+    let mut changed = false;
+
+    let mut a = 0;
+    while a < function.blocks.len() {
+        let b = match function.blocks[a].terminator {
+            Terminator::Jump(b) if b.0 != 0 && b.0 as usize != a => b.0 as usize,
+            _ => {
+                a += 1;
+                continue;
+            }
+        };
+        if predecessors(function)[b].len() != 1 {
+            a += 1;
+            continue;
+        }
+
+        let statements = std::mem::take(&mut function.blocks[b].statements);
+        let terminator =
+            std::mem::replace(&mut function.blocks[b].terminator, Terminator::Unreachable);
+        function.blocks[a].statements.extend(statements);
+        function.blocks[a].terminator = terminator;
+        changed = true;
+        // `a` might now jump to another block which can be merged into it
+    }
+
+    changed
+}
+
+fn remove_unreachable_blocks(function: &mut Function) -> bool {
+    let reachable = reachable(function);
+    if reachable.iter().all(|reachable| *reachable) {
+        return false;
+    }
+
+    // the new id of every block which is kept
+    let mut new_ids = Vec::with_capacity(function.blocks.len());
+    let mut next = 0;
+    for reachable in &reachable {
+        new_ids.push(BlockId(next));
+        if *reachable {
+            next += 1;
+        }
+    }
+
+    let blocks = std::mem::take(&mut function.blocks);
+    function.blocks = blocks
+        .into_iter()
+        .zip(reachable)
+        .filter_map(|(block, reachable)| reachable.then_some(block))
+        .collect();
+    for block in &mut function.blocks {
+        for successor in block.terminator.successors_mut() {
+            *successor = new_ids[successor.0 as usize];
+        }
+    }
+
+    true
+}
+
+fn remove_dead_assignments(function: &mut Function) -> bool {
+    let mut changed = false;
+
+    loop {
+        let mut reads = vec![0; function.locals.len()];
+        for block in &function.blocks {
+            for local in block.reads() {
+                reads[local.0 as usize] += 1;
+            }
+        }
+
+        let mut removed = false;
+        let function_ref = &*function;
+        let dead = |statement: &Statement| match statement {
+            // assigning a local to itself does nothing
+            Statement::Assign(local, Rvalue::Use(Operand::Local(value))) if local == value => true,
+            Statement::Assign(local, rvalue) => {
+                reads[local.0 as usize] == 0 && !has_side_effects(function_ref, rvalue)
+            }
+            _ => false,
+        };
+        let dead_statements = function
+            .blocks
+            .iter()
+            .map(|block| block.statements.iter().map(&dead).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        for (block, dead) in function.blocks.iter_mut().zip(dead_statements) {
+            let mut dead = dead.into_iter();
+            block.statements.retain(|_| {
+                let dead = dead.next().unwrap();
+                removed |= dead;
+                !dead
+            });
+        }
+
+        if !removed {
+            return changed;
+        }
+        changed = true;
+    }
+}
+
+fn remove_empty_locations(function: &mut Function) -> bool {
+    let mut changed = false;
+    for block in &mut function.blocks {
+        let mut i = 0;
+        while i + 1 < block.statements.len() {
+            if let [Statement::Location(_), Statement::Location(_)] = block.statements[i..i + 2] {
+                block.statements.remove(i);
+                changed = true;
+            } else {
+                i += 1;
+            }
+        }
+    }
+    changed
+}
+
+fn remove_unused_locals(function: &mut Function) -> bool {
+    let mut used = vec![false; function.locals.len()];
+    for param in &function.params {
+        used[param.0 as usize] = true;
+    }
+    for block in &function.blocks {
+        for local in block.reads() {
+            used[local.0 as usize] = true;
+        }
+        for statement in &block.statements {
+            if let Statement::Assign(local, _) = statement {
+                used[local.0 as usize] = true;
+            }
+        }
+    }
+    if used.iter().all(|used| *used) {
+        return false;
+    }
+
+    let mut new_ids = Vec::with_capacity(used.len());
+    let mut next = 0;
+    for used in &used {
+        new_ids.push(Local(next));
+        if *used {
+            next += 1;
+        }
+    }
+    let rename = |local: &mut Local| *local = new_ids[local.0 as usize];
+
+    let locals = std::mem::take(&mut function.locals);
+    function.locals = locals
+        .into_iter()
+        .zip(&used)
+        .filter_map(|(local, used)| used.then_some(local))
+        .collect();
+    function.params.iter_mut().for_each(rename);
+    for block in &mut function.blocks {
+        for statement in &mut block.statements {
+            if let Statement::Assign(local, _) = statement {
+                rename(local);
+            }
+            for operand in statement.operands_mut() {
+                if let Operand::Local(local) = operand {
+                    rename(local);
+                }
+            }
+        }
+        if let Some(Operand::Local(local)) = block.terminator.operand_mut() {
+            rename(local);
+        }
+    }
+
+    true
+}
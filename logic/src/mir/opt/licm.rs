@@ -0,0 +1,146 @@
+//! Loop-invariant code motion.
+//!
+//! For example, in
+//!
+//! ```ignore
+//! while i != 10
+//!   total = total + x * 2
+//!   i = i + 1
+//! endwhile
+//! ```
+//!
+//! `x * 2` is the same on every iteration of the loop, so it is computed once
+//! (before the loop starts) rather than on every iteration.
+
+use crate::mir::{BlockId, Function, Local, Rvalue, Statement, Terminator};
+
+use super::{
+    cfg::{dominators, predecessors, reachable},
+    has_side_effects,
+};
+
+pub(super) fn loop_invariant_code_motion(function: &mut Function) -> bool {
+    let mut changed = false;
+    for (header, body) in loops(function) {
+        if let Some(preheader) = preheader(function, header, &body) {
+            while hoist_one(function, preheader, &body) {
+                changed = true;
+            }
+        }
+    }
+    changed
+}
+
+/// Finds the loops in the function, returning the header of each loop and
+/// whether each block is part of the loop.
+///
+/// A loop is formed by a "back edge" (a jump from a block to a block which
+/// dominates it); this is what every `while` loop is lowered to.
+fn loops(function: &Function) -> Vec<(BlockId, Vec<bool>)> {
+    let dominators = dominators(function);
+    let predecessors = predecessors(function);
+    let reachable = reachable(function);
+    let mut loops: Vec<(BlockId, Vec<bool>)> = vec![];
+
+    for (from, block) in function.blocks.iter().enumerate() {
+        for header in block.terminator.successors() {
+            if !reachable[from] || !dominators[from][header.0 as usize] {
+                continue;
+            }
+
+            // the loop consists of the header, and every block which can reach
+            // the back edge without going through the header
+            let mut body = vec![false; function.blocks.len()];
+            body[header.0 as usize] = true;
+            let mut stack = vec![from];
+            while let Some(block) = stack.pop() {
+                if !std::mem::replace(&mut body[block], true) {
+                    stack.extend(predecessors[block].iter().map(|block| block.0 as usize));
+                }
+            }
+
+            match loops.iter_mut().find(|(existing, _)| *existing == header) {
+                Some((_, existing)) => {
+                    for (existing, new) in existing.iter_mut().zip(body) {
+                        *existing |= new;
+                    }
+                }
+                None => loops.push((header, body)),
+            }
+        }
+    }
+
+    loops
+}
+
+/// Returns the block which always runs immediately before the loop is entered
+/// (if there is one).
+fn preheader(function: &Function, header: BlockId, body: &[bool]) -> Option<BlockId> {
+    let predecessors = predecessors(function);
+    let mut outside = predecessors[header.0 as usize]
+        .iter()
+        .filter(|block| !body[block.0 as usize]);
+    let preheader = *outside.next()?;
+    if outside.next().is_some() {
+        return None;
+    }
+    (function.blocks[preheader.0 as usize].terminator == Terminator::Jump(header))
+        .then_some(preheader)
+}
+
+/// Moves a single loop-invariant statement into the preheader, returning
+/// `false` if there are none left.
+fn hoist_one(function: &mut Function, preheader: BlockId, body: &[bool]) -> bool {
+    let mut assignments = vec![0; function.locals.len()];
+    let mut assigned_in_loop = vec![false; function.locals.len()];
+    for (i, block) in function.blocks.iter().enumerate() {
+        for statement in &block.statements {
+            if let Statement::Assign(local, _) = statement {
+                assignments[local.0 as usize] += 1;
+                assigned_in_loop[local.0 as usize] |= body[i];
+            }
+        }
+    }
+
+    let invariant = |local: &Local, rvalue: &Rvalue| {
+        // only temporaries are moved (these are only assigned once, before
+        // they are used, so moving the assignment earlier cannot change the
+        // value which is seen by any of their uses)
+        function.locals[local.0 as usize].name.is_none()
+            && assignments[local.0 as usize] == 1
+            // loads are not moved, because the memory might be changed inside
+            // the loop (and records are not moved, because every iteration
+            // should get a new one)
+            && matches!(rvalue, Rvalue::Use(_) | Rvalue::Binary(..) | Rvalue::Unary(..))
+            && !has_side_effects(function, rvalue)
+            && rvalue
+                .operands()
+                .into_iter()
+                .filter_map(|operand| operand.as_local())
+                .all(|operand| !assigned_in_loop[operand.0 as usize])
+    };
+
+    let found = function.blocks.iter().enumerate().find_map(|(i, block)| {
+        if !body[i] {
+            return None;
+        }
+        block
+            .statements
+            .iter()
+            .position(|statement| {
+                matches!(statement, Statement::Assign(local, rvalue) if invariant(local, rvalue))
+            })
+            .map(|position| (i, position))
+    });
+
+    match found {
+        Some((block, position)) => {
+            let statement = function.blocks[block].statements.remove(position);
+            function.blocks[preheader.0 as usize]
+                .statements
+                .push(statement);
+            true
+        }
+        None => false,
+    }
+}
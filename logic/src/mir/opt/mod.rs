@@ -0,0 +1,149 @@
+//! Optimisations which are performed on the MIR (before it is passed to the
+//! code generator).
+//!
+//! Each optimisation is a separate pass, which rewrites a single function and
+//! reports whether it changed anything. The passes enabled by an
+//! [`OptLevel`] are run over and over again until none of them can make any
+//! further changes (one pass often creates new opportunities for another;
+//! for example once a constant has been propagated into a condition, the
+//! branch can be removed, which might make some code unreachable).
+//!
+//! For teaching (and debugging) the program can be observed after every pass
+//! which changes it; see [`optimise`].
+
+use super::{BinaryOp, Function, Program, Rvalue, Type};
+
+/// Information about the control flow graph of a function.
+mod cfg;
+/// Replaces uses of locals which are known to hold a constant with the
+/// constant, and evaluates operations on constants.
+mod const_fold;
+/// Replaces uses of locals which are copies of other locals.
+mod copy_prop;
+/// Removes code which can never be run, or whose result is never used.
+mod dce;
+/// Moves computations which produce the same result on every iteration of a
+/// loop out of the loop.
+mod licm;
+/// Tracks the values which locals are known to hold.
+mod propagate;
+#[cfg(test)]
+mod test;
+
+/// How much effort should be put into optimising the program.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OptLevel {
+    /// No optimisations are performed.
+    #[default]
+    O0,
+    /// Constant folding, copy propagation and dead code elimination.
+    O1,
+    /// Everything from [`OptLevel::O1`], as well as loop-invariant code
+    /// motion.
+    O2,
+}
+
+impl OptLevel {
+    /// Parses a command line flag (`-O0`, `-O1` or `-O2`).
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        match flag {
+            "-O0" => Some(OptLevel::O0),
+            "-O1" => Some(OptLevel::O1),
+            "-O2" => Some(OptLevel::O2),
+            _ => None,
+        }
+    }
+
+    /// The value of Cranelift's `opt_level` setting which corresponds to this
+    /// level.
+    pub fn cranelift_opt_level(self) -> &'static str {
+        match self {
+            OptLevel::O0 => "none",
+            OptLevel::O1 => "speed",
+            OptLevel::O2 => "speed_and_size",
+        }
+    }
+}
+
+/// A single optimisation pass.
+#[derive(Clone, Copy)]
+pub struct Pass {
+    pub name: &'static str,
+    /// Rewrites the function, returning `true` if anything was changed.
+    pub run: fn(&mut Function) -> bool,
+}
+
+/// Returns the passes which are run at the given optimisation level (in the
+/// order in which they are run).
+pub fn passes(level: OptLevel) -> Vec<Pass> {
+    let constant_folding = Pass {
+        name: "constant folding",
+        run: const_fold::constant_folding,
+    };
+    let copy_propagation = Pass {
+        name: "copy propagation",
+        run: copy_prop::copy_propagation,
+    };
+    let dead_code_elimination = Pass {
+        name: "dead code elimination",
+        run: dce::dead_code_elimination,
+    };
+    let loop_invariant_code_motion = Pass {
+        name: "loop-invariant code motion",
+        run: licm::loop_invariant_code_motion,
+    };
+
+    match level {
+        OptLevel::O0 => vec![],
+        OptLevel::O1 => vec![constant_folding, copy_propagation, dead_code_elimination],
+        OptLevel::O2 => vec![
+            constant_folding,
+            copy_propagation,
+            loop_invariant_code_motion,
+            dead_code_elimination,
+        ],
+    }
+}
+
+/// The maximum number of times the whole pipeline is run (this is just a
+/// safety net; in practice the passes stop changing the program long before
+/// this).
+const MAX_ROUNDS: usize = 16;
+
+/// Optimises the program. `observe` is called with the name of the pass and
+/// the resulting program after every pass which changes the program.
+pub fn optimise(program: &mut Program, level: OptLevel, mut observe: impl FnMut(&str, &Program)) {
+    let passes = passes(level);
+
+    for _ in 0..MAX_ROUNDS {
+        let mut changed = false;
+        for pass in &passes {
+            let mut pass_changed = false;
+            for function in &mut program.functions {
+                pass_changed |= (pass.run)(function);
+            }
+            if pass_changed {
+                observe(pass.name, program);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+}
+
+/// Returns `true` if evaluating the rvalue might do something other than
+/// produce a value (in which case it cannot be removed or moved).
+fn has_side_effects(function: &Function, rvalue: &Rvalue) -> bool {
+    match rvalue {
+        Rvalue::Call(..) => true,
+        // integer division traps if the divisor is zero
+        Rvalue::Binary(BinaryOp::Divide, left, _) => function.operand_ty(left) != Type::Real,
+        Rvalue::Use(_)
+        | Rvalue::Binary(..)
+        | Rvalue::Unary(..)
+        | Rvalue::Record { .. }
+        | Rvalue::Load { .. } => false,
+    }
+}
@@ -0,0 +1,116 @@
+//! A forward data flow analysis which works out which locals are known to be
+//! equal to some other operand (either a constant, or another local) at every
+//! point in a function, and then replaces uses of those locals with the
+//! operand.
+//!
+//! For example, in
+//!
+//! ```text
+//! bb0:
+//!   _0 = 5
+//!   branch _1, bb1, bb2
+//! bb1:
+//!   _0 = 6
+//!   jump bb2
+//! bb2:
+//!   return _0
+//! ```
+//!
+//! `_0` is known to be `5` at the end of `bb0` and `6` at the end of `bb1`;
+//! as `bb2` can be reached from either, nothing is known about `_0` at the
+//! start of `bb2` (so it is left alone).
+
+use rustc_hash::FxHashMap;
+
+use crate::mir::{Function, Local, Operand, Rvalue, Statement};
+
+/// The operand which each local is known to be equal to.
+type Facts = FxHashMap<Local, Operand>;
+
+/// Replaces uses of locals with the operand they are known to be equal to.
+/// Only facts for which `track` returns `true` are recorded (so this can be
+/// used for propagating constants, copies or both).
+///
+/// Returns `true` if anything was changed.
+pub(super) fn propagate(function: &mut Function, track: impl Fn(&Operand) -> bool) -> bool {
+    let entry_facts = analyse(function, &track);
+
+    let mut changed = false;
+    for (block, facts) in function.blocks.iter_mut().zip(entry_facts) {
+        // blocks which cannot be reached are left alone
+        let mut facts = match facts {
+            Some(facts) => facts,
+            None => continue,
+        };
+
+        for statement in &mut block.statements {
+            for operand in statement.operands_mut() {
+                changed |= substitute(operand, &facts);
+            }
+            transfer(statement, &mut facts, &track);
+        }
+        if let Some(operand) = block.terminator.operand_mut() {
+            changed |= substitute(operand, &facts);
+        }
+    }
+    changed
+}
+
+/// Works out what is known at the start of every block (`None` if the block
+/// cannot be reached).
+fn analyse(function: &Function, track: &impl Fn(&Operand) -> bool) -> Vec<Option<Facts>> {
+    let mut entry_facts: Vec<Option<Facts>> = vec![None; function.blocks.len()];
+    entry_facts[0] = Some(Facts::default());
+
+    let mut worklist = vec![0];
+    while let Some(block) = worklist.pop() {
+        let mut facts = entry_facts[block].clone().unwrap();
+        for statement in &function.blocks[block].statements {
+            transfer(statement, &mut facts, track);
+        }
+
+        for successor in function.blocks[block].terminator.successors() {
+            let successor = successor.0 as usize;
+            let new = match &entry_facts[successor] {
+                None => facts.clone(),
+                // only facts which hold on every path into the block are kept
+                Some(old) => old
+                    .iter()
+                    .filter(|(local, operand)| facts.get(local) == Some(operand))
+                    .map(|(local, operand)| (*local, operand.clone()))
+                    .collect(),
+            };
+            if entry_facts[successor].as_ref() != Some(&new) {
+                entry_facts[successor] = Some(new);
+                worklist.push(successor);
+            }
+        }
+    }
+
+    entry_facts
+}
+
+/// Updates the facts to account for the effect of the statement.
+fn transfer(statement: &Statement, facts: &mut Facts, track: &impl Fn(&Operand) -> bool) {
+    if let Statement::Assign(local, rvalue) = statement {
+        // anything we knew about the old value of the local is no longer true
+        facts.remove(local);
+        facts.retain(|_, operand| operand.as_local() != Some(*local));
+
+        if let Rvalue::Use(operand) = rvalue {
+            if track(operand) && operand.as_local() != Some(*local) {
+                facts.insert(*local, operand.clone());
+            }
+        }
+    }
+}
+
+fn substitute(operand: &mut Operand, facts: &Facts) -> bool {
+    match operand.as_local().and_then(|local| facts.get(&local)) {
+        Some(replacement) => {
+            *operand = replacement.clone();
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,154 @@
+use crate::{
+    mir::{lower, BinaryOp, Program, Rvalue, Statement},
+    parse::parse,
+    ty::type_check,
+};
+
+use super::{optimise, OptLevel};
+
+fn optimised(input: &str, level: OptLevel) -> Program {
+    let table = parse(input).unwrap();
+    let env = type_check(&table).unwrap();
+    let mut program = lower(&table, &env).unwrap();
+    optimise(&mut program, level, |_, _| {});
+    program
+}
+
+const LOOP: &str = "function f(x)
+  i = 0
+  total = 0
+  while i != 10
+    total = total + x * 2
+    i = i + 1
+  endwhile
+  return total
+endfunction
+";
+
+#[test]
+fn o0_does_nothing() {
+    let table = parse(LOOP).unwrap();
+    let env = type_check(&table).unwrap();
+    let program = lower(&table, &env).unwrap();
+    assert_eq!(optimised(LOOP, OptLevel::O0), program);
+}
+
+#[test]
+fn constant_folding() {
+    let program = optimised(
+        "function main()\n  X = 2 * 3 + 1\n  return X\nendfunction\n",
+        OptLevel::O1,
+    );
+    assert_eq!(
+        program.to_string(),
+        "function main() -> Int
+
+  bb0:
+    ;; 3:3
+    return 7
+"
+    );
+}
+
+#[test]
+fn constant_branches_are_removed() {
+    let program = optimised(
+        "function main()\n  if 1 == 2 then\n    return 1\n  else\n    return 2\n  endif\nendfunction\n",
+        OptLevel::O1,
+    );
+    assert_eq!(
+        program.to_string(),
+        "function main() -> Int
+
+  bb0:
+    ;; 5:5
+    return 2
+"
+    );
+}
+
+#[test]
+fn dead_code_after_return() {
+    let program = optimised(
+        "function main()\n  return 1\n  print_int(2)\nendfunction\n",
+        OptLevel::O1,
+    );
+    assert_eq!(
+        program.to_string(),
+        "function main() -> Int
+
+  bb0:
+    ;; 2:3
+    return 1
+"
+    );
+}
+
+#[test]
+fn copy_propagation() {
+    let program = optimised(
+        "function double(x)\n  return x * 2\nendfunction\nfunction main()\n  Y = double(4)\n  return Y\nendfunction\n",
+        OptLevel::O1,
+    );
+    assert_eq!(
+        program.function("main").unwrap().to_string(),
+        "function main() -> Int
+  let _0: Int
+
+  bb0:
+    ;; 5:3
+    _0 = call double(4)
+    ;; 6:3
+    return _0
+"
+    );
+}
+
+#[test]
+fn division_by_zero_is_not_folded() {
+    let program = optimised(
+        "function main()\n  return 1 / 0\nendfunction\n",
+        OptLevel::O2,
+    );
+    assert!(program.functions[0].blocks[0]
+        .statements
+        .iter()
+        .any(|statement| matches!(
+            statement,
+            Statement::Assign(_, Rvalue::Binary(BinaryOp::Divide, _, _))
+        )));
+}
+
+#[test]
+fn loop_invariant_code_motion() {
+    let multiplies_in_entry = |program: &Program| {
+        program.functions[0].blocks[0]
+            .statements
+            .iter()
+            .any(|statement| {
+                matches!(
+                    statement,
+                    Statement::Assign(_, Rvalue::Binary(BinaryOp::Multiply, _, _))
+                )
+            })
+    };
+
+    // `x * 2` is only moved out of the loop at `-O2`
+    assert!(!multiplies_in_entry(&optimised(LOOP, OptLevel::O1)));
+    assert!(multiplies_in_entry(&optimised(LOOP, OptLevel::O2)));
+}
+
+#[test]
+fn passes_are_observed() {
+    let table = parse("function main()\n  X = 2 * 3 + 1\n  return X\nendfunction\n").unwrap();
+    let env = type_check(&table).unwrap();
+    let mut program = lower(&table, &env).unwrap();
+
+    let mut observed = vec![];
+    optimise(&mut program, OptLevel::O1, |pass, program| {
+        observed.push((pass.to_owned(), program.to_string()))
+    });
+
+    assert_eq!(observed[0].0, "constant folding");
+    assert_eq!(observed.last().unwrap().1, program.to_string());
+}
@@ -0,0 +1,163 @@
+use crate::{
+    mir::{
+        lower, Callee, Constant, Operand, Program, Rvalue, SourceLocation, Statement, Terminator,
+    },
+    parse::{parse, parse_with_prelude},
+    ty::type_check,
+};
+
+fn mir_of(input: &str) -> Program {
+    let table = parse(input).unwrap();
+    let env = type_check(&table).unwrap();
+    lower(&table, &env).unwrap()
+}
+
+#[test]
+fn dump() {
+    let program = mir_of(
+        "function double(x)\n  return x * 2\nendfunction\nfunction main()\n  Y = double(4)\n  return Y\nendfunction\n",
+    );
+    assert_eq!(
+        program.to_string(),
+        "function double(_0: Int) -> Int
+  let _0: Int (x)
+  let _1: Int
+
+  bb0:
+    ;; 2:3
+    _1 = mul _0, 2
+    return _1
+
+function main() -> Int
+  let _0: Int
+  let _1: Int (Y)
+
+  bb0:
+    ;; 5:3
+    _0 = call double(4)
+    _1 = _0
+    ;; 6:3
+    return _1
+"
+    );
+}
+
+#[test]
+fn else_if_branches() {
+    let program = mir_of(
+        "function sign(x)\n  if x == 0 then\n    return 0\n  elseif x == 1 then\n    return 1\n  else\n    return 2\n  endif\nendfunction\n",
+    );
+    let function = program.function("sign").unwrap();
+    let branches = function
+        .blocks
+        .iter()
+        .filter(|block| matches!(block.terminator, Terminator::Branch { .. }))
+        .count();
+    assert_eq!(branches, 2);
+    let returns = function
+        .blocks
+        .iter()
+        .filter(|block| matches!(block.terminator, Terminator::Return(_)))
+        .count();
+    assert_eq!(returns, 3);
+    // every path returns, so the block after the `if` statement can never be
+    // reached
+    assert!(function
+        .blocks
+        .iter()
+        .any(|block| block.terminator == Terminator::Unreachable));
+}
+
+#[test]
+fn field_offsets_follow_the_definition() {
+    let program = mir_of(
+        "record Point\n  x of Int\n  y of Real\nendrecord\nfunction main()\n  P = Point { y: 1.5, x: 2 }\n  return P.x\nendfunction\n",
+    );
+    let function = program.function("main").unwrap();
+    let statements = &function.blocks[0].statements;
+    assert!(statements.iter().any(|statement| matches!(
+        statement,
+        Statement::Assign(_, Rvalue::Record { size: 16, fields })
+            if fields[0] == (0, Operand::Const(Constant::Int(2)))
+                && fields[1] == (8, Operand::Const(Constant::Real(1.5)))
+    )));
+    assert!(statements.iter().any(|statement| matches!(
+        statement,
+        Statement::Assign(_, Rvalue::Load { offset: 0, .. })
+    )));
+}
+
+#[test]
+fn builtins_are_resolved() {
+    let program = mir_of("function main()\n  print(abs(-1.5), True)\n  return 0\nendfunction\n");
+    let calls = program.function("main").unwrap().blocks[0]
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Assign(_, Rvalue::Call(Callee::Native(name), _))
+            | Statement::Eval(Rvalue::Call(Callee::Native(name), _)) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        calls,
+        [
+            "abs_real",
+            "write_real",
+            "write_space",
+            "write_bool",
+            "write_newline"
+        ]
+    );
+}
+
+#[test]
+fn statements_are_marked_with_their_locations() {
+    let program = mir_of(
+        "function main()\n  x = 0\n  while x != 3\n    x = x + 1\n  endwhile\n  if x == 1 then\n    print_int(1)\n  elseif x == 3 then\n    print_int(3)\n  endif\n  return x\nendfunction\n",
+    );
+    let function = program.function("main").unwrap();
+    assert_eq!(
+        function.location,
+        Some(SourceLocation { line: 1, column: 1 })
+    );
+    let mut locations = function
+        .blocks
+        .iter()
+        .flat_map(|block| &block.statements)
+        .filter_map(|statement| match statement {
+            Statement::Location(location) => Some((location.line, location.column)),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    locations.sort_unstable();
+    // every use of `x` refers to the span where it was first used, so the
+    // location of each statement has to be recorded separately
+    assert_eq!(
+        locations,
+        [
+            (2, 3),
+            (3, 3),
+            (4, 5),
+            (6, 3),
+            (7, 5),
+            (8, 3),
+            (9, 5),
+            (11, 3)
+        ]
+    );
+
+    // the prelude is not part of the program's source code
+    let table = parse_with_prelude("function main()\n  return 0\nendfunction\n").unwrap();
+    let env = type_check(&table).unwrap();
+    let program = lower(&table, &env).unwrap();
+    for function in &program.functions {
+        let has_locations = function.name == "main";
+        assert_eq!(
+            function.location.is_some(),
+            has_locations,
+            "{}",
+            function.name
+        );
+    }
+}
@@ -0,0 +1,591 @@
+//! A lossless (or "concrete") syntax tree for programs.
+//!
+//! The parse table (see [`ParseTable`](super::table::ParseTable)) only stores what the compiler needs,
+//! so the comments, blank lines and whitespace in a program are thrown away
+//! when it is parsed. The syntax tree keeps all of them: every byte of the
+//! source code is part of exactly one token, or of the trivia (whitespace,
+//! comments and lines which do not contain any tokens) attached to a token,
+//! so writing the tree out reproduces the source code byte-for-byte (even if
+//! the program does not parse).
+//!
+//! Trivia which comes after a token on the same line (spaces, and a comment
+//! at the end of the line) is attached to that token, and all other trivia is
+//! attached to the token which follows it. This means that the comments on
+//! the lines above a statement are part of the first token of the statement,
+//! which is how doc comments are found (see [`SyntaxTree::doc_comment`]).
+//!
+//! The tree is built from the tokens using only the keywords which start and
+//! end each block (indentation is treated as trivia), and is built before the
+//! rest of the program is parsed. It is stored in the parse table, and each
+//! statement in the parse table starts at the same place as the node which it
+//! was parsed from (see [`ParseTable::syntax`](super::table::ParseTable::syntax)).
+
+#[cfg(test)]
+mod test;
+
+use std::fmt;
+
+use crate::diagnostics::span::IndexOnlySpan;
+
+/// The words which are treated as keywords by the lexer. Some of these (for
+/// example `next` and `record`) can also be used as names, which is why the
+/// tree builder checks what comes after them.
+pub const KEYWORDS: &[&str] = &[
+    "else",
+    "elseif",
+    "endfunction",
+    "endif",
+    "endrecord",
+    "endwhile",
+    "extern",
+    "False",
+    "for",
+    "from",
+    "function",
+    "if",
+    "next",
+    "of",
+    "record",
+    "return",
+    "step",
+    "then",
+    "to",
+    "True",
+    "while",
+];
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    Keyword,
+    Ident,
+    Number,
+    /// A string literal (including the quotes).
+    String,
+    /// An operator, bracket or other punctuation (e.g. `==` or `,`).
+    Punct,
+    /// A new line at the end of a line which contains other tokens (new lines
+    /// which end blank lines or comments are trivia).
+    Newline,
+    /// A character which cannot start any other kind of token.
+    Unknown,
+    /// The (empty) token at the end of the file, which the trivia at the end
+    /// of the file is attached to.
+    Eof,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TriviaKind {
+    /// Spaces, tabs and carriage returns.
+    Whitespace,
+    /// A new line at the end of a line which does not contain any tokens.
+    Newline,
+    /// A comment (from the `;;` to the end of the line).
+    Comment,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Trivia {
+    pub(crate) kind: TriviaKind,
+    pub(crate) span: IndexOnlySpan,
+}
+
+impl Trivia {
+    pub fn kind(&self) -> TriviaKind {
+        self.kind
+    }
+
+    pub fn span(&self) -> IndexOnlySpan {
+        self.span
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) span: IndexOnlySpan,
+    /// The trivia which comes before the token.
+    pub(crate) leading: Vec<Trivia>,
+    /// The trivia which comes after the token (on the same line).
+    pub(crate) trailing: Vec<Trivia>,
+}
+
+impl Token {
+    pub fn kind(&self) -> TokenKind {
+        self.kind
+    }
+
+    pub fn span(&self) -> IndexOnlySpan {
+        self.span
+    }
+
+    pub fn leading(&self) -> &[Trivia] {
+        &self.leading
+    }
+
+    pub fn trailing(&self) -> &[Trivia] {
+        &self.trailing
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum NodeKind {
+    /// The whole program.
+    Root,
+    /// The statements inside a function, loop, or branch of an `if`
+    /// statement.
+    Block,
+    Function,
+    Return,
+    If,
+    While,
+    For,
+    Record,
+    /// A field of a record.
+    Field,
+    Extern,
+    /// Any other statement (e.g. an assignment or a function call).
+    Statement,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Element {
+    Node(Node),
+    /// The index of a token (in [`SyntaxTree::tokens`]).
+    Token(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    pub(crate) kind: NodeKind,
+    pub(crate) children: Vec<Element>,
+}
+
+impl Default for Node {
+    fn default() -> Self {
+        Self {
+            kind: NodeKind::Root,
+            children: vec![],
+        }
+    }
+}
+
+impl Node {
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    pub fn children(&self) -> &[Element] {
+        &self.children
+    }
+
+    /// The index of the first token in the node.
+    pub fn first_token(&self) -> Option<usize> {
+        self.children.iter().find_map(|child| match child {
+            Element::Node(node) => node.first_token(),
+            Element::Token(token) => Some(*token),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+/// The syntax tree of a program (see the [module documentation](self)).
+pub struct SyntaxTree<'i> {
+    source: &'i str,
+    tokens: Vec<Token>,
+    root: Node,
+}
+
+/// A comment in the source code.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Comment<'i> {
+    /// Where the comment starts in the source code.
+    pub(crate) index: usize,
+    /// The column which the comment starts in.
+    pub(crate) column: usize,
+    /// The text of the comment (without the `;;`).
+    pub(crate) text: &'i str,
+}
+
+impl<'i> SyntaxTree<'i> {
+    /// Builds the syntax tree for the source code (this cannot fail).
+    pub fn new(source: &'i str) -> Self {
+        let tokens = lex(source);
+        let root = Builder {
+            source,
+            tokens: &tokens,
+            next: 0,
+            ends: vec![],
+        }
+        .root();
+        Self {
+            source,
+            tokens,
+            root,
+        }
+    }
+
+    pub fn source(&self) -> &'i str {
+        self.source
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    pub fn root(&self) -> &Node {
+        &self.root
+    }
+
+    /// The text of the token (without its trivia).
+    pub fn text(&self, token: &Token) -> &'i str {
+        &self.source[token.span.range()]
+    }
+
+    /// Writes out the node (including the trivia of each of its tokens).
+    pub fn write_node(&self, node: &Node, output: &mut String) {
+        for child in &node.children {
+            match child {
+                Element::Node(node) => self.write_node(node, output),
+                Element::Token(token) => {
+                    let token = &self.tokens[*token];
+                    for trivia in &token.leading {
+                        output.push_str(&self.source[trivia.span.range()]);
+                    }
+                    output.push_str(self.text(token));
+                    for trivia in &token.trailing {
+                        output.push_str(&self.source[trivia.span.range()]);
+                    }
+                }
+            }
+        }
+    }
+
+    /// The node which starts (not including its leading trivia) at `index`
+    /// in the source code. Blocks are skipped (because they start at the same
+    /// place as their first statement).
+    pub fn node_at(&self, index: usize) -> Option<&Node> {
+        fn find<'n>(tree: &SyntaxTree, node: &'n Node, index: usize) -> Option<&'n Node> {
+            if !matches!(node.kind, NodeKind::Root | NodeKind::Block)
+                && node.first_token().map_or(false, |token| {
+                    tree.tokens[token].span.range().start == index
+                })
+            {
+                return Some(node);
+            }
+            node.children.iter().find_map(|child| match child {
+                Element::Node(child) => find(tree, child, index),
+                Element::Token(_) => None,
+            })
+        }
+        find(self, &self.root, index)
+    }
+
+    /// Returns every comment in the program, in order.
+    pub fn comments(&self) -> Vec<Comment<'i>> {
+        self.tokens
+            .iter()
+            .flat_map(|token| token.leading.iter().chain(&token.trailing))
+            .filter(|trivia| trivia.kind == TriviaKind::Comment)
+            .map(|trivia| {
+                let index = trivia.span.range().start;
+                let line = self.source[..index].rfind('\n').map_or(0, |i| i + 1);
+                Comment {
+                    index,
+                    column: self.source[line..index].chars().count(),
+                    text: self.source[trivia.span.range()][2..].trim_end(),
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the comments on the lines directly above the token which
+    /// starts at `index` (one line of the result for each line of comments),
+    /// or `None` if there are not any. A single space after the `;;` is
+    /// removed from each line.
+    pub fn doc_comment(&self, index: usize) -> Option<String> {
+        let token = self
+            .tokens
+            .binary_search_by_key(&index, |token| token.span.range().start)
+            .ok()
+            .map(|token| &self.tokens[token])?;
+
+        let is = |trivia: Option<&Trivia>, kind| trivia.map_or(false, |trivia| trivia.kind == kind);
+        let mut lines = vec![];
+        let mut rest = &token.leading[..];
+        loop {
+            // the indentation of the line, and the new line at the end of the
+            // line above
+            if is(rest.last(), TriviaKind::Whitespace) {
+                rest = &rest[..rest.len() - 1];
+            }
+            if !is(rest.last(), TriviaKind::Newline) {
+                break;
+            }
+            rest = &rest[..rest.len() - 1];
+            if !is(rest.last(), TriviaKind::Comment) {
+                break;
+            }
+            let text = &self.source[rest[rest.len() - 1].span.range()][2..];
+            lines.push(text.strip_prefix(' ').unwrap_or(text).trim_end());
+            rest = &rest[..rest.len() - 1];
+        }
+
+        if lines.is_empty() {
+            None
+        } else {
+            lines.reverse();
+            Some(lines.join("\n"))
+        }
+    }
+}
+
+impl fmt::Display for SyntaxTree<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut output = String::new();
+        self.write_node(&self.root, &mut output);
+        f.write_str(&output)
+    }
+}
+
+/// Splits the source code into tokens (and attaches the trivia to them).
+fn lex(source: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = vec![];
+    // the trivia which will be attached to the next token
+    let mut leading = vec![];
+    // whether there is a token on the current line (in which case spaces and
+    // comments are attached to the last token, and the new line at the end of
+    // the line is a token)
+    let mut line_has_token = false;
+
+    let mut start = 0;
+    while let Some(char) = source[start..].chars().next() {
+        let rest = &source[start..];
+        let length = |stop: fn(char) -> bool| rest.find(stop).unwrap_or(rest.len());
+        let (piece, length) = match char {
+            ' ' | '\t' | '\r' => (
+                Err(TriviaKind::Whitespace),
+                length(|char| !matches!(char, ' ' | '\t' | '\r')),
+            ),
+            '\n' if line_has_token => (Ok(TokenKind::Newline), 1),
+            '\n' => (Err(TriviaKind::Newline), 1),
+            ';' if rest.starts_with(";;") => {
+                (Err(TriviaKind::Comment), length(|char| char == '\n'))
+            }
+            '"' => (
+                Ok(TokenKind::String),
+                rest[1..].find('"').map_or(rest.len(), |end| end + 2),
+            ),
+            '0'..='9' => (Ok(TokenKind::Number), number_length(rest)),
+            char if char.is_alphabetic() || char == '_' => {
+                let length = length(|char| !char.is_alphanumeric() && char != '_');
+                if KEYWORDS.contains(&&rest[..length]) {
+                    (Ok(TokenKind::Keyword), length)
+                } else {
+                    (Ok(TokenKind::Ident), length)
+                }
+            }
+            _ if ["->", "==", "!="]
+                .iter()
+                .any(|punct| rest.starts_with(punct)) =>
+            {
+                (Ok(TokenKind::Punct), 2)
+            }
+            '(' | ')' | '[' | ']' | '{' | '}' | ',' | ':' | '.' | '+' | '-' | '*' | '/' | '='
+            | '<' | '>' | '!' => (Ok(TokenKind::Punct), 1),
+            char => (Ok(TokenKind::Unknown), char.len_utf8()),
+        };
+        let span = IndexOnlySpan::new(start, start + length);
+
+        match piece {
+            Ok(kind) => {
+                tokens.push(Token {
+                    kind,
+                    span,
+                    leading: std::mem::take(&mut leading),
+                    trailing: vec![],
+                });
+                line_has_token = kind != TokenKind::Newline;
+            }
+            Err(kind) => {
+                let trivia = Trivia { kind, span };
+                match tokens.last_mut() {
+                    Some(token) if line_has_token => token.trailing.push(trivia),
+                    _ => leading.push(trivia),
+                }
+            }
+        }
+        start += length;
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        span: IndexOnlySpan::new(source.len(), source.len()),
+        leading,
+        trailing: vec![],
+    });
+    tokens
+}
+
+/// The length of the number at the start of `rest` (in the same format as
+/// [`super::lit::Number`]).
+fn number_length(rest: &str) -> usize {
+    let digits = |from: usize| {
+        from + rest[from..]
+            .find(|char: char| !char.is_ascii_digit())
+            .unwrap_or(rest.len() - from)
+    };
+    let mut length = digits(0);
+    if rest[length..].starts_with('.')
+        && rest[length + 1..].starts_with(|char: char| char.is_ascii_digit())
+    {
+        length = digits(length + 1);
+    }
+    if rest[length..].starts_with('e') {
+        length = digits(length + 1);
+    }
+    length
+}
+
+/// Builds the tree from the tokens, one line at a time.
+struct Builder<'t, 'i> {
+    source: &'i str,
+    tokens: &'t [Token],
+    /// The next token.
+    next: usize,
+    /// The keywords which end each of the blocks which are being built.
+    ends: Vec<&'static [&'static str]>,
+}
+
+impl Builder<'_, '_> {
+    fn root(mut self) -> Node {
+        let mut children = self.statements();
+        children.push(Element::Token(self.next));
+        Node {
+            kind: NodeKind::Root,
+            children,
+        }
+    }
+
+    fn text(&self, token: usize) -> &str {
+        &self.source[self.tokens[token].span.range()]
+    }
+
+    /// Returns `true` if the next line starts with the keyword. Keywords which
+    /// can also be used as names (e.g. `next`) only count if they are not
+    /// followed by something which means they are being used as a name.
+    fn at(&self, keyword: &str) -> bool {
+        if self.text(self.next) != keyword || self.tokens[self.next].kind != TokenKind::Keyword {
+            return false;
+        }
+        super::ident::KEYWORDS.contains(&keyword)
+            || !matches!(self.text(self.next + 1), "=" | "." | "[")
+    }
+
+    /// Returns `true` at the end of the file, or at a keyword which ends one
+    /// of the blocks which are being built.
+    fn at_end(&self) -> bool {
+        self.tokens[self.next].kind == TokenKind::Eof
+            || self
+                .ends
+                .iter()
+                .any(|ends| ends.iter().any(|keyword| self.at(keyword)))
+    }
+
+    /// Adds the rest of the line (including the new line at the end of it) to
+    /// `children`.
+    fn line(&mut self, children: &mut Vec<Element>) {
+        loop {
+            match self.tokens[self.next].kind {
+                TokenKind::Eof => return,
+                TokenKind::Newline => {
+                    children.push(Element::Token(self.next));
+                    self.next += 1;
+                    return;
+                }
+                _ => {
+                    children.push(Element::Token(self.next));
+                    self.next += 1;
+                }
+            }
+        }
+    }
+
+    fn statements(&mut self) -> Vec<Element> {
+        let mut children = vec![];
+        while !self.at_end() {
+            children.push(Element::Node(self.statement()));
+        }
+        children
+    }
+
+    fn block(&mut self, ends: &'static [&'static str]) -> Element {
+        self.ends.push(ends);
+        let children = self.statements();
+        self.ends.pop();
+        Element::Node(Node {
+            kind: NodeKind::Block,
+            children,
+        })
+    }
+
+    /// Adds the line to `children` if it starts with the keyword.
+    fn end(&mut self, keyword: &str, children: &mut Vec<Element>) {
+        if self.at(keyword) {
+            self.line(children);
+        }
+    }
+
+    fn statement(&mut self) -> Node {
+        let mut children = vec![];
+        let kind = if self.at("function") {
+            self.line(&mut children);
+            children.push(self.block(&["endfunction"]));
+            self.end("endfunction", &mut children);
+            NodeKind::Function
+        } else if self.at("if") {
+            const ENDS: &[&str] = &["elseif", "else", "endif"];
+            self.line(&mut children);
+            children.push(self.block(ENDS));
+            while self.at("elseif") || self.at("else") {
+                self.line(&mut children);
+                children.push(self.block(ENDS));
+            }
+            self.end("endif", &mut children);
+            NodeKind::If
+        } else if self.at("while") {
+            self.line(&mut children);
+            children.push(self.block(&["endwhile"]));
+            self.end("endwhile", &mut children);
+            NodeKind::While
+        } else if self.at("for") {
+            self.line(&mut children);
+            children.push(self.block(&["next"]));
+            self.end("next", &mut children);
+            NodeKind::For
+        } else if self.at("record") {
+            self.line(&mut children);
+            self.ends.push(&["endrecord"]);
+            while !self.at_end() {
+                let mut field = vec![];
+                self.line(&mut field);
+                children.push(Element::Node(Node {
+                    kind: NodeKind::Field,
+                    children: field,
+                }));
+            }
+            self.ends.pop();
+            self.end("endrecord", &mut children);
+            NodeKind::Record
+        } else if self.at("extern") {
+            self.line(&mut children);
+            NodeKind::Extern
+        } else if self.at("return") {
+            self.line(&mut children);
+            NodeKind::Return
+        } else {
+            self.line(&mut children);
+            NodeKind::Statement
+        };
+        Node { kind, children }
+    }
+}
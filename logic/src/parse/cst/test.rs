@@ -0,0 +1,144 @@
+use std::fs;
+
+use crate::parse::{parse_with_prelude, table::PRELUDE};
+
+use super::{Element, Node, NodeKind, SyntaxTree, TokenKind, TriviaKind};
+
+/// Returns the kinds of the nodes in the tree (with the children of each node
+/// in brackets after it).
+fn shape(node: &Node) -> String {
+    let children = node
+        .children
+        .iter()
+        .filter_map(|child| match child {
+            Element::Node(node) => Some(shape(node)),
+            Element::Token(_) => None,
+        })
+        .collect::<Vec<_>>();
+    if children.is_empty() {
+        format!("{:?}", node.kind)
+    } else {
+        format!("{:?}({})", node.kind, children.join(", "))
+    }
+}
+
+#[test]
+fn round_trips() {
+    let mut programs = vec![
+        PRELUDE.to_string(),
+        String::new(),
+        "\n\n  \n".to_string(),
+        "x = \"not finished\nprint(x)".to_string(),
+        "  ;; a comment\r\n\tx=1 ;; another\r\n".to_string(),
+        "é = 1.5e3.x @ £\n".to_string(),
+        "endif\nif x then\n  while True\nendif\n".to_string(),
+    ];
+    for entry in fs::read_dir("../filetests").unwrap() {
+        programs.push(fs::read_to_string(entry.unwrap().path()).unwrap());
+    }
+
+    for program in programs {
+        let tree = SyntaxTree::new(&program);
+        assert_eq!(tree.to_string(), program);
+    }
+}
+
+#[test]
+fn builds_tree_from_keywords() {
+    let program = "record Point
+  x of Int
+endrecord
+extern function labs(x: Int) -> Int
+function main()
+    for i = 1 to 3
+  if i == 1 then
+print(i)
+      elseif i == 2 then
+      else
+        next = 2
+      endif
+    next i
+  return 0
+endfunction
+";
+    let tree = SyntaxTree::new(program);
+    assert_eq!(
+        shape(tree.root()),
+        "Root(Record(Field), Extern, Function(Block(For(Block(If(Block(Statement), Block, \
+         Block(Statement)))), Return)))"
+    );
+}
+
+#[test]
+fn attaches_trivia() {
+    let program = "x = 1 ;; one\n\n  ;; above\n  y = 2\n";
+    let tree = SyntaxTree::new(program);
+    let tokens = tree.tokens();
+    let kinds = |trivia: &[super::Trivia]| trivia.iter().map(|t| t.kind).collect::<Vec<_>>();
+
+    let one = &tokens[2];
+    assert_eq!(tree.text(one), "1");
+    assert_eq!(
+        kinds(&one.trailing),
+        [TriviaKind::Whitespace, TriviaKind::Comment]
+    );
+    assert_eq!(tokens[3].kind, TokenKind::Newline);
+
+    let y = &tokens[4];
+    assert_eq!(tree.text(y), "y");
+    assert_eq!(
+        kinds(&y.leading),
+        [
+            TriviaKind::Newline,
+            TriviaKind::Whitespace,
+            TriviaKind::Comment,
+            TriviaKind::Newline,
+            TriviaKind::Whitespace
+        ]
+    );
+    assert_eq!(tokens.last().unwrap().kind, TokenKind::Eof);
+
+    let comments = tree.comments();
+    assert_eq!(
+        comments
+            .iter()
+            .map(|c| (c.column, c.text))
+            .collect::<Vec<_>>(),
+        [(6, " one"), (2, " above")]
+    );
+}
+
+#[test]
+fn finds_doc_comments() {
+    let program = ";; not attached to anything
+
+;; Adds one to the number.
+;;
+;;   (twice)
+function increment(x)
+  return x + 1
+endfunction
+
+function f()
+  return 0
+endfunction
+";
+    let table = parse_with_prelude(program).unwrap();
+    let items = &table.root.1.inner;
+
+    assert_eq!(
+        table.doc_comment(items[0].id).as_deref(),
+        Some("Adds one to the number.\n\n  (twice)")
+    );
+    assert_eq!(table.doc_comment(items[1].id), None);
+
+    assert_eq!(
+        table.syntax(items[0].id).unwrap().kind(),
+        NodeKind::Function
+    );
+    let mut text = String::new();
+    table
+        .cst()
+        .write_node(table.syntax(items[1].id).unwrap(), &mut text);
+    assert_eq!(text, "\nfunction f()\n  return 0\nendfunction\n");
+}
@@ -35,7 +35,7 @@ impl From<ExprRef> for ItemRef {
         }
     }
 }
-impl Expr<'_> {
+impl<'i> Expr<'i> {
     pub fn as_bin_op(&self) -> Option<(&BinOp, &ExprRef, &ExprRef)> {
         if let Self::BinOp(op, a, b) = self {
             Some((op, a, b))
@@ -91,6 +91,95 @@ impl Expr<'_> {
     pub fn is_function_call(&self) -> bool {
         matches!(self, Self::FunctionCall(..))
     }
+
+    /// Displays the expression in the way it would be written in a program.
+    pub fn display<'t>(&'t self, table: &'t ParseTable<'i>) -> DisplayExpr<'t, 'i> {
+        DisplayExpr { expr: self, table }
+    }
+}
+
+/// Displays an expression (see [`Expr::display`]). Brackets are only added
+/// where they are needed.
+pub struct DisplayExpr<'t, 'i> {
+    expr: &'t Expr<'i>,
+    table: &'t ParseTable<'i>,
+}
+
+impl DisplayExpr<'_, '_> {
+    /// Writes the operand, adding brackets if it is a binary operation which
+    /// would otherwise be parsed differently.
+    fn operand(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        operand: &ExprRef,
+        needs_brackets: impl Fn((u8, u8)) -> bool,
+    ) -> fmt::Result {
+        let operand = self.table.get_expr(operand);
+        match operand {
+            Expr::BinOp(op, _, _) if op.token != BinOp::Index && needs_brackets(op.bp()) => {
+                write!(f, "({})", operand.display(self.table))
+            }
+            _ => write!(f, "{}", operand.display(self.table)),
+        }
+    }
+}
+
+impl Display for DisplayExpr<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let table = self.table;
+        match self.expr {
+            Expr::Ident(ident) => f.write_str(table.get_ident(*ident).inner()),
+            Expr::Literal(literal) => literal.token.fmt(f),
+            Expr::BinOp(op, left, right) if op.token == BinOp::Index => {
+                let (left_bp, _) = op.bp();
+                self.operand(f, left, |(_, right_bp)| right_bp < left_bp)?;
+                write!(f, "[{}]", table.get_expr(right).display(table))
+            }
+            Expr::BinOp(op, left, right) => {
+                let (left_bp, right_bp) = op.bp();
+                self.operand(f, left, |(_, bp)| bp < left_bp)?;
+                if op.token == BinOp::Dot {
+                    f.write_str(".")?;
+                } else {
+                    write!(f, " {} ", op.token)?;
+                }
+                self.operand(f, right, |(bp, _)| bp < right_bp)
+            }
+            Expr::UnOp(op, operand) => {
+                write!(f, "{}", op.token)?;
+                let (_, right_bp) = op.bp();
+                self.operand(f, operand, |(bp, _)| bp < right_bp)
+            }
+            Expr::FunctionCall(name, args) => {
+                write!(f, "{}(", table.get_ident(*name).inner())?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", table.get_expr(arg).display(table))?;
+                }
+                f.write_str(")")
+            }
+            Expr::Constructor(constructor) if constructor.fields.is_empty() => {
+                write!(f, "{} {{}}", table.get_ident(constructor.name).inner())
+            }
+            Expr::Constructor(constructor) => {
+                write!(f, "{} {{ ", table.get_ident(constructor.name).inner())?;
+                for (i, (field, value)) in constructor.fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(
+                        f,
+                        "{}: {}",
+                        table.get_ident(*field).inner(),
+                        table.get_expr(value).display(table)
+                    )?;
+                }
+                f.write_str(" }")
+            }
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -0,0 +1,121 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    diagnostics::span::{Span, Spanned},
+    ty::PrimitiveType,
+};
+
+use super::{
+    ident::{Ident, IdentRef},
+    record::parse_ty,
+    table::{Id, ItemKind, ItemRef, ParseContext},
+    utils::{Input, Parse, ParseError},
+};
+
+#[derive(Debug, Eq, PartialEq)]
+/// A declaration of a function which is implemented outside of the program
+/// (e.g. in a C library), for example
+///
+/// ```text
+/// extern function forward(distance: Int) -> Int from "libturtle.so"
+/// ```
+///
+/// If the library is omitted, the function is looked up in the compiler's own
+/// process (which makes it possible to call functions from the C standard
+/// library).
+pub struct Extern<'i> {
+    pub(crate) name: IdentRef,
+    pub(crate) parameters: Vec<(IdentRef, Spanned<PrimitiveType>)>,
+    pub(crate) returns: Spanned<PrimitiveType>,
+    /// The library which contains the function.
+    pub(crate) library: Option<Spanned<&'i str>>,
+    pub(crate) span: Span,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct ExternRef {
+    pub(crate) id: Id,
+}
+
+impl From<ExternRef> for ItemRef {
+    fn from(e: ExternRef) -> Self {
+        ItemRef {
+            id: e.id,
+            item_kind: ItemKind::Extern,
+        }
+    }
+}
+
+impl<'i> Parse<'i> for Extern<'i> {
+    type Context = ParseContext<'i>;
+    type Output = ExternRef;
+
+    fn parse(input: &mut Input<'i>, ctx: &mut ParseContext<'i>) -> Result<ExternRef, ParseError> {
+        let rec = input.start_recording();
+        input.parse_token("extern")?;
+        input.skip_whitespace()?;
+        input.parse_token("function")?;
+        input.skip_whitespace()?;
+        let name = Ident::parse(input, ctx)?;
+
+        // the parameter names are only used for documentation, so we make sure
+        // that they don't end up in the enclosing scope
+        let mut local_variables = BTreeMap::new();
+        std::mem::swap(&mut ctx.tagging.variable_ids, &mut local_variables);
+
+        input.skip_whitespace()?;
+        input.parse_token("(")?;
+        input.skip_whitespace()?;
+        let parameters = if !input.starts_with(')') {
+            input.delimited_list(
+                |input, ctx| {
+                    let name = Ident::parse(input, ctx)?;
+                    input.skip_whitespace()?;
+                    input.parse_token(":")?;
+                    input.skip_whitespace()?;
+                    Ok((name, parse_ty(input, ctx)?))
+                },
+                ')',
+                ",",
+                ctx,
+            )?
+        } else {
+            vec![]
+        };
+        input.parse_token(")")?;
+
+        std::mem::swap(&mut ctx.tagging.variable_ids, &mut local_variables);
+
+        input.skip_whitespace()?;
+        input.parse_token("->")?;
+        input.skip_whitespace()?;
+        let returns = parse_ty(input, ctx)?;
+        input.skip_whitespace()?;
+
+        let library = if input.starts_with("from") {
+            input.parse_token("from")?;
+            input.skip_whitespace()?;
+            let rec = input.start_recording();
+            input.parse_token("\"")?;
+            let library = input.eat_until(|char| char == '"')?;
+            input.parse_token("\"")?;
+            Some(Spanned::new(rec.finish_recording(input), library))
+        } else {
+            None
+        };
+        input.skip_whitespace()?;
+
+        let id = ctx.new_id();
+        ctx.table.extern_.insert(
+            id,
+            Self {
+                name,
+                parameters,
+                returns,
+                library,
+                span: rec.finish_recording(input),
+            },
+        );
+        Ok(ExternRef { id })
+    }
+}
@@ -13,7 +13,6 @@ pub struct ForLoop {
     pub(crate) var: IdentRef,
     pub(crate) between: Between,
     pub(crate) block: BlockRef,
-    pub(crate) indent: usize,
     pub(crate) span: Span,
 }
 
@@ -50,8 +49,8 @@ impl<'i> Parse<'i> for ForLoop {
 
         let block = Block::parse(input, ctx, false)?;
 
-        input.advance_indent()?;
-        input.parse_token("next")?;
+        input.advance_indent(ctx)?;
+        input.parse_block_end("next")?;
         input.skip_whitespace()?;
 
         let ident = Ident::parse(input, ctx)?;
@@ -87,7 +86,6 @@ impl<'i> Parse<'i> for ForLoop {
                 var,
                 between,
                 block,
-                indent: input.indent,
                 span: rec.finish_recording(input),
             },
         );
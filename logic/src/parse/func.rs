@@ -14,7 +14,6 @@ pub struct Func {
     pub(crate) name: IdentRef,
     pub(crate) parameters: Vec<IdentRef>,
     pub(crate) block: BlockRef,
-    pub(crate) indent: usize,
 }
 
 impl<'i> Parse<'i> for Func {
@@ -59,15 +58,14 @@ impl<'i> Parse<'i> for Func {
         std::mem::swap(&mut ctx.tagging.variable_ids, &mut local_variables);
         // ...back to parsing
 
-        input.advance_indent()?;
-        input.parse_token("endfunction")?;
+        input.advance_indent(ctx)?;
+        input.parse_block_end("endfunction")?;
         input.skip_whitespace()?;
 
         let me = Self {
             name,
             parameters,
             block,
-            indent: input.indent,
         };
         let id = ctx.new_id();
         ctx.table.func.insert(id, me);
@@ -92,7 +90,6 @@ impl From<FuncRef> for ItemRef {
 #[derive(Debug, Eq, PartialEq)]
 pub struct Return {
     pub(crate) expr: ExprRef,
-    pub(crate) indent: usize,
 }
 
 #[derive(Debug, Eq, PartialEq, Ord, PartialOrd)]
@@ -121,7 +118,6 @@ impl<'i> Parse<'i> for Return {
 
         let me = Self {
             expr: Expr::parse(input, ctx)?,
-            indent: input.indent,
         };
         let id = ctx.new_id();
         ctx.table.return_.insert(id, me);
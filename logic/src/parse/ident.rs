@@ -20,6 +20,7 @@ pub const KEYWORDS: &[&str] = &[
     "endfunction",
     "endwhile",
     "endif",
+    "extern",
     "True",
     "False",
 ];
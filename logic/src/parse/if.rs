@@ -12,7 +12,6 @@ pub struct If {
     pub(crate) r#if: Branch,
     pub(crate) else_ifs: Vec<Branch>,
     pub(crate) r#else: Option<BlockRef>,
-    pub(crate) indent: usize,
     pub(crate) span: Span,
 }
 
@@ -38,6 +37,7 @@ impl<'i> Parse<'i> for If {
         ctx: &mut ParseContext<'i>,
     ) -> Result<IfRef, super::utils::ParseError> {
         let rec = input.start_recording();
+        let start = *input.position();
 
         input.parse_token("if")?;
         let r#if = Branch {
@@ -50,6 +50,7 @@ impl<'i> Parse<'i> for If {
                 input.skip_whitespace()?;
                 input.parse_token("then")?;
                 input.skip_whitespace()?;
+                ctx.table.starts.insert(cond.id, start);
                 cond
             },
             block: { Block::parse(input, ctx, false)? },
@@ -58,14 +59,16 @@ impl<'i> Parse<'i> for If {
         let mut elseifs = vec![];
 
         loop {
-            input.advance_indent()?;
+            input.advance_indent(ctx)?;
             if input.starts_with("elseif") {
+                let start = *input.position();
                 input.parse_token("elseif")?;
                 let condition =
                     Expr::parse_bp_stop_if(input, 0, |input| input.starts_with("then"), ctx)?
                         .ok_or(ParseError::UnexpectedEndOfInput {
                             span: input.current_span(),
                         })?;
+                ctx.table.starts.insert(condition.id, start);
                 input.parse_token("then")?;
                 input.advance_whitespace_and_new_line()?;
                 let block = Block::parse(input, ctx, false)?;
@@ -74,8 +77,8 @@ impl<'i> Parse<'i> for If {
                 input.parse_token("else")?;
                 input.advance_whitespace_and_new_line()?;
                 let block = Block::parse(input, ctx, false)?;
-                input.advance_indent()?;
-                input.parse_token("endif")?;
+                input.advance_indent(ctx)?;
+                input.parse_block_end("endif")?;
                 input.skip_whitespace()?;
 
                 let id = ctx.new_id();
@@ -85,13 +88,12 @@ impl<'i> Parse<'i> for If {
                         r#if,
                         else_ifs: elseifs,
                         r#else: Some(block),
-                        indent: input.indent,
                         span: rec.finish_recording(input),
                     },
                 );
                 return Ok(IfRef { id });
             } else {
-                input.parse_token("endif")?;
+                input.parse_block_end("endif")?;
                 input.skip_whitespace()?;
                 input.assert_new_line()?;
 
@@ -102,7 +104,6 @@ impl<'i> Parse<'i> for If {
                         r#if,
                         else_ifs: elseifs,
                         r#else: None,
-                        indent: input.indent,
                         span: rec.finish_recording(input),
                     },
                 );
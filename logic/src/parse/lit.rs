@@ -84,6 +84,18 @@ impl Number<'_> {
             int_part
         }
     }
+
+    /// Returns `true` if this number has a fractional part (and should
+    /// therefore be treated as a real number rather than an integer).
+    pub(crate) fn is_real(&self) -> bool {
+        self.float.is_some()
+    }
+
+    pub(crate) fn as_real(&self) -> f64 {
+        // the `Display` implementation produces the same format which Rust
+        // uses for floating point numbers (e.g. `1.2e5`)
+        self.to_string().parse::<f64>().unwrap()
+    }
 }
 
 impl fmt::Display for Number<'_> {
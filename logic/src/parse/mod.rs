@@ -3,19 +3,24 @@
 //!
 //! There is something approaching a formal grammar in [`crate::parse::fuzz`].
 
+use crate::diagnostics::span::IndexOnlySpan;
+
 use self::{
     expr::Expr,
     func::{Func, Return},
+    r#extern::Extern,
     r#for::ForLoop,
     r#if::If,
     r#while::While,
     record::Record,
-    table::{ItemRef, ParseContext},
-    utils::{Input, Parse, ParseError},
+    table::{ItemKind, ItemRef, ParseContext},
+    utils::{Input, Parse, ParseError, ParseWarning},
 };
 
 pub mod r#block;
+pub mod cst;
 pub mod expr;
+pub mod r#extern;
 pub mod r#for;
 pub mod func;
 pub mod ident;
@@ -38,7 +43,7 @@ mod test_scopes;
 /// error messages.
 pub mod ui;
 
-pub use table::parse;
+pub use table::{parse, parse_with_prelude};
 
 /// Parses a list of statements, returning a list of [`ItemRef`]s.
 fn parse_statements<'i>(
@@ -59,20 +64,35 @@ fn parse_statements<'i>(
             }
         }
 
+        // blocks are ended by keywords (e.g. `endif`) rather than by their
+        // indentation (the program itself is not inside a block, so a stray
+        // `endif` in it is reported as an error)
         if input.is_empty()
-            // note: we rely here on the fact that `&&` is short-circuiting (i.e. if the first
-            // operand is false, then the second operand should not be evaluated)
-            || (input.indent >= 2 && input.count_indent()? == input.indent - 2)
+            || (input.indent >= 2 && input.at_block_end())
             || input.chars().all(|char| char.is_whitespace())
         {
             return Ok(nodes);
         } else {
-            input.advance_indent()?;
+            input.advance_indent(ctx)?;
             // comments
             if input.starts_with(";;") {
                 input.eat_until_or_end(|c| c == '\n')?;
             } else {
-                nodes.push(Node::parse(input, ctx)?);
+                let start = *input.position();
+                // only the first statement after a `return` is warned about
+                if let Some(ItemRef {
+                    item_kind: ItemKind::Return,
+                    ..
+                }) = nodes.last()
+                {
+                    let line = input.find('\n').unwrap_or(input.len());
+                    ctx.table.warnings.push(ParseWarning::Unreachable {
+                        span: IndexOnlySpan::new(start.index, start.index + line),
+                    });
+                }
+                let node = Node::parse(input, ctx)?;
+                ctx.table.starts.insert(node.id, start);
+                nodes.push(node);
             }
         }
     }
@@ -106,6 +126,8 @@ impl<'i> Parse<'i> for Node {
             If::parse(input, ctx).map(From::from)
         } else if input.starts_with("while ") {
             While::parse(input, ctx).map(From::from)
+        } else if input.starts_with("extern ") {
+            Extern::parse(input, ctx).map(From::from)
         } else if input.starts_with("function ") {
             Func::parse(input, ctx).map(From::from)
         } else if input.starts_with("record") {
@@ -15,7 +15,6 @@ use super::{
 pub struct Record {
     pub(crate) name: IdentRef,
     pub(crate) fields: Vec<Field>,
-    pub(crate) indent: usize,
 }
 
 #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
@@ -48,7 +47,6 @@ impl<'i> Parse<'i> for Record {
         input: &mut super::utils::Input<'i>,
         ctx: &mut ParseContext<'i>,
     ) -> Result<RecordRef, super::utils::ParseError> {
-        let indent = input.indent;
         input.parse_token("record")?;
         input.skip_whitespace()?;
         let name = Ident::parse(input, ctx)?;
@@ -69,24 +67,19 @@ impl<'i> Parse<'i> for Record {
             }
 
             if input.is_empty()
-                || (input.indent >= 2 && input.count_indent()? == input.indent - 2)
+                || input
+                    .trim_start_matches(|char: char| char.is_whitespace() && char != '\n')
+                    .starts_with("endrecord")
                 || input.chars().all(|char| char.is_whitespace())
             {
                 input.decrement_indent(2);
-                input.advance_indent()?;
-                input.parse_token("endrecord")?;
+                input.advance_indent(ctx)?;
+                input.parse_block_end("endrecord")?;
                 let id = ctx.new_id();
-                ctx.table.record_.insert(
-                    id,
-                    Self {
-                        name,
-                        fields,
-                        indent,
-                    },
-                );
+                ctx.table.record_.insert(id, Self { name, fields });
                 return Ok(RecordRef { id });
             } else {
-                input.advance_indent()?;
+                input.advance_indent(ctx)?;
                 fields.push(Field::parse(input, ctx)?);
             }
         }
@@ -115,26 +108,34 @@ impl<'i> Parse<'i> for Field {
         input.skip_whitespace()?;
         input.parse_token("of")?;
         input.skip_whitespace()?;
-        let ty_symbol_ref = Ident::parse(input, ctx)?;
-        let ty_symbol = ctx.table.get_ident(ty_symbol_ref);
-        let ty = match ty_symbol.inner() {
-            "Bool" => PrimitiveType::Bool,
-            "Int" => PrimitiveType::Int,
-            "String" => PrimitiveType::StrSlice,
-            "Pointer" => PrimitiveType::Pointer,
-            _ => {
-                return Err(ParseError::UnexpectedToken {
-                    // don't say pointer here, because this is only available internally
-                    explanation: "Expected a type here (one of `Bool`, `Int` or `String`)."
-                        .to_string(),
-                    span: ty_symbol.span(&ctx.table).into(),
-                });
-            }
-        };
+        let ty = parse_ty(input, ctx)?;
 
-        Ok(Field {
-            name,
-            ty: Spanned::new(ty_symbol.span(&ctx.table), ty),
-        })
+        Ok(Field { name, ty })
     }
 }
+
+/// Parses the name of a type (e.g. `Int`).
+pub(crate) fn parse_ty<'i>(
+    input: &mut super::utils::Input<'i>,
+    ctx: &mut ParseContext<'i>,
+) -> Result<Spanned<PrimitiveType>, ParseError> {
+    let ty_symbol_ref = Ident::parse(input, ctx)?;
+    let ty_symbol = ctx.table.get_ident(ty_symbol_ref);
+    let ty = match ty_symbol.inner() {
+        "Bool" => PrimitiveType::Bool,
+        "Int" => PrimitiveType::Int,
+        "Real" => PrimitiveType::Real,
+        "String" => PrimitiveType::StrSlice,
+        "Pointer" => PrimitiveType::Pointer,
+        _ => {
+            return Err(ParseError::UnexpectedToken {
+                // don't say pointer here, because this is only available internally
+                explanation: "Expected a type here (one of `Bool`, `Int`, `Real` or `String`)."
+                    .to_string(),
+                span: ty_symbol.span(&ctx.table).into(),
+            });
+        }
+    };
+
+    Ok(Spanned::new(ty_symbol.span(&ctx.table), ty))
+}
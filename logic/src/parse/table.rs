@@ -1,16 +1,20 @@
 use std::{collections::BTreeMap, fmt};
 
+use crate::diagnostics::position::Position;
+
 use super::{
     block::{Block, BlockRef},
+    cst::{Node, SyntaxTree},
     expr::{Expr, ExprRef},
     func::{Func, FuncRef, Return},
     ident::{Ident, IdentRef},
     parse_statements,
+    r#extern::Extern,
     r#for::ForLoop,
     r#if::If,
     r#while::While,
     record::{Record, RecordRef},
-    utils::{Input, ParseError},
+    utils::{Input, ParseError, ParseWarning},
 };
 
 /// Contains all the items parsed by the compiler.
@@ -22,6 +26,7 @@ use super::{
 pub struct ParseTable<'i> {
     pub(crate) block: BTreeMap<Id, Block>,
     pub(crate) expr: BTreeMap<Id, Expr<'i>>,
+    pub(crate) extern_: BTreeMap<Id, Extern<'i>>,
     pub(crate) for_: BTreeMap<Id, ForLoop>,
     pub(crate) ident: BTreeMap<Id, Ident<'i>>,
     pub(crate) if_: BTreeMap<Id, If>,
@@ -30,11 +35,67 @@ pub struct ParseTable<'i> {
     /// We create a root element in order to ensure that everything (except the
     /// root) has a parent.
     pub(crate) root: (Id, Block),
+    /// The items defined in the standard library prelude (see
+    /// [`PRELUDE`]). These are stored separately from the items in the
+    /// program being compiled, because they are allowed to perform privileged
+    /// operations (e.g. using pointers).
+    pub(crate) prelude: (Id, Block),
     pub(crate) func: BTreeMap<Id, Func>,
     pub(crate) while_: BTreeMap<Id, While>,
+    /// Where each statement starts in the source code, and where the line
+    /// containing the condition of each `if`, `elseif` and `while` starts
+    /// (which is stored using the id of the condition). The spans of
+    /// expressions cannot be used for this, because the span of an
+    /// identifier is always the place where it was first used.
+    pub(crate) starts: BTreeMap<Id, Position>,
+    /// The lossless syntax tree of the program (which keeps the comments and
+    /// whitespace which the rest of the table does not).
+    pub(crate) cst: SyntaxTree<'i>,
+    /// The problems with the program which did not stop it from being parsed
+    /// (e.g. lines which are not indented correctly).
+    pub(crate) warnings: Vec<ParseWarning>,
 }
 
 impl<'i> ParseTable<'i> {
+    /// The lossless syntax tree of the program (see [`SyntaxTree`]).
+    pub fn cst(&self) -> &SyntaxTree<'i> {
+        &self.cst
+    }
+
+    /// The problems with the program which did not stop it from being parsed
+    /// (see [`ParseWarning`]).
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    /// Returns the node in the syntax tree which the statement (or the
+    /// condition of an `if`, `elseif` or `while` statement) with the given id
+    /// was parsed from. This only works for statements in the program (not
+    /// in the prelude).
+    pub fn syntax(&self, id: Id) -> Option<&Node> {
+        self.starts
+            .get(&id)
+            .and_then(|start| self.cst.node_at(start.index))
+    }
+
+    /// Returns the comments on the lines directly above the statement with
+    /// the given id (see [`SyntaxTree::doc_comment`]).
+    pub fn doc_comment(&self, id: Id) -> Option<String> {
+        self.starts
+            .get(&id)
+            .and_then(|start| self.cst.doc_comment(start.index))
+    }
+
+    /// Returns `true` if the function with the given id was defined in the
+    /// prelude.
+    pub(crate) fn is_prelude_func(&self, func: Id) -> bool {
+        self.prelude
+            .1
+            .inner
+            .iter()
+            .any(|item| item.item_kind == ItemKind::Func && item.id == func)
+    }
+
     /// Retrieves the identifier corresponding to the provided reference,
     /// panicking if it does not exist.
     #[inline]
@@ -83,6 +144,7 @@ impl<'i> ParseTable<'i> {
             ItemKind::Block => self.block.get(&ref_.id).map(Item::Block),
             ItemKind::Func => self.func.get(&ref_.id).map(Item::Func),
             ItemKind::Return => self.return_.get(&ref_.id).map(Item::Return),
+            ItemKind::Extern => self.extern_.get(&ref_.id).map(Item::Extern),
         }
     }
 
@@ -130,6 +192,14 @@ impl<'i> ParseTable<'i> {
         self.block.get(&block.id)
     }
 
+    /// Finds the external function with the given name (if it has been
+    /// declared).
+    pub(crate) fn find_extern(&self, name: &str) -> Option<&Extern<'i>> {
+        self.extern_
+            .values()
+            .find(|ext| self.get_ident(ext.name).inner == name)
+    }
+
     pub(crate) fn get_record(&self, record_ty: RecordRef) -> &Record {
         self.try_get_record(record_ty).unwrap()
     }
@@ -166,6 +236,7 @@ pub enum Item<'i> {
     Block(&'i Block),
     Func(&'i Func),
     Return(&'i Return),
+    Extern(&'i Extern<'i>),
 }
 
 impl<'i> Item<'i> {
@@ -256,9 +327,39 @@ impl IdGen {
     }
 }
 
+/// The source code of the standard library prelude, which is implicitly
+/// available to every program.
+pub static PRELUDE: &str = include_str!("../prelude.pseudo");
+
+/// Parses the prelude into the provided context. Positions in the prelude start
+/// after the end of any program, so that they do not overlap with the
+/// program's positions.
+fn parse_prelude(ctx: &mut ParseContext) {
+    let mut input = Input::starting_at(PRELUDE, Position::prelude_start());
+
+    ctx.push_scope();
+    let statements = parse_statements(&mut input, ctx).expect("failed to parse the prelude");
+    let id = ctx.new_id();
+    ctx.table.prelude = (id, Block { inner: statements });
+    ctx.pop_scope(true);
+}
+
+/// Parses the provided program, and also the prelude (which the program can
+/// then use). This is what should be used to compile programs.
+pub fn parse_with_prelude(input: &str) -> Result<ParseTable<'_>, ParseError> {
+    let mut ctx = ParseContext::new();
+    parse_prelude(&mut ctx);
+    parse_into(input, ctx)
+}
+
+/// Parses the provided program (without the prelude).
 pub fn parse(input: &str) -> Result<ParseTable<'_>, ParseError> {
+    parse_into(input, ParseContext::new())
+}
+
+fn parse_into<'i>(input: &'i str, mut ctx: ParseContext<'i>) -> Result<ParseTable<'i>, ParseError> {
+    ctx.table.cst = SyntaxTree::new(input);
     let mut input = Input::new(input);
-    let mut ctx = ParseContext::new();
 
     ctx.push_scope();
 
@@ -276,6 +377,77 @@ pub fn parse(input: &str) -> Result<ParseTable<'_>, ParseError> {
     Ok(ctx.table)
 }
 
+/// How many ids each top-level item of a program can use when the items are
+/// parsed separately (see [`parse_item`]). The prelude uses the ids before the
+/// first item's.
+pub(crate) const ITEM_IDS: u32 = 1 << 20;
+
+/// The first id used by the top-level item with the given index, when the
+/// items are parsed separately (see [`ITEM_IDS`]).
+pub(crate) fn first_item_id(item: usize) -> Id {
+    Id::new((item as u32 + 1) * ITEM_IDS)
+}
+
+/// A top-level item which was parsed on its own (see [`parse_item`]).
+pub(crate) struct ParsedItem<'i> {
+    pub(crate) statements: Vec<ItemRef>,
+    /// The names which the item leaves in scope (i.e. those which the code
+    /// after it can refer to), and their ids.
+    pub(crate) names: BTreeMap<Ident<'i>, Id>,
+    /// How many ids the item used.
+    pub(crate) ids: u32,
+}
+
+/// Parses the top-level item with the given index into the context, without
+/// any of the items before it in scope. The item's ids and positions come
+/// from its own ranges (see [`ITEM_IDS`] and [`Position::item_start`]), so the
+/// same item always produces the same ids and positions, whatever else is
+/// parsed into the context alongside it.
+///
+/// Names used by more than one item therefore have a different id in each of
+/// them; these are resolved afterwards (see [`crate::query`]).
+pub(crate) fn parse_item<'i>(
+    ctx: &mut ParseContext<'i>,
+    item: usize,
+    text: &'i str,
+) -> Result<ParsedItem<'i>, ParseError> {
+    let first = first_item_id(item).as_u32();
+    ctx.tagging.monotonic = IdGen::new(first);
+    let mut input = Input::starting_at(text, Position::item_start(item));
+
+    ctx.push_scope();
+    let statements = parse_statements(&mut input, ctx)?;
+    let names = std::mem::take(&mut ctx.tagging.variable_ids);
+    ctx.pop_scope(true);
+
+    Ok(ParsedItem {
+        statements,
+        names,
+        ids: ctx.tagging.monotonic.current_id - first,
+    })
+}
+
+/// Parses the prelude, the provided top-level items (usually the signatures
+/// of the other items in the program, which the item can refer to) and then
+/// the top-level item with the given index (see [`parse_item`]). Only the
+/// statements of the last item are in the root block of the table.
+pub(crate) fn parse_item_with<'i>(
+    others: impl IntoIterator<Item = (usize, &'i str)>,
+    item: usize,
+    text: &'i str,
+) -> Result<ParseTable<'i>, ParseError> {
+    let mut ctx = ParseContext::new();
+    parse_prelude(&mut ctx);
+    for (other, text) in others {
+        parse_item(&mut ctx, other, text)?;
+    }
+    let statements = parse_item(&mut ctx, item, text)?.statements;
+
+    let id = ctx.new_id();
+    ctx.table.root = (id, Block { inner: statements });
+    Ok(ctx.table)
+}
+
 #[derive(Debug, Hash, Eq, PartialEq, Copy, Clone)]
 /// Uniquely identifies any item in the codebase.
 pub struct ItemRef {
@@ -317,6 +489,7 @@ pub enum ItemKind {
     Block,
     Func,
     Return,
+    Extern,
 }
 
 #[derive(Debug, Default)]
@@ -92,7 +92,7 @@ mod parse_reparse {
 
     #[test]
     fn ident_with_space_does_not_crash() {
-        inner((" s \n", false));
+        inner((" s \n", true));
     }
 
     #[test]
@@ -102,7 +102,7 @@ mod parse_reparse {
 
     #[test]
     fn if_else_if() {
-        inner(("if J67k then\nelseif \"o\" then\nb = hS\nendif\n", false));
+        inner(("if J67k then\nelseif \"o\" then\nb = hS\nendif\n", true));
     }
 
     #[test]
@@ -122,4 +122,77 @@ mod parse_reparse {
     fn function_inside_while() {
         inner((include_str!("examples/func-inside-while"), true));
     }
+
+    #[test]
+    fn extern_function() {
+        inner((
+            "extern function forward(distance: Int, fast: Bool) -> Int from \"libturtle.so\"\n",
+            true,
+        ));
+        inner(("extern function labs(x: Int) -> Int\n", true));
+        inner(("extern function labs(x) -> Int\n", false));
+    }
+}
+
+mod indentation {
+    use crate::parse::{parse, utils::ParseWarning};
+
+    #[test]
+    fn any_indentation_parses() {
+        let program = "function main()
+    for i = 1 to 3
+        if i == 2 then
+print(i)
+      elseif (i == 3) then
+            next = i
+        endif
+    next i
+    return 0
+endfunction
+";
+        let table = parse(program).unwrap();
+        assert_eq!(table.root.1.inner.len(), 1);
+    }
+
+    #[test]
+    fn warns_about_indentation() {
+        let table = parse("function main()\nprint(1)\n    return 0\nendfunction\n").unwrap();
+        let found = table
+            .warnings()
+            .iter()
+            .filter_map(|warning| match warning {
+                ParseWarning::Indentation {
+                    expected, found, ..
+                } => Some((*expected, *found)),
+                ParseWarning::Unreachable { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(found, [(2, 0), (2, 4)]);
+    }
+
+    #[test]
+    fn no_warnings_for_formatted_program() {
+        let table = parse("while True\n  if x then\n    print(x)\n  endif\nendwhile\n").unwrap();
+        assert!(table.warnings().is_empty());
+    }
+}
+
+mod unreachable {
+    use crate::{
+        diagnostics::span::IndexOnlySpan,
+        parse::{parse, utils::ParseWarning},
+    };
+
+    #[test]
+    fn warns_about_unreachable_statements() {
+        let program = "function main()\n  return 0\n  print(1)\n  print(2)\nendfunction\n";
+        let table = parse(program).unwrap();
+        let start = program.find("print(1)").unwrap();
+        assert_eq!(
+            table.warnings(),
+            [ParseWarning::Unreachable {
+                span: IndexOnlySpan::new(start, start + "print(1)".len())
+            }]
+        );
+    }
 }
@@ -60,7 +60,6 @@ fn test_non_alphabetic_ident() {
 
 #[test]
 fn test_missing_endfor() {
-    // todo: make this highlight the *whole* token (see the debug snapshot for specifics)
     insta::assert_snapshot!(ui_test(
         include_str!("ui-examples/missing-next").to_string()
     ))
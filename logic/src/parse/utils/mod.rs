@@ -10,6 +10,20 @@ use crate::diagnostics::{
     span::{IndexOnlySpan, Span},
 };
 
+use super::{ident::KEYWORDS, table::ParseContext};
+
+/// The keywords which end a block (and the keywords which end one branch of an
+/// `if` statement and start the next one).
+const BLOCK_ENDS: &[&str] = &[
+    "endfunction",
+    "endif",
+    "elseif",
+    "else",
+    "endwhile",
+    "next",
+    "endrecord",
+];
+
 pub trait Parse<'a>: Sized {
     type Context;
     type Output;
@@ -105,6 +119,52 @@ impl ParseError {
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq)]
+/// A problem with a program which does not stop it from being parsed.
+///
+/// This can be converted into a [codespan_reporting::diagnostic::Diagnostic] for error reporting.
+pub enum ParseWarning {
+    /// A line is not indented by the expected number of spaces (the structure
+    /// of the program comes from the keywords which start and end each block,
+    /// so this does not change what the program means).
+    Indentation {
+        span: IndexOnlySpan,
+        expected: usize,
+        found: usize,
+    },
+    /// A statement comes after a `return` statement (in the same block), so
+    /// it will never be run.
+    Unreachable { span: IndexOnlySpan },
+}
+
+impl ParseWarning {
+    /// Turns the warning into a reportable message.
+    pub fn report<ID>(&self, id: ID) -> Diagnostic<ID>
+    where
+        ID: Copy,
+    {
+        match self {
+            ParseWarning::Indentation {
+                span,
+                expected,
+                found,
+            } => Diagnostic::warning()
+                .with_message("This line is not indented correctly.")
+                .with_labels(vec![Label::primary(id, span.range()).with_message(
+                    format!("Expected {expected} spaces here, but instead found {found} spaces."),
+                )])
+                .with_notes(vec![
+                    "note: `pseudo fmt` can fix the indentation of a program".to_string(),
+                ]),
+            ParseWarning::Unreachable { span } => Diagnostic::warning()
+                .with_message("This statement will never be run.")
+                .with_labels(vec![Label::primary(id, span.range()).with_message(
+                    "This comes after a `return` statement, so it has no effect.",
+                )]),
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 /// A wrapper over the input stream (which is a string). This type returns only references to the
 /// underlying string rather than copying the bytes of the string.
@@ -132,6 +192,16 @@ impl<'a> Input<'a> {
         }
     }
 
+    /// Creates a new input, whose positions start at `position` (rather than
+    /// at the start of the file).
+    pub fn starting_at(inner: &'a str, position: Position) -> Self {
+        Self {
+            inner,
+            indent: 0,
+            position,
+        }
+    }
+
     /// Parses zero or more whitespace units (excluding new lines) and then one
     /// new line
     pub fn advance_whitespace_and_new_line(&mut self) -> Result<(), ParseError> {
@@ -160,6 +230,22 @@ impl<'a> Input<'a> {
         }
     }
 
+    /// Parses the keyword which ends a block (e.g. `endif`). Blocks only end
+    /// at one of these keywords, so if the program ends first it is the
+    /// keyword which is missing.
+    pub fn parse_block_end(&mut self, keyword: &str) -> Result<&'a str, ParseError> {
+        if self.is_empty() {
+            return Err(ParseError::UnexpectedToken {
+                explanation: format!(
+                    "Expected `{}` in this position, however, instead the program ended",
+                    keyword
+                ),
+                span: IndexOnlySpan::new(self.position.index, self.position.index),
+            });
+        }
+        self.parse_token(keyword)
+    }
+
     pub fn delimited_list<P: Fn(&mut Input<'a>, &mut C) -> Result<T, ParseError>, T, C>(
         &mut self,
         function: P,
@@ -372,24 +458,27 @@ impl<'a> Input<'a> {
         self.indent -= by;
     }
 
-    pub fn count_indent(&self) -> Result<usize, ParseError> {
-        let mut iter = self.chars();
-        let mut total = 0;
-
-        loop {
-            match iter.next() {
-                Some(' ' | '\u{C}' | '\u{B}') => total += 1,
-                Some('\t') => total += 4,
-                _ => {
-                    break;
-                }
-            }
+    /// Returns `true` if the next line starts with a keyword which ends a block
+    /// (e.g. `endif` or `next`). Some of these keywords (e.g. `next`) can also
+    /// be used as names, so they only count if they are not followed by
+    /// something which means that they are being used as a name.
+    pub fn at_block_end(&self) -> bool {
+        let line = self.trim_start_matches(|char: char| char.is_whitespace() && char != '\n');
+        let word = &line[..line
+            .find(|char: char| !char.is_alphanumeric() && char != '_')
+            .unwrap_or(line.len())];
+        if !BLOCK_ENDS.contains(&word) {
+            return false;
         }
-
-        Ok(total)
+        let after = line[word.len()..].trim_start_matches(|char| char == ' ' || char == '\t');
+        KEYWORDS.contains(&word) || !after.starts_with(['=', '.', '['])
     }
 
-    pub fn advance_indent(&mut self) -> Result<(), ParseError> {
+    /// Advances past the indentation at the start of a line, adding a warning
+    /// to the parse table if it is not what was expected (blocks are ended by
+    /// keywords, so the indentation does not affect how the program is
+    /// parsed).
+    pub fn advance_indent(&mut self, ctx: &mut ParseContext) -> Result<(), ParseError> {
         let start_recording = self.start_recording();
         let mut whitespace_units = 0;
 
@@ -407,17 +496,14 @@ impl<'a> Input<'a> {
             }
         }
 
-        if whitespace_units == self.indent {
-            Ok(())
-        } else {
-            Err(ParseError::InvalidWhitespace {
+        if whitespace_units != self.indent {
+            ctx.table.warnings.push(ParseWarning::Indentation {
                 span: start_recording.finish_recording(self).into(),
-                explanation: format!(
-                    "Expected exactly {} spaces here, but instead found {} spaces.",
-                    self.indent, whitespace_units
-                ),
-            })
+                expected: self.indent,
+                found: whitespace_units,
+            });
         }
+        Ok(())
     }
 
     /// Get a reference to the input's inner.
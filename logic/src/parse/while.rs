@@ -12,7 +12,6 @@ use super::{
 pub struct While {
     pub(crate) block: BlockRef,
     pub(crate) condition: ExprRef,
-    pub(crate) indent: usize,
     pub(crate) span: Span,
 }
 
@@ -38,20 +37,21 @@ impl<'i> Parse<'i> for While {
         ctx: &mut ParseContext<'i>,
     ) -> Result<WhileRef, super::utils::ParseError> {
         let rec = input.start_recording();
+        let start = *input.position();
         input.parse_token("while")?;
         input.skip_whitespace()?;
         let condition = Expr::parse(input, ctx)?;
+        ctx.table.starts.insert(condition.id, start);
         input.advance_whitespace_and_new_line()?;
 
         let block = Block::parse(input, ctx, false)?;
-        input.advance_indent()?;
-        input.parse_token("endwhile")?;
+        input.advance_indent(ctx)?;
+        input.parse_block_end("endwhile")?;
 
         let id = ctx.new_id();
         let me = Self {
             condition,
             block,
-            indent: input.indent,
             span: rec.finish_recording(input),
         };
 
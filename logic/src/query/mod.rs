@@ -0,0 +1,782 @@
+//! A query-based (and incremental) compilation pipeline.
+//!
+//! Rather than running each stage of the compiler from scratch every time the
+//! source code changes, the pipeline is split up into queries (for example
+//! "what are the syntax errors in the third item of the file"). Each query
+//! remembers (memoises) its result and the queries which it read while
+//! computing it. When the source code changes, a query is only recomputed if
+//! one of the queries it depends on has actually produced a different value,
+//! so (for example) editing the body of one function only re-parses that
+//! function.
+//!
+//! The algorithm is the same one used by [salsa](https://github.com/salsa-rs/salsa):
+//! every change to the input advances the "revision", and every memoised
+//! value records the revision at which it was last checked and the revision
+//! at which it last changed. If a query is recomputed but produces the same
+//! value as before, it is not considered to have changed (so the queries
+//! which depend on it do not need to be recomputed either).
+//!
+//! The stages of the pipeline are
+//! - the source code is split into its top-level items ([`Query::Items`]),
+//!   each of which is parsed on its own ([`Query::ItemSyntax`] and
+//!   [`Query::ItemDefinitions`]). The ids and positions in each item are
+//!   taken from a separate range (see [`parse_item`]), so editing one item
+//!   does not change the ids or positions in any other item.
+//! - names are resolved once every item has been parsed ([`Query::Names`]).
+//!   Where more than one item uses the same name, the ids which the later
+//!   items gave it are renamed to the one which the first item gave it (this
+//!   is what happens when the whole file is parsed at once).
+//! - constraints are collected from each item separately
+//!   ([`Query::ItemConstraints`]). The item is parsed along with the
+//!   signatures of the other items (see [`Query::Signatures`]), so they are
+//!   only collected again when the item itself or one of those signatures
+//!   changes.
+//! - unification ([`Query::Unify`]) is the only query which depends on every
+//!   item, because type inference is global (for example, the types of the
+//!   parameters of a function are inferred from its call sites).
+//! - the MIR for the functions in each item ([`Query::ItemMir`]) only depends
+//!   on the types of the item's own ids ([`Query::ItemTypes`]), so it is only
+//!   produced again if one of those types changes.
+//!
+//! [`Database::mir`] puts the MIR for each item together into the whole
+//! program.
+
+#[cfg(test)]
+mod test;
+
+use std::sync::Arc;
+
+use codespan_reporting::diagnostic::{Diagnostic, Severity};
+use rustc_hash::FxHashMap;
+
+use crate::{
+    diagnostics::position::{Position, ITEM_SIZE},
+    mir::{lower_functions, lower_record, Function, Program, Statement},
+    parse::{
+        cst::{Element, SyntaxTree},
+        parse, parse_with_prelude,
+        table::{
+            first_item_id, parse_item, parse_item_with, Id, Item, ItemRef, ParseContext, ParseTable,
+        },
+    },
+    ty::{
+        constraints::{collect_prelude, collect_program, Constraint},
+        error::TyCheckError,
+        solve_parts, TyEnv,
+    },
+};
+
+/// A query which the database can answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Query {
+    /// The source code of the file (this is the only input to the pipeline).
+    Source,
+    /// The top-level items in the file (see [`split_items`]).
+    Items,
+    /// The source code of the top-level item with the given index.
+    Item(usize),
+    /// The syntax errors (and warnings) in the top-level item with the given
+    /// index (the positions in these are relative to the start of the item).
+    ItemSyntax(usize),
+    /// The names which the top-level item with the given index defines, and
+    /// its signature (see [`Query::Signatures`]).
+    ItemDefinitions(usize),
+    /// The signature of each top-level item: the part of it which the other
+    /// items can refer to. This is the whole item for records and external
+    /// functions, and just the first line for functions (their bodies are
+    /// left out, so editing the body of a function does not change its
+    /// signature). Items which only contain statements have no signature.
+    Signatures,
+    /// The ids which are renamed once every item has been parsed (see the
+    /// module documentation).
+    Names,
+    /// The constraints collected from the prelude.
+    PreludeConstraints,
+    /// The constraints collected from the top-level item with the given index.
+    ItemConstraints(usize),
+    /// The type of everything in the program (or the type error in it). The
+    /// positions in the error are those of the separately parsed items (see
+    /// [`parse_item`]).
+    Unify,
+    /// The errors found when type checking the program.
+    Types,
+    /// The types of the ids in the prelude.
+    PreludeTypes,
+    /// The types of the ids in the top-level item with the given index (under
+    /// the ids which the item itself uses, rather than the ones they were
+    /// renamed to).
+    ItemTypes(usize),
+    /// The MIR for the functions in the prelude which the program uses.
+    PreludeMir,
+    /// The MIR for the functions and records in the top-level item with the
+    /// given index (the positions in this are relative to the start of the
+    /// item).
+    ItemMir(usize),
+    /// Every error in the file.
+    Diagnostics,
+}
+
+/// The value produced by a query.
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Source(Arc<str>),
+    Items(Arc<[TopLevelItem]>),
+    Item(Option<Arc<str>>),
+    Definitions(Arc<Definitions>),
+    Signatures(Arc<[Option<Arc<str>>]>),
+    Names(Arc<FxHashMap<Id, Id>>),
+    Constraints(Arc<Constraints>),
+    Unify(Arc<Result<TyEnv, Diagnostic<()>>>),
+    Types(Arc<TyEnv>),
+    Mir(Arc<Result<Program, Diagnostic<()>>>),
+    Diagnostics(Arc<[Diagnostic<()>]>),
+}
+
+/// What a top-level item defines (see [`Query::ItemDefinitions`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Definitions {
+    signature: Option<Arc<str>>,
+    /// The names which the item leaves in scope for the rest of the program,
+    /// and their ids.
+    names: Vec<(String, Id)>,
+    /// How many ids the item uses.
+    ids: u32,
+}
+
+/// The constraints collected from part of the program (or the error found
+/// while collecting them).
+#[derive(Debug, Clone)]
+struct Constraints(Result<Vec<Constraint>, Diagnostic<()>>);
+
+impl PartialEq for Constraints {
+    fn eq(&self, other: &Self) -> bool {
+        // the spans of the constraints are also compared, because the errors
+        // which they produce point at them
+        match (&self.0, &other.0) {
+            (Ok(a), Ok(b)) => a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.same_as(b)),
+            (Err(a), Err(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// A memoised query result.
+#[derive(Debug)]
+struct Memo {
+    value: Value,
+    /// The queries which were read to produce this value.
+    dependencies: Vec<Query>,
+    /// The last revision at which this value was known to be up to date.
+    verified_at: u64,
+    /// The revision at which this value last changed.
+    changed_at: u64,
+}
+
+/// A top-level item in the source code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TopLevelItem {
+    /// The byte offset at which the item starts.
+    pub start: usize,
+    pub text: Arc<str>,
+}
+
+/// Stores the source code of a single file, and the memoised results of the
+/// queries about it.
+#[derive(Debug)]
+pub struct Database {
+    revision: u64,
+    source: Arc<str>,
+    source_changed_at: u64,
+    memos: FxHashMap<Query, Memo>,
+    /// The dependencies of each query which is currently being computed (the
+    /// innermost query is at the end).
+    active: Vec<Vec<Query>>,
+    /// How many times each query has been computed.
+    executions: FxHashMap<Query, usize>,
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Self {
+            revision: 0,
+            source: "".into(),
+            source_changed_at: 0,
+            memos: FxHashMap::default(),
+            active: Vec::new(),
+            executions: FxHashMap::default(),
+        }
+    }
+}
+
+impl Database {
+    pub fn new(source: &str) -> Self {
+        let mut db = Self::default();
+        db.set_source(source);
+        db
+    }
+
+    /// Replaces the source code of the file.
+    pub fn set_source(&mut self, source: &str) {
+        if &*self.source != source {
+            self.revision += 1;
+            self.source = source.into();
+            self.source_changed_at = self.revision;
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The number of times the given query has been computed (rather than
+    /// reused) since the database was created.
+    pub fn executions(&self, query: Query) -> usize {
+        self.executions.get(&query).copied().unwrap_or_default()
+    }
+
+    /// Returns every error (and warning) in the file (syntax errors and
+    /// warnings are reported for each item; if there are no syntax errors,
+    /// the program is type checked).
+    pub fn diagnostics<ID>(&mut self, file_id: ID) -> Vec<Diagnostic<ID>>
+    where
+        ID: Copy,
+    {
+        match self.fetch(Query::Diagnostics) {
+            Value::Diagnostics(diagnostics) => diagnostics
+                .iter()
+                .map(|diagnostic| with_file_id(diagnostic, file_id, 0))
+                .collect(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Produces the MIR for the program, or returns the diagnostics if it
+    /// contains any errors. This is the same as lowering the whole program at
+    /// once (see [`crate::mir::lower`]), but the MIR for each item is reused
+    /// until something which it depends on changes.
+    pub fn mir<ID>(&mut self, file_id: ID) -> Result<Program, Vec<Diagnostic<ID>>>
+    where
+        ID: Copy,
+    {
+        let diagnostics = self.diagnostics(file_id);
+        if diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            return Err(diagnostics);
+        }
+
+        let mut program = match &*self.mir_query(Query::PreludeMir) {
+            Ok(program) => program.clone(),
+            Err(error) => return Err(vec![with_file_id(error, file_id, 0)]),
+        };
+        let items = self.items_query();
+        for (index, item) in items.iter().enumerate() {
+            match &*self.mir_query(Query::ItemMir(index)) {
+                Ok(part) => {
+                    let lines = self.source[..item.start].matches('\n').count() as u32;
+                    program.functions.extend(
+                        part.functions
+                            .iter()
+                            .cloned()
+                            .map(|function| move_lines(function, lines)),
+                    );
+                    program.records.extend(part.records.iter().cloned());
+                }
+                Err(error) => return Err(vec![with_file_id(&relocate(error, &items), file_id, 0)]),
+            }
+        }
+        Ok(program)
+    }
+
+    /// Retrieves the value of the query, recording it as a dependency of the
+    /// query which is currently being computed (if there is one).
+    fn fetch(&mut self, query: Query) -> Value {
+        if let Some(dependencies) = self.active.last_mut() {
+            dependencies.push(query);
+        }
+        self.refresh(query);
+        match query {
+            Query::Source => Value::Source(self.source.clone()),
+            _ => self.memos[&query].value.clone(),
+        }
+    }
+
+    /// Makes sure that the memoised value of the query is up to date, and
+    /// returns the revision at which it last changed.
+    fn refresh(&mut self, query: Query) -> u64 {
+        if let Query::Source = query {
+            return self.source_changed_at;
+        }
+
+        if let Some(memo) = self.memos.get(&query) {
+            if memo.verified_at == self.revision {
+                return memo.changed_at;
+            }
+
+            // the value can be reused if none of the queries it depends on
+            // have changed since it was last verified
+            let (dependencies, verified_at) = (memo.dependencies.clone(), memo.verified_at);
+            if dependencies
+                .iter()
+                .all(|dependency| self.refresh(*dependency) <= verified_at)
+            {
+                let memo = self.memos.get_mut(&query).unwrap();
+                memo.verified_at = self.revision;
+                return memo.changed_at;
+            }
+        }
+
+        self.execute(query)
+    }
+
+    /// Computes the value of the query (and stores it).
+    fn execute(&mut self, query: Query) -> u64 {
+        self.active.push(vec![]);
+        let value = match query {
+            Query::Source => unreachable!("the source code is an input"),
+            Query::Items => self.compute_items(),
+            Query::Item(index) => self.compute_item(index),
+            Query::ItemSyntax(index) => self.compute_item_syntax(index),
+            Query::ItemDefinitions(index) => self.compute_item_definitions(index),
+            Query::Signatures => self.compute_signatures(),
+            Query::Names => self.compute_names(),
+            Query::PreludeConstraints => self.compute_prelude_constraints(),
+            Query::ItemConstraints(index) => self.compute_item_constraints(index),
+            Query::Unify => self.compute_unify(),
+            Query::Types => self.compute_types(),
+            Query::PreludeTypes => self.compute_prelude_types(),
+            Query::ItemTypes(index) => self.compute_item_types(index),
+            Query::PreludeMir => self.compute_prelude_mir(),
+            Query::ItemMir(index) => self.compute_item_mir(index),
+            Query::Diagnostics => self.compute_diagnostics(),
+        };
+        let dependencies = self.active.pop().unwrap();
+        *self.executions.entry(query).or_default() += 1;
+
+        let changed_at = match self.memos.get(&query) {
+            Some(old) if old.value == value => old.changed_at,
+            _ => self.revision,
+        };
+        self.memos.insert(
+            query,
+            Memo {
+                value,
+                dependencies,
+                verified_at: self.revision,
+                changed_at,
+            },
+        );
+        changed_at
+    }
+
+    fn source_query(&mut self) -> Arc<str> {
+        match self.fetch(Query::Source) {
+            Value::Source(source) => source,
+            _ => unreachable!(),
+        }
+    }
+
+    fn items_query(&mut self) -> Arc<[TopLevelItem]> {
+        match self.fetch(Query::Items) {
+            Value::Items(items) => items,
+            _ => unreachable!(),
+        }
+    }
+
+    fn item_query(&mut self, index: usize) -> Option<Arc<str>> {
+        match self.fetch(Query::Item(index)) {
+            Value::Item(text) => text,
+            _ => unreachable!(),
+        }
+    }
+
+    fn definitions_query(&mut self, index: usize) -> Arc<Definitions> {
+        match self.fetch(Query::ItemDefinitions(index)) {
+            Value::Definitions(definitions) => definitions,
+            _ => unreachable!(),
+        }
+    }
+
+    fn signatures_query(&mut self) -> Arc<[Option<Arc<str>>]> {
+        match self.fetch(Query::Signatures) {
+            Value::Signatures(signatures) => signatures,
+            _ => unreachable!(),
+        }
+    }
+
+    fn names_query(&mut self) -> Arc<FxHashMap<Id, Id>> {
+        match self.fetch(Query::Names) {
+            Value::Names(renamed) => renamed,
+            _ => unreachable!(),
+        }
+    }
+
+    fn constraints_query(&mut self, query: Query) -> Arc<Constraints> {
+        match self.fetch(query) {
+            Value::Constraints(constraints) => constraints,
+            _ => unreachable!(),
+        }
+    }
+
+    fn unify_query(&mut self) -> Arc<Result<TyEnv, Diagnostic<()>>> {
+        match self.fetch(Query::Unify) {
+            Value::Unify(result) => result,
+            _ => unreachable!(),
+        }
+    }
+
+    fn types_query(&mut self, query: Query) -> Arc<TyEnv> {
+        match self.fetch(query) {
+            Value::Types(env) => env,
+            _ => unreachable!(),
+        }
+    }
+
+    fn mir_query(&mut self, query: Query) -> Arc<Result<Program, Diagnostic<()>>> {
+        match self.fetch(query) {
+            Value::Mir(result) => result,
+            _ => unreachable!(),
+        }
+    }
+
+    fn diagnostics_query(&mut self, query: Query) -> Arc<[Diagnostic<()>]> {
+        match self.fetch(query) {
+            Value::Diagnostics(diagnostics) => diagnostics,
+            _ => unreachable!(),
+        }
+    }
+
+    fn compute_items(&mut self) -> Value {
+        Value::Items(split_items(&self.source_query()).into())
+    }
+
+    fn compute_item(&mut self, index: usize) -> Value {
+        Value::Item(self.items_query().get(index).map(|item| item.text.clone()))
+    }
+
+    fn compute_item_syntax(&mut self, index: usize) -> Value {
+        let diagnostics = match self.item_query(index).as_deref().map(parse) {
+            Some(Ok(table)) => table
+                .warnings()
+                .iter()
+                .map(|warning| warning.report(()))
+                .collect(),
+            Some(Err(error)) => vec![error.report(())],
+            None => vec![],
+        };
+        Value::Diagnostics(diagnostics.into())
+    }
+
+    fn compute_item_definitions(&mut self, index: usize) -> Value {
+        // (items which contain syntax errors do not define anything, but they
+        // stop the program from being type checked anyway)
+        let definitions = self
+            .item_query(index)
+            .and_then(|text| {
+                let mut ctx = ParseContext::new();
+                let item = parse_item(&mut ctx, index, &text).ok()?;
+                Some(Definitions {
+                    signature: signature(&ctx.table, &item.statements, index, &text),
+                    names: item
+                        .names
+                        .iter()
+                        .map(|(name, id)| (name.inner().to_owned(), *id))
+                        .collect(),
+                    ids: item.ids,
+                })
+            })
+            .unwrap_or_default();
+        Value::Definitions(Arc::new(definitions))
+    }
+
+    fn compute_signatures(&mut self) -> Value {
+        let items = self.items_query();
+        let signatures = (0..items.len())
+            .map(|index| self.definitions_query(index).signature.clone())
+            .collect();
+        Value::Signatures(signatures)
+    }
+
+    fn compute_names(&mut self) -> Value {
+        let items = self.items_query();
+        let mut defined = FxHashMap::<String, Id>::default();
+        let mut renamed = FxHashMap::default();
+        for index in 0..items.len() {
+            for (name, id) in &self.definitions_query(index).names {
+                match defined.get(name) {
+                    Some(first) => {
+                        renamed.insert(*id, *first);
+                    }
+                    None => {
+                        defined.insert(name.clone(), *id);
+                    }
+                }
+            }
+        }
+        Value::Names(Arc::new(renamed))
+    }
+
+    fn compute_prelude_constraints(&mut self) -> Value {
+        let table = parse_with_prelude("").expect("failed to parse the prelude");
+        let constraints =
+            collect_prelude(&table).map_err(|error| TyCheckError::from(error).report((), &table));
+        Value::Constraints(Arc::new(Constraints(constraints)))
+    }
+
+    fn compute_item_constraints(&mut self, index: usize) -> Value {
+        let signatures = self.signatures_query();
+        let text = self.item_query(index).unwrap_or_else(|| "".into());
+        let constraints = match parse_item_with(others(&signatures, index), index, &text) {
+            Ok(table) => collect_program(&table)
+                .map_err(|error| TyCheckError::from(error).report((), &table)),
+            Err(error) => Err(error.report(())),
+        };
+        Value::Constraints(Arc::new(Constraints(constraints)))
+    }
+
+    fn compute_unify(&mut self) -> Value {
+        let items = self.items_query();
+        let renamed = self.names_query();
+        let mut parts = vec![self.constraints_query(Query::PreludeConstraints)];
+        for index in 0..items.len() {
+            parts.push(self.constraints_query(Query::ItemConstraints(index)));
+        }
+
+        let result = match parts.iter().find_map(|part| part.0.as_ref().err()) {
+            Some(error) => Err(error.clone()),
+            None => solve_parts(
+                parts.iter().map(|part| part.0.as_deref().unwrap()),
+                &renamed,
+            )
+            // the spans are stored in the constraints, so the error can be
+            // reported without a parse table
+            .map_err(|error| error.report((), &ParseTable::default())),
+        };
+        Value::Unify(Arc::new(result))
+    }
+
+    fn compute_types(&mut self) -> Value {
+        let items = self.items_query();
+        let diagnostics = match &*self.unify_query() {
+            Ok(_) => vec![],
+            Err(error) => vec![relocate(error, &items)],
+        };
+        Value::Diagnostics(diagnostics.into())
+    }
+
+    fn compute_prelude_types(&mut self) -> Value {
+        let env = match &*self.unify_query() {
+            Ok(env) => env.project(
+                env.map()
+                    .keys()
+                    .filter(|id| **id < first_item_id(0))
+                    .map(|id| (*id, *id)),
+            ),
+            Err(_) => TyEnv::new(),
+        };
+        Value::Types(Arc::new(env))
+    }
+
+    fn compute_item_types(&mut self, index: usize) -> Value {
+        let renamed = self.names_query();
+        let first = first_item_id(index).as_u32();
+        let ids = first..first + self.definitions_query(index).ids;
+        let env = match &*self.unify_query() {
+            Ok(env) => env.project(ids.map(Id::new).map(|id| {
+                let renamed = renamed.get(&id).copied().unwrap_or(id);
+                (id, renamed)
+            })),
+            Err(_) => TyEnv::new(),
+        };
+        Value::Types(Arc::new(env))
+    }
+
+    fn compute_prelude_mir(&mut self) -> Value {
+        let env = self.types_query(Query::PreludeTypes);
+        let table = parse_with_prelude("").expect("failed to parse the prelude");
+        let program = lower_functions(&table, &env, &table.prelude.1)
+            .map(|functions| Program {
+                functions,
+                records: table
+                    .record_
+                    .values()
+                    .map(|record| lower_record(record, &table))
+                    .collect(),
+            })
+            .map_err(|error| error.report(()));
+        Value::Mir(Arc::new(program))
+    }
+
+    fn compute_item_mir(&mut self, index: usize) -> Value {
+        let env = self.types_query(Query::ItemTypes(index));
+        let signatures = self.signatures_query();
+        let text = self.item_query(index).unwrap_or_else(|| "".into());
+        let program = match parse_item_with(others(&signatures, index), index, &text) {
+            Ok(table) => lower_functions(&table, &env, &table.root.1)
+                .map(|functions| Program {
+                    functions,
+                    records: table
+                        .record_
+                        .range(first_item_id(index)..first_item_id(index + 1))
+                        .map(|(_, record)| lower_record(record, &table))
+                        .collect(),
+                })
+                .map_err(|error| error.report(())),
+            Err(error) => Err(error.report(())),
+        };
+        Value::Mir(Arc::new(program))
+    }
+
+    fn compute_diagnostics(&mut self) -> Value {
+        let items = self.items_query();
+        let mut diagnostics = vec![];
+        for (index, item) in items.iter().enumerate() {
+            diagnostics.extend(
+                self.diagnostics_query(Query::ItemSyntax(index))
+                    .iter()
+                    .map(|diagnostic| with_file_id(diagnostic, (), item.start)),
+            );
+        }
+
+        // the program is only type checked if it does not contain any syntax
+        // errors (although it can contain warnings)
+        if diagnostics
+            .iter()
+            .all(|diagnostic| diagnostic.severity != Severity::Error)
+        {
+            diagnostics.extend(self.diagnostics_query(Query::Types).iter().cloned());
+        }
+        Value::Diagnostics(diagnostics.into())
+    }
+}
+
+/// Finds the signature of the (separately parsed) top-level item (see
+/// [`Query::Signatures`]).
+fn signature(
+    table: &ParseTable,
+    statements: &[ItemRef],
+    index: usize,
+    text: &str,
+) -> Option<Arc<str>> {
+    match table.get(statements.first()?)? {
+        Item::Func(func) => {
+            let start =
+                table.get_ident(func.name).span.start().index - Position::item_start(index).index;
+            let end = start + text[start..].find('\n')? + 1;
+            Some(format!("{}endfunction\n", &text[..end]).into())
+        }
+        Item::Record(_) | Item::Extern(_) => Some(text.into()),
+        _ => None,
+    }
+}
+
+/// The signatures of every top-level item except the one with the given
+/// index.
+fn others(
+    signatures: &[Option<Arc<str>>],
+    index: usize,
+) -> impl Iterator<Item = (usize, &str)> + '_ {
+    signatures
+        .iter()
+        .enumerate()
+        .filter(move |(other, _)| *other != index)
+        .filter_map(|(other, signature)| Some((other, signature.as_deref()?)))
+}
+
+/// Moves the labels of a diagnostic about separately parsed items (see
+/// [`parse_item`]) to where the items are in the source code.
+fn relocate(diagnostic: &Diagnostic<()>, items: &[TopLevelItem]) -> Diagnostic<()> {
+    let mut diagnostic = diagnostic.clone();
+    for label in &mut diagnostic.labels {
+        let item = label.range.start / ITEM_SIZE;
+        if let Some(start) = item.checked_sub(1).and_then(|index| items.get(index)) {
+            let offset = item * ITEM_SIZE - start.start;
+            label.range = label.range.start - offset..label.range.end - offset;
+        }
+    }
+    diagnostic
+}
+
+/// Moves the source locations in a function (from an item which was parsed
+/// separately) down by the given number of lines.
+fn move_lines(mut function: Function, lines: u32) -> Function {
+    let locations = function.location.iter_mut().chain(
+        function
+            .blocks
+            .iter_mut()
+            .flat_map(|block| &mut block.statements)
+            .filter_map(|statement| match statement {
+                Statement::Location(location) => Some(location),
+                _ => None,
+            }),
+    );
+    for location in locations {
+        location.line += lines;
+    }
+    function
+}
+
+/// Copies the diagnostic, attaching it to the given file and moving every
+/// label forward by `offset` bytes.
+fn with_file_id<ID>(diagnostic: &Diagnostic<()>, file_id: ID, offset: usize) -> Diagnostic<ID>
+where
+    ID: Copy,
+{
+    Diagnostic {
+        severity: diagnostic.severity,
+        code: diagnostic.code.clone(),
+        message: diagnostic.message.clone(),
+        labels: diagnostic
+            .labels
+            .iter()
+            .map(|label| codespan_reporting::diagnostic::Label {
+                style: label.style,
+                file_id,
+                range: label.range.start + offset..label.range.end + offset,
+                message: label.message.clone(),
+            })
+            .collect(),
+        notes: diagnostic.notes.clone(),
+    }
+}
+
+/// Splits the source code into its top-level items (function and record
+/// definitions, and statements).
+///
+/// The items are the top-level nodes of the syntax tree (see [`SyntaxTree`]),
+/// so they are found using the keywords which start and end each block (rather
+/// than the indentation). Each item starts at the start of the line which its
+/// first token is on, and includes everything up to the start of the next item
+/// (the first item also includes everything before it).
+pub fn split_items(source: &str) -> Vec<TopLevelItem> {
+    let tree = SyntaxTree::new(source);
+    let mut starts = tree
+        .root()
+        .children()
+        .iter()
+        .filter_map(|child| match child {
+            Element::Node(node) => node.first_token(),
+            Element::Token(_) => None,
+        })
+        .map(|token| {
+            let index = tree.tokens()[token].span().range().start;
+            source[..index].rfind('\n').map_or(0, |i| i + 1)
+        })
+        .collect::<Vec<_>>();
+    if let Some(first) = starts.first_mut() {
+        *first = 0;
+    }
+
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, &start)| {
+            let end = starts.get(i + 1).copied().unwrap_or(source.len());
+            TopLevelItem {
+                start,
+                text: source[start..end].into(),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,153 @@
+use std::{fs, path::Path};
+
+use crate::{mir::lower, parse::parse_with_prelude, ty::type_check};
+
+use super::{split_items, Database, Query};
+
+const PROGRAM: &str = "function double(x)
+  return x * 2
+endfunction
+
+function triple(x)
+  return x * 3
+endfunction
+
+X = double(1) + triple(2)
+";
+
+#[test]
+fn split_into_items() {
+    let items = split_items(PROGRAM);
+    assert_eq!(items.len(), 3);
+    assert_eq!(
+        &*items[0].text,
+        "function double(x)\n  return x * 2\nendfunction\n\n"
+    );
+    assert_eq!(items[2].start, PROGRAM.find("X =").unwrap());
+    assert_eq!(&*items[2].text, "X = double(1) + triple(2)\n");
+}
+
+#[test]
+fn editing_one_function_only_reparses_it() {
+    let mut db = Database::new(PROGRAM);
+    assert!(db.diagnostics(()).is_empty());
+    for index in 0..3 {
+        assert_eq!(db.executions(Query::ItemSyntax(index)), 1);
+    }
+
+    db.set_source(&PROGRAM.replace("x * 3", "x * 4"));
+    assert!(db.diagnostics(()).is_empty());
+    assert_eq!(db.executions(Query::ItemSyntax(0)), 1);
+    assert_eq!(db.executions(Query::ItemSyntax(1)), 2);
+    assert_eq!(db.executions(Query::ItemSyntax(2)), 1);
+}
+
+#[test]
+fn editing_one_function_only_checks_and_lowers_it_again() {
+    let mut db = Database::new(PROGRAM);
+    db.mir(()).unwrap();
+
+    // (this changes the length of the function, and the number of ids in it)
+    db.set_source(&PROGRAM.replace("x * 3", "(x + 1) * 3"));
+    db.mir(()).unwrap();
+    for (index, executions) in [1, 2, 1].into_iter().enumerate() {
+        assert_eq!(db.executions(Query::ItemConstraints(index)), executions);
+        assert_eq!(db.executions(Query::ItemMir(index)), executions);
+    }
+    // unification depends on every item
+    assert_eq!(db.executions(Query::Unify), 2);
+    assert_eq!(db.executions(Query::PreludeConstraints), 1);
+    assert_eq!(db.executions(Query::PreludeMir), 1);
+}
+
+#[test]
+fn editing_a_signature_checks_its_callers_again() {
+    let mut db = Database::new(PROGRAM);
+    db.mir(()).unwrap();
+
+    db.set_source(
+        &PROGRAM
+            .replace("triple(x)", "triple(y)")
+            .replace("x * 3", "y * 3"),
+    );
+    db.mir(()).unwrap();
+    assert_eq!(db.executions(Query::ItemConstraints(0)), 2);
+    assert_eq!(db.executions(Query::ItemConstraints(1)), 2);
+    assert_eq!(db.executions(Query::ItemConstraints(2)), 2);
+}
+
+#[test]
+fn unchanged_source_is_not_recomputed() {
+    let mut db = Database::new(PROGRAM);
+    db.diagnostics(());
+    db.set_source(PROGRAM);
+    db.diagnostics(());
+    assert_eq!(db.executions(Query::Diagnostics), 1);
+    assert_eq!(db.executions(Query::Types), 1);
+}
+
+#[test]
+fn syntax_errors_are_reported_at_the_right_position() {
+    let source = PROGRAM.replace("return x * 3", "return x * ");
+    let mut db = Database::new(&source);
+    let diagnostics = db.diagnostics(());
+    assert_eq!(diagnostics.len(), 1);
+    let error_start = diagnostics[0].labels[0].range.start;
+    assert!(error_start >= source.find("function triple").unwrap());
+    assert!(error_start < source.find("X =").unwrap());
+    // the program is only type checked once it has no syntax errors
+    assert_eq!(db.executions(Query::Types), 0);
+
+    db.set_source(PROGRAM);
+    assert!(db.diagnostics(()).is_empty());
+    assert_eq!(db.executions(Query::Types), 1);
+}
+
+#[test]
+fn type_errors() {
+    let mut db = Database::new("X = sqrt(True)\n");
+    assert_eq!(db.diagnostics(()).len(), 1);
+}
+
+#[test]
+fn type_errors_are_reported_at_the_right_position() {
+    let source = format!(
+        "{}Y = sqrt(True)\n",
+        PROGRAM.replace("x * 3", "(x + 1) * 3")
+    );
+    let mut db = Database::new(&source);
+    let diagnostics = db.diagnostics(());
+    assert_eq!(diagnostics.len(), 1);
+    for label in &diagnostics[0].labels {
+        assert!(label.range.start >= source.find("Y =").unwrap());
+    }
+}
+
+/// Lowering each item separately should produce exactly the same MIR as
+/// lowering the whole program at once.
+#[test]
+fn mir_is_the_same_as_lowering_the_whole_program() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("../filetests");
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(&path).unwrap();
+
+        let table = match parse_with_prelude(&source) {
+            Ok(table) => table,
+            Err(_) => continue,
+        };
+        let program = match type_check(&table)
+            .ok()
+            .and_then(|env| lower(&table, &env).ok())
+        {
+            Some(program) => program,
+            None => continue,
+        };
+
+        let mut db = Database::new(&source);
+        match db.mir(()) {
+            Ok(mir) => assert_eq!(mir, program, "{}", path.display()),
+            Err(diagnostics) => panic!("{}: {diagnostics:?}", path.display()),
+        }
+    }
+}
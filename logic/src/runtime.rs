@@ -0,0 +1,342 @@
+//! The runtime library, which implements the builtin functions.
+//!
+//! The functions marked `#[no_mangle]` are called by the code produced by the
+//! code generator (see `codegen::make_module`), so they use the same
+//! representation of values as compiled code (booleans are 32-bit integers and
+//! strings are pointers to null-terminated strings). The interpreter (see
+//! [`crate::interpret`]) calls the other functions directly.
+
+use std::{
+    cell::{Cell, RefCell},
+    ffi::{CStr, CString},
+    mem,
+    os::raw::c_char,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::io::{Io, Stdio};
+
+/// The error reported (by both compiled code and the interpreter) when a
+/// program runs out of fuel (see [`crate::codegen::CodegenOptions::fuel`]).
+pub(crate) const RAN_FOR_TOO_LONG: &str = "Your program ran for too long, so it was stopped. \
+    This is usually caused by a loop which never finishes (if your program is meant to take \
+    this long, you can give it more time using `--fuel`).";
+
+/// The runtime functions which can fail (e.g. `random`, if its lower bound is
+/// greater than its upper bound). Compiled code passes these functions the
+/// address of a flag as an extra argument, which they set if they fail (see
+/// [`fail`]), and stops the program if the flag is set once they return.
+pub(crate) const FALLIBLE: &[&str] = &["random_int", "random_real", "pow_int"];
+
+thread_local! {
+    /// The error which the last runtime function to fail reported.
+    static ERROR: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Records that a runtime function failed (rather than panicking, which would
+/// abort the whole process, because the runtime functions are called from
+/// compiled code). This returns a placeholder value for the function to
+/// return, which is never used (because the program stops straight away).
+unsafe fn fail<T: Default>(failed: *mut u8, error: String) -> T {
+    *failed = 1;
+    ERROR.with(|current| *current.borrow_mut() = Some(error));
+    T::default()
+}
+
+/// Returns (and clears) the error which the last runtime function to fail
+/// reported.
+pub(crate) fn take_error() -> Option<String> {
+    ERROR.with(|error| error.borrow_mut().take())
+}
+
+/// Formats a boolean (which the compiled code passes as a 32-bit integer).
+pub(crate) fn format_bool(boolean: i32) -> &'static str {
+    if boolean == 1 {
+        "True"
+    } else if boolean == 0 {
+        "False"
+    } else {
+        panic!("invalid boolean value {boolean}")
+    }
+}
+
+/// Formats a real number. The `Debug` implementation always includes a decimal
+/// point (e.g. `1.0` rather than `1`), which makes it clear that this is a real
+/// number.
+pub(crate) fn format_real(real: f64) -> String {
+    format!("{:?}", real)
+}
+
+/// Reads a string produced by the compiled code (these are null-terminated).
+unsafe fn read_string<'a>(string: *const c_char) -> &'a str {
+    CStr::from_ptr(string).to_str().unwrap()
+}
+
+/// Allocates a new string which the compiled code can use.
+///
+/// todo: free strings once they are no longer needed
+fn alloc_string(string: String) -> *const c_char {
+    CString::new(string).unwrap().into_raw()
+}
+
+thread_local! {
+    /// The I/O which compiled code is currently using (see [`with_io`]).
+    static IO: Cell<Option<*mut (dyn Io + 'static)>> = Cell::new(None);
+}
+
+/// Runs `f`, during which compiled code performs all of its input and output
+/// using `io` (rather than the standard input and output).
+pub(crate) fn with_io<R>(io: &mut dyn Io, f: impl FnOnce() -> R) -> R {
+    /// Restores the previous I/O (even if `f` panics).
+    struct Restore(Option<*mut (dyn Io + 'static)>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            IO.with(|io| io.set(self.0));
+        }
+    }
+
+    // safety: the pointer is only used while `f` is running (and `io` is
+    // borrowed for all of that time)
+    let io = unsafe { mem::transmute::<*mut dyn Io, *mut (dyn Io + 'static)>(io) };
+    let _restore = Restore(IO.with(|current| current.replace(Some(io))));
+    f()
+}
+
+/// Calls `f` with the I/O which compiled code is currently using.
+fn current_io<R>(f: impl FnOnce(&mut dyn Io) -> R) -> R {
+    match IO.with(Cell::get) {
+        // safety: see `with_io`
+        Some(io) => f(unsafe { &mut *io }),
+        None => f(&mut Stdio),
+    }
+}
+
+fn write(text: &str) {
+    current_io(|io| io.write(text)).expect("the output of the program could not be written")
+}
+
+#[no_mangle]
+pub(crate) fn print_int(int: i64) {
+    write(&format!("{int}\n"))
+}
+
+#[no_mangle]
+pub(crate) fn print_bool(boolean: i32) {
+    write(&format!("{}\n", format_bool(boolean)))
+}
+
+#[no_mangle]
+pub(crate) fn print_real(real: f64) {
+    write(&format!("{}\n", format_real(real)))
+}
+
+// the functions used to implement `print` (which prints each of its arguments
+// in turn)
+
+#[no_mangle]
+pub(crate) unsafe fn write_string(string: *const c_char) {
+    write(read_string(string))
+}
+
+#[no_mangle]
+pub(crate) fn write_int(int: i64) {
+    write(&int.to_string())
+}
+
+#[no_mangle]
+pub(crate) fn write_bool(boolean: i32) {
+    write(format_bool(boolean))
+}
+
+#[no_mangle]
+pub(crate) fn write_real(real: f64) {
+    write(&format_real(real))
+}
+
+#[no_mangle]
+pub(crate) fn write_space() {
+    write(" ")
+}
+
+#[no_mangle]
+pub(crate) fn write_newline() {
+    write("\n")
+}
+
+// string building
+
+#[no_mangle]
+pub(crate) fn str_int(int: i64) -> *const c_char {
+    alloc_string(int.to_string())
+}
+
+#[no_mangle]
+pub(crate) fn str_bool(boolean: i32) -> *const c_char {
+    alloc_string(format_bool(boolean).to_owned())
+}
+
+#[no_mangle]
+pub(crate) fn str_real(real: f64) -> *const c_char {
+    alloc_string(format_real(real))
+}
+
+#[no_mangle]
+pub(crate) fn str_string(string: *const c_char) -> *const c_char {
+    string
+}
+
+#[no_mangle]
+pub(crate) unsafe fn string_concat(left: *const c_char, right: *const c_char) -> *const c_char {
+    alloc_string([read_string(left), read_string(right)].concat())
+}
+
+#[no_mangle]
+pub(crate) fn input() -> *const c_char {
+    let line = current_io(|io| io.read_line()).expect("the input could not be read");
+    alloc_string(line)
+}
+
+thread_local! {
+    /// The state of the random number generator used by `random`.
+    static RANDOM_STATE: Cell<u64> = Cell::new(0);
+}
+
+/// Seeds the random number generator. If no seed is provided then one is
+/// derived from the current time (so each run of the program will produce
+/// different numbers).
+pub(crate) fn seed_random(seed: Option<u64>) {
+    let seed = seed.unwrap_or_else(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default()
+    });
+    RANDOM_STATE.with(|state| state.set(seed));
+}
+
+/// Produces the next number from the random number generator. This uses
+/// SplitMix64, which is fast, has a single word of state and (importantly for
+/// our tests) produces the same sequence on every platform.
+fn next_random() -> u64 {
+    RANDOM_STATE.with(|state| {
+        let next = state.get().wrapping_add(0x9e3779b97f4a7c15);
+        state.set(next);
+        let mut z = next;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    })
+}
+
+/// Returns a random integer between `lower` and `upper` (inclusive of both).
+pub(crate) fn try_random_int(lower: i64, upper: i64) -> Result<i64, String> {
+    if upper < lower {
+        return Err(format!(
+            "the lower bound ({lower}) passed to `random` is greater than the upper bound ({upper})"
+        ));
+    }
+    let range = upper.wrapping_sub(lower) as u64;
+    if range == u64::MAX {
+        return Ok(next_random() as i64);
+    }
+    Ok(lower.wrapping_add((next_random() % (range + 1)) as i64))
+}
+
+#[no_mangle]
+pub(crate) unsafe fn random_int(lower: i64, upper: i64, failed: *mut u8) -> i64 {
+    try_random_int(lower, upper).unwrap_or_else(|error| fail(failed, error))
+}
+
+/// Returns a random real number which is at least `lower`, but less than
+/// `upper`.
+pub(crate) fn try_random_real(lower: f64, upper: f64) -> Result<f64, String> {
+    if upper < lower {
+        return Err(format!(
+            "the lower bound ({lower}) passed to `random` is greater than the upper bound ({upper})"
+        ));
+    }
+    // use the top 53 bits (the size of the mantissa) to produce a number in
+    // the range [0, 1)
+    let unit = (next_random() >> 11) as f64 / (1u64 << 53) as f64;
+    Ok(lower + unit * (upper - lower))
+}
+
+#[no_mangle]
+pub(crate) unsafe fn random_real(lower: f64, upper: f64, failed: *mut u8) -> f64 {
+    try_random_real(lower, upper).unwrap_or_else(|error| fail(failed, error))
+}
+
+#[no_mangle]
+pub(crate) fn abs_int(int: i64) -> i64 {
+    int.wrapping_abs()
+}
+
+#[no_mangle]
+pub(crate) fn abs_real(real: f64) -> f64 {
+    real.abs()
+}
+
+#[no_mangle]
+pub(crate) fn sqrt_real(real: f64) -> f64 {
+    real.sqrt()
+}
+
+/// Rounds to the nearest integer (numbers which are exactly half-way between
+/// two integers are rounded away from zero).
+#[no_mangle]
+pub(crate) fn round_real(real: f64) -> i64 {
+    real.round() as i64
+}
+
+#[no_mangle]
+pub(crate) fn floor_real(real: f64) -> i64 {
+    real.floor() as i64
+}
+
+#[no_mangle]
+pub(crate) fn ceil_real(real: f64) -> i64 {
+    real.ceil() as i64
+}
+
+#[no_mangle]
+pub(crate) fn min_int(a: i64, b: i64) -> i64 {
+    a.min(b)
+}
+
+#[no_mangle]
+pub(crate) fn min_real(a: f64, b: f64) -> f64 {
+    a.min(b)
+}
+
+#[no_mangle]
+pub(crate) fn max_int(a: i64, b: i64) -> i64 {
+    a.max(b)
+}
+
+#[no_mangle]
+pub(crate) fn max_real(a: f64, b: f64) -> f64 {
+    a.max(b)
+}
+
+pub(crate) fn try_pow_int(base: i64, exponent: i64) -> Result<i64, String> {
+    match u32::try_from(exponent) {
+        Ok(exponent) => Ok(base.wrapping_pow(exponent)),
+        Err(_) if exponent < 0 => Err(format!(
+            "cannot raise an integer to a negative power ({exponent}); use a `Real` instead"
+        )),
+        Err(_) => Err(format!(
+            "the exponent ({exponent}) passed to `pow` is too large"
+        )),
+    }
+}
+
+#[no_mangle]
+pub(crate) unsafe fn pow_int(base: i64, exponent: i64, failed: *mut u8) -> i64 {
+    try_pow_int(base, exponent).unwrap_or_else(|error| fail(failed, error))
+}
+
+#[no_mangle]
+pub(crate) fn pow_real(base: f64, exponent: f64) -> f64 {
+    base.powf(exponent)
+}
@@ -0,0 +1,593 @@
+//! The public API for embedding the compiler in other tools (e.g. graders,
+//! linters and visualisers).
+//!
+//! A [`Session`] holds the options and the source code of the programs which
+//! are being worked on. Each program can be
+//!
+//! - checked (see [`Session::check`]), which parses and type checks it and
+//!   provides read-only access to its functions and records (and their types)
+//! - compiled (see [`Session::compile`]), which produces the (optimised) MIR
+//! - run, using either backend (see [`Compiled::run`] and
+//!   [`Compiled::run_captured`])
+//!
+//! Every error is returned as a [`Diagnostic`], which is plain data (so it can
+//! be displayed however the tool wants).
+//!
+//! ```ignore
+//! let mut session = Session::new(CodegenOptions::default());
+//! let id = session.add_source("main.pseudo", "function main()\n  print(1)\n  return 0\nendfunction\n");
+//! let execution = session.compile(id)?.run_captured(Backend::Interpreter, Vec::<String>::new());
+//! assert_eq!(execution.output, "1\n");
+//! ```
+
+#[cfg(test)]
+mod test;
+
+use std::{fmt, ops::Range};
+
+use codespan_reporting::{
+    diagnostic::{self, LabelStyle},
+    files::{Files, SimpleFile},
+};
+use serde::Serialize;
+
+use crate::{
+    codegen::{self, optimised_mir, CodegenOptions},
+    interpret,
+    io::{Io, MemoryIo},
+    mir,
+    parse::{
+        block::Block,
+        expr::{BinOp, Expr},
+        func::Func,
+        ident::IdentRef,
+        parse_with_prelude,
+        record::Record,
+        table::{Item, ItemKind, ParseTable},
+    },
+    ty::{type_check, PrimitiveType, Ty, TyEnv},
+};
+
+/// Identifies a source file which has been added to a [`Session`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourceId(usize);
+
+#[derive(Debug)]
+struct Source {
+    name: String,
+    text: String,
+}
+
+/// The options and programs which a tool is working with.
+#[derive(Debug, Default)]
+pub struct Session {
+    options: CodegenOptions,
+    sources: Vec<Source>,
+}
+
+impl Session {
+    pub fn new(options: CodegenOptions) -> Self {
+        Self {
+            options,
+            sources: vec![],
+        }
+    }
+
+    pub fn options(&self) -> &CodegenOptions {
+        &self.options
+    }
+
+    pub fn options_mut(&mut self) -> &mut CodegenOptions {
+        &mut self.options
+    }
+
+    /// Adds a program to the session. `name` is only used to identify the
+    /// program (e.g. a file name).
+    pub fn add_source(&mut self, name: impl Into<String>, text: impl Into<String>) -> SourceId {
+        self.sources.push(Source {
+            name: name.into(),
+            text: text.into(),
+        });
+        SourceId(self.sources.len() - 1)
+    }
+
+    pub fn name(&self, id: SourceId) -> &str {
+        &self.sources[id.0].name
+    }
+
+    pub fn text(&self, id: SourceId) -> &str {
+        &self.sources[id.0].text
+    }
+
+    /// Parses and type checks the program.
+    pub fn check(&self, id: SourceId) -> Result<Checked<'_>, Vec<Diagnostic>> {
+        let text = self.text(id);
+        let table =
+            parse_with_prelude(text).map_err(|error| vec![convert(text, error.report(()))])?;
+        let env = match type_check(&table) {
+            Ok(env) => env,
+            Err(error) => return Err(vec![convert(text, error.report((), &table))]),
+        };
+        Ok(Checked {
+            session: self,
+            source: id,
+            table,
+            env,
+        })
+    }
+
+    /// Lowers the program to MIR (and optimises it, according to the
+    /// session's options), so that it can be run.
+    pub fn compile(&self, id: SourceId) -> Result<Compiled<'_>, Vec<Diagnostic>> {
+        let checked = self.check(id)?;
+        let program = optimised_mir(&checked.table, &checked.env, &self.options)
+            .map_err(|error| vec![checked.convert(error.report(()))])?;
+        Ok(Compiled { checked, program })
+    }
+
+    /// Returns every error in the program (this is empty if it can be run).
+    pub fn diagnostics(&self, id: SourceId) -> Vec<Diagnostic> {
+        self.compile(id).err().unwrap_or_default()
+    }
+}
+
+/// A program which has been parsed and type checked.
+pub struct Checked<'s> {
+    session: &'s Session,
+    source: SourceId,
+    table: ParseTable<'s>,
+    env: TyEnv,
+}
+
+impl<'s> Checked<'s> {
+    pub fn source(&self) -> SourceId {
+        self.source
+    }
+
+    /// The warnings about the program (e.g. lines which are not indented
+    /// correctly), which do not stop it from being compiled.
+    pub fn warnings(&self) -> Vec<Diagnostic> {
+        self.table
+            .warnings()
+            .iter()
+            .map(|warning| self.convert(warning.report(())))
+            .collect()
+    }
+
+    /// The functions defined in the program (not including those in the
+    /// prelude), in the order in which they were defined.
+    pub fn functions(&self) -> impl Iterator<Item = Function<'_, 's>> {
+        self.table
+            .root
+            .1
+            .inner
+            .iter()
+            .filter(|item| item.item_kind == ItemKind::Func)
+            .map(move |item| Function {
+                checked: self,
+                func: &self.table.func[&item.id],
+            })
+    }
+
+    pub fn function(&self, name: &str) -> Option<Function<'_, 's>> {
+        self.functions().find(|function| function.name() == name)
+    }
+
+    /// The records defined in the program, in the order in which they were
+    /// defined.
+    pub fn records(&self) -> impl Iterator<Item = RecordDef<'_, 's>> {
+        self.table
+            .root
+            .1
+            .inner
+            .iter()
+            .filter(|item| item.item_kind == ItemKind::Record)
+            .map(move |item| RecordDef {
+                checked: self,
+                record: &self.table.record_[&item.id],
+            })
+    }
+
+    fn ty(&self, ty: Ty) -> Type {
+        match ty {
+            Ty::PrimitiveType(ty) => Type::Primitive(ty),
+            Ty::Record { ref_ } => {
+                let record = self.table.get_record(ref_);
+                Type::Record(self.table.get_ident(record.name).inner().to_owned())
+            }
+        }
+    }
+
+    fn convert(&self, diagnostic: diagnostic::Diagnostic<()>) -> Diagnostic {
+        convert(self.session.text(self.source), diagnostic)
+    }
+}
+
+/// The type of a value.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Type {
+    Primitive(PrimitiveType),
+    /// A record (with the given name).
+    Record(String),
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Type::Primitive(PrimitiveType::Int) => f.write_str("Int"),
+            Type::Primitive(PrimitiveType::Bool) => f.write_str("Bool"),
+            Type::Primitive(PrimitiveType::StrSlice) => f.write_str("String"),
+            Type::Primitive(PrimitiveType::Pointer) => f.write_str("Pointer"),
+            Type::Primitive(PrimitiveType::Real) => f.write_str("Real"),
+            Type::Record(name) => f.write_str(name),
+        }
+    }
+}
+
+/// A variable (or function parameter, or record field).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variable {
+    pub name: String,
+    /// This is `None` if the type could not be inferred (e.g. because the
+    /// variable is never used).
+    pub ty: Option<Type>,
+    /// The position of the name of the variable in the source code.
+    pub span: Range<usize>,
+}
+
+/// A function defined in a program.
+#[derive(Clone, Copy)]
+pub struct Function<'c, 's> {
+    checked: &'c Checked<'s>,
+    func: &'c Func,
+}
+
+impl<'c, 's> Function<'c, 's> {
+    pub fn name(&self) -> &'c str {
+        self.checked.table.get_ident(self.func.name).inner()
+    }
+
+    /// The position of the name of the function in the source code.
+    pub fn span(&self) -> Range<usize> {
+        self.checked
+            .table
+            .get_ident(self.func.name)
+            .span
+            .index_only()
+            .range()
+    }
+
+    pub fn params(&self) -> Vec<Variable> {
+        self.func
+            .parameters
+            .iter()
+            .map(|param| self.variable(*param))
+            .collect()
+    }
+
+    /// Every variable used in the function (including its parameters), in the
+    /// order in which they first appear.
+    pub fn variables(&self) -> Vec<Variable> {
+        let table = &self.checked.table;
+        let mut idents = self.func.parameters.clone();
+        block_variables(table, table.get_block(&self.func.block), &mut idents);
+        idents
+            .into_iter()
+            .map(|ident| self.variable(ident))
+            .collect()
+    }
+
+    fn variable(&self, ident_ref: IdentRef) -> Variable {
+        let ident = self.checked.table.get_ident(ident_ref);
+        Variable {
+            name: ident.inner().to_owned(),
+            ty: self
+                .checked
+                .env
+                .ty_of(ident_ref.id)
+                .map(|ty| self.checked.ty(ty)),
+            span: ident.span.index_only().range(),
+        }
+    }
+
+    /// The type of the value which the function returns (`None` if this could
+    /// not be inferred).
+    pub fn returns(&self) -> Option<Type> {
+        self.checked
+            .env
+            .ty_of(self.func.name.id)
+            .map(|ty| self.checked.ty(ty))
+    }
+}
+
+/// Adds every variable used in the block (which is not already in `idents`)
+/// to `idents`.
+fn block_variables(table: &ParseTable, block: &Block, idents: &mut Vec<IdentRef>) {
+    for item in &block.inner {
+        match table.get(item) {
+            Some(Item::Expr(expr)) => expr_variables(table, expr, idents),
+            Some(Item::If(if_)) => {
+                for branch in std::iter::once(&if_.r#if).chain(&if_.else_ifs) {
+                    expr_variables(table, table.get_expr(&branch.condition), idents);
+                    block_variables(table, table.get_block(&branch.block), idents);
+                }
+                if let Some(block) = &if_.r#else {
+                    block_variables(table, table.get_block(block), idents);
+                }
+            }
+            Some(Item::While(while_)) => {
+                expr_variables(table, table.get_expr(&while_.condition), idents);
+                block_variables(table, table.get_block(&while_.block), idents);
+            }
+            Some(Item::For(for_)) => {
+                if !idents.contains(&for_.var) {
+                    idents.push(for_.var);
+                }
+                let between = &for_.between;
+                for expr in [&between.start, &between.stop]
+                    .into_iter()
+                    .chain(&between.step)
+                {
+                    expr_variables(table, table.get_expr(expr), idents);
+                }
+                block_variables(table, table.get_block(&for_.block), idents);
+            }
+            Some(Item::Return(ret)) => expr_variables(table, table.get_expr(&ret.expr), idents),
+            Some(Item::Block(block)) => block_variables(table, block, idents),
+            _ => {}
+        }
+    }
+}
+
+fn expr_variables(table: &ParseTable, expr: &Expr, idents: &mut Vec<IdentRef>) {
+    match expr {
+        Expr::Ident(ident) => {
+            if !idents.contains(ident) {
+                idents.push(*ident);
+            }
+        }
+        Expr::Literal(_) => {}
+        // the right-hand side is the name of a field
+        Expr::BinOp(op, left, _) if op.token == BinOp::Dot => {
+            expr_variables(table, table.get_expr(left), idents)
+        }
+        Expr::BinOp(_, left, right) => {
+            expr_variables(table, table.get_expr(left), idents);
+            expr_variables(table, table.get_expr(right), idents);
+        }
+        Expr::UnOp(_, operand) => expr_variables(table, table.get_expr(operand), idents),
+        Expr::FunctionCall(_, args) => {
+            for arg in args {
+                expr_variables(table, table.get_expr(arg), idents);
+            }
+        }
+        Expr::Constructor(constructor) => {
+            for value in constructor.fields.values() {
+                expr_variables(table, table.get_expr(value), idents);
+            }
+        }
+    }
+}
+
+/// A record defined in a program.
+#[derive(Clone, Copy)]
+pub struct RecordDef<'c, 's> {
+    checked: &'c Checked<'s>,
+    record: &'c Record,
+}
+
+impl<'c, 's> RecordDef<'c, 's> {
+    pub fn name(&self) -> &'c str {
+        self.checked.table.get_ident(self.record.name).inner()
+    }
+
+    /// The position of the name of the record in the source code.
+    pub fn span(&self) -> Range<usize> {
+        self.checked
+            .table
+            .get_ident(self.record.name)
+            .span
+            .index_only()
+            .range()
+    }
+
+    pub fn fields(&self) -> Vec<Variable> {
+        self.record
+            .fields
+            .iter()
+            .map(|field| {
+                let ident = self.checked.table.get_ident(field.name);
+                Variable {
+                    name: ident.inner().to_owned(),
+                    ty: Some(Type::Primitive(*field.ty)),
+                    span: ident.span.index_only().range(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// The backend which is used to run programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Compile the program to machine code (using Cranelift) and run that.
+    Jit,
+    /// Run the program using the interpreter (see [`crate::interpret`]).
+    Interpreter,
+}
+
+/// A program which has been compiled (but not yet run).
+pub struct Compiled<'s> {
+    checked: Checked<'s>,
+    program: mir::Program,
+}
+
+impl<'s> Compiled<'s> {
+    pub fn checked(&self) -> &Checked<'s> {
+        &self.checked
+    }
+
+    pub fn mir(&self) -> &mir::Program {
+        &self.program
+    }
+
+    /// Runs the program (performing all input and output using `io`),
+    /// returning the value which `main` returned.
+    ///
+    /// When using [`Backend::Jit`], the program is compiled to machine code
+    /// every time it is run.
+    pub fn run(&self, backend: Backend, io: &mut dyn Io) -> Result<i64, Diagnostic> {
+        let options = self.checked.session.options();
+        match backend {
+            Backend::Jit => codegen::compile(&self.checked.table, &self.program, options)
+                .and_then(|executable| executable.run(io))
+                .map(i64::from)
+                .map_err(|error| self.checked.convert(error.report(()))),
+            Backend::Interpreter => interpret::run(&self.program, options.seed, options.fuel, io)
+                .map_err(|error| Diagnostic {
+                    severity: Severity::Error,
+                    message: error.message,
+                    labels: vec![],
+                    notes: vec![],
+                }),
+        }
+    }
+
+    /// Runs the program, providing it with the given lines of input and
+    /// capturing everything it prints.
+    pub fn run_captured<S: Into<String>>(
+        &self,
+        backend: Backend,
+        input: impl IntoIterator<Item = S>,
+    ) -> Execution {
+        let mut io = MemoryIo::new(input);
+        let status = match self.run(backend, &mut io) {
+            Ok(code) => ExitStatus::Exited(code),
+            Err(error) => ExitStatus::Failed(error),
+        };
+        Execution {
+            output: io.into_output(),
+            status,
+        }
+    }
+}
+
+/// The result of running a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Execution {
+    /// Everything which the program printed.
+    pub output: String,
+    pub status: ExitStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// The program finished, and `main` returned the given value.
+    Exited(i64),
+    /// The program was stopped because of an error.
+    Failed(Diagnostic),
+}
+
+/// An error (or warning) in a program.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub labels: Vec<Label>,
+    /// Additional information which is not attached to any part of the
+    /// program.
+    pub notes: Vec<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)?;
+        for label in &self.labels {
+            write!(
+                f,
+                "\n  {}:{}: {}",
+                label.start.line, label.start.column, label.message
+            )?;
+        }
+        for note in &self.notes {
+            write!(f, "\n  = {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+            Severity::Help => "help",
+        })
+    }
+}
+
+/// Some text which is attached to part of the program.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Label {
+    /// Whether this is the part of the program which the diagnostic is about
+    /// (rather than some additional context).
+    pub primary: bool,
+    pub message: String,
+    /// The byte offsets of the start and end of the part of the program.
+    pub range: Range<usize>,
+    pub start: Location,
+    pub end: Location,
+}
+
+/// A position in a program (lines and columns start at one).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Converts one of the compiler's diagnostics about `text` (e.g. one returned
+/// by [`Database::diagnostics`](crate::query::Database::diagnostics)) into a
+/// [`Diagnostic`].
+pub fn convert(text: &str, diagnostic: diagnostic::Diagnostic<()>) -> Diagnostic {
+    let file = SimpleFile::new("", text);
+    let location = |index| {
+        let location = file.location((), index).unwrap();
+        Location {
+            line: location.line_number,
+            column: location.column_number,
+        }
+    };
+
+    Diagnostic {
+        severity: match diagnostic.severity {
+            diagnostic::Severity::Bug | diagnostic::Severity::Error => Severity::Error,
+            diagnostic::Severity::Warning => Severity::Warning,
+            diagnostic::Severity::Note => Severity::Note,
+            diagnostic::Severity::Help => Severity::Help,
+        },
+        message: diagnostic.message,
+        labels: diagnostic
+            .labels
+            .into_iter()
+            .map(|label| Label {
+                primary: label.style == LabelStyle::Primary,
+                message: label.message,
+                start: location(label.range.start),
+                end: location(label.range.end),
+                range: label.range,
+            })
+            .collect(),
+        notes: diagnostic.notes,
+    }
+}
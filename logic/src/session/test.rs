@@ -0,0 +1,163 @@
+use crate::{codegen::CodegenOptions, io::MemoryIo, ty::PrimitiveType};
+
+use super::{Backend, ExitStatus, Location, Session, Severity, Type};
+
+const PROGRAM: &str = "record Point\n  x of Int\n  y of Real\nendrecord\nfunction main()\n  name = input()\n  print(\"Hello, \" + name)\n  print_int(distance(Point { x: 3, y: 4.0 }))\n  return 0\nendfunction\nfunction distance(point)\n  return point.x\nendfunction\n";
+
+#[test]
+fn functions_and_records() {
+    let mut session = Session::default();
+    let id = session.add_source("point.pseudo", PROGRAM);
+    let checked = session.check(id).unwrap();
+
+    let names = checked
+        .functions()
+        .map(|function| function.name())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["main", "distance"]);
+
+    let distance = checked.function("distance").unwrap();
+    assert_eq!(
+        distance.returns(),
+        Some(Type::Primitive(PrimitiveType::Int))
+    );
+    let params = distance.params();
+    assert_eq!(params.len(), 1);
+    assert_eq!(params[0].name, "point");
+    assert_eq!(params[0].ty, Some(Type::Record("Point".to_owned())));
+    assert_eq!(&PROGRAM[params[0].span.clone()], "point");
+    assert_eq!(&PROGRAM[distance.span()], "distance");
+
+    let records = checked.records().collect::<Vec<_>>();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].name(), "Point");
+    let fields = records[0]
+        .fields()
+        .into_iter()
+        .map(|field| (field.name, field.ty.unwrap().to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        fields,
+        [
+            ("x".to_owned(), "Int".to_owned()),
+            ("y".to_owned(), "Real".to_owned())
+        ]
+    );
+}
+
+#[test]
+fn variables() {
+    let mut session = Session::default();
+    let id = session.add_source(
+        "loop.pseudo",
+        "record Point\n  x of Int\n  y of Real\nendrecord\nfunction main()\n  total = 0\n  i = 0\n  while i != 3\n    P = Point { x: i, y: 0.5 }\n    total = total + P.x\n    i = i + 1\n  endwhile\n  print_int(total)\n  return 0\nendfunction\n",
+    );
+    let checked = session.check(id).unwrap();
+    let variables = checked
+        .function("main")
+        .unwrap()
+        .variables()
+        .into_iter()
+        .map(|variable| (variable.name, variable.ty.unwrap().to_string()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        variables,
+        [
+            ("total".to_owned(), "Int".to_owned()),
+            ("i".to_owned(), "Int".to_owned()),
+            ("P".to_owned(), "Point".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn diagnostics() {
+    let mut session = Session::default();
+    let valid = session.add_source("valid.pseudo", PROGRAM);
+    let invalid = session.add_source(
+        "invalid.pseudo",
+        "function main()\n  x = 1 + \"one\"\n  return 0\nendfunction\n",
+    );
+
+    assert!(session.diagnostics(valid).is_empty());
+
+    let diagnostics = session.diagnostics(invalid);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    let label = diagnostics[0]
+        .labels
+        .iter()
+        .find(|label| label.primary)
+        .unwrap();
+    assert_eq!(label.start.line, 2);
+    assert_eq!(
+        label.start,
+        Location {
+            line: 2,
+            column: label.range.start - "function main()\n".len() + 1
+        }
+    );
+    assert!(session.compile(invalid).is_err());
+}
+
+#[test]
+fn diagnostics_involving_the_prelude() {
+    let mut session = Session::default();
+    let program = "function main()\n  array = array_new(2)\n  print(array_get(array, \"one\"))\n  return 0\nendfunction\n";
+    let file = session.add_source("prelude.pseudo", program);
+
+    let diagnostics = session.diagnostics(file);
+    assert_eq!(diagnostics.len(), 1);
+    // the part of the type error which is in the prelude should not be shown
+    // as if it were in the program
+    assert!(diagnostics[0]
+        .labels
+        .iter()
+        .all(|label| label.start.line == 3 && label.range.end <= program.len()));
+    // (it is kept as a note instead)
+    assert!(diagnostics[0]
+        .notes
+        .iter()
+        .any(|note| note.starts_with("in the prelude")));
+}
+
+#[test]
+fn running_programs() {
+    let mut session = Session::new(CodegenOptions {
+        fuel: Some(1000),
+        ..CodegenOptions::default()
+    });
+    let id = session.add_source("point.pseudo", PROGRAM);
+    let compiled = session.compile(id).unwrap();
+    assert!(compiled.mir().function("distance").is_some());
+
+    let execution = compiled.run_captured(Backend::Interpreter, ["world"]);
+    assert_eq!(execution.output, "Hello, world\n3\n");
+    assert_eq!(execution.status, ExitStatus::Exited(0));
+
+    // a program can be run more than once
+    let mut io = MemoryIo::new(["again"]);
+    assert_eq!(compiled.run(Backend::Interpreter, &mut io), Ok(0));
+    assert_eq!(io.output(), "Hello, again\n3\n");
+}
+
+#[test]
+fn runtime_errors() {
+    let mut session = Session::new(CodegenOptions {
+        fuel: Some(1000),
+        ..CodegenOptions::default()
+    });
+    let id = session.add_source(
+        "forever.pseudo",
+        "function main()\n  print(1)\n  while True\n  endwhile\n  return 0\nendfunction\n",
+    );
+    let execution = session
+        .compile(id)
+        .unwrap()
+        .run_captured(Backend::Interpreter, Vec::<String>::new());
+    assert_eq!(execution.output, "1\n");
+    match execution.status {
+        ExitStatus::Failed(error) => assert!(error.message.contains("ran for too long")),
+        status => panic!("{status:?}"),
+    }
+}
@@ -0,0 +1,352 @@
+//! Trace tables (as used in exam questions which ask for a program to be "dry
+//! run").
+//!
+//! The program is run by the interpreter, which is paused at the start of
+//! every line (see [`Interpreter::run_to_location`]). Each line which is run
+//! becomes a row of the table, which records the new value of every variable
+//! which was assigned to while the line was running (and anything which was
+//! printed). The columns are the variables (named as they are in the source
+//! code) in the order in which they were first given a value.
+//!
+//! ```text
+//! Line | total | i | Output
+//! -----+-------+---+-------
+//! 2    | 0     |   |
+//! 3    |       | 1 |
+//! 4    |       |   |
+//! 5    | 1     |   |
+//! ```
+
+use std::{fmt, io, ptr};
+
+use crate::{
+    interpret::{Frame, Interpreter, RuntimeError, Value},
+    io::Io,
+    mir::{Function, Local, Program},
+};
+
+#[cfg(test)]
+mod test;
+
+/// A variable which has a column in the table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Column {
+    /// The function which the variable is defined in.
+    pub function: String,
+    pub name: String,
+}
+
+/// A line of the program which was run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub line: u32,
+    /// The value which each variable (in the same order as
+    /// [`TraceTable::columns`]) was given while running the line (`None` if
+    /// it was not assigned to).
+    pub values: Vec<Option<Value>>,
+    /// Everything which was printed while running the line.
+    pub output: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceTable {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Row>,
+    /// The value returned by `main` (or the error which stopped the program).
+    pub outcome: Result<i64, RuntimeError>,
+}
+
+/// Records the output of the program, while reading its input from `input`.
+struct TraceIo<I> {
+    input: I,
+    output: String,
+}
+
+impl<I: Io> Io for TraceIo<I> {
+    fn write(&mut self, text: &str) -> io::Result<()> {
+        self.output.push_str(text);
+        Ok(())
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        self.input.read_line()
+    }
+}
+
+/// The values of the named variables in a function call (which is in
+/// progress) at some point in the program.
+struct Snapshot<'p> {
+    function: &'p Function,
+    values: Vec<(Local, Option<Value>)>,
+}
+
+impl<'p> Snapshot<'p> {
+    fn new(frame: &Frame<'p>) -> Self {
+        let function = frame.function();
+        let values = (0..function.locals.len() as u32)
+            .map(Local)
+            .filter(|local| function.locals[local.0 as usize].name.is_some())
+            .map(|local| (local, frame.local(local).cloned()))
+            .collect();
+        Self { function, values }
+    }
+}
+
+/// Takes a snapshot of every call to a function written by the user (those
+/// from the prelude are not shown in the table).
+fn snapshot<'p, I: Io>(interpreter: &Interpreter<'p, I>) -> Vec<Option<Snapshot<'p>>> {
+    interpreter
+        .frames()
+        .iter()
+        .map(|frame| frame.function().location.map(|_| Snapshot::new(frame)))
+        .collect()
+}
+
+/// Runs the program (using `input` for its input), and records what happens
+/// on every line. The program's output is included in the table (rather than
+/// being written anywhere else).
+pub fn trace<I: Io>(
+    program: &Program,
+    seed: Option<u64>,
+    fuel: Option<u64>,
+    input: I,
+) -> TraceTable {
+    let mut table = TraceTable {
+        columns: vec![],
+        rows: vec![],
+        outcome: Ok(0),
+    };
+    let io = TraceIo {
+        input,
+        output: String::new(),
+    };
+    let mut interpreter = match Interpreter::new(program, seed, fuel, io) {
+        Ok(interpreter) => interpreter,
+        Err(error) => {
+            table.outcome = Err(error);
+            return table;
+        }
+    };
+
+    let mut line = None;
+    let mut before = snapshot(&interpreter);
+    loop {
+        let result = interpreter.run_to_location();
+        let after = snapshot(&interpreter);
+
+        // everything which happened since the program was last paused is
+        // attributed to the line which it was paused at
+        if let Some(line) = line {
+            let output = std::mem::take(&mut interpreter.io_mut().output);
+            table.add_row(line, &before, &after, output);
+        }
+
+        match result {
+            Ok(None) => {}
+            Ok(Some(code)) => {
+                table.outcome = Ok(code);
+                break;
+            }
+            Err(error) => {
+                table.outcome = Err(error);
+                break;
+            }
+        }
+        line = interpreter
+            .frames()
+            .last()
+            .and_then(|frame| frame.location())
+            .map(|location| location.line);
+        before = after;
+    }
+    table
+}
+
+impl TraceTable {
+    fn add_row(
+        &mut self,
+        line: u32,
+        before: &[Option<Snapshot>],
+        after: &[Option<Snapshot>],
+        output: String,
+    ) {
+        let mut values = vec![None; self.columns.len()];
+        for (index, snapshot) in after.iter().enumerate() {
+            let snapshot = match snapshot {
+                Some(snapshot) => snapshot,
+                None => continue,
+            };
+            // if a different function is now being run at this depth then all
+            // of its variables are new
+            let previous = before
+                .get(index)
+                .and_then(Option::as_ref)
+                .filter(|previous| ptr::eq(previous.function, snapshot.function));
+
+            for (i, (local, value)) in snapshot.values.iter().enumerate() {
+                let value = match value {
+                    Some(value) => value,
+                    None => continue,
+                };
+                if previous.map_or(false, |previous| {
+                    previous.values[i].1.as_ref() == Some(value)
+                }) {
+                    continue;
+                }
+                let column = Column {
+                    function: snapshot.function.name.clone(),
+                    name: snapshot.function.locals[local.0 as usize]
+                        .name
+                        .clone()
+                        .unwrap(),
+                };
+                let index = match self.columns.iter().position(|c| *c == column) {
+                    Some(index) => index,
+                    None => {
+                        self.columns.push(column);
+                        for row in &mut self.rows {
+                            row.values.push(None);
+                        }
+                        values.push(None);
+                        self.columns.len() - 1
+                    }
+                };
+                values[index] = Some(value.clone());
+            }
+        }
+
+        self.rows.push(Row {
+            line,
+            values,
+            output,
+        });
+    }
+
+    /// The heading of each column (including the line and output columns).
+    /// Variables are referred to by name, unless another function has a
+    /// variable with the same name.
+    fn headings(&self) -> Vec<String> {
+        let variables = self.columns.iter().map(|column| {
+            if self
+                .columns
+                .iter()
+                .any(|other| other.name == column.name && other.function != column.function)
+            {
+                format!("{} ({})", column.name, column.function)
+            } else {
+                column.name.clone()
+            }
+        });
+        std::iter::once("Line".to_owned())
+            .chain(variables)
+            .chain(std::iter::once("Output".to_owned()))
+            .collect()
+    }
+
+    /// The contents of each cell, row by row (the final newline is removed
+    /// from the output).
+    fn cells(&self) -> Vec<Vec<String>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                std::iter::once(row.line.to_string())
+                    .chain(row.values.iter().map(|value| match value {
+                        Some(value) => value.to_string(),
+                        None => String::new(),
+                    }))
+                    .chain(std::iter::once(
+                        row.output
+                            .strip_suffix('\n')
+                            .unwrap_or(&row.output)
+                            .to_owned(),
+                    ))
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Formats the table as comma-separated values.
+    pub fn to_csv(&self) -> String {
+        fn field(text: &str) -> String {
+            if text.contains(&[',', '"', '\n', '\r'][..]) {
+                format!("\"{}\"", text.replace('"', "\"\""))
+            } else {
+                text.to_owned()
+            }
+        }
+
+        std::iter::once(self.headings())
+            .chain(self.cells())
+            .map(|row| {
+                let row = row.iter().map(|cell| field(cell)).collect::<Vec<_>>();
+                format!("{}\n", row.join(","))
+            })
+            .collect()
+    }
+
+    /// Formats the table using Markdown.
+    pub fn to_markdown(&self) -> String {
+        fn cell(text: &str) -> String {
+            text.replace('|', "\\|").replace('\n', "<br>")
+        }
+
+        let headings = self.headings();
+        let mut markdown = format!(
+            "| {} |\n|{}\n",
+            headings.join(" | "),
+            "---|".repeat(headings.len())
+        );
+        for row in self.cells() {
+            let row = row.iter().map(|text| cell(text)).collect::<Vec<_>>();
+            markdown += &format!("| {} |\n", row.join(" | "));
+        }
+        markdown
+    }
+}
+
+/// Formats the table as plain text (with the columns aligned).
+impl fmt::Display for TraceTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let headings = self.headings();
+        let cells = self
+            .cells()
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .map(|cell| cell.replace('\n', " "))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let widths = (0..headings.len())
+            .map(|column| {
+                std::iter::once(&headings[column])
+                    .chain(cells.iter().map(|row| &row[column]))
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect::<Vec<_>>();
+
+        let write_row = |f: &mut fmt::Formatter<'_>, row: &[String]| {
+            let line = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect::<Vec<_>>()
+                .join(" | ");
+            writeln!(f, "{}", line.trim_end())
+        };
+
+        write_row(f, &headings)?;
+        let separator = widths
+            .iter()
+            .map(|width| "-".repeat(width + 2))
+            .collect::<Vec<_>>()
+            .join("+");
+        writeln!(f, "{}", &separator[1..separator.len() - 1])?;
+        for row in &cells {
+            write_row(f, row)?;
+        }
+        Ok(())
+    }
+}
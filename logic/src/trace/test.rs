@@ -0,0 +1,178 @@
+use crate::{
+    interpret::Value, io::MemoryIo, mir::lower, parse::parse_with_prelude, ty::type_check,
+};
+
+use super::{trace, Column, TraceTable};
+
+fn trace_program(input: &str, lines: &[&str]) -> TraceTable {
+    let table = parse_with_prelude(input).unwrap();
+    let env = type_check(&table).unwrap();
+    let program = lower(&table, &env).unwrap();
+    trace(
+        &program,
+        Some(42),
+        Some(10_000),
+        MemoryIo::new(lines.iter().copied()),
+    )
+}
+
+static LOOP: &str = "function main()
+  total = 0
+  i = 1
+  while i != 3
+    total = total + i
+    i = i + 1
+  endwhile
+  print(total)
+  return 0
+endfunction
+";
+
+#[test]
+fn rows_and_columns() {
+    let table = trace_program(LOOP, &[]);
+    assert_eq!(table.outcome, Ok(0));
+    assert_eq!(
+        table.columns,
+        [
+            Column {
+                function: "main".to_owned(),
+                name: "total".to_owned()
+            },
+            Column {
+                function: "main".to_owned(),
+                name: "i".to_owned()
+            }
+        ]
+    );
+
+    let rows = table
+        .rows
+        .iter()
+        .map(|row| (row.line, row.values.clone(), row.output.as_str()))
+        .collect::<Vec<_>>();
+    let int = |int| Some(Value::Int(int));
+    assert_eq!(
+        rows,
+        [
+            (2, vec![int(0), None], ""),
+            (3, vec![None, int(1)], ""),
+            (4, vec![None, None], ""),
+            (5, vec![int(1), None], ""),
+            (6, vec![None, int(2)], ""),
+            (4, vec![None, None], ""),
+            (5, vec![int(3), None], ""),
+            (6, vec![None, int(3)], ""),
+            (4, vec![None, None], ""),
+            (8, vec![None, None], "3\n"),
+            (9, vec![None, None], ""),
+        ]
+    );
+}
+
+#[test]
+fn formats() {
+    let table = trace_program(LOOP, &[]);
+    assert_eq!(
+        table.to_string().lines().take(4).collect::<Vec<_>>(),
+        [
+            "Line | total | i | Output",
+            "-----+-------+---+-------",
+            "2    | 0     |   |",
+            "3    |       | 1 |",
+        ]
+    );
+    assert!(table.to_string().contains("\n8    |       |   | 3\n"));
+
+    let csv = table.to_csv();
+    assert!(
+        csv.starts_with("Line,total,i,Output\n2,0,,\n3,,1,\n"),
+        "{csv}"
+    );
+    assert!(csv.contains("\n8,,,3\n"), "{csv}");
+
+    let markdown = table.to_markdown();
+    assert!(
+        markdown.starts_with("| Line | total | i | Output |\n|---|---|---|---|\n| 2 | 0 |  |  |\n"),
+        "{markdown}"
+    );
+}
+
+#[test]
+fn function_calls_and_input() {
+    let table = trace_program(
+        "function main()
+  name = input()
+  greeting = greet(name)
+  print(greeting)
+  return 0
+endfunction
+function greet(name)
+  return \"Hello, \" + name + \", \" + name
+endfunction
+",
+        &["Ada"],
+    );
+    assert_eq!(table.outcome, Ok(0));
+
+    // both functions have a variable called `name` (and the result of a
+    // function call is assigned after the function has returned)
+    let csv = table.to_csv();
+    assert_eq!(
+        csv,
+        "Line,name (main),name (greet),greeting,Output
+2,Ada,,,
+3,,Ada,,
+8,,,\"Hello, Ada, Ada\",
+4,,,,\"Hello, Ada, Ada\"
+5,,,,
+"
+    );
+}
+
+#[test]
+fn errors() {
+    let table = trace_program(
+        "function main()\n  x = 0\n  while True\n    x = x + 1\n  endwhile\n  return 0\nendfunction\n",
+        &[],
+    );
+    assert_eq!(
+        table.outcome.unwrap_err().message,
+        crate::runtime::RAN_FOR_TOO_LONG
+    );
+    // every line up to the point where the program was stopped is included
+    assert_eq!(table.rows[0].line, 2);
+    assert_eq!(table.rows.last().unwrap().line, 4);
+}
+
+#[test]
+fn for_loops() {
+    let table = trace_program(
+        "function main()
+  total = 0
+  for i = 1 to 3 step 2
+    total = total + i
+  next i
+  print(total)
+  return 0
+endfunction
+",
+        &[],
+    );
+    assert_eq!(table.outcome, Ok(0));
+    // the `for` line is included every time the loop checks whether it has
+    // finished
+    assert_eq!(
+        table.to_csv(),
+        "Line,total,i,Output
+2,0,,
+3,,1,
+4,1,,
+3,,3,
+4,4,,
+3,,,
+6,,,4
+7,,,
+"
+    );
+}
@@ -0,0 +1,773 @@
+//! Translates programs into (a single, self-contained file of) C99.
+//!
+//! Unlike the translation into Python, this works from the MIR (the same
+//! program which the code generator compiles), so the translated program
+//! behaves in exactly the same way as the compiled one. This means that the
+//! output is quite low-level
+//!
+//! - every MIR local becomes a C variable (temporaries are called `_1`, `_2`
+//!   and so on) and every basic block a label, with `goto`s between them
+//! - records become `struct`s, which are always used through pointers (a
+//!   record which is created in a function lives until the function returns,
+//!   as it does in the compiled program)
+//! - the runtime functions which the program uses are defined at the start of
+//!   the file (see [`runtime`]), so that (for example) real numbers are
+//!   printed in the same way, and a `--seed` produces the same random numbers
+//! - `extern` functions are declared using the types which they are called
+//!   with
+//!
+//! The compiled program's integers wrap around when they overflow, which is
+//! undefined behaviour in C (so translated programs should be compiled with
+//! `-fwrapv` if this matters).
+
+mod runtime;
+
+use std::{collections::BTreeSet, fmt::Write};
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::mir::{
+    BinaryOp, Callee, Constant, Function, Local, LocalDecl, Operand, Program, Rvalue, Statement,
+    Terminator, Type, UnaryOp,
+};
+
+use self::runtime::{implementing, HELPERS};
+
+/// Names which cannot be used for variables (or functions, or records) in the
+/// translated program (as well as the names of the helpers).
+static RESERVED: &[&str] = &[
+    // keywords
+    "auto",
+    "break",
+    "case",
+    "char",
+    "const",
+    "continue",
+    "default",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extern",
+    "float",
+    "for",
+    "goto",
+    "if",
+    "inline",
+    "int",
+    "long",
+    "register",
+    "restrict",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "switch",
+    "typedef",
+    "union",
+    "unsigned",
+    "void",
+    "volatile",
+    "while",
+    "bool",
+    "false",
+    "true",
+    // the functions (and macros) from the C library which translated programs
+    // use, or which are likely to clash with the names of functions
+    "EOF",
+    "INFINITY",
+    "INT64_MIN",
+    "NAN",
+    "NULL",
+    "abort",
+    "abs",
+    "atoi",
+    "ceil",
+    "cos",
+    "exit",
+    "exp",
+    "fabs",
+    "fflush",
+    "floor",
+    "fmax",
+    "fmin",
+    "fputc",
+    "fputs",
+    "free",
+    "getchar",
+    "isinf",
+    "isnan",
+    "labs",
+    "log",
+    "main",
+    "memcpy",
+    "printf",
+    "putchar",
+    "puts",
+    "rand",
+    "round",
+    "signbit",
+    "sin",
+    "snprintf",
+    "sprintf",
+    "sqrt",
+    "srand",
+    "stderr",
+    "stdin",
+    "stdout",
+    "strcpy",
+    "strlen",
+    "strtod",
+    "tan",
+    "time",
+    "vfprintf",
+    // the state used by the helpers
+    "fuel",
+    "random_state",
+];
+
+/// Translates the program into C. `seed` and `fuel` have the same meaning as
+/// [`crate::codegen::CodegenOptions::seed`] and
+/// [`crate::codegen::CodegenOptions::fuel`].
+pub fn to_c(program: &Program, seed: Option<u64>, fuel: Option<u64>) -> String {
+    let translated = used_functions(program);
+    let functions = translated
+        .iter()
+        .map(|function| (function.name.as_str(), global_name(&function.name)))
+        .collect::<FxHashMap<_, _>>();
+    // the names which locals cannot use (because they would hide a function)
+    let mut globals = functions.values().cloned().collect::<FxHashSet<_>>();
+    // `NAN` and `INFINITY` are defined in `math.h`
+    let mut uses_math = false;
+    for function in &translated {
+        for block in &function.blocks {
+            for statement in &block.statements {
+                if let Statement::Assign(_, Rvalue::Call(Callee::Native(symbol), _))
+                | Statement::Eval(Rvalue::Call(Callee::Native(symbol), _)) = statement
+                {
+                    globals.insert(symbol.clone());
+                }
+            }
+            uses_math |= block
+                .statements
+                .iter()
+                .flat_map(Statement::operands)
+                .chain(block.terminator.operand())
+                .any(|operand| {
+                    matches!(operand, Operand::Const(Constant::Real(real)) if !real.is_finite())
+                });
+        }
+    }
+
+    let mut c = C {
+        program,
+        functions,
+        globals,
+        externs: FxHashMap::default(),
+        helpers: FxHashSet::default(),
+        headers: BTreeSet::new(),
+        fuel: fuel.is_some(),
+    };
+    c.headers.insert("stdbool.h");
+    c.headers.insert("stdint.h");
+    c.headers.insert("stdlib.h");
+    if uses_math {
+        c.headers.insert("math.h");
+    }
+    if fuel.is_some() {
+        c.helpers.insert("use_fuel");
+    }
+
+    let definitions = translated
+        .iter()
+        .map(|function| c.function(function))
+        .collect::<Vec<_>>();
+
+    // the helpers which the program uses (and the ones which they use)
+    let mut helpers = c.helpers.clone();
+    for helper in HELPERS.iter().rev() {
+        if helpers.contains(helper.name) {
+            helpers.extend(helper.requires.iter().copied());
+        }
+    }
+    let helpers = HELPERS
+        .iter()
+        .filter(|helper| helpers.contains(helper.name))
+        .collect::<Vec<_>>();
+    c.headers
+        .extend(helpers.iter().flat_map(|helper| helper.headers.iter()));
+
+    let main = program.function("main").map(|main| {
+        let mut section = "int main(void) {\n".to_owned();
+        if helpers.iter().any(|helper| helper.name == "next_random") {
+            match seed {
+                Some(seed) => writeln!(section, "    random_state = UINT64_C({seed});").unwrap(),
+                None => {
+                    section.push_str("    random_state = (uint64_t)time(NULL);\n");
+                    c.headers.insert("time.h");
+                }
+            }
+        }
+        if let Some(fuel) = fuel {
+            let fuel = i64::try_from(fuel).unwrap_or(i64::MAX);
+            writeln!(section, "    fuel = INT64_C({fuel});").unwrap();
+        }
+        let name = &c.functions["main"];
+        match main.returns {
+            Type::Int | Type::Real | Type::Bool => {
+                writeln!(section, "    return (int){name}();").unwrap()
+            }
+            _ => writeln!(section, "    {name}();\n    return 0;").unwrap(),
+        }
+        section.push_str("}\n");
+        section
+    });
+
+    let mut sections = vec![c
+        .headers
+        .iter()
+        .map(|header| format!("#include <{header}>\n"))
+        .collect::<String>()];
+    for record in &program.records {
+        let mut section = format!("struct {} {{\n", global_name(&record.name));
+        for field in &record.fields {
+            let ty = c_type(field.ty, None);
+            writeln!(
+                section,
+                "    {};",
+                declaration(&ty, &global_name(&field.name))
+            )
+            .unwrap();
+        }
+        if record.fields.is_empty() {
+            // C does not allow empty structs
+            section.push_str("    char unused;\n");
+        }
+        section.push_str("};\n");
+        sections.push(section);
+    }
+    sections.extend(
+        helpers
+            .iter()
+            .filter(|helper| !helper.source.is_empty())
+            .map(|helper| helper.source.to_owned()),
+    );
+
+    let mut externs = c.externs.iter().collect::<Vec<_>>();
+    externs.sort_unstable_by_key(|(name, _)| **name);
+    if !externs.is_empty() {
+        sections.push(
+            externs
+                .iter()
+                .map(|(name, (params, returns))| {
+                    let params = if params.is_empty() {
+                        "void".to_owned()
+                    } else {
+                        params
+                            .iter()
+                            .map(|ty| extern_type(*ty))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    };
+                    format!(
+                        "extern {}({params});\n",
+                        declaration(&extern_type(*returns), name)
+                    )
+                })
+                .collect(),
+        );
+    }
+
+    if !translated.is_empty() {
+        sections.push(
+            translated
+                .iter()
+                .map(|function| format!("{};\n", c.signature(function)))
+                .collect(),
+        );
+    }
+    sections.extend(definitions);
+    sections.extend(main);
+
+    sections.join("\n")
+}
+
+/// Returns the functions which can be called from `main` (so that the prelude
+/// functions which the program does not use are left out), or every function
+/// if the program does not have a `main` function.
+fn used_functions(program: &Program) -> Vec<&Function> {
+    if program.function("main").is_none() {
+        return program.functions.iter().collect();
+    }
+    let mut used = FxHashSet::default();
+    let mut stack = vec!["main"];
+    while let Some(name) = stack.pop() {
+        if !used.insert(name) {
+            continue;
+        }
+        if let Some(function) = program.function(name) {
+            for statement in function.blocks.iter().flat_map(|block| &block.statements) {
+                if let Statement::Assign(_, Rvalue::Call(Callee::Function(callee), _))
+                | Statement::Eval(Rvalue::Call(Callee::Function(callee), _)) = statement
+                {
+                    stack.push(callee);
+                }
+            }
+        }
+    }
+    program
+        .functions
+        .iter()
+        .filter(|function| used.contains(function.name.as_str()))
+        .collect()
+}
+
+/// Whether `name` can be used as it is for a global name (a function or a
+/// record) in the translated program.
+fn is_reserved(name: &str) -> bool {
+    RESERVED.contains(&name) || HELPERS.iter().any(|helper| helper.name == name)
+}
+
+fn global_name(name: &str) -> String {
+    if is_reserved(name) {
+        format!("{name}_")
+    } else {
+        name.to_owned()
+    }
+}
+
+/// The C type used for values of the type (`record` is the name of the
+/// record, for [`Type::Record`]).
+fn c_type(ty: Type, record: Option<&str>) -> String {
+    match ty {
+        Type::Int => "int64_t".to_owned(),
+        Type::Real => "double".to_owned(),
+        Type::Bool => "bool".to_owned(),
+        Type::Str => "const char *".to_owned(),
+        Type::Pointer => "char *".to_owned(),
+        Type::Record => match record {
+            Some(record) => format!("struct {} *", global_name(record)),
+            None => "void *".to_owned(),
+        },
+    }
+}
+
+/// The C type used for values of the type which are passed to (or returned
+/// from) `extern` functions.
+fn extern_type(ty: Type) -> String {
+    match ty {
+        // like the compiled program, booleans are passed as 32-bit integers
+        Type::Bool => "int32_t".to_owned(),
+        ty => c_type(ty, None),
+    }
+}
+
+/// The C type used for values of the type which are stored in memory that
+/// is accessed through a pointer.
+fn memory_type(ty: Type) -> &'static str {
+    match ty {
+        Type::Int => "int64_t",
+        Type::Real => "double",
+        // like the compiled program, booleans are stored as 32-bit integers
+        Type::Bool => "int32_t",
+        Type::Str => "const char *",
+        Type::Pointer => "char *",
+        Type::Record => "void *",
+    }
+}
+
+/// Declares a variable (e.g. `int64_t x` or `char *p`).
+fn declaration(ty: &str, name: &str) -> String {
+    if ty.ends_with('*') {
+        format!("{ty}{name}")
+    } else {
+        format!("{ty} {name}")
+    }
+}
+
+fn string_literal(string: &str) -> String {
+    let mut literal = "\"".to_owned();
+    let mut previous = None;
+    for c in string.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            // `??` followed by some characters is a trigraph
+            '?' if previous == Some('?') => literal.push_str("\\?"),
+            c if c.is_ascii_control() => write!(literal, "\\{:03o}", c as u32).unwrap(),
+            c => literal.push(c),
+        }
+        previous = Some(c);
+    }
+    literal.push('"');
+    literal
+}
+
+struct C<'p> {
+    program: &'p Program,
+    /// The name of each of the program's functions in the C code.
+    functions: FxHashMap<&'p str, String>,
+    /// The names of every function which the program calls (which locals
+    /// cannot use).
+    globals: FxHashSet<String>,
+    /// The types of the parameters, and the return type, of every `extern`
+    /// function which the program calls.
+    externs: FxHashMap<&'p str, (Vec<Type>, Type)>,
+    /// The helpers (see [`HELPERS`]) which the program calls directly.
+    helpers: FxHashSet<&'static str>,
+    headers: BTreeSet<&'static str>,
+    /// Whether the program uses fuel.
+    fuel: bool,
+}
+
+impl<'p> C<'p> {
+    /// Returns the name of the record which the function returns (if it
+    /// returns one).
+    fn returned_record(&self, function: &'p Function) -> Option<&'p str> {
+        function
+            .blocks
+            .iter()
+            .find_map(|block| match &block.terminator {
+                Terminator::Return(Operand::Local(local)) => {
+                    function.locals[local.0 as usize].record.as_deref()
+                }
+                _ => None,
+            })
+    }
+
+    /// The declaration of the function (without the `;`).
+    fn signature(&self, function: &'p Function) -> String {
+        let names = self.local_names(function);
+        let params = if function.params.is_empty() {
+            "void".to_owned()
+        } else {
+            function
+                .params
+                .iter()
+                .map(|param| {
+                    let local = &function.locals[param.0 as usize];
+                    declaration(&local_type(local), &names[param.0 as usize])
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let returns = c_type(function.returns, self.returned_record(function));
+        let name = &self.functions[function.name.as_str()];
+        format!("static {}({params})", declaration(&returns, name))
+    }
+
+    /// The name of each local in the function (in the C code).
+    fn local_names(&self, function: &Function) -> Vec<String> {
+        let mut used = FxHashSet::default();
+        function
+            .locals
+            .iter()
+            .enumerate()
+            .map(|(i, local)| {
+                let mut name = match &local.name {
+                    Some(name) => {
+                        // names like `_1` are used for temporaries
+                        let is_temporary = name
+                            .strip_prefix('_')
+                            .map_or(false, |rest| rest.bytes().all(|b| b.is_ascii_digit()));
+                        if is_temporary || is_reserved(name) || self.globals.contains(name) {
+                            format!("{name}_")
+                        } else {
+                            name.clone()
+                        }
+                    }
+                    None => format!("_{i}"),
+                };
+                while !used.insert(name.clone()) {
+                    name.push('_');
+                }
+                name
+            })
+            .collect()
+    }
+
+    fn function(&mut self, function: &'p Function) -> String {
+        let names = self.local_names(function);
+        let mut output = format!("{} {{\n", self.signature(function));
+
+        let mut declared = false;
+        for (i, local) in function.locals.iter().enumerate() {
+            if !function.params.contains(&Local(i as u32)) {
+                writeln!(
+                    output,
+                    "    {};",
+                    declaration(&local_type(local), &names[i])
+                )
+                .unwrap();
+                declared = true;
+            }
+        }
+        if declared {
+            output.push('\n');
+        }
+
+        // blocks only need a label if they are jumped to from somewhere other
+        // than the block before them
+        let mut labelled = vec![false; function.blocks.len()];
+        // fuel is used on entry to the function, and at the start of every
+        // block which is the target of a backwards jump (in the same way as
+        // the compiled program)
+        let mut uses_fuel = vec![false; function.blocks.len()];
+        uses_fuel[0] = true;
+        for (i, block) in function.blocks.iter().enumerate() {
+            for target in block.terminator.successors() {
+                if target.0 as usize != i + 1 {
+                    labelled[target.0 as usize] = true;
+                }
+                if target.0 as usize <= i {
+                    uses_fuel[target.0 as usize] = true;
+                }
+            }
+        }
+
+        for (i, block) in function.blocks.iter().enumerate() {
+            if labelled[i] {
+                writeln!(output, "bb{i}:").unwrap();
+            }
+            if self.fuel && uses_fuel[i] {
+                output.push_str("    use_fuel();\n");
+            }
+            for statement in &block.statements {
+                let statement = match statement {
+                    Statement::Assign(local, rvalue) => {
+                        let name = &names[local.0 as usize];
+                        let decl = &function.locals[local.0 as usize];
+                        let value = self.rvalue(function, &names, rvalue, Some(decl));
+                        match rvalue {
+                            // the printing functions do not return anything,
+                            // but calls to them can still be assigned to
+                            // variables
+                            Rvalue::Call(Callee::Native(symbol), _)
+                                if implementing(symbol).map_or(false, |helper| !helper.returns) =>
+                            {
+                                format!("{value};\n    {name} = 0;")
+                            }
+                            _ => format!("{name} = {value};"),
+                        }
+                    }
+                    Statement::Eval(rvalue) => {
+                        format!("{};", self.rvalue(function, &names, rvalue, None))
+                    }
+                    Statement::Store {
+                        address,
+                        offset,
+                        value,
+                    } => {
+                        let ty = function.operand_ty(value);
+                        let place = self
+                            .field(function, &names, address, *offset)
+                            .unwrap_or_else(|| pointer_place(&names, address, *offset, ty));
+                        format!("{place} = {};", operand(&names, value))
+                    }
+                    Statement::Location(location) => format!("/* line {} */", location.line),
+                };
+                writeln!(output, "    {statement}").unwrap();
+            }
+
+            let next = i + 1;
+            match &block.terminator {
+                Terminator::Jump(target) if target.0 as usize == next => {}
+                Terminator::Jump(target) => writeln!(output, "    goto bb{};", target.0).unwrap(),
+                Terminator::Branch {
+                    condition,
+                    then,
+                    otherwise,
+                } => {
+                    let condition = operand(&names, condition);
+                    match (then.0 as usize == next, otherwise.0 as usize == next) {
+                        (true, true) => {}
+                        (true, false) => {
+                            writeln!(output, "    if (!{condition}) goto bb{};", otherwise.0)
+                                .unwrap()
+                        }
+                        (false, true) => {
+                            writeln!(output, "    if ({condition}) goto bb{};", then.0).unwrap()
+                        }
+                        (false, false) => writeln!(
+                            output,
+                            "    if ({condition}) goto bb{};\n    goto bb{};",
+                            then.0, otherwise.0
+                        )
+                        .unwrap(),
+                    }
+                }
+                Terminator::Return(value) => {
+                    writeln!(output, "    return {};", operand(&names, value)).unwrap()
+                }
+                Terminator::Unreachable => output.push_str("    abort();\n"),
+            }
+        }
+
+        output.push_str("}\n");
+        output
+    }
+
+    /// Translates a computation. `destination` is the local which the result
+    /// is assigned to (if there is one).
+    fn rvalue(
+        &mut self,
+        function: &'p Function,
+        names: &[String],
+        rvalue: &'p Rvalue,
+        destination: Option<&LocalDecl>,
+    ) -> String {
+        match rvalue {
+            Rvalue::Use(value) => operand(names, value),
+            Rvalue::Binary(op, left, right) => {
+                let left = operand(names, left);
+                let right = operand(names, right);
+                let op = match op {
+                    BinaryOp::Add | BinaryOp::Offset => "+",
+                    BinaryOp::Subtract => "-",
+                    BinaryOp::Multiply => "*",
+                    // integer division rounds towards zero (in C and in the
+                    // compiled program)
+                    BinaryOp::Divide => "/",
+                    BinaryOp::Equal => "==",
+                    BinaryOp::NotEqual => "!=",
+                    BinaryOp::LessThan => "<",
+                    BinaryOp::Concat => {
+                        self.helpers.insert("string_concat");
+                        return format!("string_concat({left}, {right})");
+                    }
+                };
+                format!("{left} {op} {right}")
+            }
+            Rvalue::Unary(UnaryOp::Negate, value) => {
+                let value = operand(names, value);
+                if value.starts_with('-') {
+                    format!("-({value})")
+                } else {
+                    format!("-{value}")
+                }
+            }
+            Rvalue::Call(callee, args) => {
+                let name = match callee {
+                    Callee::Function(name) => self.functions[name.as_str()].clone(),
+                    Callee::Native(symbol) => match implementing(symbol) {
+                        Some(helper) => {
+                            self.helpers.insert(helper.name);
+                            helper.name.to_owned()
+                        }
+                        None => {
+                            let params = args.iter().map(|arg| function.operand_ty(arg)).collect();
+                            // the return type is the type of the local which
+                            // the result is assigned to
+                            let returns = destination.map_or(Type::Int, |local| local.ty);
+                            self.externs.entry(symbol).or_insert((params, returns));
+                            symbol.clone()
+                        }
+                    },
+                };
+                let args = args
+                    .iter()
+                    .map(|arg| operand(names, arg))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                format!("{name}({args})")
+            }
+            Rvalue::Record { fields, .. } => {
+                let record = destination
+                    .and_then(|local| local.record.as_deref())
+                    .and_then(|record| self.program.record(record))
+                    .expect("records are always assigned to locals which point to them");
+                let fields = fields
+                    .iter()
+                    .map(|(offset, value)| {
+                        let field = record
+                            .fields
+                            .iter()
+                            .find(|field| field.offset == *offset)
+                            .expect("the record does not have a field at this offset");
+                        format!(".{} = {}", global_name(&field.name), operand(names, value))
+                    })
+                    .collect::<Vec<_>>();
+                let name = global_name(&record.name);
+                if fields.is_empty() {
+                    format!("&(struct {name}){{ 0 }}")
+                } else {
+                    format!("&(struct {name}){{ {} }}", fields.join(", "))
+                }
+            }
+            Rvalue::Load {
+                ty,
+                address,
+                offset,
+            } => match self.field(function, names, address, *offset) {
+                Some(field) => field,
+                None if *ty == Type::Bool => {
+                    format!("({} == 1)", pointer_place(names, address, *offset, *ty))
+                }
+                None => pointer_place(names, address, *offset, *ty),
+            },
+        }
+    }
+
+    /// The field at `address + offset`, if `address` points to a record.
+    fn field(
+        &self,
+        function: &Function,
+        names: &[String],
+        address: &Operand,
+        offset: i32,
+    ) -> Option<String> {
+        let local = address.as_local()?;
+        let record = self
+            .program
+            .record(function.locals[local.0 as usize].record.as_deref()?)?;
+        let field = record.fields.iter().find(|field| field.offset == offset)?;
+        Some(format!(
+            "{}->{}",
+            names[local.0 as usize],
+            global_name(&field.name)
+        ))
+    }
+}
+
+/// The value of type `ty` at `address + offset`, where `address` is a plain
+/// pointer.
+fn pointer_place(names: &[String], address: &Operand, offset: i32, ty: Type) -> String {
+    let pointer = match memory_type(ty) {
+        ty if ty.ends_with('*') => format!("{ty}*"),
+        ty => format!("{ty} *"),
+    };
+    let address = operand(names, address);
+    if offset == 0 {
+        format!("*({pointer}){address}")
+    } else {
+        format!("*({pointer})({address} + {offset})")
+    }
+}
+
+fn operand(names: &[String], operand: &Operand) -> String {
+    match operand {
+        Operand::Local(local) => names[local.0 as usize].clone(),
+        Operand::Const(Constant::Int(i64::MIN)) => "INT64_MIN".to_owned(),
+        Operand::Const(Constant::Int(int)) => int.to_string(),
+        Operand::Const(Constant::Real(real)) if real.is_nan() => "NAN".to_owned(),
+        Operand::Const(Constant::Real(real)) if real.is_infinite() => {
+            if real.is_sign_negative() {
+                "-INFINITY".to_owned()
+            } else {
+                "INFINITY".to_owned()
+            }
+        }
+        Operand::Const(Constant::Real(real)) => format!("{real:?}"),
+        Operand::Const(Constant::Bool(boolean)) => boolean.to_string(),
+        Operand::Const(Constant::Str(string)) => string_literal(string),
+    }
+}
+
+fn local_type(local: &LocalDecl) -> String {
+    c_type(local.ty, local.record.as_deref())
+}
@@ -0,0 +1,625 @@
+//! The runtime library for translated programs.
+//!
+//! These functions implement the builtins in the same way as the native
+//! runtime (see `crate::runtime`), so that a translated program produces the
+//! same output as the compiled one (real numbers are printed in the same
+//! format, and `random` uses the same generator). Only the helpers which a
+//! program uses (and the ones which they use in turn) are included in it.
+
+/// A C function which is defined in translated programs that need it.
+pub(super) struct Helper {
+    /// The name of the function in the C code.
+    pub name: &'static str,
+    /// The runtime function which this implements (if it implements one).
+    pub symbol: Option<&'static str>,
+    /// Whether the function returns a value (calls to the ones which do not
+    /// can still be assigned to variables, which are then set to zero).
+    pub returns: bool,
+    /// The headers which the function needs.
+    pub headers: &'static [&'static str],
+    /// The other helpers which the function calls (these always come before
+    /// it in [`HELPERS`]).
+    pub requires: &'static [&'static str],
+    /// The definition of the function (this is empty for functions which the
+    /// C library provides).
+    pub source: &'static str,
+}
+
+pub(super) static HELPERS: &[Helper] = &[
+    Helper {
+        name: "malloc",
+        symbol: Some("malloc"),
+        returns: true,
+        headers: &["stdlib.h"],
+        requires: &[],
+        source: "",
+    },
+    Helper {
+        name: "realloc",
+        symbol: Some("realloc"),
+        returns: true,
+        headers: &["stdlib.h"],
+        requires: &[],
+        source: "",
+    },
+    Helper {
+        name: "free_pointer",
+        symbol: Some("free"),
+        returns: true,
+        headers: &["stdlib.h"],
+        requires: &[],
+        source: "\
+/* Frees the memory which the pointer points to (the result is a null pointer,
+   so that this can be used as an expression). */
+static char *free_pointer(char *pointer) {
+    free(pointer);
+    return NULL;
+}
+",
+    },
+    Helper {
+        name: "fail",
+        symbol: None,
+        returns: false,
+        headers: &["stdarg.h", "stdio.h", "stdlib.h"],
+        requires: &[],
+        source: "\
+/* Reports an error (formatted in the same way as `printf`) and stops the
+   program. */
+static void fail(const char *format, ...) {
+    va_list args;
+    fflush(stdout);
+    fputs(\"error: \", stderr);
+    va_start(args, format);
+    vfprintf(stderr, format, args);
+    va_end(args);
+    fputc('\\n', stderr);
+    exit(1);
+}
+",
+    },
+    Helper {
+        name: "use_fuel",
+        symbol: None,
+        returns: false,
+        headers: &["stdint.h"],
+        requires: &["fail"],
+        source: "\
+/* The amount of fuel which the program has left (one unit is used every time
+   a function is called, and on every iteration of a loop). */
+static int64_t fuel;
+
+static void use_fuel(void) {
+    fuel--;
+    if (fuel < 0) {
+        fail(\"Your program ran for too long, so it was stopped. This is usually caused by a loop which never finishes (if your program is meant to take this long, you can give it more time using `--fuel`).\");
+    }
+}
+",
+    },
+    Helper {
+        name: "format_real",
+        symbol: None,
+        returns: false,
+        headers: &["math.h", "stdio.h", "stdlib.h", "string.h"],
+        requires: &[],
+        source: "\
+/* Writes a real number to `buffer` (which must have space for at least 32
+   bytes) in the same format as the compiler, i.e. using the fewest digits
+   which still read back as the same number, and always including either a
+   decimal point or an exponent (for example `1.0`, `0.1` or `2.5e-7`). */
+static void format_real(char *buffer, double real) {
+    char scientific[32];
+    char digits[20];
+    int length = 0;
+    int exponent;
+    const char *c;
+
+    if (isnan(real)) {
+        strcpy(buffer, \"NaN\");
+        return;
+    }
+    if (real < 0 || (real == 0 && signbit(real))) {
+        *buffer++ = '-';
+        real = -real;
+    }
+    if (isinf(real)) {
+        strcpy(buffer, \"inf\");
+        return;
+    }
+    if (real == 0) {
+        strcpy(buffer, \"0.0\");
+        return;
+    }
+
+    /* find the shortest representation of the number (as `d.ddde+x`) */
+    for (int precision = 0; precision < 17; precision++) {
+        snprintf(scientific, sizeof scientific, \"%.*e\", precision, real);
+        if (strtod(scientific, NULL) == real) {
+            break;
+        }
+    }
+    for (c = scientific; *c != 'e'; c++) {
+        if (*c != '.') {
+            digits[length++] = *c;
+        }
+    }
+    exponent = atoi(c + 1);
+    while (length > 1 && digits[length - 1] == '0') {
+        length--;
+    }
+
+    if (exponent < -4 || exponent >= 16) {
+        *buffer++ = digits[0];
+        if (length > 1) {
+            *buffer++ = '.';
+            memcpy(buffer, digits + 1, length - 1);
+            buffer += length - 1;
+        }
+        sprintf(buffer, \"e%d\", exponent);
+    } else if (exponent < 0) {
+        buffer += sprintf(buffer, \"0.%.*s\", -exponent - 1, \"000\");
+        memcpy(buffer, digits, length);
+        buffer[length] = '\\0';
+    } else {
+        for (int i = 0; i <= exponent; i++) {
+            *buffer++ = i < length ? digits[i] : '0';
+        }
+        *buffer++ = '.';
+        if (length > exponent + 1) {
+            memcpy(buffer, digits + exponent + 1, length - exponent - 1);
+            buffer += length - exponent - 1;
+        } else {
+            *buffer++ = '0';
+        }
+        *buffer = '\\0';
+    }
+}
+",
+    },
+    Helper {
+        name: "print_int",
+        symbol: Some("print_int"),
+        returns: false,
+        headers: &["inttypes.h", "stdint.h", "stdio.h"],
+        requires: &[],
+        source: "\
+static void print_int(int64_t integer) {
+    printf(\"%\" PRId64 \"\\n\", integer);
+}
+",
+    },
+    Helper {
+        name: "print_bool",
+        symbol: Some("print_bool"),
+        returns: false,
+        headers: &["stdbool.h", "stdio.h"],
+        requires: &[],
+        source: "\
+static void print_bool(bool boolean) {
+    puts(boolean ? \"True\" : \"False\");
+}
+",
+    },
+    Helper {
+        name: "print_real",
+        symbol: Some("print_real"),
+        returns: false,
+        headers: &["stdio.h"],
+        requires: &["format_real"],
+        source: "\
+static void print_real(double real) {
+    char buffer[32];
+    format_real(buffer, real);
+    puts(buffer);
+}
+",
+    },
+    Helper {
+        name: "write_string",
+        symbol: Some("write_string"),
+        returns: false,
+        headers: &["stdio.h"],
+        requires: &[],
+        source: "\
+static void write_string(const char *string) {
+    fputs(string, stdout);
+}
+",
+    },
+    Helper {
+        name: "write_int",
+        symbol: Some("write_int"),
+        returns: false,
+        headers: &["inttypes.h", "stdint.h", "stdio.h"],
+        requires: &[],
+        source: "\
+static void write_int(int64_t integer) {
+    printf(\"%\" PRId64, integer);
+}
+",
+    },
+    Helper {
+        name: "write_bool",
+        symbol: Some("write_bool"),
+        returns: false,
+        headers: &["stdbool.h", "stdio.h"],
+        requires: &[],
+        source: "\
+static void write_bool(bool boolean) {
+    fputs(boolean ? \"True\" : \"False\", stdout);
+}
+",
+    },
+    Helper {
+        name: "write_real",
+        symbol: Some("write_real"),
+        returns: false,
+        headers: &["stdio.h"],
+        requires: &["format_real"],
+        source: "\
+static void write_real(double real) {
+    char buffer[32];
+    format_real(buffer, real);
+    fputs(buffer, stdout);
+}
+",
+    },
+    Helper {
+        name: "write_space",
+        symbol: Some("write_space"),
+        returns: false,
+        headers: &["stdio.h"],
+        requires: &[],
+        source: "\
+static void write_space(void) {
+    putchar(' ');
+}
+",
+    },
+    Helper {
+        name: "write_newline",
+        symbol: Some("write_newline"),
+        returns: false,
+        headers: &["stdio.h"],
+        requires: &[],
+        source: "\
+static void write_newline(void) {
+    putchar('\\n');
+}
+",
+    },
+    Helper {
+        name: "str_int",
+        symbol: Some("str_int"),
+        returns: true,
+        headers: &["inttypes.h", "stdint.h", "stdio.h", "stdlib.h"],
+        requires: &[],
+        source: "\
+static const char *str_int(int64_t integer) {
+    char *string = malloc(21);
+    sprintf(string, \"%\" PRId64, integer);
+    return string;
+}
+",
+    },
+    Helper {
+        name: "str_bool",
+        symbol: Some("str_bool"),
+        returns: true,
+        headers: &["stdbool.h"],
+        requires: &[],
+        source: "\
+static const char *str_bool(bool boolean) {
+    return boolean ? \"True\" : \"False\";
+}
+",
+    },
+    Helper {
+        name: "str_real",
+        symbol: Some("str_real"),
+        returns: true,
+        headers: &["stdlib.h"],
+        requires: &["format_real"],
+        source: "\
+static const char *str_real(double real) {
+    char *string = malloc(32);
+    format_real(string, real);
+    return string;
+}
+",
+    },
+    Helper {
+        name: "str_string",
+        symbol: Some("str_string"),
+        returns: true,
+        headers: &[],
+        requires: &[],
+        source: "\
+static const char *str_string(const char *string) {
+    return string;
+}
+",
+    },
+    Helper {
+        name: "string_concat",
+        symbol: Some("string_concat"),
+        returns: true,
+        headers: &["stdlib.h", "string.h"],
+        requires: &[],
+        source: "\
+static const char *string_concat(const char *left, const char *right) {
+    size_t left_length = strlen(left);
+    size_t right_length = strlen(right);
+    char *string = malloc(left_length + right_length + 1);
+    memcpy(string, left, left_length);
+    memcpy(string + left_length, right, right_length + 1);
+    return string;
+}
+",
+    },
+    Helper {
+        name: "input",
+        symbol: Some("input"),
+        returns: true,
+        headers: &["stdio.h", "stdlib.h"],
+        requires: &[],
+        source: "\
+/* Reads a line of input (without the line ending). */
+static const char *input(void) {
+    size_t length = 0;
+    size_t capacity = 16;
+    char *line = malloc(capacity);
+    int c;
+
+    fflush(stdout);
+    while ((c = getchar()) != EOF && c != '\\n') {
+        if (length + 1 == capacity) {
+            capacity *= 2;
+            line = realloc(line, capacity);
+        }
+        line[length++] = (char)c;
+    }
+    while (length > 0 && line[length - 1] == '\\r') {
+        length--;
+    }
+    line[length] = '\\0';
+    return line;
+}
+",
+    },
+    Helper {
+        name: "next_random",
+        symbol: None,
+        returns: true,
+        headers: &["stdint.h"],
+        requires: &[],
+        source: "\
+/* The state of the random number generator. This uses SplitMix64, like the
+   compiler, so that a program produces the same numbers from the same seed. */
+static uint64_t random_state;
+
+static uint64_t next_random(void) {
+    uint64_t z;
+    random_state += UINT64_C(0x9e3779b97f4a7c15);
+    z = random_state;
+    z = (z ^ (z >> 30)) * UINT64_C(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)) * UINT64_C(0x94d049bb133111eb);
+    return z ^ (z >> 31);
+}
+",
+    },
+    Helper {
+        name: "random_int",
+        symbol: Some("random_int"),
+        returns: true,
+        headers: &["inttypes.h", "stdint.h"],
+        requires: &["fail", "next_random"],
+        source: "\
+/* Returns a random integer between `lower` and `upper` (inclusive of both). */
+static int64_t random_int(int64_t lower, int64_t upper) {
+    uint64_t range;
+    if (upper < lower) {
+        fail(\"the lower bound (%\" PRId64 \") passed to `random` is greater than the upper \"
+             \"bound (%\" PRId64 \")\", lower, upper);
+    }
+    range = (uint64_t)upper - (uint64_t)lower;
+    if (range == UINT64_MAX) {
+        return (int64_t)next_random();
+    }
+    return (int64_t)((uint64_t)lower + next_random() % (range + 1));
+}
+",
+    },
+    Helper {
+        name: "random_real",
+        symbol: Some("random_real"),
+        returns: true,
+        headers: &["stdint.h"],
+        requires: &["fail", "next_random"],
+        source: "\
+/* Returns a random real number which is at least `lower`, but less than
+   `upper`. */
+static double random_real(double lower, double upper) {
+    double unit;
+    if (upper < lower) {
+        fail(\"the lower bound (%g) passed to `random` is greater than the upper bound (%g)\",
+             lower, upper);
+    }
+    /* use the top 53 bits (the size of the mantissa) to produce a number in
+       the range [0, 1) */
+    unit = (double)(next_random() >> 11) / (double)(UINT64_C(1) << 53);
+    return lower + unit * (upper - lower);
+}
+",
+    },
+    Helper {
+        name: "abs_int",
+        symbol: Some("abs_int"),
+        returns: true,
+        headers: &["stdint.h"],
+        requires: &[],
+        source: "\
+static int64_t abs_int(int64_t integer) {
+    return integer < 0 ? (int64_t)(0 - (uint64_t)integer) : integer;
+}
+",
+    },
+    Helper {
+        name: "abs_real",
+        symbol: Some("abs_real"),
+        returns: true,
+        headers: &["math.h"],
+        requires: &[],
+        source: "\
+static double abs_real(double real) {
+    return fabs(real);
+}
+",
+    },
+    Helper {
+        name: "sqrt_real",
+        symbol: Some("sqrt_real"),
+        returns: true,
+        headers: &["math.h"],
+        requires: &[],
+        source: "\
+static double sqrt_real(double real) {
+    return sqrt(real);
+}
+",
+    },
+    Helper {
+        name: "round_real",
+        symbol: Some("round_real"),
+        returns: true,
+        headers: &["math.h", "stdint.h"],
+        requires: &[],
+        source: "\
+/* Rounds to the nearest integer (numbers which are exactly half-way between
+   two integers are rounded away from zero). */
+static int64_t round_real(double real) {
+    return (int64_t)round(real);
+}
+",
+    },
+    Helper {
+        name: "floor_real",
+        symbol: Some("floor_real"),
+        returns: true,
+        headers: &["math.h", "stdint.h"],
+        requires: &[],
+        source: "\
+static int64_t floor_real(double real) {
+    return (int64_t)floor(real);
+}
+",
+    },
+    Helper {
+        name: "ceil_real",
+        symbol: Some("ceil_real"),
+        returns: true,
+        headers: &["math.h", "stdint.h"],
+        requires: &[],
+        source: "\
+static int64_t ceil_real(double real) {
+    return (int64_t)ceil(real);
+}
+",
+    },
+    Helper {
+        name: "min_int",
+        symbol: Some("min_int"),
+        returns: true,
+        headers: &["stdint.h"],
+        requires: &[],
+        source: "\
+static int64_t min_int(int64_t a, int64_t b) {
+    return a < b ? a : b;
+}
+",
+    },
+    Helper {
+        name: "min_real",
+        symbol: Some("min_real"),
+        returns: true,
+        headers: &["math.h"],
+        requires: &[],
+        source: "\
+static double min_real(double a, double b) {
+    return fmin(a, b);
+}
+",
+    },
+    Helper {
+        name: "max_int",
+        symbol: Some("max_int"),
+        returns: true,
+        headers: &["stdint.h"],
+        requires: &[],
+        source: "\
+static int64_t max_int(int64_t a, int64_t b) {
+    return a > b ? a : b;
+}
+",
+    },
+    Helper {
+        name: "max_real",
+        symbol: Some("max_real"),
+        returns: true,
+        headers: &["math.h"],
+        requires: &[],
+        source: "\
+static double max_real(double a, double b) {
+    return fmax(a, b);
+}
+",
+    },
+    Helper {
+        name: "pow_int",
+        symbol: Some("pow_int"),
+        returns: true,
+        headers: &["inttypes.h", "stdint.h"],
+        requires: &["fail"],
+        source: "\
+static int64_t pow_int(int64_t base, int64_t exponent) {
+    uint64_t result = 1;
+    uint64_t factor = (uint64_t)base;
+    if (exponent < 0) {
+        fail(\"cannot raise an integer to a negative power (%\" PRId64 \"); use a `Real` instead\",
+             exponent);
+    }
+    if (exponent > UINT32_MAX) {
+        fail(\"the exponent (%\" PRId64 \") passed to `pow` is too large\", exponent);
+    }
+    /* multiply by the base raised to each power of two in the exponent
+       (wrapping around if the result is too big, like the compiler does) */
+    for (; exponent > 0; exponent >>= 1) {
+        if (exponent & 1) {
+            result *= factor;
+        }
+        factor *= factor;
+    }
+    return (int64_t)result;
+}
+",
+    },
+    Helper {
+        name: "pow_real",
+        symbol: Some("pow_real"),
+        returns: true,
+        headers: &["math.h"],
+        requires: &[],
+        source: "\
+static double pow_real(double base, double exponent) {
+    return pow(base, exponent);
+}
+",
+    },
+];
+
+/// Finds the helper which implements the runtime function with the given
+/// name.
+pub(super) fn implementing(symbol: &str) -> Option<&'static Helper> {
+    HELPERS.iter().find(|helper| helper.symbol == Some(symbol))
+}
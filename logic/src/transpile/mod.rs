@@ -0,0 +1,15 @@
+//! Translates programs into other programming languages (so that students can
+//! see how the code they have written would look in a "real" language).
+//!
+//! The translation into Python works from the parse table (rather than the
+//! MIR), so that the structure of the original program (and the names and
+//! comments in it) can be kept. The translation into C works from the MIR
+//! instead, so that it behaves in exactly the same way as the compiled
+//! program (which means that the two can be compared when testing the
+//! compiler).
+
+pub mod c;
+pub mod python;
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,763 @@
+//! Translates programs into Python 3.
+//!
+//! The aim is to produce the code which a teacher would write by hand (rather
+//! than to exactly emulate the compiler), so
+//!
+//! - records become dataclasses, `for` loops use `range` and the printing
+//!   functions (`print_int`, `print_bool`, etc) all become `print`
+//! - arrays (from the prelude) become lists
+//! - `random` uses Python's `random` module (so a program will not produce
+//!   the same numbers as it does when it is compiled, even with a seed)
+//! - integer division uses `//` (which rounds down, rather than towards zero)
+//!   and `round` rounds numbers which are half-way between two integers to
+//!   the nearest even number
+//!
+//! Comments and names are kept, except for names which are Python keywords
+//! (or which the translated program needs for something else), which have an
+//! underscore added to the end of them.
+
+use std::{collections::BTreeSet, fmt::Display, iter};
+
+use rustc_hash::FxHashSet;
+
+use crate::{
+    builtin,
+    parse::{
+        block::{Block, BlockRef},
+        cst::Comment,
+        expr::{BinOp, Expr, ExprRef, UnOp},
+        func::Func,
+        ident::IdentRef,
+        lit::Literal,
+        r#extern::Extern,
+        r#for::{Between, ForLoop},
+        r#if::If,
+        record::Record,
+        table::{Id, Item, ItemRef, ParseTable},
+    },
+    ty::{PrimitiveType, Ty, TyEnv},
+};
+
+/// Names which cannot be used for variables (or functions, or records) in the
+/// translated program.
+static RESERVED: &[&str] = &[
+    // keywords
+    "None",
+    "and",
+    "as",
+    "assert",
+    "async",
+    "await",
+    "break",
+    "class",
+    "continue",
+    "def",
+    "del",
+    "elif",
+    "else",
+    "except",
+    "finally",
+    "for",
+    "from",
+    "global",
+    "if",
+    "import",
+    "in",
+    "is",
+    "lambda",
+    "nonlocal",
+    "not",
+    "or",
+    "pass",
+    "raise",
+    "return",
+    "try",
+    "while",
+    "with",
+    "yield",
+    // the modules and functions which translated programs use
+    "abs",
+    "ctypes",
+    "dataclass",
+    "input",
+    "math",
+    "max",
+    "min",
+    "print",
+    "random",
+    "range",
+    "round",
+    "str",
+    "sys",
+];
+
+/// Functions which are defined in the translated program when a prelude
+/// function is used in a way which cannot be translated into a single
+/// expression.
+static HELPERS: &[(&str, &str)] = &[
+    (
+        "array_set",
+        "def array_set(array, index, value):\n    array[index] = value\n    return value\n",
+    ),
+    (
+        "array_free",
+        "def array_free(array):\n    array.clear()\n    return array\n",
+    ),
+];
+
+// How tightly each kind of Python expression binds (operands which bind less
+// tightly than their operator have to be bracketed).
+const COMPARISON: u8 = 1;
+const SUM: u8 = 2;
+const PRODUCT: u8 = 3;
+const UNARY: u8 = 4;
+const POWER: u8 = 5;
+const POSTFIX: u8 = 6;
+const ATOM: u8 = 7;
+
+/// Translates the (type checked) program into Python. `source` is the source
+/// code of the program (the comments are taken from the syntax tree in the
+/// parse table).
+pub fn to_python(table: &ParseTable, env: &TyEnv, source: &str) -> String {
+    let mut python = Python {
+        table,
+        env,
+        source,
+        output: String::new(),
+        comments: table.cst.comments(),
+        next_comment: 0,
+        starts: vec![],
+        position: 0,
+        imports: BTreeSet::new(),
+        dataclass: false,
+        helpers: BTreeSet::new(),
+        records: FxHashSet::default(),
+    };
+    python.find_starts(&table.root.1);
+    python.starts.sort_unstable();
+
+    for (i, item) in table.root.1.inner.iter().enumerate() {
+        if i > 0 {
+            python.output.push_str("\n\n");
+        }
+        python.statement(item, 0);
+    }
+    python.comments_before(usize::MAX, 0);
+
+    let has_main = table.root.1.inner.iter().any(|item| {
+        matches!(table.get(item), Some(Item::Func(func)) if table.get_ident(func.name).inner() == "main")
+    });
+    if has_main {
+        python.imports.insert("sys");
+        python
+            .output
+            .push_str("\n\nif __name__ == \"__main__\":\n    sys.exit(main())\n");
+    }
+
+    let mut imports = python
+        .imports
+        .iter()
+        .map(|module| format!("import {module}\n"))
+        .collect::<String>();
+    if python.dataclass {
+        imports.push_str("from dataclasses import dataclass\n");
+    }
+    let helpers = HELPERS
+        .iter()
+        .filter(|(name, _)| python.helpers.contains(name))
+        .map(|(_, helper)| helper.to_string());
+    iter::once(imports)
+        .chain(helpers)
+        .chain(iter::once(python.output))
+        .filter(|section| !section.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+struct Python<'t, 'i, 's> {
+    table: &'t ParseTable<'i>,
+    env: &'t TyEnv,
+    source: &'s str,
+    output: String,
+    comments: Vec<Comment<'i>>,
+    /// The first comment which has not been written yet.
+    next_comment: usize,
+    /// Where each statement (and each condition of an `if` or `while`
+    /// statement) in the program starts, in order.
+    starts: Vec<usize>,
+    /// The start of the statement (or condition) which was written last.
+    position: usize,
+    /// The modules which the program imports.
+    imports: BTreeSet<&'static str>,
+    dataclass: bool,
+    /// The helper functions (see [`HELPERS`]) which the program uses.
+    helpers: BTreeSet<&'static str>,
+    /// The records which have been defined so far.
+    records: FxHashSet<String>,
+}
+
+impl Python<'_, '_, '_> {
+    fn find_starts(&mut self, block: &Block) {
+        let table = self.table;
+        let mut add = |id: Id| {
+            if let Some(start) = table.starts.get(&id) {
+                self.starts.push(start.index);
+            }
+        };
+        for item in &block.inner {
+            add(item.id);
+            match table.get(item) {
+                Some(Item::If(stmt)) => {
+                    for branch in iter::once(&stmt.r#if).chain(&stmt.else_ifs) {
+                        add(branch.condition.id);
+                    }
+                }
+                Some(Item::While(stmt)) => add(stmt.condition.id),
+                _ => {}
+            }
+        }
+
+        for item in &block.inner {
+            match table.get(item) {
+                Some(Item::If(stmt)) => {
+                    for branch in iter::once(&stmt.r#if).chain(&stmt.else_ifs) {
+                        self.find_starts(table.get_block(&branch.block));
+                    }
+                    if let Some(block) = &stmt.r#else {
+                        self.find_starts(table.get_block(block));
+                    }
+                }
+                Some(Item::While(stmt)) => self.find_starts(table.get_block(&stmt.block)),
+                Some(Item::For(stmt)) => self.find_starts(table.get_block(&stmt.block)),
+                Some(Item::Func(func)) => self.find_starts(table.get_block(&func.block)),
+                Some(Item::Block(block)) => self.find_starts(block),
+                _ => {}
+            }
+        }
+    }
+
+    fn line(&mut self, indent: usize, text: impl Display) {
+        for _ in 0..indent {
+            self.output.push_str("    ");
+        }
+        self.output.push_str(&text.to_string());
+        self.output.push('\n');
+    }
+
+    /// Writes the comments which come before `index` in the source code.
+    fn comments_before(&mut self, index: usize, indent: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.index >= index {
+                break;
+            }
+            let text = comment.text;
+            self.line(indent, format_args!("#{text}"));
+            self.next_comment += 1;
+        }
+    }
+
+    /// Moves to the start of the statement (or condition) with the given id,
+    /// writing the comments which come before it.
+    fn start(&mut self, id: Id, indent: usize) {
+        if let Some(start) = self.table.starts.get(&id) {
+            self.comments_before(start.index, indent);
+            self.position = start.index;
+        }
+    }
+
+    /// The column which the statement with the given id starts in.
+    fn column(&self, id: Id) -> usize {
+        self.table.starts.get(&id).map_or(0, |start| {
+            let line = self.source[..start.index].rfind('\n').map_or(0, |i| i + 1);
+            start.index - line
+        })
+    }
+
+    /// Writes the body of a statement which starts in `column`.
+    fn block(&mut self, block: &BlockRef, indent: usize, column: usize) {
+        let block = self.table.get_block(block);
+        for item in &block.inner {
+            self.statement(item, indent);
+        }
+
+        // the comments at the end of the block are those which come before the
+        // next statement, and are indented more than the statement which
+        // contains the block
+        let next = self
+            .starts
+            .iter()
+            .copied()
+            .find(|start| *start > self.position)
+            .unwrap_or(usize::MAX);
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.index >= next || comment.column <= column {
+                break;
+            }
+            let text = comment.text;
+            self.line(indent, format_args!("#{text}"));
+            self.next_comment += 1;
+        }
+
+        if block.inner.is_empty() {
+            self.line(indent, "pass");
+        }
+    }
+
+    fn statement(&mut self, item: &ItemRef, indent: usize) {
+        let table = self.table;
+        self.start(item.id, indent);
+        let column = self.column(item.id);
+        match table.get(item) {
+            Some(Item::Expr(_)) => self.expr_statement(ExprRef { id: item.id }, indent),
+            Some(Item::Return(ret)) => {
+                let value = self.expr(ret.expr, 0);
+                self.line(indent, format_args!("return {value}"));
+            }
+            Some(Item::If(stmt)) => self.r#if(stmt, indent, column),
+            Some(Item::While(stmt)) => {
+                let condition = self.expr(stmt.condition, 0);
+                self.line(indent, format_args!("while {condition}:"));
+                self.block(&stmt.block, indent + 1, column);
+            }
+            Some(Item::For(stmt)) => self.r#for(stmt, indent, column),
+            Some(Item::Func(func)) => self.func(func, indent, column),
+            Some(Item::Record(record)) => self.record(record, indent),
+            Some(Item::Extern(ext)) => self.r#extern(ext, indent),
+            Some(Item::Block(block)) => {
+                for item in &block.inner {
+                    self.statement(item, indent);
+                }
+            }
+            Some(Item::Ident(_)) | None => unreachable!(),
+        }
+    }
+
+    fn expr_statement(&mut self, expr: ExprRef, indent: usize) {
+        let table = self.table;
+        let text = match table.get_expr(&expr) {
+            Expr::BinOp(op, target, value) if op.token == BinOp::SetEquals => {
+                format!("{} = {}", self.expr(*target, 0), self.expr(*value, 0))
+            }
+            Expr::FunctionCall(name, args) => match (table.get_ident(*name).inner(), &args[..]) {
+                ("array_set", [array, index, value]) => format!(
+                    "{}[{}] = {}",
+                    self.expr(*array, POSTFIX),
+                    self.expr(*index, 0),
+                    self.expr(*value, 0)
+                ),
+                ("array_free", [array]) if table.get_expr(array).is_ident() => {
+                    format!("del {}", self.expr(*array, 0))
+                }
+                _ => self.expr(expr, 0),
+            },
+            _ => self.expr(expr, 0),
+        };
+        self.line(indent, text);
+    }
+
+    fn r#if(&mut self, stmt: &If, indent: usize, column: usize) {
+        let condition = self.expr(stmt.r#if.condition, 0);
+        self.line(indent, format_args!("if {condition}:"));
+        self.block(&stmt.r#if.block, indent + 1, column);
+        for branch in &stmt.else_ifs {
+            self.start(branch.condition.id, indent);
+            let condition = self.expr(branch.condition, 0);
+            self.line(indent, format_args!("elif {condition}:"));
+            self.block(&branch.block, indent + 1, column);
+        }
+        if let Some(block) = &stmt.r#else {
+            self.line(indent, "else:");
+            self.block(block, indent + 1, column);
+        }
+    }
+
+    fn r#for(&mut self, stmt: &ForLoop, indent: usize, column: usize) {
+        let var = self.name(stmt.var);
+        let range = self.range(&stmt.between);
+        self.line(indent, format_args!("for {var} in {range}:"));
+        self.block(&stmt.block, indent + 1, column);
+    }
+
+    /// Translates the bounds of a `for` loop into a call to `range` (which
+    /// does not include the upper bound).
+    fn range(&mut self, between: &Between) -> String {
+        let start = self.expr(between.start, 0);
+        let step = between.step.map(|step| (step, self.int_literal(step)));
+        let stop = match step {
+            Some((_, Some(step))) if step < 0 => self.offset(between.stop, -1),
+            Some((step, None)) => {
+                let step = self.expr(step, SUM);
+                let stop = self.expr(between.stop, SUM);
+                format!("{stop} + (1 if {step} > 0 else -1)")
+            }
+            _ => self.offset(between.stop, 1),
+        };
+        match step {
+            Some((step, _)) => format!("range({start}, {stop}, {})", self.expr(step, 0)),
+            None if start == "0" => format!("range({stop})"),
+            None => format!("range({start}, {stop})"),
+        }
+    }
+
+    /// Adds `offset` to the expression.
+    fn offset(&mut self, expr: ExprRef, offset: i64) -> String {
+        match self.int_literal(expr) {
+            Some(value) => (value + offset).to_string(),
+            None if offset < 0 => format!("{} - {}", self.expr(expr, SUM), -offset),
+            None => format!("{} + {offset}", self.expr(expr, SUM)),
+        }
+    }
+
+    /// Returns the value of the expression if it is an integer literal (or a
+    /// negated integer literal).
+    fn int_literal(&self, expr: ExprRef) -> Option<i64> {
+        match self.table.get_expr(&expr) {
+            Expr::Literal(literal) => match &literal.token {
+                Literal::Number(number) if !number.is_real() => Some(number.as_int()),
+                _ => None,
+            },
+            Expr::UnOp(op, operand) if op.token == UnOp::Negative => {
+                self.int_literal(*operand).map(|value| -value)
+            }
+            _ => None,
+        }
+    }
+
+    fn func(&mut self, func: &Func, indent: usize, column: usize) {
+        let params = func
+            .parameters
+            .iter()
+            .map(|param| {
+                let name = self.name(*param);
+                match self.ty(param.id) {
+                    Some(ty) => format!("{name}: {ty}"),
+                    None => name,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let returns = self
+            .ty(func.name.id)
+            .map(|ty| format!(" -> {ty}"))
+            .unwrap_or_default();
+        let name = self.name(func.name);
+        self.line(indent, format_args!("def {name}({params}){returns}:"));
+        self.block(&func.block, indent + 1, column);
+    }
+
+    fn record(&mut self, record: &Record, indent: usize) {
+        self.dataclass = true;
+        let name = self.name(record.name);
+        self.line(indent, "@dataclass");
+        self.line(indent, format_args!("class {name}:"));
+        for field in &record.fields {
+            let field_name = self.name(field.name);
+            self.line(
+                indent + 1,
+                format_args!("{field_name}: {}", primitive(field.ty.token)),
+            );
+        }
+
+        // records are printed in the same way as they are written (e.g.
+        // `Point { x: 1, y: 2 }`)
+        let fields = record
+            .fields
+            .iter()
+            .map(|field| {
+                let field_name = self.name(field.name);
+                let written = self.table.get_ident(field.name).inner();
+                format!("{written}: {{self.{field_name}}}")
+            })
+            .collect::<Vec<_>>();
+        let shown = if fields.is_empty() {
+            "{{}}".to_owned()
+        } else {
+            format!("{{{{ {} }}}}", fields.join(", "))
+        };
+        if !record.fields.is_empty() {
+            self.line(0, "");
+        }
+        self.line(indent + 1, "def __str__(self) -> str:");
+        self.line(
+            indent + 2,
+            format_args!(
+                "return f\"{} {shown}\"",
+                self.table.get_ident(record.name).inner()
+            ),
+        );
+        self.records.insert(name);
+    }
+
+    /// Translates the declaration into a function loaded using `ctypes`.
+    fn r#extern(&mut self, ext: &Extern, indent: usize) {
+        self.imports.insert("ctypes");
+        let symbol = self.table.get_ident(ext.name).inner();
+        let name = self.name(ext.name);
+        let library = ext
+            .library
+            .as_ref()
+            .map_or("None".to_owned(), |library| quote(library.token));
+        let params = ext
+            .parameters
+            .iter()
+            .map(|(_, ty)| c_type(ty.token))
+            .collect::<Vec<_>>()
+            .join(", ");
+        self.line(
+            indent,
+            format_args!("{name} = ctypes.CDLL({library}).{symbol}"),
+        );
+        self.line(indent, format_args!("{name}.argtypes = [{params}]"));
+        self.line(
+            indent,
+            format_args!("{name}.restype = {}", c_type(ext.returns.token)),
+        );
+    }
+
+    /// The name of the variable (or function, or record) in Python.
+    fn name(&self, ident: IdentRef) -> String {
+        let name = self.table.get_ident(ident).inner();
+        if RESERVED.contains(&name) {
+            format!("{name}_")
+        } else {
+            name.to_owned()
+        }
+    }
+
+    /// The type annotation for the item with the given id.
+    fn ty(&self, id: Id) -> Option<String> {
+        Some(match self.env.ty_of(id)? {
+            Ty::PrimitiveType(ty) => primitive(ty).to_owned(),
+            Ty::Record { ref_ } => {
+                let name = self.name(self.table.get_record(ref_).name);
+                // records which have not been defined yet must be referred to
+                // using a string
+                if self.records.contains(&name) {
+                    name
+                } else {
+                    quote(&name)
+                }
+            }
+        })
+    }
+
+    /// Translates the expression, bracketing it if it binds less tightly than
+    /// `min`.
+    fn expr(&mut self, expr: ExprRef, min: u8) -> String {
+        let (text, binds) = self.expr_binds(expr);
+        if binds < min {
+            format!("({text})")
+        } else {
+            text
+        }
+    }
+
+    /// Translates the expression, also returning how tightly it binds.
+    fn expr_binds(&mut self, expr: ExprRef) -> (String, u8) {
+        let table = self.table;
+        match table.get_expr(&expr) {
+            Expr::Ident(ident) => (self.name(*ident), ATOM),
+            Expr::Literal(literal) => (
+                match &literal.token {
+                    Literal::String(string) => quote(string),
+                    Literal::Number(number) if number.is_real() => number.to_string(),
+                    Literal::Number(number) => number.as_int().to_string(),
+                    Literal::Bool(true) => "True".to_owned(),
+                    Literal::Bool(false) => "False".to_owned(),
+                },
+                ATOM,
+            ),
+            Expr::BinOp(op, left, right) => {
+                let (left, right) = (*left, *right);
+                match op.token {
+                    // assignments inside expressions are rare, but possible
+                    BinOp::SetEquals => (
+                        format!("({} := {})", self.expr(left, ATOM), self.expr(right, 0)),
+                        ATOM,
+                    ),
+                    BinOp::Dot => (
+                        format!("{}.{}", self.expr(left, POSTFIX), self.expr(right, ATOM)),
+                        POSTFIX,
+                    ),
+                    BinOp::Index => (
+                        format!("{}[{}]", self.expr(left, POSTFIX), self.expr(right, 0)),
+                        POSTFIX,
+                    ),
+                    // comparisons are bracketed on both sides (`a == b == c`
+                    // means something different in Python)
+                    BinOp::IsEqual | BinOp::IsNotEqual => (
+                        format!(
+                            "{} {} {}",
+                            self.expr(left, COMPARISON + 1),
+                            op.token,
+                            self.expr(right, COMPARISON + 1)
+                        ),
+                        COMPARISON,
+                    ),
+                    BinOp::Add | BinOp::Subtract => (
+                        format!(
+                            "{} {} {}",
+                            self.expr(left, SUM),
+                            op.token,
+                            self.expr(right, SUM + 1)
+                        ),
+                        SUM,
+                    ),
+                    BinOp::Multiply | BinOp::Divide => {
+                        let symbol = match self.env.ty_of(expr.id) {
+                            _ if op.token == BinOp::Multiply => "*",
+                            Some(Ty::PrimitiveType(PrimitiveType::Int)) => "//",
+                            _ => "/",
+                        };
+                        (
+                            format!(
+                                "{} {symbol} {}",
+                                self.expr(left, PRODUCT),
+                                self.expr(right, PRODUCT + 1)
+                            ),
+                            PRODUCT,
+                        )
+                    }
+                }
+            }
+            Expr::UnOp(op, operand) => match op.token {
+                UnOp::Positive | UnOp::Negative => {
+                    (format!("{}{}", op.token, self.expr(*operand, UNARY)), UNARY)
+                }
+                // pointers are only used by the prelude (which is not
+                // translated)
+                UnOp::Deref => self.expr_binds(*operand),
+            },
+            Expr::FunctionCall(name, args) => self.call(expr, *name, args),
+            Expr::Constructor(constructor) => {
+                let name = table.get_ident(constructor.name).inner();
+                let mut fields = constructor.fields.iter().collect::<Vec<_>>();
+                // use the order in which the fields were declared
+                if let Some(record) = table
+                    .record_
+                    .values()
+                    .find(|record| table.get_ident(record.name).inner() == name)
+                {
+                    let position = |field: &IdentRef| {
+                        record.fields.iter().position(|declared| {
+                            table.get_ident(declared.name).inner()
+                                == table.get_ident(*field).inner()
+                        })
+                    };
+                    fields.sort_by_key(|(field, _)| position(field));
+                }
+                let fields = fields
+                    .into_iter()
+                    .map(|(field, value)| format!("{}={}", self.name(*field), self.expr(*value, 0)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                (
+                    format!("{}({fields})", self.name(constructor.name)),
+                    POSTFIX,
+                )
+            }
+        }
+    }
+
+    fn call(&mut self, expr: ExprRef, name: IdentRef, args: &[ExprRef]) -> (String, u8) {
+        let function = self.table.get_ident(name).inner();
+        let mut args_list = || {
+            args.iter()
+                .map(|arg| self.expr(*arg, 0))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        // builtins take priority over other functions (as they do when the
+        // program is compiled)
+        if builtin::lookup(function).is_some() {
+            return match (function, args) {
+                ("print" | "print_int" | "print_bool" | "print_real", _) => {
+                    (format!("print({})", args_list()), POSTFIX)
+                }
+                ("sqrt" | "floor" | "ceil", _) => {
+                    let args = args_list();
+                    self.imports.insert("math");
+                    (format!("math.{function}({args})"), POSTFIX)
+                }
+                ("random", _) => {
+                    let args = args_list();
+                    self.imports.insert("random");
+                    let function = match self.env.ty_of(expr.id) {
+                        Some(Ty::PrimitiveType(PrimitiveType::Real)) => "uniform",
+                        _ => "randint",
+                    };
+                    (format!("random.{function}({args})"), POSTFIX)
+                }
+                ("pow", [base, exponent]) => (
+                    format!(
+                        "{} ** {}",
+                        self.expr(*base, POSTFIX),
+                        self.expr(*exponent, UNARY)
+                    ),
+                    POWER,
+                ),
+                // the rest (e.g. `str` and `abs`) have the same names in Python
+                _ => (format!("{function}({})", args_list()), POSTFIX),
+            };
+        }
+
+        match (function, args) {
+            ("array_new", [length]) => (
+                format!("[0] * {}", self.expr(*length, PRODUCT + 1)),
+                PRODUCT,
+            ),
+            ("array_get", [array, index]) => (
+                format!("{}[{}]", self.expr(*array, POSTFIX), self.expr(*index, 0)),
+                POSTFIX,
+            ),
+            ("array_set", _) | ("array_free", _) => {
+                let args = args_list();
+                let (helper, _) = HELPERS.iter().find(|(name, _)| *name == function).unwrap();
+                self.helpers.insert(helper);
+                (format!("{function}({args})"), POSTFIX)
+            }
+            _ => {
+                let args = args_list();
+                (format!("{}({args})", self.name(name)), POSTFIX)
+            }
+        }
+    }
+}
+
+fn primitive(ty: PrimitiveType) -> &'static str {
+    match ty {
+        PrimitiveType::Int => "int",
+        PrimitiveType::Bool => "bool",
+        PrimitiveType::StrSlice => "str",
+        PrimitiveType::Pointer => "list",
+        PrimitiveType::Real => "float",
+    }
+}
+
+fn c_type(ty: PrimitiveType) -> &'static str {
+    match ty {
+        PrimitiveType::Int => "ctypes.c_int64",
+        PrimitiveType::Bool => "ctypes.c_bool",
+        PrimitiveType::StrSlice => "ctypes.c_char_p",
+        PrimitiveType::Pointer => "ctypes.c_void_p",
+        PrimitiveType::Real => "ctypes.c_double",
+    }
+}
+
+/// Writes the text as a Python string literal.
+fn quote(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for char in text.chars() {
+        match char {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            char => quoted.push(char),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,322 @@
+use crate::{
+    codegen::{optimised_mir, CodegenOptions},
+    parse::parse_with_prelude,
+    runtime::RAN_FOR_TOO_LONG,
+    ty::type_check,
+};
+
+use super::{c::to_c, python::to_python};
+
+fn python(program: &str) -> String {
+    let table = parse_with_prelude(program).unwrap();
+    let env = type_check(&table).unwrap();
+    to_python(&table, &env, program)
+}
+
+fn c(program: &str, seed: Option<u64>, fuel: Option<u64>) -> String {
+    let table = parse_with_prelude(program).unwrap();
+    let env = type_check(&table).unwrap();
+    let program = optimised_mir(&table, &env, &CodegenOptions::default()).unwrap();
+    to_c(&program, seed, fuel)
+}
+
+#[test]
+fn python_program() {
+    let program = ";; adds up the numbers in a list
+record Point
+  x of Int
+  y of Int
+endrecord
+
+function main()
+  values = array_new(10)
+  ;; fill in the list
+  for i = 0 to 9
+    array_set(values, i, i * i)
+  next i
+  print(total(values, 10) / 3)
+  p = Point { y: 2, x: 1 }
+  print_int(p.x)
+  if p.y == 1 then
+    print(\"one\")
+  elseif p.y == 2 then
+    ;; this branch is taken
+    print(\"two\")
+  else
+    print(\"many\")
+  endif
+  return 0
+endfunction
+
+function total(list, length)
+  sum = 0
+  for i = length - 1 to 0 step -1
+    sum = sum + array_get(list, i)
+  next i
+  return sum
+endfunction
+";
+    assert_eq!(
+        python(program),
+        r#"import sys
+from dataclasses import dataclass
+
+
+# adds up the numbers in a list
+@dataclass
+class Point:
+    x: int
+    y: int
+
+    def __str__(self) -> str:
+        return f"Point {{ x: {self.x}, y: {self.y} }}"
+
+
+def main() -> int:
+    values = [0] * 10
+    # fill in the list
+    for i in range(10):
+        values[i] = i * i
+    print(total(values, 10) // 3)
+    p = Point(x=1, y=2)
+    print(p.x)
+    if p.y == 1:
+        print("one")
+    elif p.y == 2:
+        # this branch is taken
+        print("two")
+    else:
+        print("many")
+    return 0
+
+
+def total(list: list, length: int) -> int:
+    sum = 0
+    for i in range(length - 1, -1, -1):
+        sum = sum + list[i]
+    return sum
+
+
+if __name__ == "__main__":
+    sys.exit(main())
+"#
+    );
+}
+
+#[test]
+fn brackets_and_builtins() {
+    let program = "function main()
+  x = (1 + 2) * -(3 - 4)
+  print(x / (2 * 3), pow(-2, 3), sqrt(2.0) / 2.0)
+  ;; comparisons cannot be chained in Python
+  print((x == 3) == True)
+  ;; `input` is the name of a Python function
+  input = str(x)
+  while x != 0
+    x = x - 1
+    ;; nothing else happens
+  endwhile
+  return 0
+endfunction
+";
+    assert_eq!(
+        python(program),
+        r#"import math
+import sys
+
+
+def main() -> int:
+    x = (1 + 2) * -(3 - 4)
+    print(x // (2 * 3), (-2) ** 3, math.sqrt(2.0) / 2.0)
+    # comparisons cannot be chained in Python
+    print((x == 3) == True)
+    # `input` is the name of a Python function
+    input_ = str(x)
+    while x != 0:
+        x = x - 1
+        # nothing else happens
+    return 0
+
+
+if __name__ == "__main__":
+    sys.exit(main())
+"#
+    );
+}
+
+#[test]
+fn empty_blocks_and_externs() {
+    let program = "extern function labs(x: Int) -> Int
+
+record Empty
+endrecord
+
+function main()
+  e = Empty {}
+  while False
+  endwhile
+  return labs(0)
+endfunction
+";
+    assert_eq!(
+        python(program),
+        r#"import ctypes
+import sys
+from dataclasses import dataclass
+
+
+labs = ctypes.CDLL(None).labs
+labs.argtypes = [ctypes.c_int64]
+labs.restype = ctypes.c_int64
+
+
+@dataclass
+class Empty:
+    def __str__(self) -> str:
+        return f"Empty {{}}"
+
+
+def main() -> int:
+    e = Empty()
+    while False:
+        pass
+    return labs(0)
+
+
+if __name__ == "__main__":
+    sys.exit(main())
+"#
+    );
+}
+
+#[test]
+fn c_program() {
+    let program = "record Point
+  x of Int
+  visible of Bool
+endrecord
+
+function main()
+  p = Point { x: 3, visible: True }
+  total = 0
+  i = 0
+  while i != 3
+    total = total + square(i)
+    i = i + 1
+  endwhile
+  if p.visible then
+    print_int(total)
+  endif
+  return 0
+endfunction
+
+function square(n)
+  return n * n
+endfunction
+";
+    assert_eq!(
+        c(program, None, None),
+        r#"#include <inttypes.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+struct Point {
+    int64_t x;
+    bool visible;
+};
+
+static void print_int(int64_t integer) {
+    printf("%" PRId64 "\n", integer);
+}
+
+static int64_t main_(void);
+static int64_t square(int64_t n);
+
+static int64_t main_(void) {
+    struct Point *_0;
+    struct Point *p;
+    int64_t total;
+    int64_t i;
+    bool _4;
+    int64_t _5;
+    int64_t _6;
+    int64_t _7;
+    bool _8;
+    int64_t _9;
+
+    /* line 7 */
+    _0 = &(struct Point){ .x = 3, .visible = true };
+    p = _0;
+    /* line 8 */
+    total = 0;
+    /* line 9 */
+    i = 0;
+bb1:
+    /* line 10 */
+    _4 = i != 3;
+    if (!_4) goto bb3;
+    /* line 11 */
+    _5 = square(i);
+    _6 = total + _5;
+    total = _6;
+    /* line 12 */
+    _7 = i + 1;
+    i = _7;
+    goto bb1;
+bb3:
+    /* line 14 */
+    _8 = p->visible;
+    if (_8) goto bb5;
+    goto bb6;
+bb4:
+    /* line 17 */
+    return 0;
+bb5:
+    /* line 15 */
+    print_int(total);
+    _9 = 0;
+    goto bb4;
+bb6:
+    goto bb4;
+}
+
+static int64_t square(int64_t n) {
+    int64_t _1;
+
+    /* line 21 */
+    _1 = n * n;
+    return _1;
+}
+
+int main(void) {
+    return (int)main_();
+}
+"#
+    );
+}
+
+#[test]
+fn c_runtime_and_names() {
+    let program = "extern function labs(x: Int) -> Int
+
+function main()
+  double = labs(0 - 4)
+  print(double, random(1, 6))
+  return 0
+endfunction
+";
+    let c = c(program, Some(7), Some(10));
+    // `extern` functions are declared using the types they are called with
+    assert!(c.contains("extern int64_t labs(int64_t);\n"));
+    // names which are C keywords are changed
+    assert!(c.contains("    double_ = _1;\n"));
+    // the seed and the amount of fuel are set before the program starts
+    assert!(c.contains("    random_state = UINT64_C(7);\n    fuel = INT64_C(10);\n"));
+    assert!(c.contains("    use_fuel();\n"));
+    assert!(c.contains(&format!("fail(\"{RAN_FOR_TOO_LONG}\");")));
+    // only the helpers which the program uses are included
+    assert!(c.contains("static int64_t random_int(int64_t lower, int64_t upper) {"));
+    assert!(!c.contains("format_real"));
+}
@@ -1,12 +1,16 @@
 //! Collects constraints from an AST.
 
+use rustc_hash::FxHashMap;
+
 use crate::{
-    diagnostics::span::{HasSpan, Spanned},
+    builtin::{self, Slot},
+    diagnostics::span::{HasSpan, Span, Spanned},
     parse::{
-        expr::{BinOp, Expr, UnOp},
+        expr::{BinOp, Expr, ExprRef, UnOp},
         func::{Func, FuncRef, Return},
-        ident::Ident,
+        ident::{Ident, IdentRef},
         lit::Literal,
+        r#extern::Extern,
         r#for::ForLoop,
         r#if::{Branch, If},
         r#while::While,
@@ -31,6 +35,17 @@ pub(crate) enum ConstraintInner {
     /// produced during the constraint-gathering phase - it is used when we
     /// eliminate the variable ids.
     TyToTy { ty: Spanned<Ty>, to: Spanned<Ty> },
+    /// A specific item must have one of the given types. This is used for the
+    /// generic slots of builtins and for the operands of unary `+` and `-`
+    /// (the builtin or operator is named by `operation`), which only support
+    /// some types. These constraints are not unified; instead they are checked
+    /// once every other constraint has been solved (see
+    /// [`super::type_check`]).
+    IdToOneOf {
+        id: Spanned<Id>,
+        operation: &'static str,
+        tys: Vec<PrimitiveType>,
+    },
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
@@ -43,16 +58,85 @@ impl Constraint {
     pub(crate) fn new(id: ConstraintId, inner: ConstraintInner) -> Self {
         Self { id, inner }
     }
+
+    /// Gives the constraint a new [`ConstraintId`], and replaces each id in it
+    /// which is renamed (i.e. a key of `renamed`) with its new id.
+    pub(crate) fn renamed(self, id: ConstraintId, renamed: &FxHashMap<Id, Id>) -> Self {
+        let rename = |spanned: Spanned<Id>| Spanned {
+            token: renamed
+                .get(&spanned.token)
+                .copied()
+                .unwrap_or(spanned.token),
+            ..spanned
+        };
+        let inner = match self.inner {
+            ConstraintInner::IdToTy { id, ty } => ConstraintInner::IdToTy { id: rename(id), ty },
+            ConstraintInner::IdToId { id, to } => ConstraintInner::IdToId {
+                id: rename(id),
+                to: rename(to),
+            },
+            ConstraintInner::TyToTy { ty, to } => ConstraintInner::TyToTy { ty, to },
+            ConstraintInner::IdToOneOf { id, operation, tys } => ConstraintInner::IdToOneOf {
+                id: rename(id),
+                operation,
+                tys,
+            },
+        };
+        Self { id, inner }
+    }
+
+    /// Whether the constraint is the same as `other`, and also came from the
+    /// same places in the source code (the spans are not otherwise compared,
+    /// see [`Span`]).
+    pub(crate) fn same_as(&self, other: &Self) -> bool {
+        self == other
+            && self
+                .inner
+                .spans()
+                .into_iter()
+                .zip(other.inner.spans())
+                .all(|(a, b)| (a.start(), a.stop()) == (b.start(), b.stop()))
+    }
 }
 
+impl ConstraintInner {
+    /// The spans of the two sides of the constraint.
+    fn spans(&self) -> [Span; 2] {
+        match self {
+            ConstraintInner::IdToTy { id, ty } => [id.span, ty.span],
+            ConstraintInner::IdToId { id, to } => [id.span, to.span],
+            ConstraintInner::TyToTy { ty, to } => [ty.span, to.span],
+            ConstraintInner::IdToOneOf { id, .. } => [id.span, id.span],
+        }
+    }
+}
+
+/// Collects the constraints from the prelude and then the program.
 pub(crate) fn collect<'i>(
     ast: &'i ParseTable<'i>,
 ) -> Result<Vec<Constraint>, ConstraintGatheringError> {
     let mut visitor = ConstraintVisitor::new();
-    visitor
-        .visit_table(ast)
-        .into_iter()
-        .collect::<Result<_, _>>()?;
+    visitor.collect_prelude(ast)?;
+    visitor.collect_program(ast)?;
+    Ok(visitor.take_constraints())
+}
+
+/// Collects the constraints from the prelude only.
+pub(crate) fn collect_prelude<'i>(
+    ast: &'i ParseTable<'i>,
+) -> Result<Vec<Constraint>, ConstraintGatheringError> {
+    let mut visitor = ConstraintVisitor::new();
+    visitor.collect_prelude(ast)?;
+    Ok(visitor.take_constraints())
+}
+
+/// Collects the constraints from the program only (i.e. the items in the root
+/// block of the table).
+pub(crate) fn collect_program<'i>(
+    ast: &'i ParseTable<'i>,
+) -> Result<Vec<Constraint>, ConstraintGatheringError> {
+    let mut visitor = ConstraintVisitor::new();
+    visitor.collect_program(ast)?;
     Ok(visitor.take_constraints())
 }
 
@@ -60,6 +144,9 @@ struct ConstraintVisitor {
     constraints: Vec<Constraint>,
     current_func: Option<FuncRef>,
     id: ConstraintId,
+    /// Whether the code currently being visited is allowed to perform
+    /// privileged operations (this is only the case for the prelude).
+    privileged: bool,
 }
 
 impl ConstraintVisitor {
@@ -91,12 +178,40 @@ impl ConstraintVisitor {
             constraints: vec![],
             current_func: None,
             id: ConstraintId::default(),
+            privileged: false,
         }
     }
 
     fn take_constraints(self) -> Vec<Constraint> {
         self.constraints
     }
+
+    fn collect_prelude<'i>(
+        &mut self,
+        ast: &'i ParseTable<'i>,
+    ) -> Result<(), ConstraintGatheringError> {
+        // the prelude is allowed to perform privileged operations, but the
+        // program itself is not
+        self.privileged = true;
+        self.visit_block(
+            WithId {
+                id: ast.prelude.0,
+                inner: &ast.prelude.1,
+            },
+            ast,
+        )
+        .into_iter()
+        .collect::<Result<_, _>>()?;
+        self.privileged = false;
+        Ok(())
+    }
+
+    fn collect_program<'i>(
+        &mut self,
+        ast: &'i ParseTable<'i>,
+    ) -> Result<(), ConstraintGatheringError> {
+        self.visit_table(ast).into_iter().collect()
+    }
 }
 
 impl<'i> IdVisitor<'i> for ConstraintVisitor {
@@ -104,6 +219,14 @@ impl<'i> IdVisitor<'i> for ConstraintVisitor {
 
     fn visit_rec(&mut self, rec: WithId<&'i Record>, table: &'i ParseTable<'i>) -> Self::Output {
         for field in &rec.inner().fields {
+            if !self.privileged && field.ty.token == PrimitiveType::Pointer {
+                return Err(ConstraintGatheringError::PrivilegedOperation {
+                    span: field.ty.span.into(),
+                    explanation: "Fields of type `Pointer` can only be used inside the standard \
+                                  library."
+                        .to_owned(),
+                });
+            }
             self.add_constraint(ConstraintInner::IdToTy {
                 id: Spanned::new(table.get_ident(field.name).span(table), field.name.id),
                 ty: field.ty.map(Ty::PrimitiveType),
@@ -117,6 +240,15 @@ impl<'i> IdVisitor<'i> for ConstraintVisitor {
         expr: WithId<&'i Expr<'i>>,
         table: &'i ParseTable<'i>,
     ) -> Self::Output {
+        if !self.privileged {
+            check_unprivileged(
+                WithId {
+                    inner: expr.inner,
+                    id: expr.id,
+                },
+                table,
+            )?;
+        }
         self.extend_constraints(collect_expr(expr, table, None)?);
         Ok(())
     }
@@ -207,6 +339,7 @@ impl<'i> IdVisitor<'i> for ConstraintVisitor {
     }
 
     fn visit_while(&mut self, stmt: WithId<&'i While>, table: &'i ParseTable<'i>) -> Self::Output {
+        self.visit_expr(table.get_expr_with_id(stmt.inner().condition), table)?;
         self.add_constraint(ConstraintInner::IdToTy {
             id: Spanned::new(
                 table.get_expr(&stmt.inner().condition).span(table),
@@ -248,6 +381,22 @@ impl<'i> IdVisitor<'i> for ConstraintVisitor {
     }
 
     fn visit_func(&mut self, func: WithId<&'i Func>, table: &'i ParseTable<'i>) -> Self::Output {
+        if !self.privileged {
+            let name = table.get_ident(func.inner().name);
+            if table.func.iter().any(|(id, prelude_func)| {
+                table.is_prelude_func(*id) && table.get_ident(prelude_func.name) == name
+            }) {
+                return Err(ConstraintGatheringError::ShadowsPrelude {
+                    span: name.span(table).into(),
+                    explanation: format!(
+                        "The standard library already defines a function called `{}`, so \
+                         please choose a different name.",
+                        name.inner
+                    ),
+                });
+            }
+        }
+
         let prev = self.current_func;
         self.current_func = Some(FuncRef { id: func.id() });
         self.visit_block(table.get_block_with_id(func.inner().block), table)
@@ -257,6 +406,49 @@ impl<'i> IdVisitor<'i> for ConstraintVisitor {
         Ok(())
     }
 
+    /// External functions don't introduce any constraints (their signatures are
+    /// used directly when they are called), but we check that they don't clash
+    /// with the standard library.
+    fn visit_extern(
+        &mut self,
+        ext: WithId<&'i Extern<'i>>,
+        table: &'i ParseTable<'i>,
+    ) -> Self::Output {
+        let name = table.get_ident(ext.inner().name);
+        if builtin::lookup(name.inner).is_some()
+            || table.func.iter().any(|(id, prelude_func)| {
+                table.is_prelude_func(*id) && table.get_ident(prelude_func.name) == name
+            })
+        {
+            return Err(ConstraintGatheringError::ShadowsPrelude {
+                span: name.span(table).into(),
+                explanation: format!(
+                    "The standard library already defines a function called `{}`, so please \
+                     choose a different name.",
+                    name.inner
+                ),
+            });
+        }
+        if !self.privileged {
+            if let Some(ty) = ext
+                .inner()
+                .parameters
+                .iter()
+                .map(|(_, ty)| ty)
+                .chain(std::iter::once(&ext.inner().returns))
+                .find(|ty| ty.token == PrimitiveType::Pointer)
+            {
+                return Err(ConstraintGatheringError::PrivilegedOperation {
+                    span: ty.span.into(),
+                    explanation: "The `Pointer` type can only be used inside the standard \
+                                  library."
+                        .to_owned(),
+                });
+            }
+        }
+        Ok(())
+    }
+
     /// Doesn't do anything.
     fn visit_ident(&mut self, _: WithId<&Ident<'i>>, _: &'i ParseTable<'i>) -> Self::Output {
         Ok(())
@@ -289,6 +481,9 @@ fn collect_expr<'i>(
         Expr::Literal(lit) => {
             let ty = match lit.token {
                 Literal::String(_) => Ty::PrimitiveType(PrimitiveType::StrSlice),
+                Literal::Number(ref number) if number.is_real() => {
+                    Ty::PrimitiveType(PrimitiveType::Real)
+                }
                 Literal::Number(_) => Ty::PrimitiveType(PrimitiveType::Int),
                 Literal::Bool(_) => Ty::PrimitiveType(PrimitiveType::Bool),
             };
@@ -418,19 +613,13 @@ fn collect_expr<'i>(
                     constraints.extend(collect_expr(table.get_expr_with_id(*right), table, None)?);
                 }
                 Expr::UnOp(op, ref pointer) if op.token.is_deref() => {
-                    // first constraint - the parent expression to the expression
-                    // to which we are applying the pointer
-                    // i.e. if the expression is structured something like this
-                    // (note: not valid Rust code)
-                    //  Expr(id1, Pointer(id2, Expr))
-                    // we would add
-                    // id1 == id2
+                    // first constraint - the assignment expression has the same
+                    // type as the value being stored
                     constraints.push(ConstraintInner::IdToId {
                         id: Spanned::new(expr.inner().span(table), expr.id()),
-                        // todo: use the span of the operator
                         to: Spanned::new(
-                            table.get_expr(pointer).span(table),
-                            table.get_expr_with_id(*pointer).id(),
+                            table.get_expr(right).span(table),
+                            table.get_expr_with_id(*right).id(),
                         ),
                     });
                     constraints.push(ConstraintInner::IdToTy {
@@ -444,7 +633,8 @@ fn collect_expr<'i>(
                             Ty::PrimitiveType(PrimitiveType::Pointer),
                         ),
                     });
-                    constraints.extend(collect_expr(table.get_expr_with_id(*pointer), table, None)?)
+                    constraints.extend(collect_expr(table.get_expr_with_id(*pointer), table, None)?);
+                    constraints.extend(collect_expr(table.get_expr_with_id(*right), table, None)?);
                 }
                 _ => {
                     return Err(ConstraintGatheringError::CannotAssignToExpression {
@@ -487,18 +677,22 @@ fn collect_expr<'i>(
             }
         },
         Expr::UnOp(op, arg) => {
-            constraints.push(ConstraintInner::IdToId {
-                id: Spanned::new(expr.inner().span(table), expr.id),
-                to: Spanned::new(table.get_expr(arg).span(table), arg.id)
-            });
             match op.token {
-            UnOp::Positive | UnOp::Negative => constraints.push(ConstraintInner::IdToTy {
-                id: Spanned::new(table.get_expr(arg).span(table), arg.id),
-                ty: Spanned::new(
-                    table.get_expr(arg).span(table),
-                    Ty::PrimitiveType(PrimitiveType::Int),
-                ),
-            }),
+            // these can be applied to both integers and real numbers (the
+            // code generator checks which one it has been given)
+            UnOp::Positive | UnOp::Negative => {
+                constraints.push(ConstraintInner::IdToId {
+                    id: Spanned::new(expr.inner().span(table), expr.id),
+                    to: Spanned::new(table.get_expr(arg).span(table), arg.id)
+                });
+                constraints.push(ConstraintInner::IdToOneOf {
+                    id: Spanned::new(table.get_expr(arg).span(table), arg.id),
+                    operation: if let UnOp::Positive = op.token { "+" } else { "-" },
+                    tys: vec![PrimitiveType::Int, PrimitiveType::Real],
+                });
+            }
+            // memory is untyped, so the type of the value being loaded is
+            // determined by how it is used
             UnOp::Deref => constraints.push(ConstraintInner::IdToTy {
                 id: Spanned::new(table.get_expr(arg).span(table), arg.id),
                 ty: Spanned::new(
@@ -506,7 +700,9 @@ fn collect_expr<'i>(
                     Ty::PrimitiveType(PrimitiveType::Pointer),
                 ),
             }),
-        }},
+            }
+            constraints.extend(collect_expr(table.get_expr_with_id(*arg), table, None)?);
+        },
         Expr::Constructor(rec) => {
             let find = table
                 .record_
@@ -569,200 +765,30 @@ fn collect_expr<'i>(
             }
         }
         Expr::FunctionCall(func, params) => {
-            if table.get_ident(*func).inner == "print_int" {
-                if params.len() != 1 {
-                    return Err(ConstraintGatheringError::MismatchedFunctionCall {
-                        span: table.get_ident(*func).span(table).into(),
-                        explanation: format!(
-                            "This function accepts 1
-                            parameter, but you've called it with `{}` arguments.",
-                            params.len()
-                        ),
-                    });
-                }
-                let param = &params[0];
-                constraints.push(ConstraintInner::IdToTy {
-                    id: Spanned::new(table.get_expr(param).span(table), param.id),
-                    ty: Spanned::new(
-                        table.get_expr(param).span(table),
-                        Ty::PrimitiveType(PrimitiveType::Int),
-                    ),
-                });
-                constraints.push(ConstraintInner::IdToId {
-                    id: Spanned::new(expr.inner().span(table), expr.id()),
-                    to: Spanned::new(table.get_expr(param).span(table), param.id),
-                });
-                constraints.extend(collect_expr(
-                    table.get_expr_with_id(*param),
-                    table,
-                    Some(Ty::PrimitiveType(PrimitiveType::Int)),
-                )?);
-            } else if table.get_ident(*func).inner == "print_bool" {
-                if params.len() != 1 {
-                    return Err(ConstraintGatheringError::MismatchedFunctionCall {
-                        span: table.get_ident(*func).span(table).into(),
-                        explanation: format!(
-                            "This function accepts 1
-                            parameter, but you've called it with `{}` arguments.",
-                            params.len()
-                        ),
-                    });
-                }
-                let param = &params[0];
-                constraints.push(ConstraintInner::IdToTy {
-                    id: Spanned::new(table.get_expr(param).span(table), param.id),
-                    ty: Spanned::new(
-                        table.get_expr(param).span(table),
-                        Ty::PrimitiveType(PrimitiveType::Bool),
-                    ),
-                });
-                constraints.push(ConstraintInner::IdToId {
-                    id: Spanned::new(expr.inner().span(table), expr.id()),
-                    to: Spanned::new(table.get_expr(param).span(table), param.id),
-                });
-                constraints.extend(collect_expr(
-                    table.get_expr_with_id(*param),
-                    table,
-                    Some(Ty::PrimitiveType(PrimitiveType::Bool)),
-                )?);
-            } else if table.get_ident(*func).inner == "print" {
-                if params.len() != 1 {
-                    return Err(ConstraintGatheringError::MismatchedFunctionCall {
-                        span: table.get_ident(*func).span(table).into(),
-                        explanation: format!(
-                            "This function accepts 1
-                            parameter, but you've called it with `{}` arguments.",
-                            params.len()
-                        ),
-                    });
-                }
-                let param = &params[0];
-                constraints.push(ConstraintInner::IdToTy {
-                    id: Spanned::new(table.get_expr(param).span(table), param.id),
-                    ty: Spanned::new(
-                        table.get_expr(param).span(table),
-                        Ty::PrimitiveType(PrimitiveType::StrSlice),
-                    ),
-                });
-                constraints.push(ConstraintInner::IdToId {
-                    id: Spanned::new(expr.inner().span(table), expr.id()),
-                    to: Spanned::new(
-                        table.get_expr(param).span(table),
-                        table.get_expr_with_id(*param).id(),
-                    ),
-                });
-                constraints.extend(collect_expr(
-                    table.get_expr_with_id(*param),
-                    table,
-                    Some(Ty::PrimitiveType(PrimitiveType::StrSlice)),
-                )?);
-            // todo: make memory allocation functions standard-library only!
-            } else if table.get_ident(*func).inner == "malloc" {
-                if params.len() != 1 {
-                    return Err(ConstraintGatheringError::MismatchedFunctionCall {
-                        span: table.get_ident(*func).span(table).into(),
-                        explanation: format!(
-                            "This function accepts 1
-                            parameter, but you've called it with `{}` arguments.",
-                            params.len()
-                        ),
-                    });
-                }
-                let param = &params[0];
-                constraints.extend(collect_expr(table.get_expr_with_id(*param), table, None)?);
-                constraints.push(ConstraintInner::IdToTy {
-                    id: Spanned::new(
-                        table.get_expr(param).span(table),
-                        table.get_expr_with_id(*param).id(),
-                    ),
-                    ty: Spanned::new(
-                        table.get_expr(param).span(table),
-                        Ty::PrimitiveType(PrimitiveType::Int),
-                    ),
-                });
-                constraints.push(ConstraintInner::IdToTy {
-                    id: Spanned::new(
-                        table.get_ident(*func).span(table),
-                        table.get_ident_with_id(*func).id(),
-                    ),
-                    ty: Spanned::new(
-                        table.get_ident(*func).span(table),
-                        Ty::PrimitiveType(PrimitiveType::Pointer),
-                    ),
-                });
-                constraints.push(ConstraintInner::IdToId {
-                    id: Spanned::new(
-                        table.get_ident(*func).span(table),
-                        table.get_ident_with_id(*func).id(),
-                    ),
-                    to: Spanned::new(expr.inner().span(table), expr.id()),
-                });
-            } else if table.get_ident(*func).inner == "free" {
-                if params.len() != 1 {
-                    return Err(ConstraintGatheringError::MismatchedFunctionCall {
-                        span: table.get_ident(*func).span(table).into(),
-                        explanation: format!(
-                            "This function accepts 1
-                            parameter, but you've called it with `{}` arguments.",
-                            params.len()
-                        ),
-                    });
-                }
-                let param = &params[0];
-                constraints.push(ConstraintInner::IdToTy {
-                    id: Spanned::new(table.get_expr(param).span(table), param.id),
-                    ty: Spanned::new(
-                        table.get_expr(param).span(table),
-                        Ty::PrimitiveType(PrimitiveType::Pointer),
-                    ),
-                });
-                constraints.push(ConstraintInner::IdToId {
-                    id: Spanned::new(expr.inner().span(table), expr.id()),
-                    to: Spanned::new(
-                        table.get_expr(param).span(table),
-                        table.get_expr_with_id(*param).id(),
-                    ),
-                });
-                constraints.extend(collect_expr(table.get_expr_with_id(*param), table, None)?);
-            } else if table.get_ident(*func).inner() == "realloc" {
-                if params.len() != 2 {
-                    return Err(ConstraintGatheringError::MismatchedFunctionCall {
-                        span: table.get_ident(*func).span(table).into(),
-                        explanation: format!(
-                            "This function accepts 2
-                            parameter, but you've called it with `{}` arguments.",
-                            params.len()
-                        ),
-                    });
-                }
-                let pointer = &params[0];
-                constraints.push(ConstraintInner::IdToTy {
-                    id: Spanned::new(table.get_expr(pointer).span(table), pointer.id),
-                    ty: Spanned::new(
-                        table.get_expr(pointer).span(table),
-                        Ty::PrimitiveType(PrimitiveType::Pointer),
-                    ),
-                });
-                constraints.extend(collect_expr(
-                    table.get_expr_with_id(*pointer),
+            if let Some(builtin) = builtin::lookup(table.get_ident(*func).inner) {
+                constraints.extend(collect_call_with_signature(
+                    builtin.params,
+                    builtin.variadic,
+                    builtin.returns,
+                    expr,
+                    *func,
+                    params,
                     table,
-                    Some(Ty::PrimitiveType(PrimitiveType::Pointer)),
                 )?);
-                let new_size = &params[1];
-                constraints.push(ConstraintInner::IdToTy {
-                    id: Spanned::new(
-                        table.get_expr(new_size).span(table),
-                        table.get_expr_with_id(*new_size).id(),
-                    ),
-                    ty: Spanned::new(
-                        table.get_expr(new_size).span(table),
-                        Ty::PrimitiveType(PrimitiveType::Int),
-                    ),
-                });
-                constraints.extend(collect_expr(
-                    table.get_expr_with_id(*new_size),
+            } else if let Some(ext) = table.find_extern(table.get_ident(*func).inner) {
+                let signature = ext
+                    .parameters
+                    .iter()
+                    .map(|(_, ty)| Slot::Concrete(ty.token))
+                    .collect::<Vec<_>>();
+                constraints.extend(collect_call_with_signature(
+                    &signature,
+                    false,
+                    Slot::Concrete(ext.returns.token),
+                    expr,
+                    *func,
+                    params,
                     table,
-                    Some(Ty::PrimitiveType(PrimitiveType::Int)),
                 )?);
             } else if let Some(function) = table.func.iter().find(|function| {
                 table.get_ident(function.1.name).inner == table.get_ident(*func).inner
@@ -821,3 +847,132 @@ fn collect_expr<'i>(
 
     Ok(constraints)
 }
+
+/// Collects the constraints for a call to a function with a known signature
+/// (i.e. a builtin function, or an external function).
+fn collect_call_with_signature<'i>(
+    signature: &[Slot],
+    variadic: bool,
+    returns: Slot,
+    expr: WithId<&'i Expr<'i>>,
+    func: IdentRef,
+    params: &[ExprRef],
+    table: &'i ParseTable<'i>,
+) -> Result<Vec<ConstraintInner>, ConstraintGatheringError> {
+    if variadic && params.len() + 1 < signature.len() {
+        return Err(ConstraintGatheringError::MismatchedFunctionCall {
+            span: table.get_ident(func).span(table).into(),
+            explanation: format!(
+                "This function accepts at least `{}`
+                parameters, but you've called it with `{}` arguments.",
+                signature.len() - 1,
+                params.len()
+            ),
+        });
+    } else if !variadic && params.len() != signature.len() {
+        return Err(ConstraintGatheringError::MismatchedFunctionCall {
+            span: table.get_ident(func).span(table).into(),
+            explanation: format!(
+                "This function accepts `{}`
+                parameters, but you've called it with `{}` arguments.",
+                signature.len(),
+                params.len()
+            ),
+        });
+    }
+
+    let mut constraints = vec![];
+
+    // (only builtins have generic slots)
+    let builtin = builtin::lookup(table.get_ident(func).inner);
+    // every generic slot must have the same type, so we constrain each of them
+    // to have the same type as the first one (which must be one of the types
+    // which the builtin supports)
+    let mut generic: Option<Spanned<Id>> = None;
+    let mut constrain_slot =
+        |constraints: &mut Vec<ConstraintInner>, slot: Slot, id: Spanned<Id>| match slot {
+            Slot::Concrete(ty) => constraints.push(ConstraintInner::IdToTy {
+                ty: Spanned::new(id.span, Ty::PrimitiveType(ty)),
+                id,
+            }),
+            Slot::Generic => match generic {
+                Some(generic) => constraints.push(ConstraintInner::IdToId { id, to: generic }),
+                None => {
+                    generic = Some(id);
+                    if let Some(builtin) = builtin {
+                        constraints.push(ConstraintInner::IdToOneOf {
+                            id,
+                            operation: builtin.name,
+                            tys: builtin.generic_types(),
+                        });
+                    }
+                }
+            },
+            Slot::Any => {}
+        };
+
+    // the last slot of a variadic signature is repeated for every remaining
+    // argument
+    let slots = signature
+        .iter()
+        .chain(signature.last().filter(|_| variadic).into_iter().cycle());
+    for (slot, param) in slots.zip(params) {
+        constrain_slot(
+            &mut constraints,
+            *slot,
+            Spanned::new(table.get_expr(param).span(table), param.id),
+        );
+        let ty = match slot {
+            Slot::Concrete(ty) => Some(Ty::PrimitiveType(*ty)),
+            Slot::Generic | Slot::Any => None,
+        };
+        constraints.extend(collect_expr(table.get_expr_with_id(*param), table, ty)?);
+    }
+    constrain_slot(
+        &mut constraints,
+        returns,
+        Spanned::new(expr.inner().span(table), expr.id()),
+    );
+
+    Ok(constraints)
+}
+
+/// Checks that the expression does not perform any operations which only the
+/// prelude is allowed to perform.
+fn check_unprivileged<'i>(
+    expr: WithId<&'i Expr<'i>>,
+    table: &'i ParseTable<'i>,
+) -> Result<(), ConstraintGatheringError> {
+    // note: we point at the operator (rather than the whole expression)
+    let memory_access = |span: Span| ConstraintGatheringError::PrivilegedOperation {
+        span: span.into(),
+        explanation: "This accesses memory directly, which is only allowed inside the \
+                      standard library (try using one of the `array_` functions instead)."
+            .to_owned(),
+    };
+
+    match expr.inner() {
+        Expr::Ident(_) | Expr::Literal(_) => Ok(()),
+        Expr::UnOp(op, _) if op.token.is_deref() => Err(memory_access(op.span)),
+        Expr::UnOp(_, arg) => check_unprivileged(table.get_expr_with_id(*arg), table),
+        Expr::BinOp(op, _, _) if op.token == BinOp::Index => Err(memory_access(op.span)),
+        Expr::BinOp(_, left, right) => {
+            check_unprivileged(table.get_expr_with_id(*left), table)?;
+            check_unprivileged(table.get_expr_with_id(*right), table)
+        }
+        Expr::FunctionCall(func, params) => {
+            if let Some(builtin) = builtin::lookup(table.get_ident(*func).inner) {
+                if builtin.privileged {
+                    return Err(memory_access(table.get_ident(*func).span(table)));
+                }
+            }
+            params
+                .iter()
+                .try_for_each(|param| check_unprivileged(table.get_expr_with_id(*param), table))
+        }
+        Expr::Constructor(con) => con
+            .fields
+            .values()
+            .try_for_each(|field| check_unprivileged(table.get_expr_with_id(*field), table)),
+    }
+}
@@ -49,6 +49,12 @@ pub(crate) fn print_constraint_set<'a>(
             ConstraintInner::TyToTy { ty, to } => {
                 println!("{ty:?} = {to:?}")
             }
+            ConstraintInner::IdToOneOf { id, operation, tys } => {
+                println!(
+                    "{} is one of {tys:?} (operand of `{operation}`)",
+                    id.as_u32()
+                )
+            }
         }
     }
 }
@@ -8,6 +8,12 @@ use super::track::ErrorReporter;
 pub enum TyCheckError {
     ConstraintGatheringError(ConstraintGatheringError),
     Reportable(ErrorReporter),
+    /// A builtin was called with a type which it does not support (e.g.
+    /// `abs(True)`).
+    UnsupportedType {
+        span: IndexOnlySpan,
+        explanation: String,
+    },
 }
 
 impl From<ConstraintGatheringError> for TyCheckError {
@@ -24,6 +30,11 @@ impl TyCheckError {
         match self {
             TyCheckError::ConstraintGatheringError(err) => err.report(id),
             TyCheckError::Reportable(reporter) => reporter.report(id, table),
+            TyCheckError::UnsupportedType { span, explanation } => Diagnostic::error()
+                .with_message("Your program contains a type error!")
+                .with_labels(vec![
+                    Label::primary(id, span.range()).with_message(explanation)
+                ]),
         }
     }
 
@@ -62,6 +73,18 @@ pub enum ConstraintGatheringError {
         span: IndexOnlySpan,
         explanation: String,
     },
+    /// The program tried to perform an operation which only the prelude is
+    /// allowed to perform (e.g. accessing memory directly).
+    PrivilegedOperation {
+        span: IndexOnlySpan,
+        explanation: String,
+    },
+    /// The program defined a function with the same name as one in the
+    /// prelude.
+    ShadowsPrelude {
+        span: IndexOnlySpan,
+        explanation: String,
+    },
 }
 
 impl ConstraintGatheringError {
@@ -75,6 +98,8 @@ impl ConstraintGatheringError {
             | ConstraintGatheringError::MismatchedFunctionCall { span, explanation }
             | ConstraintGatheringError::ReturnOutsideFunction { span, explanation }
             | ConstraintGatheringError::LiteralForFieldOrMethodAccess { span, explanation }
+            | ConstraintGatheringError::PrivilegedOperation { span, explanation }
+            | ConstraintGatheringError::ShadowsPrelude { span, explanation }
             | ConstraintGatheringError::UnresolvableRecord { span, explanation } => diagnostic
                 .with_labels(vec![
                     Label::primary(id, span.range()).with_message(explanation)
@@ -3,13 +3,16 @@
 use core::fmt;
 use std::{collections::BTreeMap, fmt::Debug, hash::Hash};
 
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 #[cfg(all(test, feature = "fuzzcheck"))]
 mod fuzz;
 #[cfg(test)]
 mod fuzz2;
+// (`very_invalid_case` matches on a single pattern, which newer versions of
+// clippy warn about)
 #[cfg(test)]
+#[allow(clippy::single_match)]
 mod test;
 #[cfg(test)]
 mod ui;
@@ -30,10 +33,10 @@ use crate::{
 use self::{
     constraints::{Constraint, ConstraintInner},
     error::TyCheckError,
-    track::{ConstraintPosition, ErrorReporter, TraceTable, UnificationOperation},
+    track::{ConstraintId, ConstraintPosition, ErrorReporter, TraceTable, UnificationOperation},
 };
 
-mod constraints;
+pub(crate) mod constraints;
 
 /// A primitive type. All other types are built out of these (using the
 /// language constructs we have for building composite types - currently just
@@ -58,6 +61,9 @@ pub enum PrimitiveType {
     /// "pointer" in an external interface (i.e. language user-facing - i.e. no
     /// mentions in the docs, etc).
     Pointer,
+    /// A real number. These are stored as 64-bit IEEE 754 floating point
+    /// numbers.
+    Real,
 }
 
 #[derive(Hash, PartialEq, Eq, Clone, Copy)]
@@ -81,7 +87,17 @@ impl fmt::Debug for Ty {
 }
 
 pub fn type_check<'i>(table: &'i ParseTable<'i>) -> Result<TyEnv, TyCheckError> {
-    let constraints: FxHashSet<Constraint> = collect(table)?.into_iter().collect();
+    solve(collect(table)?)
+}
+
+/// Solves the constraints (which must each have a different
+/// [`ConstraintId`]), producing the type of every item which they
+/// mention.
+fn solve(constraints: Vec<Constraint>) -> Result<TyEnv, TyCheckError> {
+    let (restrictions, constraints): (Vec<Constraint>, Vec<Constraint>) = constraints
+        .into_iter()
+        .partition(|constraint| matches!(constraint.inner, ConstraintInner::IdToOneOf { .. }));
+    let constraints: FxHashSet<Constraint> = constraints.into_iter().collect();
 
     let mut trace_table = TraceTable::default();
 
@@ -89,9 +105,52 @@ pub fn type_check<'i>(table: &'i ParseTable<'i>) -> Result<TyEnv, TyCheckError>
         TyCheckError::Reportable(ErrorReporter::new(trace_table, errored_on))
     })?;
 
+    check_restrictions(restrictions, &env)?;
+
     Ok(env)
 }
 
+/// Type checks a program whose constraints were collected in parts (for
+/// example from each top-level item separately, see [`crate::query`]). Each
+/// id in the constraints which is a key of `renamed` is replaced with its
+/// value first.
+pub(crate) fn solve_parts<'c>(
+    parts: impl IntoIterator<Item = &'c [Constraint]>,
+    renamed: &FxHashMap<Id, Id>,
+) -> Result<TyEnv, TyCheckError> {
+    let constraints = parts
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .map(|(i, constraint)| constraint.clone().renamed(ConstraintId::new(i), renamed))
+        .collect();
+    solve(constraints)
+}
+
+/// Checks that each item which may only have some types (see
+/// [`ConstraintInner::IdToOneOf`]) has one of those types. Items whose type
+/// could not be inferred are left alone (these are reported when the program
+/// is lowered).
+fn check_restrictions(restrictions: Vec<Constraint>, env: &TyEnv) -> Result<(), TyCheckError> {
+    for restriction in restrictions {
+        if let ConstraintInner::IdToOneOf { id, operation, tys } = restriction.inner {
+            let explanation = match env.ty_of(*id) {
+                Some(Ty::PrimitiveType(ty)) if tys.contains(&ty) => continue,
+                Some(Ty::PrimitiveType(ty)) => {
+                    format!("`{operation}` cannot be used with values of type `{ty:?}`.")
+                }
+                Some(Ty::Record { .. }) => format!("`{operation}` cannot be used with records."),
+                None => continue,
+            };
+            return Err(TyCheckError::UnsupportedType {
+                span: id.span.index_only(),
+                explanation,
+            });
+        }
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug, Hash)]
 /// A substitution to be made as part of type inference.
 enum SubstitutionInner {
@@ -101,20 +160,20 @@ enum SubstitutionInner {
     ConcreteForX(Spanned<Ty>, Spanned<Id>),
 }
 
-#[derive(Hash, Clone, Debug)]
+#[derive(Hash, Clone, Debug, PartialEq, Eq)]
 /// The value of this type (which may have been inferred).
 pub enum TyInfo {
     EqId(Id),
     EqTy(Ty),
 }
 
-#[derive(Hash, Clone, Debug)]
+#[derive(Hash, Clone, Debug, PartialEq, Eq)]
 /// Information corresponding to an [crate::id::Id].
 pub struct Info {
     ty: TyInfo,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct TyEnv {
     map: BTreeMap<Id, Info>,
 }
@@ -165,12 +224,34 @@ impl TyEnv {
         })
     }
 
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             map: Default::default(),
         }
     }
 
+    /// Produces an environment which only contains the types of the given
+    /// ids. Each id is given the type which this environment has for the id
+    /// paired with it (this is how ids which were renamed after parsing are
+    /// mapped back to the ids used in the parse table, see [`crate::query`]).
+    pub(crate) fn project(&self, ids: impl IntoIterator<Item = (Id, Id)>) -> TyEnv {
+        TyEnv {
+            map: ids
+                .into_iter()
+                .filter_map(|(id, renamed)| {
+                    self.ty_of(renamed).map(|ty| {
+                        (
+                            id,
+                            Info {
+                                ty: TyInfo::EqTy(ty),
+                            },
+                        )
+                    })
+                })
+                .collect(),
+        }
+    }
+
     fn feed_substitution(&mut self, u: SubstitutionInner) {
         match u {
             // we remove y from the system by equating it to x
@@ -220,6 +301,8 @@ fn unify(
                 None
             }
         }
+        // these are checked once unification has finished (see `type_check`)
+        ConstraintInner::IdToOneOf { .. } => None,
         ConstraintInner::TyToTy { ref ty, ref to } => {
             // here we need to work backwards, and work out where this constraint came from!
             // maybe we need a way of "undoing" the algorithm?
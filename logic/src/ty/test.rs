@@ -6,12 +6,13 @@ use crate::{
     diagnostics::span::{Span, Spanned},
     parse::{
         expr::{BinOp, Expr},
-        parse,
+        parse, parse_with_prelude,
         record::RecordRef,
         table::Id,
     },
     ty::{
         constraints::{Constraint, ConstraintInner},
+        error::{ConstraintGatheringError, TyCheckError},
         track::{ConstraintId, TraceTable},
         type_check, unify, PrimitiveType, Ty, TyEnv,
     },
@@ -386,3 +387,180 @@ fn unary_op() {
         Some(Ty::PrimitiveType(PrimitiveType::Int))
     );
 }
+
+#[test]
+fn real_literal() {
+    let table = parse("X = -1.5").unwrap();
+    let env = type_check(&table).unwrap();
+    let expr = table.get(&table.root.1.inner[0]).unwrap();
+    let expr = expr.as_expr().unwrap();
+    let (_, lhs, _) = expr.as_bin_op().unwrap();
+    assert_eq!(
+        env.ty_of(lhs.id),
+        Some(Ty::PrimitiveType(PrimitiveType::Real))
+    );
+}
+
+#[test]
+fn random() {
+    let table = parse("X = random(1, 6)\nY = random(0.0, 1.0)").unwrap();
+    let env = type_check(&table).unwrap();
+    for (statement, ty) in [PrimitiveType::Int, PrimitiveType::Real]
+        .into_iter()
+        .enumerate()
+    {
+        let expr = table.get(&table.root.1.inner[statement]).unwrap();
+        let expr = expr.as_expr().unwrap();
+        let (_, lhs, _) = expr.as_bin_op().unwrap();
+        assert_eq!(env.ty_of(lhs.id), Some(Ty::PrimitiveType(ty)));
+    }
+
+    let table = parse("X = random(1, 6.0)").unwrap();
+    assert!(type_check(&table).is_err());
+}
+
+#[test]
+fn maths_builtins() {
+    let table = parse("X = abs(-1.5)\nY = round(X)\nZ = max(Y, 2)").unwrap();
+    let env = type_check(&table).unwrap();
+    for (statement, ty) in [PrimitiveType::Real, PrimitiveType::Int, PrimitiveType::Int]
+        .into_iter()
+        .enumerate()
+    {
+        let expr = table.get(&table.root.1.inner[statement]).unwrap();
+        let expr = expr.as_expr().unwrap();
+        let (_, lhs, _) = expr.as_bin_op().unwrap();
+        assert_eq!(env.ty_of(lhs.id), Some(Ty::PrimitiveType(ty)));
+    }
+
+    let table = parse("X = sqrt(4)").unwrap();
+    assert!(type_check(&table).is_err());
+    let table = parse("X = min(1)").unwrap();
+    assert!(type_check(&table).is_err());
+}
+
+#[test]
+fn prelude_type_checks() {
+    let table = parse_with_prelude(
+        "function main()\n  array = array_new(2)\n  array_set(array, 1, 5)\n  print_int(array_get(array, 1))\n  return 0\nendfunction\n",
+    )
+    .unwrap();
+    type_check(&table).unwrap();
+}
+
+#[test]
+fn arrays_all_have_the_same_item_type() {
+    // this is a limitation of the prelude (as its functions are not generic)
+    let table = parse_with_prelude(
+        "function main()\n  ints = array_new(1)\n  array_set(ints, 0, 5)\n  bools = array_new(1)\n  array_set(bools, 0, True)\n  return 0\nendfunction\n",
+    )
+    .unwrap();
+    assert!(type_check(&table).is_err());
+}
+
+#[test]
+fn privileged_operations_outside_prelude() {
+    for program in [
+        "X = malloc(8)",
+        "X = *Y",
+        "X = Y[8]",
+        "function f(x)\n  return *x\nendfunction\n",
+        "while *X\nendwhile\n",
+        "record R\n  x of Pointer\nendrecord\n",
+    ] {
+        let table = parse_with_prelude(program).unwrap();
+        match type_check(&table) {
+            Err(TyCheckError::ConstraintGatheringError(
+                ConstraintGatheringError::PrivilegedOperation { .. },
+            )) => {}
+            other => panic!("expected a privilege error for `{program}`, but got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn cannot_shadow_prelude() {
+    let table = parse_with_prelude("function array_new(x)\n  return x\nendfunction\n").unwrap();
+    assert!(matches!(
+        type_check(&table),
+        Err(TyCheckError::ConstraintGatheringError(
+            ConstraintGatheringError::ShadowsPrelude { .. }
+        ))
+    ));
+}
+
+#[test]
+fn extern_call() {
+    let table = parse_with_prelude(
+        "extern function labs(x: Int) -> Int\nfunction main()\n  X = labs(-1)\n  return X\nendfunction\n",
+    )
+    .unwrap();
+    type_check(&table).unwrap();
+
+    let table =
+        parse_with_prelude("extern function labs(x: Int) -> Int\nX = labs(True)\n").unwrap();
+    assert!(type_check(&table).is_err());
+
+    let table = parse_with_prelude("extern function free(x: Int) -> Int\n").unwrap();
+    assert!(type_check(&table).is_err());
+}
+
+#[test]
+fn polymorphic_print() {
+    let table = parse(
+        "X = 1.5\nprint(\"Total: \" + str(X), X, 2, True)\nprint()\nY = str(False) + \"!\"\n",
+    )
+    .unwrap();
+    let env = type_check(&table).unwrap();
+    let expr = table.get(&table.root.1.inner[3]).unwrap();
+    let (_, lhs, _) = expr.as_expr().unwrap().as_bin_op().unwrap();
+    assert_eq!(
+        env.ty_of(lhs.id),
+        Some(Ty::PrimitiveType(PrimitiveType::StrSlice))
+    );
+
+    let table = parse("X = \"a\" + 1").unwrap();
+    assert!(type_check(&table).is_err());
+}
+
+#[test]
+fn unary_plus_and_minus_only_apply_to_numbers() {
+    for program in ["X = -1", "X = -1.5", "Y = 2\nX = +Y"] {
+        let table = parse(program).unwrap();
+        type_check(&table).unwrap();
+    }
+
+    for program in ["X = -True", "X = +\"a\""] {
+        let table = parse(program).unwrap();
+        match type_check(&table) {
+            Err(TyCheckError::UnsupportedType { .. }) => {}
+            other => panic!("expected `{program}` not to type check, but got {other:?}"),
+        }
+    }
+}
+
+#[test]
+fn generic_builtins_only_accept_supported_types() {
+    for program in [
+        "X = abs(-1)",
+        "X = pow(2.0, 0.5)",
+        "X = str(True)",
+        "X = min(Y, 2)",
+    ] {
+        let table = parse(program).unwrap();
+        type_check(&table).unwrap();
+    }
+
+    for program in [
+        "X = abs(True)",
+        "X = min(\"a\", \"b\")",
+        "Y = False\nX = random(Y, Y)",
+        "record R\n  x of Int\nendrecord\nX = abs(R { x: 1 })\n",
+    ] {
+        let table = parse(program).unwrap();
+        match type_check(&table) {
+            Err(TyCheckError::UnsupportedType { .. }) => {}
+            other => panic!("expected `{program}` not to type check, but got {other:?}"),
+        }
+    }
+}
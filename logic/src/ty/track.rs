@@ -64,7 +64,10 @@ use codespan_reporting::diagnostic::{Diagnostic, Label};
 use rustc_hash::FxHashMap;
 
 use crate::{
-    diagnostics::span::{HasSpan, Spanned},
+    diagnostics::{
+        self,
+        span::{HasSpan, Spanned},
+    },
     parse::table::{Id, ParseTable},
 };
 
@@ -227,6 +230,8 @@ impl UnificationOperation {
                 }
             },
             (UnificationOperation::Swap { .. }, ConstraintInner::TyToTy { .. }) => unreachable!(),
+            // these are never unified
+            (_, ConstraintInner::IdToOneOf { .. }) => unreachable!(),
         }
     }
 }
@@ -302,9 +307,10 @@ impl ErrorReporter {
                         ),
                     );
                 }
+                ConstraintInner::IdToOneOf { .. } => unreachable!(),
             }
         }
 
-        diagnostic
+        diagnostics::prelude_labels_to_notes(diagnostic)
     }
 }
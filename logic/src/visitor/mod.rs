@@ -3,6 +3,7 @@ use crate::parse::{
     expr::Expr,
     func::{Func, Return},
     ident::Ident,
+    r#extern::Extern,
     r#for::ForLoop,
     r#if::If,
     r#while::While,
@@ -46,6 +47,8 @@ pub trait Visitor<'i> {
 
     fn visit_func(&mut self, func: &'i Func, table: &'i ParseTable<'i>) -> Self::Output;
 
+    fn visit_extern(&mut self, ext: &'i Extern<'i>, table: &'i ParseTable<'i>) -> Self::Output;
+
     fn visit_ident(&mut self, ident: &'i Ident<'i>, table: &'i ParseTable<'i>) -> Self::Output;
 
     fn visit_block(&mut self, block: &'i Block, table: &'i ParseTable<'i>) -> Vec<Self::Output> {
@@ -65,6 +68,7 @@ pub trait Visitor<'i> {
                 }
                 crate::parse::table::Item::Func(func) => self.visit_func(func, table),
                 crate::parse::table::Item::Return(ret) => self.visit_ret(ret, table),
+                crate::parse::table::Item::Extern(ext) => self.visit_extern(ext, table),
             };
             output.push(n)
         }
@@ -115,6 +119,12 @@ pub trait IdVisitor<'i> {
 
     fn visit_func(&mut self, func: WithId<&'i Func>, table: &'i ParseTable<'i>) -> Self::Output;
 
+    fn visit_extern(
+        &mut self,
+        ext: WithId<&'i Extern<'i>>,
+        table: &'i ParseTable<'i>,
+    ) -> Self::Output;
+
     fn visit_ident(
         &mut self,
         ident: WithId<&'i Ident<'i>>,
@@ -196,6 +206,13 @@ pub trait IdVisitor<'i> {
                     },
                     table,
                 ),
+                crate::parse::table::Item::Extern(ext) => self.visit_extern(
+                    WithId {
+                        inner: ext,
+                        id: item.id,
+                    },
+                    table,
+                ),
             };
             output.push(n)
         }
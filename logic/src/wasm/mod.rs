@@ -0,0 +1,618 @@
+//! A backend which compiles the MIR to a standalone WebAssembly module (so
+//! that programs can be run in a web browser, e.g. by the online playground).
+//!
+//! The module only depends on a handful of functions provided by the host,
+//! which are imported from the `env` module
+//!
+//! - `print(address: i32, length: i32)` writes `length` bytes of UTF-8 text
+//!   (starting at `address` in the module's memory) to the output
+//! - `input(address: i32, capacity: i32) -> i32` reads a line of input
+//!   (without the line ending), writes at most `capacity` bytes of it to
+//!   `address` and returns the number of bytes written
+//! - `format_real(value: f64, address: i32) -> i32` writes the textual
+//!   representation of a real number (in the same format as the compiler's
+//!   runtime, e.g. `1.0` or `2.5e-7`) to `address`, returning its length (at
+//!   most 32 bytes)
+//! - `pow(base: f64, exponent: f64) -> f64` (e.g. `Math.pow`)
+//!
+//! Everything else (memory allocation, string building, random numbers and
+//! so on) is implemented inside the module itself (see [`runtime`]). The
+//! module exports its `memory`, the program's `main` function and a
+//! `seed(seed: i64)` function which can be called before `main` to seed the
+//! random number generator.
+//!
+//! Memory is laid out as follows
+//!
+//! ```ignore
+//!   ┌───┬────────────────┬──────────────────┬─────────────────────────┐
+//!   │   │ constant data  │ stack (records)  │ heap (grows as needed)… │
+//!   └───┴────────────────┴──────────────────┴─────────────────────────┘
+//!   0   8
+//! ```
+//!
+//! (the first few bytes are unused, so that no valid pointer is ever zero).
+//! Integers are 64 bits wide, but pointers (including strings and records)
+//! are 32-bit addresses into the module's memory.
+//!
+//! WebAssembly only has structured control flow, so each function is compiled
+//! to a loop containing a `br_table` which jumps to the current basic block;
+//! at the end of each block we store the number of the next block in a local
+//! and jump back to the start of the loop.
+
+mod runtime;
+#[cfg(test)]
+mod test;
+
+use std::{borrow::Cow, fmt};
+
+use rustc_hash::FxHashMap;
+use wasm_encoder::{
+    BlockType, CodeSection, ConstExpr, DataSection, EntityType, ExportKind, ExportSection,
+    Function as WasmFunction, FunctionSection, GlobalSection, GlobalType, ImportSection,
+    Instruction, MemArg, MemorySection, MemoryType, Module, TypeSection, ValType,
+};
+
+use crate::mir::{
+    BinaryOp, Callee, Constant, Function, Local, Operand, Program, Rvalue, Statement, Terminator,
+    Type, UnaryOp,
+};
+
+use self::runtime::{HELPERS, IMPORTS, STRINGS};
+
+/// Constant data is stored from this address onwards.
+const DATA_START: u32 = 8;
+/// The amount of memory (in bytes) reserved for records.
+const STACK_SIZE: u32 = 1 << 20;
+const PAGE_SIZE: u32 = 1 << 16;
+
+// the indices of the module's global variables
+const STACK_POINTER: u32 = 0;
+const HEAP: u32 = 1;
+const RANDOM_STATE: u32 = 2;
+
+/// The reason why a program could not be compiled to WebAssembly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WasmError {
+    pub message: String,
+}
+
+impl WasmError {
+    fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for WasmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Compiles the program to a WebAssembly module (returning its binary
+/// encoding).
+///
+/// `seed` is used to seed the random number generator, in the same way as
+/// [`crate::codegen::CodegenOptions::seed`], except that if no seed is
+/// provided the host should call `seed` itself (as the module cannot find
+/// out the current time).
+pub fn compile(program: &Program, seed: Option<u64>) -> Result<Vec<u8>, WasmError> {
+    if program.function("main").is_none() {
+        return Err(WasmError::new(
+            "Your program does not have a `main` function.",
+        ));
+    }
+
+    let symbols = Symbols::new(program);
+
+    let mut types = TypeSection::new();
+    let mut imports = ImportSection::new();
+    let mut functions = FunctionSection::new();
+    let mut code = CodeSection::new();
+    let mut next_type = 0;
+    let mut declare_type = |params: &[ValType], results: &[ValType]| {
+        types.function(params.iter().copied(), results.iter().copied());
+        next_type += 1;
+        next_type - 1
+    };
+
+    for import in IMPORTS {
+        let ty = declare_type(import.params, import.results);
+        imports.import("env", import.name, EntityType::Function(ty));
+    }
+    for helper in HELPERS {
+        functions.function(declare_type(helper.params, helper.results));
+        let mut body = WasmFunction::new(helper.locals.iter().map(|local| (1, *local)));
+        for instruction in (helper.body)(&symbols) {
+            body.instruction(&instruction);
+        }
+        body.instruction(&Instruction::End);
+        code.function(&body);
+    }
+    for function in &program.functions {
+        let params = function
+            .params
+            .iter()
+            .map(|param| val_type(function.local_ty(*param)))
+            .collect::<Vec<_>>();
+        functions.function(declare_type(&params, &[val_type(function.returns)]));
+        code.function(&FunctionCompiler::new(function, &symbols).compile()?);
+    }
+
+    let mut memories = MemorySection::new();
+    memories.memory(MemoryType {
+        minimum: u64::from((symbols.heap_start + PAGE_SIZE - 1) / PAGE_SIZE),
+        maximum: None,
+        memory64: false,
+        shared: false,
+    });
+
+    let mut globals = GlobalSection::new();
+    let mut global = |val_type, init: &ConstExpr| {
+        globals.global(
+            GlobalType {
+                val_type,
+                mutable: true,
+            },
+            init,
+        );
+    };
+    global(
+        ValType::I32,
+        &ConstExpr::i32_const(symbols.stack_start as i32),
+    );
+    global(
+        ValType::I32,
+        &ConstExpr::i32_const(symbols.heap_start as i32),
+    );
+    global(
+        ValType::I64,
+        &ConstExpr::i64_const(seed.unwrap_or(0) as i64),
+    );
+
+    let mut exports = ExportSection::new();
+    exports.export("memory", ExportKind::Memory, 0);
+    exports.export("main", ExportKind::Func, symbols.functions["main"]);
+    exports.export("seed", ExportKind::Func, symbols.runtime["seed"]);
+
+    let mut data = DataSection::new();
+    data.active(
+        0,
+        &ConstExpr::i32_const(DATA_START as i32),
+        symbols.data.iter().copied(),
+    );
+
+    let mut module = Module::new();
+    module
+        .section(&types)
+        .section(&imports)
+        .section(&functions)
+        .section(&memories)
+        .section(&globals)
+        .section(&exports)
+        .section(&code)
+        .section(&data);
+    Ok(module.finish())
+}
+
+/// The index of every function in the module, and the address of every
+/// constant string.
+struct Symbols<'p> {
+    /// The functions which the host provides.
+    imports: FxHashMap<&'static str, u32>,
+    /// The functions which implement the builtins.
+    runtime: FxHashMap<&'static str, u32>,
+    /// The functions defined by the program.
+    functions: FxHashMap<&'p str, u32>,
+    strings: FxHashMap<&'p str, u32>,
+    /// The contents of the data segment (which is placed at [`DATA_START`]).
+    data: Vec<u8>,
+    stack_start: u32,
+    heap_start: u32,
+}
+
+impl<'p> Symbols<'p> {
+    fn new(program: &'p Program) -> Self {
+        let imports = IMPORTS
+            .iter()
+            .map(|import| import.name)
+            .zip(0..)
+            .collect::<FxHashMap<_, _>>();
+        let runtime = HELPERS
+            .iter()
+            .map(|helper| helper.name)
+            .zip(imports.len() as u32..)
+            .collect::<FxHashMap<_, _>>();
+        let functions = program
+            .functions
+            .iter()
+            .map(|function| function.name.as_str())
+            .zip((imports.len() + runtime.len()) as u32..)
+            .collect();
+
+        let mut symbols = Self {
+            imports,
+            runtime,
+            functions,
+            strings: FxHashMap::default(),
+            data: vec![],
+            stack_start: 0,
+            heap_start: 0,
+        };
+        for string in STRINGS {
+            symbols.add_string(string);
+        }
+        for function in &program.functions {
+            for block in &function.blocks {
+                let operands = block
+                    .statements
+                    .iter()
+                    .flat_map(Statement::operands)
+                    .chain(block.terminator.operand());
+                for operand in operands {
+                    if let Operand::Const(Constant::Str(string)) = operand {
+                        symbols.add_string(string);
+                    }
+                }
+            }
+        }
+
+        symbols.stack_start = align(DATA_START + symbols.data.len() as u32);
+        symbols.heap_start = symbols.stack_start + STACK_SIZE;
+        symbols
+    }
+
+    /// Adds a (null-terminated) string to the data segment.
+    fn add_string(&mut self, string: &'p str) {
+        if !self.strings.contains_key(string) {
+            let address = DATA_START + self.data.len() as u32;
+            self.strings.insert(string, address);
+            self.data.extend(string.as_bytes());
+            self.data.push(0);
+        }
+    }
+
+    /// Calls the runtime function with the given name.
+    fn call(&self, name: &str) -> Instruction<'static> {
+        Instruction::Call(self.runtime[name])
+    }
+
+    /// Calls the function with the given name which the host provides.
+    fn import(&self, name: &str) -> Instruction<'static> {
+        Instruction::Call(self.imports[name])
+    }
+
+    /// Produces the address of a string which is stored in the data segment.
+    fn string(&self, string: &str) -> Instruction<'static> {
+        Instruction::I32Const(self.strings[string] as i32)
+    }
+}
+
+/// Rounds up to a multiple of eight bytes.
+fn align(address: u32) -> u32 {
+    (address + 7) & !7
+}
+
+fn val_type(ty: Type) -> ValType {
+    match ty {
+        Type::Int => ValType::I64,
+        Type::Real => ValType::F64,
+        Type::Bool | Type::Str | Type::Pointer | Type::Record => ValType::I32,
+    }
+}
+
+fn mem_arg(offset: u32, ty: ValType) -> MemArg {
+    MemArg {
+        offset: u64::from(offset),
+        align: if ty == ValType::I32 { 2 } else { 3 },
+        memory_index: 0,
+    }
+}
+
+/// Compiles a single function.
+struct FunctionCompiler<'f, 'p> {
+    function: &'f Function,
+    symbols: &'f Symbols<'p>,
+    /// The index of the WebAssembly local which holds each MIR local (the
+    /// parameters have to come first).
+    locals: Vec<u32>,
+    /// Holds the number of the block which should be run next.
+    next_block: u32,
+    /// Holds the value of the stack pointer when the function was entered
+    /// (this is restored when it returns, which frees any records which it
+    /// created).
+    frame: u32,
+    /// Holds the address of the record which is being built.
+    record: u32,
+    instructions: Vec<Instruction<'static>>,
+}
+
+impl<'f, 'p> FunctionCompiler<'f, 'p> {
+    fn new(function: &'f Function, symbols: &'f Symbols<'p>) -> Self {
+        let mut locals = vec![0; function.locals.len()];
+        let order = function.params.iter().copied().chain(
+            (0..function.locals.len() as u32)
+                .map(Local)
+                .filter(|local| !function.params.contains(local)),
+        );
+        for (index, local) in order.enumerate() {
+            locals[local.0 as usize] = index as u32;
+        }
+
+        let count = function.locals.len() as u32;
+        Self {
+            function,
+            symbols,
+            locals,
+            next_block: count,
+            frame: count + 1,
+            record: count + 2,
+            instructions: vec![],
+        }
+    }
+
+    fn compile(mut self) -> Result<WasmFunction, WasmError> {
+        let blocks = self.function.blocks.len() as u32;
+
+        self.push(Instruction::GlobalGet(STACK_POINTER));
+        self.push(Instruction::LocalSet(self.frame));
+        self.push(Instruction::Loop(BlockType::Empty));
+        for _ in 0..blocks {
+            self.push(Instruction::Block(BlockType::Empty));
+        }
+        self.push(Instruction::LocalGet(self.next_block));
+        self.push(Instruction::BrTable(Cow::Owned((0..blocks).collect()), 0));
+        for (index, block) in self.function.blocks.iter().enumerate() {
+            self.push(Instruction::End);
+            for statement in &block.statements {
+                self.compile_statement(statement)?;
+            }
+            // the number of blocks which are still open (the loop is just
+            // outside them)
+            let depth = blocks - 1 - index as u32;
+            self.compile_terminator(&block.terminator, depth);
+        }
+        self.push(Instruction::End);
+        self.push(Instruction::Unreachable);
+        self.push(Instruction::End);
+
+        let params = self.function.params.len();
+        let mut locals = vec![0; self.function.locals.len() - params];
+        for (local, index) in self.locals.iter().enumerate() {
+            if let Some(index) = index.checked_sub(params as u32) {
+                locals[index as usize] = local;
+            }
+        }
+        let locals = locals
+            .into_iter()
+            .map(|local| (1, val_type(self.function.locals[local].ty)))
+            // `next_block`, `frame` and `record`
+            .chain([(3, ValType::I32)])
+            .collect::<Vec<_>>();
+        let mut function = WasmFunction::new(locals);
+        for instruction in &self.instructions {
+            function.instruction(instruction);
+        }
+        Ok(function)
+    }
+
+    fn push(&mut self, instruction: Instruction<'static>) {
+        self.instructions.push(instruction);
+    }
+
+    fn compile_statement(&mut self, statement: &Statement) -> Result<(), WasmError> {
+        match statement {
+            Statement::Assign(local, rvalue) => {
+                self.compile_rvalue(rvalue, Some(self.function.local_ty(*local)))?;
+                self.push(Instruction::LocalSet(self.locals[local.0 as usize]));
+            }
+            Statement::Eval(rvalue) => self.compile_rvalue(rvalue, None)?,
+            Statement::Store {
+                address,
+                offset,
+                value,
+            } => {
+                self.compile_operand(address);
+                let offset = self.compile_offset(*offset);
+                self.compile_store(value, offset);
+            }
+            Statement::Location(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Compiles the end of a block (`depth` is the number of `block`s between
+    /// the current block and the dispatch loop).
+    fn compile_terminator(&mut self, terminator: &Terminator, depth: u32) {
+        match terminator {
+            Terminator::Jump(target) => {
+                self.push(Instruction::I32Const(target.0 as i32));
+                self.push(Instruction::LocalSet(self.next_block));
+                self.push(Instruction::Br(depth));
+            }
+            Terminator::Branch {
+                condition,
+                then,
+                otherwise,
+            } => {
+                self.push(Instruction::I32Const(then.0 as i32));
+                self.push(Instruction::I32Const(otherwise.0 as i32));
+                self.compile_operand(condition);
+                self.push(Instruction::Select);
+                self.push(Instruction::LocalSet(self.next_block));
+                self.push(Instruction::Br(depth));
+            }
+            Terminator::Return(value) => {
+                self.push(Instruction::LocalGet(self.frame));
+                self.push(Instruction::GlobalSet(STACK_POINTER));
+                self.compile_operand(value);
+                self.push(Instruction::Return);
+            }
+            Terminator::Unreachable => self.push(Instruction::Unreachable),
+        }
+    }
+
+    fn compile_operand(&mut self, operand: &Operand) {
+        let instruction = match operand {
+            Operand::Local(local) => Instruction::LocalGet(self.locals[local.0 as usize]),
+            Operand::Const(Constant::Int(int)) => Instruction::I64Const(*int),
+            Operand::Const(Constant::Real(real)) => Instruction::F64Const(*real),
+            Operand::Const(Constant::Bool(boolean)) => Instruction::I32Const(*boolean as i32),
+            Operand::Const(Constant::Str(string)) => self.symbols.string(string),
+        };
+        self.push(instruction);
+    }
+
+    /// Prepares to access memory at `offset` bytes from the address on top of
+    /// the stack, returning the offset which should be used in the load (or
+    /// store) instruction (these must be positive).
+    fn compile_offset(&mut self, offset: i32) -> u32 {
+        u32::try_from(offset).unwrap_or_else(|_| {
+            self.push(Instruction::I32Const(offset));
+            self.push(Instruction::I32Add);
+            0
+        })
+    }
+
+    /// Stores the value at the address (and offset) on top of the stack.
+    fn compile_store(&mut self, value: &Operand, offset: u32) {
+        self.compile_operand(value);
+        let ty = val_type(self.function.operand_ty(value));
+        self.push(match ty {
+            ValType::I64 => Instruction::I64Store(mem_arg(offset, ty)),
+            ValType::F64 => Instruction::F64Store(mem_arg(offset, ty)),
+            _ => Instruction::I32Store(mem_arg(offset, ty)),
+        });
+    }
+
+    /// Compiles the rvalue, whose result has the type `ty` (this is `None` if
+    /// the result is not used, in which case nothing is left on the stack).
+    fn compile_rvalue(&mut self, rvalue: &Rvalue, ty: Option<Type>) -> Result<(), WasmError> {
+        match rvalue {
+            Rvalue::Use(operand) => self.compile_operand(operand),
+            Rvalue::Binary(BinaryOp::Concat, left, right) => {
+                self.compile_operand(left);
+                self.compile_operand(right);
+                self.push(self.symbols.call("string_concat"));
+            }
+            Rvalue::Binary(op, left, right) => {
+                let operand_ty = self.function.operand_ty(left);
+                self.compile_operand(left);
+                self.compile_operand(right);
+                if *op == BinaryOp::Offset {
+                    self.push(Instruction::I32WrapI64);
+                }
+                self.push(match (op, val_type(operand_ty)) {
+                    (BinaryOp::Add, ValType::F64) => Instruction::F64Add,
+                    (BinaryOp::Subtract, ValType::F64) => Instruction::F64Sub,
+                    (BinaryOp::Multiply, ValType::F64) => Instruction::F64Mul,
+                    (BinaryOp::Divide, ValType::F64) => Instruction::F64Div,
+                    (BinaryOp::Equal, ValType::F64) => Instruction::F64Eq,
+                    (BinaryOp::NotEqual, ValType::F64) => Instruction::F64Ne,
+                    (BinaryOp::Add, ValType::I64) => Instruction::I64Add,
+                    (BinaryOp::Subtract, ValType::I64) => Instruction::I64Sub,
+                    (BinaryOp::Multiply, ValType::I64) => Instruction::I64Mul,
+                    (BinaryOp::Divide, ValType::I64) => Instruction::I64DivS,
+                    (BinaryOp::Equal, ValType::I64) => Instruction::I64Eq,
+                    (BinaryOp::NotEqual, ValType::I64) => Instruction::I64Ne,
+                    (BinaryOp::LessThan, ValType::I64) => Instruction::I64LtS,
+                    // note: like the Cranelift backend, this compares strings
+                    // by address
+                    (BinaryOp::Equal, _) => Instruction::I32Eq,
+                    (BinaryOp::NotEqual, _) => Instruction::I32Ne,
+                    (BinaryOp::Offset, _) => Instruction::I32Add,
+                    (op, ty) => unreachable!("cannot apply `{op}` to values of type {ty:?}"),
+                });
+            }
+            Rvalue::Unary(UnaryOp::Negate, operand) => {
+                if self.function.operand_ty(operand) == Type::Real {
+                    self.compile_operand(operand);
+                    self.push(Instruction::F64Neg);
+                } else {
+                    self.push(Instruction::I64Const(0));
+                    self.compile_operand(operand);
+                    self.push(Instruction::I64Sub);
+                }
+            }
+            Rvalue::Call(Callee::Function(name), args) => {
+                for arg in args {
+                    self.compile_operand(arg);
+                }
+                self.push(Instruction::Call(self.symbols.functions[name.as_str()]));
+            }
+            Rvalue::Call(Callee::Native(symbol), args) => {
+                let helper = HELPERS
+                    .iter()
+                    .find(|helper| helper.name == symbol)
+                    .ok_or_else(|| {
+                        WasmError::new(format!(
+                            "The function `{symbol}` is implemented natively, so it cannot be \
+                             compiled to WebAssembly."
+                        ))
+                    })?;
+                for arg in args {
+                    self.compile_operand(arg);
+                }
+                self.push(self.symbols.call(symbol));
+                // some runtime functions (e.g. the printing functions) do not
+                // return anything, but calls to them can still be assigned
+                // to variables
+                if helper.results.is_empty() {
+                    if let Some(ty) = ty {
+                        self.push(zero(ty));
+                    }
+                    return Ok(());
+                }
+            }
+            Rvalue::Record { size, fields } => {
+                self.push(Instruction::GlobalGet(STACK_POINTER));
+                self.push(Instruction::LocalTee(self.record));
+                self.push(Instruction::I32Const(align(*size) as i32));
+                self.push(Instruction::I32Add);
+                self.push(Instruction::GlobalSet(STACK_POINTER));
+                // check that there is enough space for the record
+                self.push(Instruction::GlobalGet(STACK_POINTER));
+                self.push(Instruction::I32Const(self.symbols.heap_start as i32));
+                self.push(Instruction::I32GtU);
+                self.push(Instruction::If(BlockType::Empty));
+                self.push(Instruction::Unreachable);
+                self.push(Instruction::End);
+                for (offset, value) in fields {
+                    self.push(Instruction::LocalGet(self.record));
+                    let offset = self.compile_offset(*offset);
+                    self.compile_store(value, offset);
+                }
+                self.push(Instruction::LocalGet(self.record));
+            }
+            Rvalue::Load {
+                ty: loaded,
+                address,
+                offset,
+            } => {
+                self.compile_operand(address);
+                let offset = self.compile_offset(*offset);
+                let loaded = val_type(*loaded);
+                self.push(match loaded {
+                    ValType::I64 => Instruction::I64Load(mem_arg(offset, loaded)),
+                    ValType::F64 => Instruction::F64Load(mem_arg(offset, loaded)),
+                    _ => Instruction::I32Load(mem_arg(offset, loaded)),
+                });
+            }
+        }
+
+        if ty.is_none() {
+            self.push(Instruction::Drop);
+        }
+        Ok(())
+    }
+}
+
+/// Produces the "zero" value of the type.
+fn zero(ty: Type) -> Instruction<'static> {
+    match val_type(ty) {
+        ValType::I64 => Instruction::I64Const(0),
+        ValType::F64 => Instruction::F64Const(0.0),
+        _ => Instruction::I32Const(0),
+    }
+}
@@ -0,0 +1,763 @@
+//! The runtime library for WebAssembly modules.
+//!
+//! These functions implement the builtins (in the same way as the native
+//! runtime in `crate::runtime`), but are written directly in WebAssembly so
+//! that they can be included in every module. Booleans are 32-bit integers
+//! and strings are addresses of null-terminated UTF-8 data.
+//!
+//! note: memory is never freed; `free` does nothing (and the allocator just
+//! hands out the next unused part of the heap)
+
+use wasm_encoder::{
+    BlockType, Instruction,
+    Instruction::*,
+    MemArg,
+    ValType::{self, F64, I32, I64},
+};
+
+use super::{Symbols, HEAP, RANDOM_STATE};
+
+/// A function which the host provides.
+pub(super) struct Import {
+    pub name: &'static str,
+    pub params: &'static [ValType],
+    pub results: &'static [ValType],
+}
+
+/// A function which is defined in every module.
+pub(super) struct Helper {
+    pub name: &'static str,
+    pub params: &'static [ValType],
+    pub results: &'static [ValType],
+    /// The types of the locals used by the function (other than its
+    /// parameters).
+    pub locals: &'static [ValType],
+    /// Produces the body of the function (without the final `end`).
+    pub body: fn(&Symbols) -> Vec<Instruction<'static>>,
+}
+
+/// The strings which the runtime uses.
+pub(super) static STRINGS: &[&str] = &["True", "False", " ", "\n"];
+
+/// The maximum length (in bytes) of a line of input.
+const INPUT_CAPACITY: i32 = 4096;
+
+/// The size of the buffer passed to `format_real` (the longest possible
+/// real number is 24 bytes long).
+const REAL_CAPACITY: i64 = 32;
+
+pub(super) static IMPORTS: &[Import] = &[
+    Import {
+        name: "print",
+        params: &[I32, I32],
+        results: &[],
+    },
+    Import {
+        name: "input",
+        params: &[I32, I32],
+        results: &[I32],
+    },
+    Import {
+        name: "format_real",
+        params: &[F64, I32],
+        results: &[I32],
+    },
+    Import {
+        name: "pow",
+        params: &[F64, F64],
+        results: &[F64],
+    },
+];
+
+fn byte() -> MemArg {
+    MemArg {
+        offset: 0,
+        align: 0,
+        memory_index: 0,
+    }
+}
+
+/// Traps if the condition (on top of the stack) is true.
+fn trap_if() -> [Instruction<'static>; 3] {
+    [If(BlockType::Empty), Unreachable, End]
+}
+
+pub(super) static HELPERS: &[Helper] = &[
+    // memory management
+    Helper {
+        name: "malloc",
+        params: &[I64],
+        results: &[I32],
+        locals: &[I32],
+        body: |_| {
+            let mut body = vec![LocalGet(0), I64Const(i32::MAX as i64), I64GtU];
+            body.extend(trap_if());
+            body.extend([
+                GlobalGet(HEAP),
+                LocalSet(1),
+                // round the size up to a multiple of eight bytes
+                GlobalGet(HEAP),
+                LocalGet(0),
+                I32WrapI64,
+                I32Const(7),
+                I32Add,
+                I32Const(-8),
+                I32And,
+                I32Add,
+                GlobalSet(HEAP),
+                // grow the memory if the heap no longer fits
+                GlobalGet(HEAP),
+                MemorySize(0),
+                I32Const(16),
+                I32Shl,
+                I32GtU,
+                If(BlockType::Empty),
+                GlobalGet(HEAP),
+                MemorySize(0),
+                I32Const(16),
+                I32Shl,
+                I32Sub,
+                I32Const(0xffff),
+                I32Add,
+                I32Const(16),
+                I32ShrU,
+                MemoryGrow(0),
+                I32Const(-1),
+                I32Eq,
+            ]);
+            body.extend(trap_if());
+            body.extend([End, LocalGet(1)]);
+            body
+        },
+    },
+    Helper {
+        name: "realloc",
+        params: &[I32, I64],
+        results: &[I32],
+        locals: &[I32],
+        body: |symbols| {
+            vec![
+                LocalGet(1),
+                symbols.call("malloc"),
+                LocalSet(2),
+                // this may copy some bytes from past the end of the old
+                // allocation, but they are always inside the heap
+                LocalGet(2),
+                LocalGet(0),
+                LocalGet(1),
+                I32WrapI64,
+                MemoryCopy {
+                    src_mem: 0,
+                    dst_mem: 0,
+                },
+                LocalGet(2),
+            ]
+        },
+    },
+    Helper {
+        name: "free",
+        params: &[I32],
+        results: &[],
+        locals: &[],
+        body: |_| vec![],
+    },
+    // strings
+    Helper {
+        name: "strlen",
+        params: &[I32],
+        results: &[I32],
+        locals: &[I32],
+        body: |_| {
+            vec![
+                LocalGet(0),
+                LocalSet(1),
+                Block(BlockType::Empty),
+                Loop(BlockType::Empty),
+                LocalGet(1),
+                I32Load8U(byte()),
+                I32Eqz,
+                BrIf(1),
+                LocalGet(1),
+                I32Const(1),
+                I32Add,
+                LocalSet(1),
+                Br(0),
+                End,
+                End,
+                LocalGet(1),
+                LocalGet(0),
+                I32Sub,
+            ]
+        },
+    },
+    Helper {
+        name: "string_concat",
+        params: &[I32, I32],
+        results: &[I32],
+        locals: &[I32, I32, I32],
+        body: |symbols| {
+            let copy = MemoryCopy {
+                src_mem: 0,
+                dst_mem: 0,
+            };
+            vec![
+                LocalGet(0),
+                symbols.call("strlen"),
+                LocalSet(2),
+                LocalGet(1),
+                symbols.call("strlen"),
+                LocalSet(3),
+                LocalGet(2),
+                LocalGet(3),
+                I32Add,
+                I32Const(1),
+                I32Add,
+                I64ExtendI32U,
+                symbols.call("malloc"),
+                LocalSet(4),
+                LocalGet(4),
+                LocalGet(0),
+                LocalGet(2),
+                copy.clone(),
+                LocalGet(4),
+                LocalGet(2),
+                I32Add,
+                LocalGet(1),
+                LocalGet(3),
+                copy,
+                // the null terminator
+                LocalGet(4),
+                LocalGet(2),
+                I32Add,
+                LocalGet(3),
+                I32Add,
+                I32Const(0),
+                I32Store8(byte()),
+                LocalGet(4),
+            ]
+        },
+    },
+    Helper {
+        name: "str_string",
+        params: &[I32],
+        results: &[I32],
+        locals: &[],
+        body: |_| vec![LocalGet(0)],
+    },
+    Helper {
+        name: "str_int",
+        params: &[I64],
+        results: &[I32],
+        locals: &[I32, I64, I32],
+        body: |symbols| {
+            vec![
+                // the digits are written backwards from the end of the buffer
+                I64Const(24),
+                symbols.call("malloc"),
+                I32Const(23),
+                I32Add,
+                LocalTee(1),
+                I32Const(0),
+                I32Store8(byte()),
+                LocalGet(0),
+                I64Const(0),
+                I64LtS,
+                LocalSet(3),
+                // the magnitude (as an unsigned number, so that this also
+                // works for the smallest integer)
+                I64Const(0),
+                LocalGet(0),
+                I64Sub,
+                LocalGet(0),
+                LocalGet(3),
+                Select,
+                LocalSet(2),
+                Loop(BlockType::Empty),
+                LocalGet(1),
+                I32Const(1),
+                I32Sub,
+                LocalTee(1),
+                LocalGet(2),
+                I64Const(10),
+                I64RemU,
+                I32WrapI64,
+                I32Const(b'0' as i32),
+                I32Add,
+                I32Store8(byte()),
+                LocalGet(2),
+                I64Const(10),
+                I64DivU,
+                LocalTee(2),
+                I64Const(0),
+                I64Ne,
+                BrIf(0),
+                End,
+                LocalGet(3),
+                If(BlockType::Empty),
+                LocalGet(1),
+                I32Const(1),
+                I32Sub,
+                LocalTee(1),
+                I32Const(b'-' as i32),
+                I32Store8(byte()),
+                End,
+                LocalGet(1),
+            ]
+        },
+    },
+    Helper {
+        name: "str_bool",
+        params: &[I32],
+        results: &[I32],
+        locals: &[],
+        body: |symbols| {
+            vec![
+                symbols.string("True"),
+                symbols.string("False"),
+                LocalGet(0),
+                Select,
+            ]
+        },
+    },
+    Helper {
+        name: "str_real",
+        params: &[F64],
+        results: &[I32],
+        locals: &[I32],
+        body: |symbols| {
+            vec![
+                I64Const(REAL_CAPACITY),
+                symbols.call("malloc"),
+                LocalSet(1),
+                LocalGet(1),
+                LocalGet(0),
+                LocalGet(1),
+                symbols.import("format_real"),
+                I32Add,
+                I32Const(0),
+                I32Store8(byte()),
+                LocalGet(1),
+            ]
+        },
+    },
+    Helper {
+        name: "input",
+        params: &[],
+        results: &[I32],
+        locals: &[I32],
+        body: |symbols| {
+            vec![
+                I64Const(INPUT_CAPACITY as i64 + 1),
+                symbols.call("malloc"),
+                LocalSet(0),
+                LocalGet(0),
+                LocalGet(0),
+                I32Const(INPUT_CAPACITY),
+                symbols.import("input"),
+                I32Add,
+                I32Const(0),
+                I32Store8(byte()),
+                LocalGet(0),
+            ]
+        },
+    },
+    // printing
+    Helper {
+        name: "write_string",
+        params: &[I32],
+        results: &[],
+        locals: &[],
+        body: |symbols| {
+            vec![
+                LocalGet(0),
+                LocalGet(0),
+                symbols.call("strlen"),
+                symbols.import("print"),
+            ]
+        },
+    },
+    Helper {
+        name: "write_int",
+        params: &[I64],
+        results: &[],
+        locals: &[],
+        body: |symbols| {
+            vec![
+                LocalGet(0),
+                symbols.call("str_int"),
+                symbols.call("write_string"),
+            ]
+        },
+    },
+    Helper {
+        name: "write_bool",
+        params: &[I32],
+        results: &[],
+        locals: &[],
+        body: |symbols| {
+            vec![
+                LocalGet(0),
+                symbols.call("str_bool"),
+                symbols.call("write_string"),
+            ]
+        },
+    },
+    Helper {
+        name: "write_real",
+        params: &[F64],
+        results: &[],
+        locals: &[],
+        body: |symbols| {
+            vec![
+                LocalGet(0),
+                symbols.call("str_real"),
+                symbols.call("write_string"),
+            ]
+        },
+    },
+    Helper {
+        name: "write_space",
+        params: &[],
+        results: &[],
+        locals: &[],
+        body: |symbols| vec![symbols.string(" "), I32Const(1), symbols.import("print")],
+    },
+    Helper {
+        name: "write_newline",
+        params: &[],
+        results: &[],
+        locals: &[],
+        body: |symbols| vec![symbols.string("\n"), I32Const(1), symbols.import("print")],
+    },
+    Helper {
+        name: "print_int",
+        params: &[I64],
+        results: &[],
+        locals: &[],
+        body: |symbols| {
+            vec![
+                LocalGet(0),
+                symbols.call("write_int"),
+                symbols.call("write_newline"),
+            ]
+        },
+    },
+    Helper {
+        name: "print_bool",
+        params: &[I32],
+        results: &[],
+        locals: &[],
+        body: |symbols| {
+            vec![
+                LocalGet(0),
+                symbols.call("write_bool"),
+                symbols.call("write_newline"),
+            ]
+        },
+    },
+    Helper {
+        name: "print_real",
+        params: &[F64],
+        results: &[],
+        locals: &[],
+        body: |symbols| {
+            vec![
+                LocalGet(0),
+                symbols.call("write_real"),
+                symbols.call("write_newline"),
+            ]
+        },
+    },
+    // random numbers (this is the same generator as the native runtime uses,
+    // so a program produces the same numbers given the same seed)
+    Helper {
+        name: "seed",
+        params: &[I64],
+        results: &[],
+        locals: &[],
+        body: |_| vec![LocalGet(0), GlobalSet(RANDOM_STATE)],
+    },
+    Helper {
+        name: "next_random",
+        params: &[],
+        results: &[I64],
+        locals: &[I64],
+        body: |_| {
+            vec![
+                GlobalGet(RANDOM_STATE),
+                I64Const(0x9e3779b97f4a7c15_u64 as i64),
+                I64Add,
+                GlobalSet(RANDOM_STATE),
+                GlobalGet(RANDOM_STATE),
+                LocalTee(0),
+                LocalGet(0),
+                I64Const(30),
+                I64ShrU,
+                I64Xor,
+                I64Const(0xbf58476d1ce4e5b9_u64 as i64),
+                I64Mul,
+                LocalTee(0),
+                LocalGet(0),
+                I64Const(27),
+                I64ShrU,
+                I64Xor,
+                I64Const(0x94d049bb133111eb_u64 as i64),
+                I64Mul,
+                LocalTee(0),
+                LocalGet(0),
+                I64Const(31),
+                I64ShrU,
+                I64Xor,
+            ]
+        },
+    },
+    Helper {
+        name: "random_int",
+        params: &[I64, I64],
+        results: &[I64],
+        locals: &[I64],
+        body: |symbols| {
+            let mut body = vec![LocalGet(1), LocalGet(0), I64LtS];
+            body.extend(trap_if());
+            body.extend([
+                LocalGet(1),
+                LocalGet(0),
+                I64Sub,
+                LocalTee(2),
+                I64Const(-1),
+                I64Eq,
+                If(BlockType::Result(I64)),
+                symbols.call("next_random"),
+                Else,
+                LocalGet(0),
+                symbols.call("next_random"),
+                LocalGet(2),
+                I64Const(1),
+                I64Add,
+                I64RemU,
+                I64Add,
+                End,
+            ]);
+            body
+        },
+    },
+    Helper {
+        name: "random_real",
+        params: &[F64, F64],
+        results: &[F64],
+        locals: &[],
+        body: |symbols| {
+            let mut body = vec![LocalGet(1), LocalGet(0), F64Lt];
+            body.extend(trap_if());
+            body.extend([
+                LocalGet(0),
+                symbols.call("next_random"),
+                I64Const(11),
+                I64ShrU,
+                F64ConvertI64U,
+                F64Const((1u64 << 53) as f64),
+                F64Div,
+                LocalGet(1),
+                LocalGet(0),
+                F64Sub,
+                F64Mul,
+                F64Add,
+            ]);
+            body
+        },
+    },
+    // maths
+    Helper {
+        name: "abs_int",
+        params: &[I64],
+        results: &[I64],
+        locals: &[],
+        body: |_| {
+            vec![
+                I64Const(0),
+                LocalGet(0),
+                I64Sub,
+                LocalGet(0),
+                LocalGet(0),
+                I64Const(0),
+                I64LtS,
+                Select,
+            ]
+        },
+    },
+    Helper {
+        name: "abs_real",
+        params: &[F64],
+        results: &[F64],
+        locals: &[],
+        body: |_| vec![LocalGet(0), F64Abs],
+    },
+    Helper {
+        name: "sqrt_real",
+        params: &[F64],
+        results: &[F64],
+        locals: &[],
+        body: |_| vec![LocalGet(0), F64Sqrt],
+    },
+    // these use saturating conversions, which behave in the same way as `as`
+    // in Rust
+    Helper {
+        name: "round_real",
+        params: &[F64],
+        results: &[I64],
+        locals: &[F64],
+        body: |_| {
+            vec![
+                // numbers which are half-way between two integers are
+                // rounded away from zero
+                LocalGet(0),
+                F64Trunc,
+                LocalTee(1),
+                F64Const(1.0),
+                LocalGet(0),
+                F64Copysign,
+                F64Add,
+                LocalGet(1),
+                LocalGet(0),
+                LocalGet(1),
+                F64Sub,
+                F64Abs,
+                F64Const(0.5),
+                F64Ge,
+                Select,
+                I64TruncSatF64S,
+            ]
+        },
+    },
+    Helper {
+        name: "floor_real",
+        params: &[F64],
+        results: &[I64],
+        locals: &[],
+        body: |_| vec![LocalGet(0), F64Floor, I64TruncSatF64S],
+    },
+    Helper {
+        name: "ceil_real",
+        params: &[F64],
+        results: &[I64],
+        locals: &[],
+        body: |_| vec![LocalGet(0), F64Ceil, I64TruncSatF64S],
+    },
+    Helper {
+        name: "min_int",
+        params: &[I64, I64],
+        results: &[I64],
+        locals: &[],
+        body: |_| {
+            vec![
+                LocalGet(0),
+                LocalGet(1),
+                LocalGet(0),
+                LocalGet(1),
+                I64LtS,
+                Select,
+            ]
+        },
+    },
+    Helper {
+        name: "max_int",
+        params: &[I64, I64],
+        results: &[I64],
+        locals: &[],
+        body: |_| {
+            vec![
+                LocalGet(0),
+                LocalGet(1),
+                LocalGet(0),
+                LocalGet(1),
+                I64GtS,
+                Select,
+            ]
+        },
+    },
+    Helper {
+        name: "min_real",
+        params: &[F64, F64],
+        results: &[F64],
+        locals: &[],
+        body: |_| ignoring_nan(F64Min),
+    },
+    Helper {
+        name: "max_real",
+        params: &[F64, F64],
+        results: &[F64],
+        locals: &[],
+        body: |_| ignoring_nan(F64Max),
+    },
+    Helper {
+        name: "pow_int",
+        params: &[I64, I64],
+        results: &[I64],
+        locals: &[I64],
+        body: |_| {
+            // the exponent must be positive (and fit in 32 bits)
+            let mut body = vec![LocalGet(1), I64Const(u32::MAX as i64), I64GtU];
+            body.extend(trap_if());
+            body.extend([
+                // exponentiation by squaring
+                I64Const(1),
+                LocalSet(2),
+                Block(BlockType::Empty),
+                Loop(BlockType::Empty),
+                LocalGet(1),
+                I64Eqz,
+                BrIf(1),
+                LocalGet(1),
+                I64Const(1),
+                I64And,
+                I32WrapI64,
+                If(BlockType::Empty),
+                LocalGet(2),
+                LocalGet(0),
+                I64Mul,
+                LocalSet(2),
+                End,
+                LocalGet(0),
+                LocalGet(0),
+                I64Mul,
+                LocalSet(0),
+                LocalGet(1),
+                I64Const(1),
+                I64ShrU,
+                LocalSet(1),
+                Br(0),
+                End,
+                End,
+                LocalGet(2),
+            ]);
+            body
+        },
+    },
+    Helper {
+        name: "pow_real",
+        params: &[F64, F64],
+        results: &[F64],
+        locals: &[],
+        body: |symbols| vec![LocalGet(0), LocalGet(1), symbols.import("pow")],
+    },
+];
+
+/// Applies `min` or `max` to the two parameters, returning the other one if
+/// either of them is NaN (like Rust does, but unlike WebAssembly).
+fn ignoring_nan(op: Instruction<'static>) -> Vec<Instruction<'static>> {
+    vec![
+        LocalGet(0),
+        LocalGet(1),
+        LocalGet(0),
+        LocalGet(1),
+        op,
+        LocalGet(0),
+        LocalGet(0),
+        F64Ne,
+        Select,
+        LocalGet(1),
+        LocalGet(1),
+        F64Ne,
+        Select,
+    ]
+}
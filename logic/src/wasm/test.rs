@@ -0,0 +1,234 @@
+use std::{fs, path::Path};
+
+use wasmi::{core::F64, Caller, Engine, Extern, Linker, Memory, Module, Store, Value};
+
+use crate::{
+    interpret, io::MemoryIo, mir::lower, parse::parse_with_prelude, runtime, ty::type_check,
+};
+
+use super::{compile, WasmError};
+
+/// The state of the host which the module is run in.
+#[derive(Default)]
+struct Host {
+    input: Vec<&'static str>,
+    output: Vec<u8>,
+}
+
+fn memory(caller: &Caller<'_, Host>) -> Memory {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .unwrap()
+}
+
+/// Runs the compiled module using `wasmi`, returning what it printed (or a
+/// description of the trap which occurred).
+fn execute(wasm: &[u8], input: Vec<&'static str>) -> Result<String, String> {
+    let engine = Engine::default();
+    let module = Module::new(&engine, wasm).unwrap();
+    let mut store = Store::new(
+        &engine,
+        Host {
+            input,
+            ..Host::default()
+        },
+    );
+
+    let mut linker = Linker::<Host>::new(&engine);
+    linker
+        .func_wrap(
+            "env",
+            "print",
+            |mut caller: Caller<'_, Host>, address: i32, length: i32| {
+                let mut text = vec![0; length as usize];
+                memory(&caller)
+                    .read(&caller, address as usize, &mut text)
+                    .unwrap();
+                caller.data_mut().output.extend(text);
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "env",
+            "input",
+            |mut caller: Caller<'_, Host>, address: i32, capacity: i32| {
+                let line = caller.data_mut().input.remove(0);
+                let line = &line.as_bytes()[..line.len().min(capacity as usize)];
+                memory(&caller)
+                    .write(&mut caller, address as usize, line)
+                    .unwrap();
+                line.len() as i32
+            },
+        )
+        .unwrap()
+        .func_wrap(
+            "env",
+            "format_real",
+            |mut caller: Caller<'_, Host>, real: F64, address: i32| {
+                let text = runtime::format_real(real.into());
+                memory(&caller)
+                    .write(&mut caller, address as usize, text.as_bytes())
+                    .unwrap();
+                text.len() as i32
+            },
+        )
+        .unwrap()
+        .func_wrap("env", "pow", |base: F64, exponent: F64| {
+            F64::from(f64::from(base).powf(exponent.into()))
+        })
+        .unwrap();
+
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .unwrap()
+        .start(&mut store)
+        .unwrap();
+    let main = instance.get_func(&store, "main").unwrap();
+    let mut results = [Value::I64(0)];
+    main.call(&mut store, &[], &mut results)
+        .map_err(|error| error.to_string())?;
+
+    Ok(String::from_utf8(store.into_data().output).unwrap())
+}
+
+fn compile_source(input: &str, seed: Option<u64>) -> Result<Vec<u8>, WasmError> {
+    let table = parse_with_prelude(input).unwrap();
+    let env = type_check(&table).unwrap();
+    let program = lower(&table, &env).unwrap();
+    compile(&program, seed)
+}
+
+fn run(input: &str) -> Result<String, String> {
+    execute(&compile_source(input, Some(42)).unwrap(), vec![])
+}
+
+#[test]
+fn prints() {
+    assert_eq!(
+        run(
+            "function main()\n  X = 2.5\n  print(\"Total: \" + str(X))\n  print(1, True, X, \"hello\")\n  print(-1234567, False, 1.0 / 3.0)\n  print_int(7)\n  return 0\nendfunction\n"
+        )
+        .unwrap(),
+        "Total: 2.5\n1 True 2.5 hello\n-1234567 False 0.3333333333333333\n7\n"
+    );
+}
+
+#[test]
+fn recursion() {
+    assert_eq!(
+        run(
+            "function main()\n  print(fib(13))\n  return 0\nendfunction\nfunction fib(n)\n  if n == 0 then\n    return 0\n  elseif n == 1 then\n    return 1\n  endif\n  return fib(n - 1) + fib(n - 2)\nendfunction\n"
+        )
+        .unwrap(),
+        "233\n"
+    );
+}
+
+#[test]
+fn loops_and_records() {
+    assert_eq!(
+        run(
+            "record Point\n  x of Int\n  y of Real\n  visible of Bool\nendrecord\nfunction main()\n  i = 0\n  while i != 3\n    P = Point { x: i, y: 0.5, visible: True }\n    print(P.x, P.y, P.visible)\n    i = i + 1\n  endwhile\n  return 0\nendfunction\n"
+        )
+        .unwrap(),
+        "0 0.5 True\n1 0.5 True\n2 0.5 True\n"
+    );
+}
+
+#[test]
+fn prelude_arrays() {
+    assert_eq!(
+        run(
+            "function main()\n  array = array_new(20)\n  array_set(array, 0, 10)\n  array_set(array, 19, 30)\n  print_int(array_get(array, 0))\n  print_int(array_get(array, 19))\n  array_free(array)\n  return 0\nendfunction\n"
+        )
+        .unwrap(),
+        "10\n30\n"
+    );
+}
+
+#[test]
+fn maths() {
+    assert_eq!(
+        run(
+            "function main()\n  print(abs(-3), abs(-2.5), sqrt(16.0), pow(2, 10), pow(2.0, 0.5))\n  print(round(2.5), round(-2.5), round(2.4), floor(-1.5), ceil(1.2))\n  print(min(3, 4), max(3, 4), min(1.5, 0.5), max(1.5, 0.5))\n  return 0\nendfunction\n"
+        )
+        .unwrap(),
+        "3 2.5 4.0 1024 1.4142135623730951\n3 -3 2 -2 2\n3 4 0.5 1.5\n"
+    );
+}
+
+#[test]
+fn input() {
+    let wasm = compile_source(
+        "function main()\n  name = input()\n  print(\"Hello, \" + name + \"!\")\n  return 0\nendfunction\n",
+        None,
+    )
+    .unwrap();
+    assert_eq!(execute(&wasm, vec!["world"]).unwrap(), "Hello, world!\n");
+}
+
+#[test]
+fn division_by_zero_traps() {
+    assert!(
+        run("function main()\n  x = 0\n  print_int(1 / x)\n  return 0\nendfunction\n").is_err()
+    );
+}
+
+#[test]
+fn extern_functions_are_not_supported() {
+    let error = compile_source(
+        "extern function labs(x: Int) -> Int\nfunction main()\n  print_int(labs(-5))\n  return 0\nendfunction\n",
+        None,
+    )
+    .unwrap_err();
+    assert!(error.message.contains("`labs`"), "{error}");
+}
+
+/// Every filetest should produce the same output when it is compiled to
+/// WebAssembly as it does when it is interpreted.
+#[test]
+fn filetests_agree_with_the_interpreter() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("../filetests");
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        let source = fs::read_to_string(&path).unwrap();
+        let seed = source
+            .lines()
+            .find_map(|line| line.strip_prefix(";; seed:"))
+            .map(|seed| seed.trim().parse().unwrap());
+        // programs which run out of fuel are skipped (see below)
+        let fuel = source
+            .lines()
+            .find_map(|line| line.strip_prefix(";; flags:"))
+            .and_then(|flags| {
+                let mut flags = flags.split_whitespace();
+                flags.find(|flag| *flag == "--fuel")?;
+                flags.next()?.parse().ok()
+            });
+
+        let table = match parse_with_prelude(&source) {
+            Ok(table) => table,
+            Err(_) => continue,
+        };
+        let program = match type_check(&table)
+            .ok()
+            .and_then(|env| lower(&table, &env).ok())
+        {
+            Some(program) => program,
+            None => continue,
+        };
+
+        let mut expected = MemoryIo::default();
+        if interpret::run(&program, seed, fuel, &mut expected).is_err() {
+            continue;
+        }
+        let wasm = compile(&program, seed).unwrap();
+        assert_eq!(
+            execute(&wasm, vec![]).unwrap(),
+            expected.output(),
+            "{}",
+            path.display()
+        );
+    }
+}
@@ -4,7 +4,6 @@ use lsp_server::{ExtractError, Notification, Request, RequestId};
 use serde::de::DeserializeOwned;
 
 /// Try to cast the request into the requested form.
-#[allow(unused)]
 pub fn cast_req<R>(
     req: Request,
 ) -> Result<(RequestId, <R as lsp_types::request::Request>::Params), ExtractError<Request>>
@@ -4,12 +4,16 @@
 //! todo: handle errors properly
 
 use codespan_lsp::{byte_span_to_range, position_to_byte_index};
-use codespan_reporting::{diagnostic::LabelStyle, files::Files};
+use codespan_reporting::{
+    diagnostic::{LabelStyle, Severity},
+    files::Files,
+};
+use logic::{fmt::format, parse::parse_with_prelude, query::Database};
 use lsp_server::{Connection, ExtractError, Message};
 use lsp_types::{
     notification::{DidChangeTextDocument, DidOpenTextDocument, Notification, PublishDiagnostics},
-    DiagnosticRelatedInformation, Location, PublishDiagnosticsParams,
-    TextDocumentContentChangeEvent,
+    DiagnosticRelatedInformation, DiagnosticSeverity, Location, PublishDiagnosticsParams,
+    TextDocumentContentChangeEvent, TextEdit,
 };
 use ropey::Rope;
 use rustc_hash::FxHashMap;
@@ -166,42 +170,56 @@ impl FileContainer {
         }
     }
 
+    /// Formats the file (see [`logic::fmt`]), returning an edit which replaces the whole file
+    /// with the formatted program. No edits are returned if the file is already formatted, or if
+    /// it cannot be parsed (in which case the diagnostics explain why).
+    pub(crate) fn format(&self, id: &lsp_types::Url) -> Option<Vec<TextEdit>> {
+        let input = self.inner.get(id)?.to_string();
+        let table = parse_with_prelude(&input).ok()?;
+        let formatted = format(&table, &input);
+        if formatted == input {
+            return Some(vec![]);
+        }
+        // todo: robust error handling
+        let range = byte_span_to_range(self, id, 0..input.len()).unwrap();
+        Some(vec![TextEdit::new(range, formatted)])
+    }
+
     /// Runs the compiler on the source files and sends the diagnostics back to the editor.
     ///
-    /// todo: incremental compilation
-    fn publish_diagnostics(&self, conn: &Connection) {
-        for (url, file) in &self.inner {
-            // todo: generate multiple errors
-            let mut errors = vec![];
-
-            let input = file.rope.to_string();
-
-            let res = logic::parse::parse(&input)
-                .map_err(|err| err.report(&url))
-                .and_then(|tagged_ast| {
-                    logic::ty::type_check(&tagged_ast)
-                        .map_err(|e| e.report(&url, &tagged_ast))
-                        .map(|_| ())
-                });
-
-            match res {
-                Ok(()) => return,
-                Err(e) => errors.push(e),
-            }
+    /// The compiler is query-based (see [`logic::query`]), so only the parts of each file which
+    /// have changed since the last time this was called are re-checked.
+    fn publish_diagnostics(&mut self, conn: &Connection) {
+        let reports = self
+            .inner
+            .iter_mut()
+            .map(|(url, file)| {
+                let input = file.rope.to_string();
+                file.db.set_source(&input);
+                (url.clone(), file.db.diagnostics(()))
+            })
+            .collect::<Vec<_>>();
 
+        for (url, errors) in &reports {
             let diagnostics = errors
                 .iter()
                 .map(|diagnostic| -> lsp_types::Diagnostic {
                     let primary_label = diagnostic
                         .labels
                         .iter()
-                        .find(|label| label.style == LabelStyle::Primary)
-                        .expect("internal error - malshaped diagnostic (please report)");
-
-                    let primary_label_range = primary_label.range.clone();
-                    // todo: robust error handling
-                    let primary_label_range =
-                        byte_span_to_range(self, url, primary_label_range).unwrap();
+                        .find(|label| label.style == LabelStyle::Primary);
+
+                    // diagnostics about the prelude have no primary label (as
+                    // they are not about any part of the program), so they
+                    // are shown at the start of the file
+                    let (primary_label_range, message) = match primary_label {
+                        // todo: robust error handling
+                        Some(label) => (
+                            byte_span_to_range(self, url, label.range.clone()).unwrap(),
+                            label.message.clone(),
+                        ),
+                        None => (lsp_types::Range::default(), diagnostic.message.clone()),
+                    };
 
                     let further_information = diagnostic
                         .labels
@@ -222,7 +240,13 @@ impl FileContainer {
 
                     lsp_types::Diagnostic {
                         range: primary_label_range,
-                        message: primary_label.message.clone(),
+                        severity: Some(match diagnostic.severity {
+                            Severity::Bug | Severity::Error => DiagnosticSeverity::Error,
+                            Severity::Warning => DiagnosticSeverity::Warning,
+                            Severity::Note => DiagnosticSeverity::Information,
+                            Severity::Help => DiagnosticSeverity::Hint,
+                        }),
+                        message,
                         related_information: Some(further_information),
                         ..Default::default()
                     }
@@ -248,6 +272,7 @@ impl FileContainer {
 #[derive(Default)]
 pub(crate) struct SingleFile {
     rope: Rope,
+    db: Database,
 }
 
 impl std::ops::Deref for SingleFile {
@@ -262,6 +287,7 @@ impl SingleFile {
     fn new(text: &str) -> Self {
         Self {
             rope: Rope::from_str(text),
+            db: Database::new(text),
         }
     }
 }
@@ -15,8 +15,9 @@ use cast::{cast_not, cast_req};
 use files::FileContainer;
 use lsp_server::{Connection, ExtractError, Message, Response};
 use lsp_types::{
-    notification::Exit, request::Shutdown, InitializeParams, ServerCapabilities,
-    TextDocumentSyncCapability, TextDocumentSyncKind,
+    notification::Exit,
+    request::{Formatting, Shutdown},
+    InitializeParams, OneOf, ServerCapabilities, TextDocumentSyncCapability, TextDocumentSyncKind,
 };
 use serde_json::{from_value, to_value, Value};
 
@@ -35,6 +36,7 @@ fn run(conn: Connection) -> LspResult {
         text_document_sync: Some(TextDocumentSyncCapability::Kind(
             TextDocumentSyncKind::Incremental,
         )),
+        document_formatting_provider: Some(OneOf::Left(true)),
         ..Default::default()
     })
     .unwrap();
@@ -70,12 +72,26 @@ fn main_loop(conn: Connection, params: Value) -> LspResult {
                 files.handle_notification(not, &conn)
             }
             lsp_server::Message::Request(req) => {
-                if let Ok((id, ())) = cast_req::<Shutdown>(req) {
-                    shutdown_received = true;
+                let req = match cast_req::<Shutdown>(req) {
+                    Ok((id, ())) => {
+                        shutdown_received = true;
+                        conn.sender
+                            .send(Message::Response(Response::new_ok(
+                                id,
+                                to_value(()).unwrap(),
+                            )))
+                            .unwrap();
+                        continue;
+                    }
+                    Err(ExtractError::MethodMismatch(req)) => req,
+                    Err(_) => panic!(),
+                };
+                if let Ok((id, params)) = cast_req::<Formatting>(req) {
+                    let edits = files.format(&params.text_document.uri);
                     conn.sender
                         .send(Message::Response(Response::new_ok(
                             id,
-                            to_value(()).unwrap(),
+                            to_value(edits).unwrap(),
                         )))
                         .unwrap();
                 }
@@ -6,11 +6,12 @@ use lsp_types::{
         DidChangeTextDocument, DidOpenTextDocument, Exit, Initialized,
         Notification as LspNotification,
     },
-    request::{Initialize, Request, Shutdown},
-    ClientCapabilities, DidChangeTextDocumentParams, DidOpenTextDocumentParams, InitializeParams,
-    InitializedParams, Position, Range, TextDocumentContentChangeEvent, WorkspaceFolder,
+    request::{Formatting, Initialize, Request, Shutdown},
+    ClientCapabilities, DidChangeTextDocumentParams, DidOpenTextDocumentParams,
+    DocumentFormattingParams, FormattingOptions, InitializeParams, InitializedParams, Position,
+    Range, TextDocumentContentChangeEvent, TextDocumentIdentifier, TextEdit, WorkspaceFolder,
 };
-use serde_json::to_value;
+use serde_json::{from_value, to_value};
 
 use crate::run;
 
@@ -180,3 +181,119 @@ fn smoke_test_1() {
 
     assert!(lsp_server.join().is_ok());
 }
+
+#[test]
+/// Checks that the server formats documents (and replaces the whole document when it does).
+#[allow(deprecated)]
+fn formatting() {
+    let (server, client) = Connection::memory();
+
+    let lsp_server = thread::spawn(|| run(server));
+
+    client
+        .sender
+        .send(Message::Request(lsp_server::Request::new(
+            1.into(),
+            Initialize::METHOD.to_string(),
+            to_value(InitializeParams {
+                process_id: None,
+                root_path: None,
+                root_uri: None,
+                initialization_options: None,
+                capabilities: ClientCapabilities::default(),
+                trace: None,
+                workspace_folders: None,
+                client_info: None,
+                locale: None,
+            })
+            .unwrap(),
+        )))
+        .expect("failed to initialize");
+    client
+        .receiver
+        .recv_timeout(Duration::from_secs(10))
+        .expect("failed to receive response");
+    client
+        .sender
+        .send(Message::Notification(Notification::new(
+            Initialized::METHOD.to_string(),
+            InitializedParams {},
+        )))
+        .expect("failed to send message");
+
+    let uri = lsp_types::Url::parse("file:///Users/pseudodemo/main.pseudo").unwrap();
+    client
+        .sender
+        .send(Message::Notification(Notification::new(
+            DidOpenTextDocument::METHOD.to_string(),
+            to_value(DidOpenTextDocumentParams {
+                text_document: lsp_types::TextDocumentItem {
+                    uri: uri.clone(),
+                    language_id: "pseudo".to_string(),
+                    version: 1,
+                    text: "x  =  (1+2)\nprint(x)".to_string(),
+                },
+            })
+            .unwrap(),
+        )))
+        .expect("failed to send message");
+
+    client
+        .sender
+        .send(Message::Request(lsp_server::Request::new(
+            2.into(),
+            Formatting::METHOD.to_string(),
+            to_value(DocumentFormattingParams {
+                text_document: TextDocumentIdentifier { uri },
+                options: FormattingOptions {
+                    tab_size: 2,
+                    insert_spaces: true,
+                    ..Default::default()
+                },
+                work_done_progress_params: Default::default(),
+            })
+            .unwrap(),
+        )))
+        .expect("failed to send message");
+
+    let edits = loop {
+        match client
+            .receiver
+            .recv_timeout(Duration::from_secs(10))
+            .expect("failed to receive response")
+        {
+            Message::Response(response) => {
+                assert_eq!(response.id, 2.into());
+                break from_value::<Option<Vec<TextEdit>>>(response.result.unwrap()).unwrap();
+            }
+            // the diagnostics for the file
+            Message::Notification(_) => {}
+            msg => panic!("invalid message: {:#?}", msg),
+        }
+    };
+    assert_eq!(
+        edits,
+        Some(vec![TextEdit::new(
+            Range::new(Position::new(0, 0), Position::new(1, 8)),
+            "x = 1 + 2\nprint(x)\n".to_string()
+        )])
+    );
+
+    client
+        .sender
+        .send(Message::Request(lsp_server::Request::new(
+            3.into(),
+            Shutdown::METHOD.to_string(),
+            to_value(()).unwrap(),
+        )))
+        .unwrap();
+    client
+        .sender
+        .send(Message::Notification(Notification::new(
+            Exit::METHOD.to_string(),
+            to_value(()).unwrap(),
+        )))
+        .unwrap();
+
+    assert!(lsp_server.join().is_ok());
+}
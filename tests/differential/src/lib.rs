@@ -0,0 +1,77 @@
+//! Differential testing: runs programs using both the Cranelift backend and
+//! the MIR interpreter, and checks that they behave in the same way.
+//!
+//! The interpreter is much simpler than the JIT (and checks for things such as
+//! out-of-bounds accesses), so whenever the two disagree, one of them has a
+//! bug. Mismatches are shrunk to a small program before they are reported.
+
+use std::fmt;
+
+pub mod run;
+pub mod shrink;
+
+#[cfg(test)]
+mod test;
+
+use run::{run, Backend, Outcome};
+
+/// A program which behaves differently depending on which backend is used to
+/// run it.
+#[derive(Debug)]
+pub struct Mismatch {
+    pub source: String,
+    pub jit: Outcome,
+    pub interpreter: Outcome,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "program:\n{}", self.source)?;
+        writeln!(f, "jit: {:?}", self.jit)?;
+        write!(f, "interpreter: {:?}", self.interpreter)
+    }
+}
+
+/// Runs the program using both backends, and returns a [`Mismatch`] if they
+/// disagree.
+pub fn compare(source: &str, flags: &[&str]) -> Result<(), Mismatch> {
+    let jit = run(Backend::Jit, source, flags);
+    let interpreter = run(Backend::Interpreter, source, flags);
+    if jit.agrees_with(&interpreter) {
+        Ok(())
+    } else {
+        Err(Mismatch {
+            source: source.to_owned(),
+            jit,
+            interpreter,
+        })
+    }
+}
+
+/// Checks that both backends agree on the program, panicking (with the
+/// smallest program we could find which still demonstrates the problem) if
+/// they do not.
+pub fn check(source: &str, flags: &[&str]) {
+    if let Err(mismatch) = compare(source, flags) {
+        let shrunk = shrink::shrink(source, |candidate| compare(candidate, flags).is_err());
+        let shrunk = compare(&shrunk, flags).unwrap_err();
+        panic!("the backends disagree on\n{mismatch}\n\nwhich can be reduced to\n{shrunk}");
+    }
+}
+
+/// Checks whether the program is accepted by the compiler (without running
+/// it), so that we don't waste time running programs which can't be compared.
+pub fn compiles(source: &str) -> bool {
+    let table = match logic::parse::parse_with_prelude(source) {
+        Ok(table) => table,
+        Err(_) => return false,
+    };
+    let env = match logic::ty::type_check(&table) {
+        Ok(env) => env,
+        Err(_) => return false,
+    };
+    matches!(
+        logic::mir::lower(&table, &env),
+        Ok(program) if program.function("main").is_some()
+    )
+}
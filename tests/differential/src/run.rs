@@ -0,0 +1,159 @@
+//! Runs programs using the `pseudo` binary.
+//!
+//! Each program is run in a separate process, so that a program which crashes
+//! the JIT (for example, by dividing by zero) can be compared with the
+//! interpreter (which reports an error instead).
+
+use std::{
+    env,
+    io::{Read, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::Once,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Programs which run for longer than this are stopped (and we don't try to
+/// compare their output).
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A way of running programs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// The Cranelift JIT (the default).
+    Jit,
+    /// The MIR interpreter (`pseudo run --interpret`).
+    Interpreter,
+}
+
+impl Backend {
+    fn args(self) -> &'static [&'static str] {
+        match self {
+            Backend::Jit => &[],
+            Backend::Interpreter => &["run", "--interpret"],
+        }
+    }
+}
+
+/// The exit code which the compiler uses when the program cannot be compiled,
+/// or when something goes wrong while it is running.
+const ERROR: i32 = 1;
+
+/// How the compiler exited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// The compiler exited with this exit code (zero if the program was
+    /// compiled and ran successfully, [`ERROR`] if there was an error in the
+    /// program, and something else if the compiler itself panicked).
+    Exited(i32),
+    /// The compiler was killed by a signal (for example, because the program
+    /// divided by zero in the JIT).
+    Crashed,
+    /// The program did not finish in time.
+    TimedOut,
+}
+
+/// The result of running a program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    pub status: Status,
+    pub stdout: String,
+}
+
+impl Outcome {
+    /// Checks whether two runs of the same program behaved in the same way.
+    pub fn agrees_with(&self, other: &Outcome) -> bool {
+        match (self.status, other.status) {
+            // we don't know what would have happened
+            (Status::TimedOut, _) | (_, Status::TimedOut) => true,
+            (Status::Exited(0), Status::Exited(0)) => self.stdout == other.stdout,
+            // when a program fails, anything which it printed on the current
+            // line might not have been written yet (and the JIT crashes where
+            // the interpreter reports an error)
+            (Status::Exited(a), Status::Exited(b)) if a == b => {
+                complete_lines(&self.stdout) == complete_lines(&other.stdout)
+            }
+            (Status::Crashed, Status::Crashed | Status::Exited(ERROR))
+            | (Status::Exited(ERROR), Status::Crashed) => {
+                complete_lines(&self.stdout) == complete_lines(&other.stdout)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn complete_lines(output: &str) -> &str {
+    match output.rfind('\n') {
+        Some(end) => &output[..=end],
+        None => "",
+    }
+}
+
+/// Returns the path to the `pseudo` binary (building it first, if this has not
+/// already been done).
+pub fn compiler() -> PathBuf {
+    static BUILD: Once = Once::new();
+
+    let workspace = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../..");
+    BUILD.call_once(|| {
+        let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned()))
+            .args(["build", "--bin", "pseudo"])
+            .current_dir(&workspace)
+            .status()
+            .expect("failed to run cargo");
+        assert!(status.success(), "failed to build the compiler");
+    });
+
+    env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| workspace.join("target"))
+        .join("debug")
+        .join("pseudo")
+}
+
+/// Runs the program using the given backend (passing `flags` to the
+/// compiler).
+pub fn run(backend: Backend, source: &str, flags: &[&str]) -> Outcome {
+    let mut file = tempfile::Builder::new()
+        .suffix(".pseudo")
+        .tempfile()
+        .unwrap();
+    file.write_all(source.as_bytes()).unwrap();
+
+    let mut child = Command::new(compiler())
+        .args(backend.args())
+        .args(flags)
+        .arg(file.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("failed to run the compiler");
+
+    // the output is read on another thread, so that the program does not
+    // block if it fills up the pipe
+    let mut stdout = child.stdout.take().unwrap();
+    let reader = thread::spawn(move || {
+        let mut output = vec![];
+        stdout.read_to_end(&mut output).unwrap();
+        output
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status.code().map_or(Status::Crashed, Status::Exited);
+        }
+        if started.elapsed() > TIMEOUT {
+            child.kill().unwrap();
+            child.wait().unwrap();
+            break Status::TimedOut;
+        }
+        thread::sleep(Duration::from_millis(5));
+    };
+
+    Outcome {
+        status,
+        stdout: String::from_utf8_lossy(&reader.join().unwrap()).into_owned(),
+    }
+}
@@ -0,0 +1,41 @@
+//! Shrinks programs (so that when the backends disagree, we can report the
+//! smallest program which demonstrates the problem).
+
+/// Removes as many lines from the program as possible, while ensuring that it
+/// remains "interesting" (i.e. it still demonstrates the problem).
+///
+/// This is a simple version of the delta debugging algorithm: we first try to
+/// remove large chunks of lines, and then progressively smaller ones. Most
+/// candidates will not parse (for example, because an `endif` was removed
+/// without the corresponding `if`), but then both backends fail in the same
+/// way, so they are not interesting and are thrown away.
+pub fn shrink(source: &str, mut is_interesting: impl FnMut(&str) -> bool) -> String {
+    let mut lines = source.lines().collect::<Vec<_>>();
+    let mut chunk = (lines.len() / 2).max(1);
+
+    loop {
+        let mut removed = false;
+        let mut start = 0;
+        while start < lines.len() {
+            let end = (start + chunk).min(lines.len());
+            let candidate = [&lines[..start], &lines[end..]].concat();
+            if is_interesting(&join(&candidate)) {
+                lines = candidate;
+                removed = true;
+            } else {
+                start += chunk;
+            }
+        }
+
+        if !removed {
+            if chunk == 1 {
+                return join(&lines);
+            }
+            chunk /= 2;
+        }
+    }
+}
+
+fn join(lines: &[&str]) -> String {
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
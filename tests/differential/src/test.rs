@@ -0,0 +1,129 @@
+use std::{env, fs, path::Path};
+
+use fuzzcheck::{DefaultMutator, Mutator};
+use generator::Block;
+
+use crate::{
+    check, compiles,
+    run::{Outcome, Status},
+    shrink::shrink,
+};
+
+/// See `tests/e2e` for the meaning of these.
+static SEED_PREFIX: &str = ";; seed:";
+static FLAGS_PREFIX: &str = ";; flags:";
+
+/// The number of generated programs to test (can be overridden by setting
+/// `DIFFERENTIAL_PROGRAMS`).
+const DEFAULT_PROGRAMS: usize = 200;
+
+#[test]
+fn filetests_agree() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../filetests");
+    for entry in fs::read_dir(directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().and_then(|extension| extension.to_str()) != Some("pseudo") {
+            continue;
+        }
+        let source = fs::read_to_string(&path).unwrap();
+        // the interpreter cannot call native functions
+        if source.contains("extern function") {
+            continue;
+        }
+
+        let mut flags = vec![];
+        for line in source.lines() {
+            if let Some(seed) = line.strip_prefix(SEED_PREFIX) {
+                flags.extend(["--seed", seed.trim()]);
+            } else if let Some(extra) = line.strip_prefix(FLAGS_PREFIX) {
+                flags.extend(extra.split_whitespace());
+            }
+        }
+        if !flags.contains(&"--seed") {
+            flags.extend(["--seed", "0"]);
+        }
+
+        check(&source, &flags);
+    }
+}
+
+#[test]
+fn generated_programs_agree() {
+    let programs = env::var("DIFFERENTIAL_PROGRAMS")
+        .ok()
+        .and_then(|programs| programs.parse().ok())
+        .unwrap_or(DEFAULT_PROGRAMS);
+
+    let mutator = Block::default_mutator();
+    let mut tested = 0;
+    // most generated programs are rejected by the type checker, so give up
+    // eventually rather than looping forever
+    for _ in 0..programs * 100 {
+        if tested == programs {
+            break;
+        }
+        let (block, _) = mutator.random_arbitrary(4096.0);
+        let source = block.to_program();
+        if !compiles(&source) {
+            continue;
+        }
+        tested += 1;
+        for level in ["-O0", "-O2"] {
+            check(&source, &["--seed", "0", level]);
+        }
+    }
+    assert!(tested > 0, "none of the generated programs compiled");
+}
+
+fn outcome(status: Status, stdout: &str) -> Outcome {
+    Outcome {
+        status,
+        stdout: stdout.to_owned(),
+    }
+}
+
+#[test]
+fn outcomes() {
+    let success = outcome(Status::Exited(0), "1\n2\n");
+    assert!(success.agrees_with(&success.clone()));
+    assert!(!success.agrees_with(&outcome(Status::Exited(0), "1\n")));
+    assert!(!success.agrees_with(&outcome(Status::Exited(1), "1\n2\n")));
+    assert!(success.agrees_with(&outcome(Status::TimedOut, "")));
+
+    // output on the last (unfinished) line might not have been flushed
+    let error = outcome(Status::Exited(1), "1\n2");
+    assert!(error.agrees_with(&outcome(Status::Exited(1), "1\n")));
+    assert!(!outcome(Status::Exited(1), "1\n2\n").agrees_with(&outcome(Status::Exited(1), "1\n")));
+
+    // an error in the program is not the same as the compiler panicking
+    assert!(!error.agrees_with(&outcome(Status::Exited(101), "1\n")));
+
+    // the JIT crashes where the interpreter reports an error
+    assert!(outcome(Status::Crashed, "1\n").agrees_with(&error));
+    assert!(!outcome(Status::Crashed, "1\n").agrees_with(&outcome(Status::Exited(101), "1\n")));
+}
+
+#[test]
+fn generated_programs_have_a_main_function() {
+    let mutator = Block::default_mutator();
+    for _ in 0..100 {
+        let (block, _) = mutator.random_arbitrary(4096.0);
+        let program = block.to_program();
+        assert!(program.contains("function main()\n"), "{program}");
+    }
+}
+
+#[test]
+fn shrinking() {
+    let source = "a\nb\nc\nd\ne\nf\ng\n";
+    assert_eq!(
+        shrink(source, |candidate| candidate.contains('c')
+            && candidate.contains('f')),
+        "c\nf\n"
+    );
+    assert_eq!(
+        shrink(source, |candidate| candidate.contains("b\nc")),
+        "b\nc\n"
+    );
+    assert_eq!(shrink(source, |_| false), source);
+}
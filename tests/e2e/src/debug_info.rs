@@ -0,0 +1,248 @@
+//! Checks the DWARF debug information which is included in object files when
+//! `-g` is passed to the compiler.
+
+use std::{borrow::Cow, path::Path};
+
+use gimli::{constants, AttributeValue, Dwarf, EndianSlice, LittleEndian};
+use logic::codegen::{emit_object, optimised_mir, CodegenOptions, Target};
+use object::{Object, ObjectSection, ObjectSymbol, RelocationTarget};
+
+static PROGRAM: &str = "function main()
+  total = add(1, 2)
+  print_int(total)
+  return 0
+endfunction
+function add(a, b)
+  sum = a + b
+  return sum
+endfunction
+";
+
+fn object_file(triple: &str) -> Vec<u8> {
+    let table = logic::parse::parse_with_prelude(PROGRAM).unwrap();
+    let env = logic::ty::type_check(&table).unwrap();
+    let options = CodegenOptions {
+        debug_info: true,
+        ..CodegenOptions::default()
+    };
+    let program = optimised_mir(&table, &env, &options).unwrap();
+    emit_object(
+        &table,
+        &program,
+        &options,
+        &Target::from_triple(triple).unwrap(),
+        Path::new("add.pseudo"),
+    )
+    .unwrap()
+    .bytes
+}
+
+/// A variable (or parameter) which is described by the debug information.
+#[derive(Debug, PartialEq)]
+struct Variable {
+    tag: constants::DwTag,
+    name: String,
+    ty: String,
+}
+
+/// A function which is described by the debug information.
+#[derive(Debug, PartialEq)]
+struct Subprogram {
+    name: String,
+    decl_line: u64,
+    variables: Vec<Variable>,
+}
+
+/// The parts of the debug information which we check.
+#[derive(Debug)]
+struct DebugInfo {
+    name: String,
+    /// The file name and line of every row in the line table.
+    lines: Vec<(String, u64)>,
+    subprograms: Vec<Subprogram>,
+    /// Whether the location of any variable is described.
+    has_locations: bool,
+}
+
+type Slice<'a> = EndianSlice<'a, LittleEndian>;
+
+fn string(dwarf: &Dwarf<Slice>, value: AttributeValue<Slice>) -> String {
+    dwarf
+        .attr_string(
+            &dwarf.unit(dwarf.units().next().unwrap().unwrap()).unwrap(),
+            value,
+        )
+        .unwrap()
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn read_debug_info(bytes: &[u8]) -> DebugInfo {
+    let file = object::File::parse(bytes).unwrap();
+    let load = |id: gimli::SectionId| -> Result<Cow<[u8]>, gimli::Error> {
+        Ok(file
+            .section_by_name(id.name())
+            .map(|section| section.uncompressed_data().unwrap())
+            .unwrap_or(Cow::Borrowed(&[])))
+    };
+    let sections = gimli::Dwarf::load(load).unwrap();
+    let dwarf = sections.borrow(|section| EndianSlice::new(section, LittleEndian));
+
+    let header = dwarf.units().next().unwrap().unwrap();
+    let unit = dwarf.unit(header).unwrap();
+
+    let mut lines = vec![];
+    let program = unit.line_program.clone().unwrap();
+    let mut rows = program.rows();
+    while let Some((header, row)) = rows.next_row().unwrap() {
+        if row.end_sequence() {
+            continue;
+        }
+        let file = row.file(header).unwrap();
+        let name = string(&dwarf, file.path_name());
+        lines.push((name, row.line().unwrap().get()));
+    }
+
+    let mut name = None;
+    let mut subprograms = vec![];
+    let mut has_locations = false;
+    let mut entries = unit.entries();
+    while let Some((_, entry)) = entries.next_dfs().unwrap() {
+        let attr = |name| entry.attr_value(name).unwrap();
+        let name_of = |value: Option<AttributeValue<Slice>>| string(&dwarf, value.unwrap());
+        match entry.tag() {
+            constants::DW_TAG_compile_unit => name = Some(name_of(attr(constants::DW_AT_name))),
+            constants::DW_TAG_subprogram => subprograms.push(Subprogram {
+                name: name_of(attr(constants::DW_AT_name)),
+                decl_line: attr(constants::DW_AT_decl_line)
+                    .and_then(|line| line.udata_value())
+                    .unwrap_or(0),
+                variables: vec![],
+            }),
+            tag @ (constants::DW_TAG_formal_parameter | constants::DW_TAG_variable) => {
+                has_locations |= attr(constants::DW_AT_location).is_some();
+                let ty = match attr(constants::DW_AT_type) {
+                    Some(AttributeValue::UnitRef(offset)) => unit.entry(offset).unwrap(),
+                    other => panic!("unexpected type {other:?}"),
+                };
+                subprograms.last_mut().unwrap().variables.push(Variable {
+                    tag,
+                    name: name_of(attr(constants::DW_AT_name)),
+                    ty: name_of(ty.attr_value(constants::DW_AT_name).unwrap()),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    DebugInfo {
+        name: name.unwrap(),
+        lines,
+        subprograms,
+        has_locations,
+    }
+}
+
+#[test]
+fn object_files_contain_debug_info() {
+    for triple in ["x86_64-unknown-linux-gnu", "aarch64-unknown-linux-gnu"] {
+        let bytes = object_file(triple);
+        let info = read_debug_info(&bytes);
+
+        assert_eq!(info.name, "add.pseudo", "{triple}");
+        // every line with code on it (and only those lines) is in the line
+        // table
+        let mut lines = info
+            .lines
+            .iter()
+            .map(|(file, line)| {
+                assert_eq!(file, "add.pseudo", "{triple}");
+                *line
+            })
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines.dedup();
+        assert_eq!(lines, [1, 2, 3, 4, 6, 7, 8], "{triple}");
+
+        let variable = |tag, name: &str, ty: &str| Variable {
+            tag,
+            name: name.to_owned(),
+            ty: ty.to_owned(),
+        };
+        let main = info
+            .subprograms
+            .iter()
+            .find(|subprogram| subprogram.name == "main")
+            .unwrap();
+        assert_eq!(main.decl_line, 1, "{triple}");
+        assert_eq!(
+            main.variables,
+            [variable(constants::DW_TAG_variable, "total", "Int")],
+            "{triple}"
+        );
+        let add = info
+            .subprograms
+            .iter()
+            .find(|subprogram| subprogram.name == "add")
+            .unwrap();
+        assert_eq!(add.decl_line, 6, "{triple}");
+        assert_eq!(
+            add.variables,
+            [
+                variable(constants::DW_TAG_formal_parameter, "a", "Int"),
+                variable(constants::DW_TAG_formal_parameter, "b", "Int"),
+                variable(constants::DW_TAG_variable, "sum", "Int"),
+            ],
+            "{triple}"
+        );
+        assert!(info.has_locations, "{triple}");
+
+        // the addresses of the functions are filled in by the linker (the
+        // relocations may refer to the function, or to the start of the
+        // section containing it)
+        let file = object::File::parse(&*bytes).unwrap();
+        let debug_info = file.section_by_name(".debug_info").unwrap();
+        let is_relocated_to = |section, address| {
+            debug_info
+                .relocations()
+                .any(|(_, relocation)| match relocation.target() {
+                    RelocationTarget::Symbol(symbol) => {
+                        let symbol = file.symbol_by_index(symbol).unwrap();
+                        symbol.section_index() == section
+                            && symbol.address() as i64 + relocation.addend() == address
+                    }
+                    _ => false,
+                })
+        };
+        for function in ["main", "add"] {
+            let symbol = file
+                .symbols()
+                .find(|symbol| symbol.name() == Ok(function))
+                .unwrap();
+            assert!(
+                is_relocated_to(symbol.section_index(), symbol.address() as i64),
+                "{triple}: {function}"
+            );
+        }
+    }
+}
+
+#[test]
+fn debug_info_is_only_generated_for_elf_targets() {
+    let table = logic::parse::parse_with_prelude(PROGRAM).unwrap();
+    let env = logic::ty::type_check(&table).unwrap();
+    let options = CodegenOptions {
+        debug_info: true,
+        ..CodegenOptions::default()
+    };
+    let program = optimised_mir(&table, &env, &options).unwrap();
+    let error = emit_object(
+        &table,
+        &program,
+        &options,
+        &Target::from_triple("aarch64-apple-darwin").unwrap(),
+        Path::new("add.pseudo"),
+    )
+    .unwrap_err();
+    assert!(error.message.contains("ELF"), "{error}");
+}
@@ -0,0 +1,31 @@
+//! Checks that the file tests are formatted (which also checks that
+//! formatting a program does not change it once it has been formatted).
+
+use std::fs::{self, read_to_string};
+
+use logic::{fmt::format, parse::parse_with_prelude};
+
+#[test]
+fn filetests_are_formatted() {
+    let mut paths = fs::read_dir("../../filetests")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "pseudo"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    for path in paths {
+        let text = read_to_string(&path).unwrap();
+        // some of the file tests check the errors which the parser reports
+        let table = match parse_with_prelude(&text) {
+            Ok(table) => table,
+            Err(_) => continue,
+        };
+        assert_eq!(
+            format(&table, &text),
+            text,
+            "`{}` is not formatted",
+            path.display()
+        );
+    }
+}
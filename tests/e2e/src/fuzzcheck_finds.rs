@@ -2,14 +2,20 @@
 
 use logic::{
     diagnostics::reportable_error::ReportableError,
+    io::MemoryIo,
     parse::utils::ParseError,
     ty::error::{ConstraintGatheringError, TyCheckError},
 };
 
 fn compile_for_fuzzing(input: &str) {
-    let table = logic::parse::parse(input).unwrap();
+    let table = logic::parse::parse_with_prelude(input).unwrap();
     if let Ok(ty_checked) = logic::ty::type_check(&table) {
-        let _ = logic::codegen::codegen(&table, &ty_checked);
+        let _ = logic::codegen::codegen(
+            &table,
+            &ty_checked,
+            &Default::default(),
+            &mut MemoryIo::default(),
+        );
     }
 }
 
@@ -55,7 +61,7 @@ impl ExecutionStatus {
 
 /// Runs the provided code fragment, returning the status of the execution.
 fn run_test(input: &str) -> ExecutionStatus {
-    let tree = match logic::parse::parse(input) {
+    let tree = match logic::parse::parse_with_prelude(input) {
         Ok(tree) => tree,
         Err(error) => return ExecutionStatus::FailedParsing(error),
     };
@@ -65,7 +71,12 @@ fn run_test(input: &str) -> ExecutionStatus {
         Err(err) => return ExecutionStatus::FailedTypeChecking(err),
     };
 
-    let codegen = match logic::codegen::codegen(&tree, &ty_env) {
+    let codegen = match logic::codegen::codegen(
+        &tree,
+        &ty_env,
+        &Default::default(),
+        &mut MemoryIo::default(),
+    ) {
         Ok(res) => res,
         Err(err) => return ExecutionStatus::FailedCodeGeneration(err),
     };
@@ -134,10 +145,19 @@ fn loop_and_record_inside_function() {
 
 #[test]
 fn while_with_function_call_inside_function() {
-    let binding = run_test("function Q ()\n  while R(p,)\n  endwhile\nendfunction\nQ = False\n");
-    let error = binding.as_failed_code_generation().unwrap();
-    assert!(error.explanation().contains("type"));
-    assert!(error.explanation().contains("function parameter"));
+    let result = run_test("function Q ()\n  while R(p,)\n  endwhile\nendfunction\nQ = False\n");
+    let error = result.as_failed_type_checking().unwrap();
+    let cge = error.as_constraint_gathering_error().unwrap();
+    if let ConstraintGatheringError::UnresolvableFunction {
+        span: _,
+        explanation,
+    } = cge
+    {
+        assert!(explanation.contains("`R`"));
+    } else {
+        dbg!(cge);
+        panic!("wrong error type");
+    }
 }
 
 #[test]
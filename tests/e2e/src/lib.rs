@@ -1,8 +1,14 @@
 #[cfg(test)]
 use std::{fs::read_to_string, process::Command};
 
+#[cfg(test)]
+mod debug_info;
+#[cfg(test)]
+mod fmt;
 #[cfg(test)]
 mod fuzzcheck_finds;
+#[cfg(test)]
+mod transpile;
 
 #[cfg(test)]
 use lang_tester::LangTester;
@@ -13,6 +19,13 @@ use run_script::{IoOptions, ScriptOptions};
 #[test]
 fn integration_tests() {
     static COMMENT_PREFIX: &str = ";;";
+    /// Tests which use random numbers can specify the seed which should be
+    /// passed to the compiler (so that their output is reproducible) by
+    /// adding a line of the form `;; seed: 42` to their header.
+    static SEED_PREFIX: &str = ";; seed:";
+    /// Tests can pass other flags to the compiler (for example, to enable
+    /// optimisations) using a line of the form `;; flags: -O2`.
+    static FLAGS_PREFIX: &str = ";; flags:";
     let options = ScriptOptions {
         output_redirection: IoOptions::Inherit,
         ..ScriptOptions::new()
@@ -37,6 +50,7 @@ fn integration_tests() {
                 .lines()
                 .skip_while(|l| !l.starts_with(COMMENT_PREFIX))
                 .take_while(|l| l.starts_with(COMMENT_PREFIX))
+                .filter(|l| !l.starts_with(SEED_PREFIX) && !l.starts_with(FLAGS_PREFIX))
                 .map(|l| &l[COMMENT_PREFIX.len()..])
                 .collect::<Vec<_>>()
                 .join("\n")
@@ -44,6 +58,20 @@ fn integration_tests() {
         .test_cmds(move |p| {
             let mut compiler = Command::new("pseudo");
             compiler.args(&[p.to_str().unwrap()]);
+            if let Some(seed) = read_to_string(p)
+                .unwrap()
+                .lines()
+                .find_map(|l| l.strip_prefix(SEED_PREFIX))
+            {
+                compiler.args(&["--seed", seed.trim()]);
+            }
+            if let Some(flags) = read_to_string(p)
+                .unwrap()
+                .lines()
+                .find_map(|l| l.strip_prefix(FLAGS_PREFIX))
+            {
+                compiler.args(flags.split_whitespace());
+            }
 
             vec![("compiler", compiler)]
         })
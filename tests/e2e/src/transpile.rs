@@ -0,0 +1,152 @@
+//! Checks that the programs in the file tests produce the same output when
+//! they are translated into Python (and run using `python3`) or C (and
+//! compiled using `cc`) as they do when they are compiled.
+
+use std::{
+    fs::{self, read_to_string},
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use logic::{
+    codegen::{optimised_mir, CodegenOptions},
+    parse::parse_with_prelude,
+    session::ExitStatus,
+    transpile::{c::to_c, python::to_python},
+    ty::type_check,
+    Backend, Session,
+};
+
+/// Returns the file tests which run successfully (with their source code).
+fn successful_filetests() -> Vec<(PathBuf, String)> {
+    let mut paths = fs::read_dir("../../filetests")
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "pseudo"))
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let text = read_to_string(&path).unwrap();
+            (path, text)
+        })
+        .filter(|(_, text)| {
+            text.lines()
+                .filter_map(|line| line.strip_prefix(";;"))
+                .any(|line| line.trim() == "status: success")
+        })
+        .collect()
+}
+
+#[test]
+fn python_programs_produce_the_same_output() {
+    if Command::new("python3").arg("--version").output().is_err() {
+        eprintln!("`python3` is not installed, so the translated programs cannot be run");
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut checked = 0;
+    for (path, text) in successful_filetests() {
+        // programs which use random numbers produce different numbers in
+        // Python
+        if text.contains("random(") {
+            continue;
+        }
+
+        let mut session = Session::new(CodegenOptions::default());
+        let id = session.add_source(path.to_string_lossy(), text.clone());
+        let expected = session
+            .compile(id)
+            .unwrap()
+            .run_captured(Backend::Jit, Vec::<String>::new());
+
+        let table = parse_with_prelude(&text).unwrap();
+        let env = type_check(&table).unwrap();
+        let python = dir
+            .path()
+            .join(Path::new(path.file_name().unwrap()).with_extension("py"));
+        fs::write(&python, to_python(&table, &env, &text)).unwrap();
+        let output = Command::new("python3").arg(&python).output().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            expected.output,
+            "{}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            ExitStatus::Exited(output.status.code().unwrap().into()),
+            expected.status,
+            "{}",
+            path.display()
+        );
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
+
+#[test]
+fn c_programs_produce_the_same_output() {
+    if Command::new("cc").arg("--version").output().is_err() {
+        eprintln!("`cc` is not installed, so the translated programs cannot be compiled");
+        return;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let options = CodegenOptions {
+        seed: Some(42),
+        ..CodegenOptions::default()
+    };
+
+    let mut checked = 0;
+    for (path, text) in successful_filetests() {
+        let mut session = Session::new(options.clone());
+        let id = session.add_source(path.to_string_lossy(), text.clone());
+        let expected = session
+            .compile(id)
+            .unwrap()
+            .run_captured(Backend::Jit, Vec::<String>::new());
+
+        let table = parse_with_prelude(&text).unwrap();
+        let env = type_check(&table).unwrap();
+        let program = optimised_mir(&table, &env, &options).unwrap();
+        let source = dir
+            .path()
+            .join(Path::new(path.file_name().unwrap()).with_extension("c"));
+        let executable = source.with_extension("");
+        fs::write(&source, to_c(&program, options.seed, options.fuel)).unwrap();
+        let compiled = Command::new("cc")
+            .args(["-std=c99", "-fwrapv", "-o"])
+            .arg(&executable)
+            .arg(&source)
+            .arg("-lm")
+            .output()
+            .unwrap();
+        assert!(
+            compiled.status.success(),
+            "{}: {}",
+            path.display(),
+            String::from_utf8_lossy(&compiled.stderr)
+        );
+        let output = Command::new(&executable).output().unwrap();
+
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            expected.output,
+            "{}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        assert_eq!(
+            ExitStatus::Exited(output.status.code().unwrap().into()),
+            expected.status,
+            "{}",
+            path.display()
+        );
+        checked += 1;
+    }
+    assert!(checked > 0);
+}
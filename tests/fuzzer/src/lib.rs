@@ -32,8 +32,13 @@ pub fn run_fuzzer() {
 #[cfg(test)]
 #[cfg(fuzzing)]
 fn compile_for_fuzzing(input: &str) {
-    let table = logic::parse::parse(input).unwrap();
+    let table = logic::parse::parse_with_prelude(input).unwrap();
     if let Ok(ty_checked) = logic::ty::type_check(&table) {
-        let _ = logic::codegen::codegen(&table, &ty_checked);
+        let _ = logic::codegen::codegen(
+            &table,
+            &ty_checked,
+            &Default::default(),
+            &mut logic::io::MemoryIo::default(),
+        );
     }
 }
@@ -51,6 +51,31 @@ impl Block {
         }
         Ok(())
     }
+
+    /// Formats the block as a complete program: functions and records are
+    /// defined at the top level, and every other statement is placed inside
+    /// the `main` function (which then returns zero).
+    pub fn to_program(&self) -> String {
+        let (items, statements): (Vec<Node>, Vec<Node>) = self
+            .inner
+            .iter()
+            .cloned()
+            .partition(|node| matches!(node, Node::Function { .. } | Node::Record { .. }));
+        format!(
+            "{}function main()\n{}  return 0\nendfunction\n",
+            Block { inner: items },
+            Indented(&Block { inner: statements }, 2)
+        )
+    }
+}
+
+/// Formats a block indented by the given number of spaces.
+struct Indented<'a>(&'a Block, usize);
+
+impl fmt::Display for Indented<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(self.1, f)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]